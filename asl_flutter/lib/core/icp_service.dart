@@ -71,26 +71,30 @@ class ICPService {
         
         // Artifact Management
         'get_all_artifacts_public': IDL.Func(
-          [],
-          [IDL.Vec(IDL.Record({
-            'id': IDL.Nat64,
-            'name': IDL.Text,
-            'description': IDL.Text,
-            'metadata': IDL.Vec(IDL.Tuple(IDL.Text, IDL.Text)),
-            'images': IDL.Vec(IDL.Text),
-            'creator': IDL.Principal,
-            'created_at': IDL.Nat64,
-            'updated_at': IDL.Nat64,
-            'status': IDL.Variant({'PendingVerification': IDL.Null, 'Verified': IDL.Null, 'Disputed': IDL.Null, 'Rejected': IDL.Null}),
-            'heritage_proof': IDL.Opt(IDL.Text),
-            'authenticity_score': IDL.Nat32,
-            'history': IDL.Vec(IDL.Record({
-              'timestamp': IDL.Nat64,
-              'action': IDL.Text,
-              'actor': IDL.Principal,
-              'details': IDL.Text,
+          [IDL.Record({'cursor': IDL.Opt(IDL.Nat64), 'limit': IDL.Opt(IDL.Nat32)})],
+          [IDL.Record({
+            'items': IDL.Vec(IDL.Record({
+              'id': IDL.Nat64,
+              'name': IDL.Text,
+              'description': IDL.Text,
+              'metadata': IDL.Vec(IDL.Tuple(IDL.Text, IDL.Text)),
+              'images': IDL.Vec(IDL.Text),
+              'creator': IDL.Principal,
+              'created_at': IDL.Nat64,
+              'updated_at': IDL.Nat64,
+              'status': IDL.Variant({'PendingVerification': IDL.Null, 'Verified': IDL.Null, 'Disputed': IDL.Null, 'Rejected': IDL.Null}),
+              'heritage_proof': IDL.Opt(IDL.Text),
+              'authenticity_score': IDL.Nat32,
+              'history': IDL.Vec(IDL.Record({
+                'timestamp': IDL.Nat64,
+                'action': IDL.Text,
+                'actor': IDL.Principal,
+                'details': IDL.Text,
+              })),
             })),
-          }))],
+            'next_cursor': IDL.Opt(IDL.Nat64),
+            'total': IDL.Nat64,
+          })],
           ['query'],
         ),
         'get_artifact_public': IDL.Func(
@@ -147,18 +151,22 @@ class ICPService {
         
         // Proposal Management
         'get_all_proposals_public': IDL.Func(
-          [],
-          [IDL.Vec(IDL.Record({
-            'id': IDL.Nat64,
-            'proposal_type': IDL.Variant({'VerifyArtifact': IDL.Null, 'DisputeArtifact': IDL.Null, 'UpdateArtifactStatus': IDL.Null, 'GrantUserRole': IDL.Null}),
-            'title': IDL.Text,
-            'description': IDL.Text,
-            'status': IDL.Variant({'Active': IDL.Null, 'Passed': IDL.Null, 'Rejected': IDL.Null, 'Executed': IDL.Null}),
-            'created_at': IDL.Nat64,
-            'voting_deadline': IDL.Nat64,
-            'votes_for': IDL.Nat32,
-            'votes_against': IDL.Nat32,
-          }))],
+          [IDL.Record({'cursor': IDL.Opt(IDL.Nat64), 'limit': IDL.Opt(IDL.Nat32)})],
+          [IDL.Record({
+            'items': IDL.Vec(IDL.Record({
+              'id': IDL.Nat64,
+              'proposal_type': IDL.Variant({'VerifyArtifact': IDL.Null, 'DisputeArtifact': IDL.Null, 'UpdateArtifactStatus': IDL.Null, 'GrantUserRole': IDL.Null}),
+              'title': IDL.Text,
+              'description': IDL.Text,
+              'status': IDL.Variant({'Active': IDL.Null, 'Passed': IDL.Null, 'Rejected': IDL.Null, 'Executed': IDL.Null}),
+              'created_at': IDL.Nat64,
+              'voting_deadline': IDL.Nat64,
+              'votes_for': IDL.Nat32,
+              'votes_against': IDL.Nat32,
+            })),
+            'next_cursor': IDL.Opt(IDL.Nat64),
+            'total': IDL.Nat64,
+          })],
           ['query'],
         ),
         'get_proposal_public': IDL.Func(
@@ -188,15 +196,19 @@ class ICPService {
         
         // NFT Management
         'get_all_nfts_public': IDL.Func(
-          [],
-          [IDL.Vec(IDL.Record({
-            'id': IDL.Nat64,
-            'artifact_id': IDL.Nat64,
-            'owner': IDL.Principal,
-            'created_at': IDL.Nat64,
-            'metadata': IDL.Vec(IDL.Tuple(IDL.Text, IDL.Text)),
-            'is_transferable': IDL.Bool,
-          }))],
+          [IDL.Record({'cursor': IDL.Opt(IDL.Nat64), 'limit': IDL.Opt(IDL.Nat32)})],
+          [IDL.Record({
+            'items': IDL.Vec(IDL.Record({
+              'id': IDL.Nat64,
+              'artifact_id': IDL.Nat64,
+              'owner': IDL.Principal,
+              'created_at': IDL.Nat64,
+              'metadata': IDL.Vec(IDL.Tuple(IDL.Text, IDL.Text)),
+              'is_transferable': IDL.Bool,
+            })),
+            'next_cursor': IDL.Opt(IDL.Nat64),
+            'total': IDL.Nat64,
+          })],
           ['query'],
         ),
         'get_nfts_by_owner_public': IDL.Func(
@@ -251,6 +263,22 @@ class ICPService {
     }
   }
   
+  // Helper method to walk every page of a paginated list endpoint
+  Future<List<dynamic>> _collectPages(String methodName) async {
+    final items = <dynamic>[];
+    List<dynamic> cursor = [];
+    do {
+      final page = await _callActor<Map<String, dynamic>>(
+        methodName,
+        [{'cursor': cursor, 'limit': []}],
+        isQuery: true,
+      );
+      items.addAll(page['items'] as List);
+      cursor = page['next_cursor'] as List;
+    } while (cursor.isNotEmpty);
+    return items;
+  }
+  
   // User Management Methods
   Future<String> registerUser(
     UserRole role,
@@ -286,11 +314,7 @@ class ICPService {
   
   // Artifact Methods
   Future<List<Artifact>> getAllArtifacts() async {
-    final result = await _callActor<List<dynamic>>(
-      'get_all_artifacts_public',
-      [],
-      isQuery: true,
-    );
+    final result = await _collectPages('get_all_artifacts_public');
     
     return result.map((json) => Artifact.fromJson(json as Map<String, dynamic>)).toList();
   }
@@ -334,11 +358,7 @@ class ICPService {
   
   // Proposal Methods
   Future<List<ProposalResponse>> getAllProposals() async {
-    final result = await _callActor<List<dynamic>>(
-      'get_all_proposals_public',
-      [],
-      isQuery: true,
-    );
+    final result = await _collectPages('get_all_proposals_public');
     
     return result.map((json) => ProposalResponse.fromJson(json as Map<String, dynamic>)).toList();
   }
@@ -373,11 +393,7 @@ class ICPService {
   
   // NFT Methods
   Future<List<ProofOfHeritageNFT>> getAllNFTs() async {
-    final result = await _callActor<List<dynamic>>(
-      'get_all_nfts_public',
-      [],
-      isQuery: true,
-    );
+    final result = await _collectPages('get_all_nfts_public');
     
     return result.map((json) => ProofOfHeritageNFT.fromJson(json as Map<String, dynamic>)).toList();
   }
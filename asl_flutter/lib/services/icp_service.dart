@@ -71,6 +71,20 @@ class ICPService {
     throw Exception('Invalid result format');
   }
   
+  // Helper method to walk every page of a paginated list endpoint
+  Future<List<dynamic>> _collectPages(String methodName) async {
+    final items = <dynamic>[];
+    List<dynamic> cursor = [];
+    do {
+      final page = await _callMethod(methodName, [
+        {'cursor': cursor, 'limit': []}
+      ]) as Map<String, dynamic>;
+      items.addAll(page['items'] as List);
+      cursor = page['next_cursor'] as List;
+    } while (cursor.isNotEmpty);
+    return items;
+  }
+  
   // ========== USER MANAGEMENT ==========
   
   Future<String> registerUser(UserRole role, String? institution, List<String> specialization) async {
@@ -104,9 +118,13 @@ class ICPService {
     return _handleResult(result, (data) => data as int);
   }
   
-  Future<String> voteOnArtifact(int artifactId, bool vote) async {
-    final result = await _callMethod('vote_on_artifact_public', [artifactId, vote]);
-    return _handleResult(result, (data) => data as String);
+  Future<int> voteOnArtifact(int artifactId, bool vote, {int? expertiseRelevance}) async {
+    final result = await _callMethod('vote_on_artifact_public', [
+      artifactId,
+      vote,
+      expertiseRelevance == null ? [] : [expertiseRelevance],
+    ]);
+    return _handleResult(result, (data) => data as int);
   }
   
   Future<String> updateArtifactStatus(int artifactId, ArtifactStatus status) async {
@@ -120,8 +138,8 @@ class ICPService {
   }
   
   Future<List<Artifact>> getAllArtifacts() async {
-    final result = await _callMethod('get_all_artifacts_public', []);
-    return (result as List).map((item) => Artifact.fromJson(item)).toList();
+    final result = await _collectPages('get_all_artifacts_public');
+    return result.map((item) => Artifact.fromJson(item)).toList();
   }
   
   Future<List<Artifact>> searchArtifacts(String query) async {
@@ -167,8 +185,8 @@ class ICPService {
   }
   
   Future<List<ProposalResponse>> getAllProposals() async {
-    final result = await _callMethod('get_all_proposals_public', []);
-    return (result as List).map((item) => ProposalResponse.fromJson(item)).toList();
+    final result = await _collectPages('get_all_proposals_public');
+    return result.map((item) => ProposalResponse.fromJson(item)).toList();
   }
   
   Future<List<ProposalResponse>> getActiveProposals() async {
@@ -224,7 +242,7 @@ class ICPService {
   }
   
   Future<List<ProofOfHeritageNFT>> getAllNFTs() async {
-    final result = await _callMethod('get_all_nfts_public', []);
-    return (result as List).map((item) => ProofOfHeritageNFT.fromJson(item)).toList();
+    final result = await _collectPages('get_all_nfts_public');
+    return result.map((item) => ProofOfHeritageNFT.fromJson(item)).toList();
   }
 }
\ No newline at end of file
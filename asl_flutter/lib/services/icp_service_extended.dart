@@ -151,8 +151,10 @@ extension ICPServiceExtended on ICPService {
   }
   
   Future<List<AuditEntry>> getAuditLogs(int? limit) async {
-    final result = await _callMethod('get_audit_logs', [limit]);
-    return (result as List).map((item) => AuditEntry.fromJson(item)).toList();
+    final result = await _callMethod('get_audit_logs', [
+      {'cursor': [], 'limit': limit == null ? [] : [limit]}
+    ]);
+    return (result['items'] as List).map((item) => AuditEntry.fromJson(item)).toList();
   }
   
   Future<List<AuditEntry>> getSecurityAlerts() async {
@@ -12,30 +12,52 @@ use modules::utils::*;
 use modules::audit::log_audit_event;
 
 use modules::ai_analysis::{
-    AIAnalysisResult, add_provenance_entry, get_provenance_chain, verify_provenance_integrity,
+    AIAnalysisResult, ProvenanceIntegrityReport, add_provenance_entry, get_provenance_chain, verify_provenance_integrity,
     analyze_artifact_with_ai, get_ai_analysis, get_similar_artifacts
 };
 use modules::community::{
     create_community_post, create_community_reply, like_community_post, like_community_reply,
-    get_community_post, get_all_community_posts, get_community_posts_by_category,
+    get_community_post, list_community_posts, get_community_posts_by_category,
     get_community_posts_by_author, search_community_posts, get_community_stats,
-    get_featured_posts, get_pinned_posts, moderate_post,
+    get_featured_posts, get_pinned_posts, moderate_post, recount_active_posts,
     CreatePostRequest, CreateReplyRequest, CommunityCategory, ModerationAction
 };
 
 use modules::artifacts::{
     create_artifact, update_artifact_status,
-    get_artifact, get_all_artifacts, get_artifacts_by_status, get_artifacts_by_creator, search_artifacts
+    get_artifact, list_artifacts, get_artifacts_by_status, get_artifacts_by_creator, search_artifacts,
+    get_artifacts_by_tag, get_artifacts_by_country
 };
+use modules::indexes::{count_artifacts_by_status, rebuild_artifact_index};
+use modules::search::{SearchHit, ensure_search_index};
+use modules::query::{ArtifactFilter, ArtifactSort, ArtifactQueryResult};
+use modules::geo::{BoundingBox, NearbyArtifact, rebuild_geo_index};
+use modules::media::migrate_inline_images;
+use modules::certificates::seal_unsealed_certificates;
+use modules::icrc7::backfill_mint_blocks;
+use modules::scheduler::start_proposal_scheduler;
+use modules::reviews::vote_on_artifact;
 use modules::dao::{
     create_proposal, execute_proposal,
-    get_proposal, get_all_proposals, get_active_proposals, get_proposals_by_status,
-    add_comment_to_proposal
+    get_proposal, list_proposals, get_active_proposals, get_proposals_by_status,
+    add_comment_to_proposal, rebuild_proposal_schedule
+};
+use modules::voting::{vote_on_proposal, get_vote_details, change_vote, rebuild_vote_index};
+use modules::delegation::rebuild_delegate_index;
+use modules::migrations::{
+    MigrationReport, QuarantinedRecord, run_schema_migrations, get_last_migration_reports, migrate_id_counters
+};
+use modules::lifecycle::{
+    MemoryAssignment, UpgradeTask, verify_memory_layout, record_memory_layout, save_heap_state, restore_heap_state,
+    get_recorded_memory_layout, run_upgrade_task, mark_upgrade_tasks_current
+};
+use modules::certification::{
+    CertifiedArtifact, CertifiedNFT, get_certified_artifact, get_certified_nft, rebuild_certified_tree
 };
-use modules::voting::{vote_on_proposal, get_vote_details, change_vote};
 use modules::nft::{
     issue_heritage_nft, add_expert_endorsement, update_nft_access_rights,
-    get_nft, get_nft_by_artifact, get_nfts_by_owner, get_all_nfts
+    get_nft, get_nft_by_artifact, get_nft_by_certificate_number, get_nfts_by_owner, list_nfts,
+    rebuild_nft_owner_index
 };
 
 
@@ -76,40 +98,7 @@ fn register_user(role: UserRole, institution: Option<String>, specialization: Ve
     let now = get_time();
     
     // Determine initial permissions based on role
-    let permissions = match role {
-        UserRole::Institution => UserPermissions {
-            can_submit_artifacts: true,
-            can_create_proposals: true,
-            can_vote: true, 
-            can_verify_institutions: true,
-            can_moderate: true,
-            voting_weight: 3,
-        },
-        UserRole::Expert => UserPermissions {
-            can_submit_artifacts: true,
-            can_create_proposals: true,
-            can_vote: false, 
-            can_verify_institutions: false,
-            can_moderate: false,
-            voting_weight: 2,
-        },
-        UserRole::Community => UserPermissions {
-            can_submit_artifacts: false,
-            can_create_proposals: false,
-            can_vote: true,
-            can_verify_institutions: false,
-            can_moderate: false,
-            voting_weight: 1,
-        },
-        _ => UserPermissions {
-            can_submit_artifacts: false,
-            can_create_proposals: false,
-            can_vote: false,
-            can_verify_institutions: false,
-            can_moderate: false,
-            voting_weight: 1,
-        },
-    };
+    let permissions = default_permissions_for_role(&role);
 
     let user = User {
         role: role.clone(),
@@ -182,7 +171,7 @@ fn get_provenance_chain_public(artifact_id: u64) -> Result<Vec<HistoryEntry>, St
 }
 
 #[query]
-fn verify_provenance_integrity_public(artifact_id: u64) -> Result<bool, String> {
+fn verify_provenance_integrity_public(artifact_id: u64) -> Result<ProvenanceIntegrityReport, String> {
     verify_provenance_integrity(artifact_id)
 }
 
@@ -197,12 +186,14 @@ fn vote_on_proposal_public(
     proposal_id: u64,
     vote_type: VoteType,
     rationale: Option<String>,
+    credits: Option<u32>,
 ) -> Result<String, String> {
     let request = VoteRequest {
         proposal_id,
         vote_type,
         rationale,
         expertise_relevance: Some(1), // Use available enum
+        credits,
     };
     vote_on_proposal(request)
 }
@@ -252,8 +243,8 @@ fn get_proposal_public(proposal_id: u64) -> Result<Proposal, String> {
 }
 
 #[query]
-fn get_all_proposals_public() -> Vec<ProposalResponse> {
-    get_all_proposals()
+fn get_all_proposals_public(page: PageRequest) -> Page<ProposalResponse> {
+    list_proposals(page)
 }
 
 #[query]
@@ -285,22 +276,23 @@ fn submit_artifact_public(
         images: vec![image_url],
         metadata: Vec::new(),
         heritage_proof: Some("user_submission".to_string()),
+        cultural_significance: None,
+        geographic_origin: None,
+        dating_information: None,
+        physical_properties: None,
+        conservation_status: None,
+        media: None,
     };
     
     create_artifact(request)
 }
 
+// Votes in the artifact's verification round, opening one if needed, and
+// returns the round's proposal id
 #[update]
-fn vote_on_artifact_public(artifact_id: u64, vote: bool) -> Result<String, String> {
-    // Convert boolean to VoteType using available variants
+fn vote_on_artifact_public(artifact_id: u64, vote: bool, expertise_relevance: Option<u32>) -> Result<u64, String> {
     let vote_type = if vote { VoteType::For } else { VoteType::Against };
-    let request = VoteRequest {
-        proposal_id: artifact_id, // Use artifact_id as proposal_id for voting
-        vote_type,
-        rationale: None,
-        expertise_relevance: Some(1),
-    };
-    vote_on_proposal(request)
+    vote_on_artifact(artifact_id, vote_type, None, expertise_relevance)
 }
 
 #[update]
@@ -317,8 +309,8 @@ fn get_artifact_public(artifact_id: u64) -> Result<Artifact, String> {
 }
 
 #[query]
-fn get_all_artifacts_public() -> Vec<Artifact> {
-    get_all_artifacts()
+fn get_all_artifacts_public(page: PageRequest) -> Page<Artifact> {
+    list_artifacts(page)
 }
 
 #[query]
@@ -326,6 +318,36 @@ fn search_artifacts_public(query: String) -> Vec<Artifact> {
     search_artifacts(query)
 }
 
+#[query]
+fn search_artifacts_ranked_public(query: String, page: PageRequest) -> Page<SearchHit> {
+    modules::search::search(&query, &page)
+}
+
+#[query]
+fn query_artifacts(filter: ArtifactFilter, sort: ArtifactSort, page: PageRequest) -> ArtifactQueryResult {
+    modules::query::query_artifacts(filter, sort, page)
+}
+
+#[query]
+fn find_artifacts_near_public(latitude: f64, longitude: f64, radius_km: f64, page: PageRequest) -> Result<Page<NearbyArtifact>, String> {
+    modules::geo::find_artifacts_near(latitude, longitude, radius_km, &page)
+}
+
+#[query]
+fn find_artifacts_in_bounding_box_public(bbox: BoundingBox, page: PageRequest) -> Result<Page<Artifact>, String> {
+    modules::geo::find_artifacts_in_bounding_box(bbox, &page)
+}
+
+#[query]
+fn find_artifacts_in_polygon_public(polygon: Vec<(f64, f64)>, page: PageRequest) -> Result<Page<Artifact>, String> {
+    modules::geo::find_artifacts_in_polygon(polygon, &page)
+}
+
+#[query]
+fn export_artifacts_geojson_public(bbox: Option<BoundingBox>, page: PageRequest) -> Result<String, String> {
+    modules::geo::export_artifacts_geojson(bbox, &page)
+}
+
 #[query]
 fn get_artifacts_by_status_public(status: ArtifactStatus) -> Vec<Artifact> {
     get_artifacts_by_status(status)
@@ -336,6 +358,21 @@ fn get_artifacts_by_creator_public(creator: Principal) -> Vec<Artifact> {
     get_artifacts_by_creator(creator)
 }
 
+#[query]
+fn get_artifacts_by_tag_public(tag: String) -> Vec<Artifact> {
+    get_artifacts_by_tag(tag)
+}
+
+#[query]
+fn get_artifacts_by_country_public(country: String) -> Vec<Artifact> {
+    get_artifacts_by_country(country)
+}
+
+#[query]
+fn get_certified_artifact_public(artifact_id: u64) -> Result<CertifiedArtifact, String> {
+    get_certified_artifact(artifact_id)
+}
+
 // ============================================================================
 // NFT HERITAGE CERTIFICATE SERVICES
 // Non-fungible token system for heritage authentication and ownership tracking
@@ -367,14 +404,24 @@ fn get_nft_by_artifact_public(artifact_id: u64) -> Result<ProofOfHeritageNFT, St
     get_nft_by_artifact(artifact_id)
 }
 
+#[query]
+fn get_nft_by_certificate_public(certificate_number: String) -> Result<ProofOfHeritageNFT, String> {
+    get_nft_by_certificate_number(certificate_number)
+}
+
 #[query]
 fn get_nfts_by_owner_public(owner: Principal) -> Vec<ProofOfHeritageNFT> {
     get_nfts_by_owner(owner)
 }
 
 #[query]
-fn get_all_nfts_public() -> Vec<ProofOfHeritageNFT> {
-    get_all_nfts()
+fn get_all_nfts_public(page: PageRequest) -> Page<ProofOfHeritageNFT> {
+    list_nfts(page)
+}
+
+#[query]
+fn get_certified_nft_public(nft_id: u64) -> Result<CertifiedNFT, String> {
+    get_certified_nft(nft_id)
 }
 
 // ============================================================================
@@ -541,8 +588,8 @@ fn get_community_post_public(post_id: u64) -> Result<modules::community::Communi
 }
 
 #[query]
-fn get_all_community_posts_public() -> Vec<modules::community::CommunityPost> {
-    get_all_community_posts()
+fn get_all_community_posts_public(page: PageRequest) -> Page<modules::community::CommunityPost> {
+    list_community_posts(page)
 }
 
 #[query]
@@ -591,7 +638,7 @@ fn get_system_stats() -> SystemStats {
     let total_users = USERS.with(|users| users.borrow().len() as u64);
     let total_nfts = NFTS.with(|nfts| nfts.borrow().len() as u64);
     
-    let verified_artifacts = get_artifacts_by_status(ArtifactStatus::Verified).len() as u64;
+    let verified_artifacts = count_artifacts_by_status(&ArtifactStatus::Verified);
     let active_proposals = get_active_proposals().len() as u64;
 
     SystemStats {
@@ -606,9 +653,8 @@ fn get_system_stats() -> SystemStats {
 }
 
 #[query]
-fn get_audit_logs(limit: Option<u64>) -> Vec<AuditEntry> {
-    let limit = limit.unwrap_or(50) as usize;
-    modules::audit::get_recent_audit_logs(limit)
+fn get_audit_logs(page: PageRequest) -> Page<AuditEntry> {
+    modules::audit::list_audit_logs(page)
 }
 
 #[query]
@@ -616,6 +662,24 @@ fn get_security_alerts() -> Vec<AuditEntry> {
     modules::audit::get_security_alerts()
 }
 
+#[query]
+fn get_memory_layout() -> Vec<MemoryAssignment> {
+    get_recorded_memory_layout()
+}
+
+#[query]
+fn get_migration_reports() -> Vec<MigrationReport> {
+    get_last_migration_reports()
+}
+
+#[query]
+fn get_quarantined_records() -> Result<Vec<QuarantinedRecord>, String> {
+    if !can_moderate(get_caller()) {
+        return Err("Only moderators can inspect quarantined records".to_string());
+    }
+    Ok(modules::migrations::get_quarantined_records())
+}
+
 // ============================================================================
 // CANISTER LIFECYCLE
 // ============================================================================
@@ -623,6 +687,11 @@ fn get_security_alerts() -> Vec<AuditEntry> {
 #[init]
 fn init() {
     // Initialize any required state
+    record_memory_layout();
+    mark_upgrade_tasks_current();
+    rebuild_certified_tree();
+    ensure_search_index();
+    start_proposal_scheduler();
     log_audit_event(
         AuditEventType::SystemMaintenance,
         None,
@@ -633,7 +702,8 @@ fn init() {
 
 #[pre_upgrade]
 fn pre_upgrade() {
-    // Any cleanup before upgrade
+    // Heap-only state would otherwise be lost with the Wasm heap
+    save_heap_state();
     log_audit_event(
         AuditEventType::SystemMaintenance,
         None,
@@ -644,7 +714,82 @@ fn pre_upgrade() {
 
 #[post_upgrade]
 fn post_upgrade() {
-    // Any setup after upgrade
+    // Refuse the upgrade before touching any memory if store ids moved
+    verify_memory_layout();
+    // Schema migrations must run before anything reads the stores
+    let migration_reports = run_schema_migrations();
+    restore_heap_state();
+    // Store-sized work only runs when its task version changed
+    let renumbered_entries = run_upgrade_task(UpgradeTask::IdCounters, migrate_id_counters);
+    let migrated_images = run_upgrade_task(UpgradeTask::InlineImages, migrate_inline_images);
+    let sealed_certificates = run_upgrade_task(UpgradeTask::CertificateDocuments, seal_unsealed_certificates);
+    run_upgrade_task(UpgradeTask::CommunityPostCount, recount_active_posts);
+    let minted_blocks = backfill_mint_blocks();
+    // The certified tree lives on the heap and is always rebuilt
+    rebuild_certified_tree();
+    run_upgrade_task(UpgradeTask::ArtifactIndex, rebuild_artifact_index);
+    run_upgrade_task(UpgradeTask::GeoIndex, rebuild_geo_index);
+    run_upgrade_task(UpgradeTask::NftOwnerIndex, rebuild_nft_owner_index);
+    run_upgrade_task(UpgradeTask::ProposalSchedule, rebuild_proposal_schedule);
+    run_upgrade_task(UpgradeTask::VoteIndex, rebuild_vote_index);
+    run_upgrade_task(UpgradeTask::DelegateIndex, rebuild_delegate_index);
+    ensure_search_index();
+    start_proposal_scheduler();
+
+    for report in migration_reports {
+        if report.migrated == 0 && report.failed == 0 {
+            continue;
+        }
+        log_audit_event(
+            AuditEventType::SystemMaintenance,
+            None,
+            format!(
+                "Schema migration of {} to v{}: {} migrated, {} failed, {} up to date",
+                report.store, report.schema_version, report.migrated, report.failed, report.up_to_date
+            ),
+            if report.failed > 0 { AuditSeverity::Warning } else { AuditSeverity::Info }
+        );
+    }
+
+    if !renumbered_entries.is_empty() {
+        let moves: Vec<String> = renumbered_entries.iter()
+            .map(|entry| format!("artifact {}: {} -> {}", entry.artifact_id, entry.old_id, entry.new_id))
+            .collect();
+        log_audit_event(
+            AuditEventType::SystemMaintenance,
+            None,
+            format!("Renumbered {} colliding history entries ({})", renumbered_entries.len(), moves.join(", ")),
+            AuditSeverity::Info
+        );
+    }
+
+    if migrated_images > 0 {
+        log_audit_event(
+            AuditEventType::SystemMaintenance,
+            None,
+            format!("Moved {} inline artifact images to the media store", migrated_images),
+            AuditSeverity::Info
+        );
+    }
+
+    if sealed_certificates > 0 {
+        log_audit_event(
+            AuditEventType::SystemMaintenance,
+            None,
+            format!("Sealed {} heritage certificate documents", sealed_certificates),
+            AuditSeverity::Info
+        );
+    }
+
+    if minted_blocks > 0 {
+        log_audit_event(
+            AuditEventType::SystemMaintenance,
+            None,
+            format!("Recorded {} existing Heritage NFTs in the ICRC-3 log", minted_blocks),
+            AuditSeverity::Info
+        );
+    }
+
     log_audit_event(
         AuditEventType::SystemMaintenance,
         None,
@@ -694,8 +839,24 @@ pub struct HealthStatus {
 #[cfg(test)]
 mod candid_tests {
     use super::*;
+    use crate::modules::ai_analysis::ProvenanceEventType;
+    use crate::modules::community::{CommunityPost, CommunityStats};
+    use crate::modules::changes::FieldChange;
+    use crate::modules::media::MediaAsset;
+    use crate::modules::http::{HttpRequest, HttpResponse, StreamingCallbackHttpResponse, StreamingCallbackToken};
+    use crate::modules::certificates::{CertificateDocument, CertificateVerification};
+    use crate::modules::signing::{SignatureRecord, SignatureScheme, SignedSubject, SigningPublicKey};
+    use crate::modules::icrc3::{ArchiveInfo, GetArchivesArgs, GetBlocksArgs, GetBlocksResult, Icrc3Value, SupportedBlockType};
+    use candid::Nat;
+    use crate::modules::custody::{CustodyTransfer, CustodyTransferRequest};
+    use crate::modules::scheduler::{SchedulerConfig, SchedulerRun};
+    use crate::modules::governance::GovernanceRule;
+    use crate::modules::delegation::{Delegation, DelegationScope};
+    use crate::modules::icrc7::{Account, SupportedStandard, TransferArg, TransferResult};
+    candid::export_service!();
+
     #[test]
     fn print_candid() {
-        println!("{}", candid::export_service!());
+        println!("{}", __export_service());
     }
 }
@@ -1,12 +1,12 @@
 use ic_cdk::{query, update};
-use candid::CandidType;
+use candid::{CandidType, Principal};
 use serde::{Deserialize, Serialize};
 use crate::modules::types::*;
-use crate::modules::storage::{AI_ANALYSES, get_next_id};
+use crate::modules::storage::{AI_ANALYSES, ARTIFACTS, get_next_id, IdKind};
 use crate::modules::auth::*;
 use crate::modules::utils::*;
 use crate::modules::audit::log_audit_event;
-use crate::{ARTIFACTS, get_all_artifacts};
+use crate::modules::artifacts::{append_history_entry, store_artifact, get_all_artifacts};
 
 // ============================================================================
 // AI-POWERED ARTIFACT ANALYSIS SYSTEM
@@ -203,6 +203,31 @@ pub enum ProvenanceEventType {
     DigitalRegistration,
 }
 
+#[derive(CandidType, Serialize, Deserialize, Clone, Debug)]
+pub struct ProvenanceIntegrityReport {
+    pub artifact_id: u64,
+    pub entries_checked: u64,
+    pub is_valid: bool,
+    pub failures: Vec<IntegrityFailure>,
+}
+
+#[derive(CandidType, Serialize, Deserialize, Clone, Debug)]
+pub struct IntegrityFailure {
+    pub entry_index: u64,
+    pub entry_id: u64,
+    pub reason: IntegrityFailureReason,
+    pub expected_hash: Option<String>,
+    pub actual_hash: Option<String>,
+}
+
+#[derive(CandidType, Serialize, Deserialize, Clone, Debug)]
+pub enum IntegrityFailureReason {
+    // `previous_hash` does not point at the preceding entry's `immutable_hash`
+    BrokenLink,
+    // The entry content no longer matches its `immutable_hash`
+    HashMismatch,
+}
+
 #[update]
 pub fn add_provenance_entry(
     artifact_id: u64,
@@ -217,35 +242,8 @@ pub fn add_provenance_entry(
         return Err("Insufficient permissions to add provenance entry".to_string());
     }
 
-    let entry_id = get_next_id(10); // Provenance entry counter
-    let timestamp = get_time();
-    
-    // Get artifact to add to its history
-    let artifact_exists = ARTIFACTS.with(|artifacts| {
-        artifacts.borrow().contains_key(&artifact_id)
-    });
-
-    if !artifact_exists {
-        return Err("Artifact not found".to_string());
-    }
-
-    // Add to artifact history instead of separate provenance chain
-    ARTIFACTS.with(|artifacts| {
-        let mut artifacts = artifacts.borrow_mut();
-        if let Some(mut artifact) = artifacts.get(&artifact_id) {
-            let history_entry = HistoryEntry {
-                id: entry_id,
-                timestamp,
-                action: format!("Provenance: {:?}", event_type),
-                actor: caller,
-                details: format!("Location: {:?}, Custodian: {:?}", location, custodian),
-                evidence: if documentation.is_empty() { None } else { Some(documentation.join(", ")) },
-                immutable_hash: create_hash(&format!("{:?}:{}:{}", event_type, timestamp, caller)),
-            };
-            artifact.history.push(history_entry);
-            artifacts.insert(artifact_id, artifact);
-        }
-    });
+    let details = format!("Location: {:?}, Custodian: {:?}", location, custodian);
+    let entry_id = append_provenance_entry(artifact_id, event_type.clone(), caller, details, &documentation)?;
 
     log_audit_event(
         AuditEventType::DataModification,
@@ -257,6 +255,36 @@ pub fn add_provenance_entry(
     Ok(entry_id)
 }
 
+// Appends a provenance entry to the artifact's history and has it signed
+pub fn append_provenance_entry(
+    artifact_id: u64,
+    event_type: ProvenanceEventType,
+    actor: Principal,
+    details: String,
+    documentation: &[String],
+) -> Result<u64, String> {
+    let entry_id = ARTIFACTS.with(|artifacts| {
+        let mut artifacts = artifacts.borrow_mut();
+        let mut artifact = artifacts.get(&artifact_id).ok_or_else(|| "Artifact not found".to_string())?;
+        let entry_id = get_next_id(IdKind::HistoryEntry);
+        let history_entry = HistoryEntry {
+            id: entry_id,
+            timestamp: get_time(),
+            action: format!("Provenance: {:?}", event_type),
+            actor,
+            details,
+            evidence: if documentation.is_empty() { None } else { Some(documentation.join(", ")) },
+            previous_hash: None,
+            immutable_hash: String::new(),
+        };
+        append_history_entry(&mut artifact, history_entry);
+        store_artifact(&mut artifacts, artifact);
+        Ok::<_, String>(entry_id)
+    })?;
+    // Signed on demand through signing::sign_record, not on every append
+    Ok(entry_id)
+}
+
 #[query]
 pub fn get_provenance_chain(artifact_id: u64) -> Result<Vec<HistoryEntry>, String> {
     ARTIFACTS.with(|artifacts| {
@@ -267,16 +295,129 @@ pub fn get_provenance_chain(artifact_id: u64) -> Result<Vec<HistoryEntry>, Strin
 }
 
 #[query]
-pub fn verify_provenance_integrity(artifact_id: u64) -> Result<bool, String> {
+pub fn verify_provenance_integrity(artifact_id: u64) -> Result<ProvenanceIntegrityReport, String> {
     let history = get_provenance_chain(artifact_id)?;
-    
-    // Verify history integrity by checking if all entries are valid
-    for entry in &history {
-        let expected_hash = create_hash(&format!("{}:{}:{}", entry.action, entry.timestamp, entry.actor));
+    let mut failures = Vec::new();
+    let mut previous_hash: Option<&String> = None;
+
+    for (index, entry) in history.iter().enumerate() {
+        // Every entry must commit to the hash of the entry before it
+        if entry.previous_hash.as_ref() != previous_hash {
+            failures.push(IntegrityFailure {
+                entry_index: index as u64,
+                entry_id: entry.id,
+                reason: IntegrityFailureReason::BrokenLink,
+                expected_hash: previous_hash.cloned(),
+                actual_hash: entry.previous_hash.clone(),
+            });
+        }
+
+        let expected_hash = hash_history_entry(artifact_id, entry);
         if entry.immutable_hash != expected_hash {
-            return Ok(false);
+            failures.push(IntegrityFailure {
+                entry_index: index as u64,
+                entry_id: entry.id,
+                reason: IntegrityFailureReason::HashMismatch,
+                expected_hash: Some(expected_hash),
+                actual_hash: Some(entry.immutable_hash.clone()),
+            });
         }
+
+        previous_hash = Some(&entry.immutable_hash);
+    }
+
+    Ok(ProvenanceIntegrityReport {
+        artifact_id,
+        entries_checked: history.len() as u64,
+        is_valid: failures.is_empty(),
+        failures,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use candid::Encode;
+    use ic_stable_structures::Storable;
+    use std::borrow::Cow;
+    use crate::modules::testing::{artifact_request, create_artifact_as, principal, signature_requests};
+
+    // An artifact with a creation entry and two provenance entries
+    fn artifact_with_provenance() -> u64 {
+        let artifact_id = create_artifact_as(principal(1), artifact_request("Carved lintel"));
+        for event_type in [ProvenanceEventType::Excavation, ProvenanceEventType::Transfer] {
+            add_provenance_entry(artifact_id, event_type, Some("Luxor".to_string()), None, Vec::new()).unwrap();
+        }
+        artifact_id
+    }
+
+    // Rewrites the stored history without going through the chaining writers
+    fn tamper<F: FnOnce(&mut Vec<HistoryEntry>)>(artifact_id: u64, change: F) {
+        ARTIFACTS.with(|artifacts| {
+            let mut artifact = artifacts.borrow().get(&artifact_id).unwrap();
+            change(&mut artifact.history);
+            artifacts.borrow_mut().insert(artifact_id, artifact);
+        });
+    }
+
+    #[test]
+    fn intact_chains_verify() {
+        let report = verify_provenance_integrity(artifact_with_provenance()).unwrap();
+        assert!(report.is_valid);
+        assert_eq!(report.entries_checked, 3);
+    }
+
+    #[test]
+    fn reports_an_altered_entry() {
+        let artifact_id = artifact_with_provenance();
+        tamper(artifact_id, |history| history[1].details = "Location: Cairo".to_string());
+
+        let report = verify_provenance_integrity(artifact_id).unwrap();
+        assert!(!report.is_valid);
+        assert_eq!(report.failures.len(), 1);
+        let failure = &report.failures[0];
+        assert!(matches!(failure.reason, IntegrityFailureReason::HashMismatch));
+        assert_eq!(failure.entry_index, 1);
+        assert_ne!(failure.expected_hash, failure.actual_hash);
+    }
+
+    #[test]
+    fn reports_a_broken_link() {
+        let artifact_id = artifact_with_provenance();
+        // Dropping an entry leaves the next one pointing at a hash that is gone
+        tamper(artifact_id, |history| { history.remove(1); });
+
+        let report = verify_provenance_integrity(artifact_id).unwrap();
+        assert_eq!(report.failures.len(), 1);
+        let failure = &report.failures[0];
+        assert!(matches!(failure.reason, IntegrityFailureReason::BrokenLink));
+        assert_eq!(failure.entry_index, 1);
+        let history = get_provenance_chain(artifact_id).unwrap();
+        assert_eq!(failure.expected_hash.as_ref(), Some(&history[0].immutable_hash));
+    }
+
+    #[test]
+    fn provenance_entries_are_not_signed_on_append() {
+        artifact_with_provenance();
+        assert!(signature_requests().is_empty());
+    }
+
+    #[test]
+    fn legacy_entries_verify_once_migrated() {
+        let artifact_id = artifact_with_provenance();
+        let mut artifact = ARTIFACTS.with(|artifacts| artifacts.borrow().get(&artifact_id)).unwrap();
+        for (i, entry) in artifact.history.iter_mut().enumerate() {
+            entry.previous_hash = None;
+            entry.immutable_hash = format!("{:016x}", i);
+        }
+
+        // Unsealed, every entry after the first fails its link and every hash fails
+        ARTIFACTS.with(|artifacts| artifacts.borrow_mut().insert(artifact_id, artifact.clone()));
+        assert_eq!(verify_provenance_integrity(artifact_id).unwrap().failures.len(), 5);
+
+        // Records written before the chain existed are bare Candid and sealed when read
+        let legacy = Artifact::from_bytes(Cow::Owned(Encode!(&artifact).unwrap()));
+        ARTIFACTS.with(|artifacts| artifacts.borrow_mut().insert(artifact_id, legacy));
+        assert!(verify_provenance_integrity(artifact_id).unwrap().is_valid);
     }
-    
-    Ok(true)
 }
@@ -3,7 +3,7 @@ use candid::{CandidType, Principal};
 use serde::{Deserialize, Serialize};
 use crate::modules::types::*;
 use crate::modules::ai_analysis::RiskLevel;
-use crate::modules::storage::{ANALYTICS_REPORTS, PATTERN_ANALYSES, ARTIFACTS, USERS, PROPOSALS, get_next_id};
+use crate::modules::storage::{ANALYTICS_REPORTS, PATTERN_ANALYSES, ARTIFACTS, USERS, PROPOSALS, get_next_id, IdKind};
 use crate::modules::auth::*;
 use crate::modules::utils::*;
 use std::collections::HashMap;
@@ -176,7 +176,7 @@ pub fn generate_analytics_report(
     let start = start_time.unwrap_or(now - (30 * 24 * 60 * 60 * 1000_000_000)); // 30 days ago
     let end = end_time.unwrap_or(now);
 
-    let report_id = get_next_id(14); // Analytics report counter
+    let report_id = get_next_id(IdKind::AnalyticsReport);
 
     // Generate metrics based on report type
     let (metrics, insights, visualizations, recommendations) = match report_type {
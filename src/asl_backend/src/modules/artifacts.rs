@@ -5,6 +5,15 @@ use crate::modules::storage::*;
 use crate::modules::auth::*;
 use crate::modules::utils::*;
 use crate::modules::audit::log_audit_event;
+use crate::modules::certification::certify_artifact;
+use crate::modules::indexes::{
+    artifact_index_keys, update_artifact_index, find_artifacts_by_status, find_artifacts_by_creator,
+    find_artifacts_by_tag, find_artifacts_by_country
+};
+use crate::modules::search::{search_document, update_search_index, parse_query, rank_artifacts};
+use crate::modules::geo::{artifact_point, update_geo_index, validate_geographic_origin};
+use crate::modules::media::{validate_image_url, validate_media_ids};
+use crate::modules::changes::{artifact_at, diff_artifacts, encode_changes, FieldChange};
 
 // ============================================================================
 // ARTIFACT MANAGEMENT SYSTEM
@@ -32,50 +41,64 @@ pub fn create_artifact(request: CreateArtifactRequest) -> Result<u64, String> {
     //     return Err("At least one image is required".to_string());
     // }
 
-    let artifact_id = get_next_id(1); // Artifact ID counter
+    validate_descriptive_fields(
+        request.cultural_significance.as_ref(),
+        request.geographic_origin.as_ref(),
+        request.dating_information.as_ref(),
+        request.physical_properties.as_ref(),
+    )?;
+    for image in &request.images {
+        validate_image_url(image)?;
+    }
+    let media = request.media.unwrap_or_default();
+    validate_media_ids(&media, caller)?;
+
+    let artifact_id = get_next_id(IdKind::Artifact);
     let now = get_time();
     
-    // Create initial history entry
-    let history_entry = HistoryEntry {
-        id: get_next_id(8), // History entry ID counter
-        timestamp: now,
-        action: "Created".to_string(),
-        actor: caller,
-        details: "Initial artifact submission".to_string(),
-        evidence: None,
-        immutable_hash: create_hash(&format!("{}:{}:{}", artifact_id, caller, now)),
-    };
-
-    let artifact = Artifact {
+    let mut artifact = Artifact {
         id: artifact_id,
         name: request.name.clone(),
         description: request.description.clone(),
         metadata: request.metadata,
         images: request.images,
+        media,
         creator: caller,
         created_at: now,
         updated_at: now,
         status: ArtifactStatus::PendingVerification,
         heritage_proof: request.heritage_proof,
         authenticity_score: 0,
-        history: vec![history_entry],
+        history: Vec::new(),
         verification_level: VerificationLevel::Unverified,
-        cultural_significance: CulturalSignificance {
+        cultural_significance: request.cultural_significance.unwrap_or(CulturalSignificance {
             historical_period: None,
             cultural_group: None,
             significance_level: SignificanceLevel::Local,
             unesco_status: None,
             cultural_tags: Vec::new(),
-        },
-        geographic_origin: None,
-        dating_information: None,
-        physical_properties: None,
-        conservation_status: ConservationStatus::Good,
+        }),
+        geographic_origin: request.geographic_origin,
+        dating_information: request.dating_information,
+        physical_properties: request.physical_properties,
+        conservation_status: request.conservation_status.unwrap_or(ConservationStatus::Good),
         digital_fingerprint: Some(create_hash(&format!("{}:{}", artifact_id, now))),
     };
 
+    // Create initial history entry
+    append_history_entry(&mut artifact, HistoryEntry {
+        id: get_next_id(IdKind::HistoryEntry),
+        timestamp: now,
+        action: "Created".to_string(),
+        actor: caller,
+        details: encode_changes("Initial artifact submission", &[]),
+        evidence: None,
+        previous_hash: None,
+        immutable_hash: String::new(),
+    });
+
     ARTIFACTS.with(|artifacts| {
-        artifacts.borrow_mut().insert(artifact_id, artifact);
+        store_artifact(&mut artifacts.borrow_mut(), artifact);
     });
 
     // Update user stats
@@ -98,6 +121,70 @@ pub fn create_artifact(request: CreateArtifactRequest) -> Result<u64, String> {
     Ok(artifact_id)
 }
 
+fn validate_descriptive_fields(
+    cultural_significance: Option<&CulturalSignificance>,
+    geographic_origin: Option<&GeographicOrigin>,
+    dating_information: Option<&DatingInformation>,
+    physical_properties: Option<&PhysicalProperties>,
+) -> Result<(), String> {
+    if let Some(significance) = cultural_significance {
+        validate_cultural_significance(significance)?;
+    }
+    if let Some(origin) = geographic_origin {
+        validate_geographic_origin(origin)?;
+    }
+    if let Some(dating) = dating_information {
+        validate_dating_information(dating)?;
+    }
+    if let Some(properties) = physical_properties {
+        validate_physical_properties(properties)?;
+    }
+    Ok(())
+}
+
+// Links `entry` to the artifact's latest history entry, seals it with its
+// SHA-256 hash and appends it to the history.
+pub fn append_history_entry(artifact: &mut Artifact, mut entry: HistoryEntry) {
+    entry.previous_hash = artifact.history.last().map(|last| last.immutable_hash.clone());
+    entry.immutable_hash = hash_history_entry(artifact.id, &entry);
+    artifact.history.push(entry);
+}
+
+// Stamps `artifact` as updated now and appends a history entry holding the
+// field-level diff against `before`.
+pub fn record_changes(artifact: &mut Artifact, before: &Artifact, action: &str, note: &str, evidence: Option<String>) {
+    let now = get_time();
+    artifact.updated_at = now;
+    let changes = diff_artifacts(before, artifact);
+    append_history_entry(artifact, HistoryEntry {
+        id: get_next_id(IdKind::HistoryEntry),
+        timestamp: now,
+        action: action.to_string(),
+        actor: get_caller(),
+        details: encode_changes(note, &changes),
+        evidence,
+        previous_hash: None,
+        immutable_hash: String::new(),
+    });
+}
+
+// Every write to ARTIFACTS goes through here so the certified tree, the
+// secondary, search and geo indexes never drift from the stored record.
+pub fn store_artifact(artifacts: &mut ArtifactStore, artifact: Artifact) {
+    let artifact_id = artifact.id;
+    let new_keys = artifact_index_keys(&artifact);
+    let new_document = search_document(&artifact);
+    let new_point = artifact_point(&artifact);
+    certify_artifact(&artifact);
+    let previous = artifacts.insert(artifact_id, artifact);
+    update_artifact_index(
+        previous.as_ref().map(artifact_index_keys).unwrap_or_default(),
+        new_keys
+    );
+    update_search_index(artifact_id, previous.as_ref().map(search_document), new_document);
+    update_geo_index(artifact_id, previous.as_ref().and_then(artifact_point), new_point);
+}
+
 #[update]
 pub fn update_artifact_metadata(artifact_id: u64, new_metadata: Vec<(String, String)>) -> Result<String, String> {
     let caller = get_caller();
@@ -111,24 +198,11 @@ pub fn update_artifact_metadata(artifact_id: u64, new_metadata: Vec<(String, Str
                 return Err("You don't have permission to update this artifact".to_string());
             }
 
-            let now = get_time();
-            
-            // Add history entry
-            let history_entry = HistoryEntry {
-                id: get_next_id(8),
-                timestamp: now,
-                action: "MetadataUpdated".to_string(),
-                actor: caller,
-                details: "Artifact metadata updated".to_string(),
-                evidence: None,
-                immutable_hash: create_hash(&format!("{}:{}:{}", artifact_id, caller, now)),
-            };
-
+            let before = artifact.clone();
             artifact.metadata = new_metadata;
-            artifact.updated_at = now;
-            artifact.history.push(history_entry);
+            record_changes(&mut artifact, &before, "MetadataUpdated", "Artifact metadata updated", None);
             
-            artifacts.insert(artifact_id, artifact);
+            store_artifact(&mut artifacts, artifact);
 
             log_audit_event(
                 AuditEventType::DataModification,
@@ -144,9 +218,141 @@ pub fn update_artifact_metadata(artifact_id: u64, new_metadata: Vec<(String, Str
     })
 }
 
+#[update]
+pub fn patch_artifact(artifact_id: u64, patch: ArtifactPatch) -> Result<String, String> {
+    let caller = get_caller();
+    validate_descriptive_fields(
+        patch.cultural_significance.as_ref(),
+        patch.geographic_origin.as_ref(),
+        patch.dating_information.as_ref(),
+        patch.physical_properties.as_ref(),
+    )?;
+    for image in patch.images.iter().flatten() {
+        validate_image_url(image)?;
+    }
+    if let Some(media) = &patch.media {
+        validate_media_ids(media, caller)?;
+    }
+
+    ARTIFACTS.with(|artifacts| {
+        let mut artifacts = artifacts.borrow_mut();
+        let before = artifacts.get(&artifact_id)
+            .ok_or_else(|| "Artifact not found".to_string())?;
+
+        // Same rule as metadata: only the creator or verified institutions
+        if before.creator != caller && !is_verified_institution(caller) {
+            return Err("You don't have permission to update this artifact".to_string());
+        }
+
+        let mut artifact = before.clone();
+        apply_patch(&mut artifact, patch);
+
+        let changes = diff_artifacts(&before, &artifact);
+        if changes.is_empty() {
+            return Ok("Artifact already up to date".to_string());
+        }
+
+        let changed_fields: Vec<&str> = changes.iter().map(|change| change.field.as_str()).collect();
+        let summary = format!("Artifact fields updated: {}", changed_fields.join(", "));
+        record_changes(&mut artifact, &before, "FieldsUpdated", &summary, None);
+
+        store_artifact(&mut artifacts, artifact);
+
+        log_audit_event(
+            AuditEventType::DataModification,
+            Some(artifact_id),
+            summary.clone(),
+            AuditSeverity::Info
+        );
+
+        Ok(summary)
+    })
+}
+
+fn apply_patch(artifact: &mut Artifact, patch: ArtifactPatch) {
+    for field in &patch.clear {
+        match field {
+            ClearableField::HeritageProof => artifact.heritage_proof = None,
+            ClearableField::GeographicOrigin => artifact.geographic_origin = None,
+            ClearableField::DatingInformation => artifact.dating_information = None,
+            ClearableField::PhysicalProperties => artifact.physical_properties = None,
+        }
+    }
+
+    if let Some(name) = patch.name {
+        artifact.name = name;
+    }
+    if let Some(description) = patch.description {
+        artifact.description = description;
+    }
+    if let Some(metadata) = patch.metadata {
+        artifact.metadata = metadata;
+    }
+    if let Some(images) = patch.images {
+        artifact.images = images;
+    }
+    if let Some(media) = patch.media {
+        artifact.media = media;
+    }
+    if let Some(heritage_proof) = patch.heritage_proof {
+        artifact.heritage_proof = Some(heritage_proof);
+    }
+    if let Some(cultural_significance) = patch.cultural_significance {
+        artifact.cultural_significance = cultural_significance;
+    }
+    if let Some(geographic_origin) = patch.geographic_origin {
+        artifact.geographic_origin = Some(geographic_origin);
+    }
+    if let Some(dating_information) = patch.dating_information {
+        artifact.dating_information = Some(dating_information);
+    }
+    if let Some(physical_properties) = patch.physical_properties {
+        artifact.physical_properties = Some(physical_properties);
+    }
+    if let Some(conservation_status) = patch.conservation_status {
+        artifact.conservation_status = conservation_status;
+    }
+}
+
+#[update]
+pub fn update_artifact_origin(artifact_id: u64, origin: GeographicOrigin) -> Result<String, String> {
+    let caller = get_caller();
+    validate_geographic_origin(&origin)?;
+
+    ARTIFACTS.with(|artifacts| {
+        let mut artifacts = artifacts.borrow_mut();
+        if let Some(mut artifact) = artifacts.get(&artifact_id) {
+
+            // Same rule as metadata: only the creator or verified institutions
+            if artifact.creator != caller && !is_verified_institution(caller) {
+                return Err("You don't have permission to update this artifact".to_string());
+            }
+
+            let before = artifact.clone();
+            let note = format!("Geographic origin set to {}", origin.country);
+            artifact.geographic_origin = Some(origin);
+            record_changes(&mut artifact, &before, "OriginUpdated", &note, None);
+
+            store_artifact(&mut artifacts, artifact);
+
+            log_audit_event(
+                AuditEventType::DataModification,
+                Some(artifact_id),
+                "Artifact geographic origin updated".to_string(),
+                AuditSeverity::Info
+            );
+
+            Ok("Artifact origin updated successfully".to_string())
+        } else {
+            Err("Artifact not found".to_string())
+        }
+    })
+}
+
 #[update]
 pub fn add_artifact_image(artifact_id: u64, image_data: String) -> Result<String, String> {
     let caller = get_caller();
+    validate_image_url(&image_data)?;
     
     ARTIFACTS.with(|artifacts| {
         let mut artifacts = artifacts.borrow_mut();
@@ -157,24 +363,11 @@ pub fn add_artifact_image(artifact_id: u64, image_data: String) -> Result<String
                 return Err("You don't have permission to add images to this artifact".to_string());
             }
 
-            let now = get_time();
+            let before = artifact.clone();
+            artifact.images.push(image_data.clone());
+            record_changes(&mut artifact, &before, "ImageAdded", "New image added to artifact", Some(image_data));
             
-            // Add history entry
-            let history_entry = HistoryEntry {
-                id: get_next_id(8),
-                timestamp: now,
-                action: "ImageAdded".to_string(),
-                actor: caller,
-                details: "New image added to artifact".to_string(),
-                evidence: Some(image_data.clone()),
-                immutable_hash: create_hash(&format!("{}:{}:{}", artifact_id, caller, now)),
-            };
-
-            artifact.images.push(image_data);
-            artifact.updated_at = now;
-            artifact.history.push(history_entry);
-            
-            artifacts.insert(artifact_id, artifact);
+            store_artifact(&mut artifacts, artifact);
 
             log_audit_event(
                 AuditEventType::DataModification,
@@ -190,10 +383,46 @@ pub fn add_artifact_image(artifact_id: u64, image_data: String) -> Result<String
     })
 }
 
+// Attaches a committed upload, see modules::media
 #[update]
-pub fn update_artifact_status(artifact_id: u64, new_status: ArtifactStatus, reason: String) -> Result<String, String> {
+pub fn add_artifact_media(artifact_id: u64, media_id: u64) -> Result<String, String> {
     let caller = get_caller();
-    
+    validate_media_ids(&[media_id], caller)?;
+
+    ARTIFACTS.with(|artifacts| {
+        let mut artifacts = artifacts.borrow_mut();
+        if let Some(mut artifact) = artifacts.get(&artifact_id) {
+
+            // Same rule as images
+            if artifact.creator != caller && !can_moderate(caller) {
+                return Err("You don't have permission to add media to this artifact".to_string());
+            }
+            if artifact.media.contains(&media_id) {
+                return Err("Media is already attached to this artifact".to_string());
+            }
+
+            let before = artifact.clone();
+            artifact.media.push(media_id);
+            record_changes(&mut artifact, &before, "MediaAdded", "Media attached to artifact", None);
+
+            store_artifact(&mut artifacts, artifact);
+
+            log_audit_event(
+                AuditEventType::DataModification,
+                Some(artifact_id),
+                format!("Media {} attached to artifact", media_id),
+                AuditSeverity::Info
+            );
+
+            Ok("Media added successfully".to_string())
+        } else {
+            Err("Artifact not found".to_string())
+        }
+    })
+}
+
+#[update]
+pub fn update_artifact_status(artifact_id: u64, new_status: ArtifactStatus, reason: String) -> Result<String, String> {
     // // Only moderators can directly update artifact status
     // if !can_moderate(caller) {
     //     return Err("Only moderators can update artifact status directly".to_string());
@@ -202,25 +431,12 @@ pub fn update_artifact_status(artifact_id: u64, new_status: ArtifactStatus, reas
     ARTIFACTS.with(|artifacts| {
         let mut artifacts = artifacts.borrow_mut();
         if let Some(mut artifact) = artifacts.get(&artifact_id) {
-            let now = get_time();
-            let old_status = artifact.status.clone();
-            
-            // Add history entry
-            let history_entry = HistoryEntry {
-                id: get_next_id(8),
-                timestamp: now,
-                action: "StatusChanged".to_string(),
-                actor: caller,
-                details: format!("Status changed from {:?} to {:?}: {}", old_status, new_status, reason),
-                evidence: None,
-                immutable_hash: create_hash(&format!("{}:{}:{}", artifact_id, caller, now)),
-            };
-
+            let before = artifact.clone();
+            let note = format!("Status changed from {:?} to {:?}: {}", before.status, new_status, reason);
             artifact.status = new_status.clone();
-            artifact.updated_at = now;
-            artifact.history.push(history_entry);
+            record_changes(&mut artifact, &before, "StatusChanged", &note, None);
             
-            artifacts.insert(artifact_id, artifact);
+            store_artifact(&mut artifacts, artifact);
 
             log_audit_event(
                 AuditEventType::ArtifactVerification,
@@ -244,6 +460,22 @@ pub fn get_artifact(artifact_id: u64) -> Result<Artifact, String> {
     })
 }
 
+// The artifact as it was at `timestamp` (nanoseconds), rebuilt from the diffs
+// in its history
+#[query]
+pub fn get_artifact_at(artifact_id: u64, timestamp: u64) -> Result<Artifact, String> {
+    artifact_at(&get_artifact(artifact_id)?, timestamp)
+}
+
+// Fields that differ between the versions of the artifact at `from` and `to`
+#[query]
+pub fn diff_artifact_versions(artifact_id: u64, from: u64, to: u64) -> Result<Vec<FieldChange>, String> {
+    let current = get_artifact(artifact_id)?;
+    let before = artifact_at(&current, from)?;
+    let after = artifact_at(&current, to)?;
+    Ok(diff_artifacts(&before, &after))
+}
+
 #[query]
 pub fn get_all_artifacts() -> Vec<Artifact> {
     let mut artifacts = Vec::new();
@@ -258,34 +490,41 @@ pub fn get_all_artifacts() -> Vec<Artifact> {
     artifacts
 }
 
+pub fn list_artifacts(page: PageRequest) -> Page<Artifact> {
+    ARTIFACTS.with(|artifact_store| {
+        let artifact_store = artifact_store.borrow();
+        paginate_newest_first(&artifact_store, &page, artifact_store.len(), Some)
+    })
+}
+
 #[query]
 pub fn get_artifacts_by_status(status: ArtifactStatus) -> Vec<Artifact> {
-    get_all_artifacts()
-        .into_iter()
-        .filter(|a| std::mem::discriminant(&a.status) == std::mem::discriminant(&status))
-        .collect()
+    find_artifacts_by_status(&status)
 }
 
 #[query]
 pub fn get_artifacts_by_creator(creator: candid::Principal) -> Vec<Artifact> {
-    get_all_artifacts()
-        .into_iter()
-        .filter(|a| a.creator == creator)
-        .collect()
+    find_artifacts_by_creator(creator)
 }
 
+#[query]
+pub fn get_artifacts_by_tag(tag: String) -> Vec<Artifact> {
+    find_artifacts_by_tag(&tag)
+}
+
+#[query]
+pub fn get_artifacts_by_country(country: String) -> Vec<Artifact> {
+    find_artifacts_by_country(&country)
+}
+
+// Best matches first; see modules::search for the query syntax
 #[query]
 pub fn search_artifacts(query: String) -> Vec<Artifact> {
-    let query_lower = query.to_lowercase();
-    get_all_artifacts()
-        .into_iter()
-        .filter(|a| {
-            a.name.to_lowercase().contains(&query_lower) ||
-            a.description.to_lowercase().contains(&query_lower) ||
-            a.metadata.iter().any(|(key, value)| 
-                key.to_lowercase().contains(&query_lower) ||
-                value.to_lowercase().contains(&query_lower)
-            )
-        })
-        .collect()
+    let ranked = rank_artifacts(&parse_query(&query));
+    ARTIFACTS.with(|artifact_store| {
+        let artifact_store = artifact_store.borrow();
+        ranked.into_iter()
+            .filter_map(|(artifact_id, _)| artifact_store.get(&artifact_id))
+            .collect()
+    })
 }
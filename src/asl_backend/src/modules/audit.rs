@@ -3,15 +3,15 @@ use candid::Principal;
 use crate::modules::types::*;
 use crate::modules::storage::AUDIT_LOG;
 use crate::modules::auth::get_caller;
-use crate::modules::utils::{get_time, create_hash};
-use crate::modules::storage::get_next_id;
+use crate::modules::utils::{get_time, create_hash, paginate_newest_first};
+use crate::modules::storage::{get_next_id, IdKind};
 
 // ============================================================================
 // AUDIT AND COMPLIANCE SYSTEM
 // ============================================================================
 
 pub fn log_audit_event(event_type: AuditEventType, target_id: Option<u64>, details: String, severity: AuditSeverity) {
-    let audit_id = get_next_id(6); // Audit log counter
+    let audit_id = get_next_id(IdKind::AuditEntry);
     let caller = get_caller();
     let timestamp = get_time();
     
@@ -64,20 +64,11 @@ pub fn get_audit_logs_for_user(user_principal: Principal) -> Vec<AuditEntry> {
     logs
 }
 
-pub fn get_recent_audit_logs(limit: usize) -> Vec<AuditEntry> {
-    let mut logs = Vec::new();
+pub fn list_audit_logs(page: PageRequest) -> Page<AuditEntry> {
     AUDIT_LOG.with(|audit_log| {
-        for (_, entry) in audit_log.borrow().iter() {
-            logs.push(entry.clone());
-        }
-    });
-    
-    // Sort by timestamp (most recent first)
-    logs.sort_by(|a, b| b.timestamp.cmp(&a.timestamp));
-    
-    // Limit results
-    logs.truncate(limit);
-    logs
+        let audit_log = audit_log.borrow();
+        paginate_newest_first(&audit_log, &page, audit_log.len(), Some)
+    })
 }
 
 pub fn get_security_alerts() -> Vec<AuditEntry> {
@@ -1,5 +1,4 @@
 use candid::Principal;
-use ic_cdk::caller;
 
 use crate::modules::types::*;
 use crate::modules::storage::USERS;
@@ -8,8 +7,14 @@ use crate::modules::storage::USERS;
 // AUTHENTICATION & AUTHORIZATION
 // ============================================================================
 
+#[cfg(not(test))]
 pub fn get_caller() -> Principal {
-    caller()
+    ic_cdk::caller()
+}
+
+#[cfg(test)]
+pub fn get_caller() -> Principal {
+    crate::modules::testing::caller()
 }
 
 pub fn is_verified_institution(caller: Principal) -> bool {
@@ -87,6 +92,13 @@ pub fn is_expert_or_institution(caller: Principal) -> bool {
     })
 }
 
+// Loose match between a specialization and an expertise area, either way round
+pub fn expertise_matches(specialization: &str, area: &str) -> bool {
+    let specialization = specialization.to_lowercase();
+    let area = area.to_lowercase();
+    specialization.contains(&area) || area.contains(&specialization)
+}
+
 pub fn has_required_expertise(caller: Principal, required_expertise: &[String]) -> bool {
     if required_expertise.is_empty() {
         return true; // No specific expertise required
@@ -96,10 +108,7 @@ pub fn has_required_expertise(caller: Principal, required_expertise: &[String])
         users.borrow().get(&caller)
             .map(|user| {
                 required_expertise.iter().any(|req| {
-                    user.specialization.iter().any(|spec| 
-                        spec.to_lowercase().contains(&req.to_lowercase()) ||
-                        req.to_lowercase().contains(&spec.to_lowercase())
-                    )
+                    user.specialization.iter().any(|spec| expertise_matches(spec, req))
                 })
             })
             .unwrap_or(false)
@@ -116,3 +125,40 @@ pub fn can_moderate(caller: Principal) -> bool {
             .unwrap_or(false)
     })
 }
+
+pub fn default_permissions_for_role(role: &UserRole) -> UserPermissions {
+    match role {
+        UserRole::Institution => UserPermissions {
+            can_submit_artifacts: true,
+            can_create_proposals: true,
+            can_vote: true, 
+            can_verify_institutions: true,
+            can_moderate: true,
+            voting_weight: 3,
+        },
+        UserRole::Expert => UserPermissions {
+            can_submit_artifacts: true,
+            can_create_proposals: true,
+            can_vote: false, 
+            can_verify_institutions: false,
+            can_moderate: false,
+            voting_weight: 2,
+        },
+        UserRole::Community => UserPermissions {
+            can_submit_artifacts: false,
+            can_create_proposals: false,
+            can_vote: true,
+            can_verify_institutions: false,
+            can_moderate: false,
+            voting_weight: 1,
+        },
+        _ => UserPermissions {
+            can_submit_artifacts: false,
+            can_create_proposals: false,
+            can_vote: false,
+            can_verify_institutions: false,
+            can_moderate: false,
+            voting_weight: 1,
+        },
+    }
+}
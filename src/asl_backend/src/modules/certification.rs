@@ -0,0 +1,191 @@
+use candid::{CandidType, Encode};
+use ic_cdk::api::data_certificate;
+use ic_certification::{AsHashTree, Hash, HashTree, RbTree};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::cell::RefCell;
+
+use crate::modules::types::*;
+use crate::modules::storage::{ARTIFACTS, NFTS};
+
+// ============================================================================
+// CERTIFIED DATA
+// ============================================================================
+//
+// The canister certifies a two-level Merkle tree:
+//
+//   "artifacts" -> { artifact_id (u64, big endian) -> sha256(candid(Artifact)) }
+//   "nfts"      -> { nft_id (u64, big endian)      -> sha256(candid(ProofOfHeritageNFT)) }
+//
+// Its root hash is the canister's certified data. A client verifies a response
+// by checking the certificate's BLS signature against the IC root key, that
+// `canister/<canister_id>/certified_data` in the certificate equals the root of
+// the witness, and that the witness leaf at `<store>/<id>` equals the SHA-256
+// of the Candid encoding of the returned record.
+
+const ARTIFACTS_LABEL: &str = "artifacts";
+const NFTS_LABEL: &str = "nfts";
+
+type CertifiedTree = RbTree<&'static str, RbTree<Vec<u8>, Hash>>;
+
+thread_local! {
+    // Heap-only; rebuilt from stable storage on init and upgrade
+    static CERTIFIED_TREE: RefCell<CertifiedTree> = const { RefCell::new(RbTree::new()) };
+}
+
+#[derive(CandidType, Serialize, Deserialize, Clone, Debug)]
+pub struct CertifiedArtifact {
+    pub artifact: Artifact,
+    pub certificate: Vec<u8>,
+    pub witness: Vec<u8>,
+}
+
+#[derive(CandidType, Serialize, Deserialize, Clone, Debug)]
+pub struct CertifiedNFT {
+    pub nft: ProofOfHeritageNFT,
+    pub certificate: Vec<u8>,
+    pub witness: Vec<u8>,
+}
+
+pub fn certify_artifact(artifact: &Artifact) {
+    certify_entry(ARTIFACTS_LABEL, artifact.id, hash_record(artifact));
+}
+
+pub fn certify_nft(nft: &ProofOfHeritageNFT) {
+    certify_entry(NFTS_LABEL, nft.id, hash_record(nft));
+}
+
+// Recomputes the whole tree from the stable stores; used on init and after upgrades
+pub fn rebuild_certified_tree() {
+    let mut artifacts_tree = RbTree::new();
+    ARTIFACTS.with(|artifacts| {
+        for (id, artifact) in artifacts.borrow().iter() {
+            artifacts_tree.insert(id.to_be_bytes().to_vec(), hash_record(&artifact));
+        }
+    });
+
+    let mut nfts_tree = RbTree::new();
+    NFTS.with(|nfts| {
+        for (id, nft) in nfts.borrow().iter() {
+            nfts_tree.insert(id.to_be_bytes().to_vec(), hash_record(&nft));
+        }
+    });
+
+    CERTIFIED_TREE.with(|tree| {
+        let mut tree = tree.borrow_mut();
+        *tree = RbTree::new();
+        tree.insert(ARTIFACTS_LABEL, artifacts_tree);
+        tree.insert(NFTS_LABEL, nfts_tree);
+        publish_root(&tree);
+    });
+}
+
+pub fn get_certified_artifact(artifact_id: u64) -> Result<CertifiedArtifact, String> {
+    let artifact = ARTIFACTS.with(|artifacts| artifacts.borrow().get(&artifact_id))
+        .ok_or_else(|| "Artifact not found".to_string())?;
+
+    Ok(CertifiedArtifact {
+        artifact,
+        certificate: current_certificate()?,
+        witness: build_witness(ARTIFACTS_LABEL, artifact_id)?,
+    })
+}
+
+pub fn get_certified_nft(nft_id: u64) -> Result<CertifiedNFT, String> {
+    let nft = NFTS.with(|nfts| nfts.borrow().get(&nft_id))
+        .ok_or_else(|| "Heritage NFT not found".to_string())?;
+
+    Ok(CertifiedNFT {
+        nft,
+        certificate: current_certificate()?,
+        witness: build_witness(NFTS_LABEL, nft_id)?,
+    })
+}
+
+fn hash_record<T: CandidType>(record: &T) -> Hash {
+    let bytes = Encode!(record).expect("Failed to encode record for certification");
+    Sha256::digest(bytes).into()
+}
+
+fn certify_entry(label: &'static str, id: u64, hash: Hash) {
+    CERTIFIED_TREE.with(|tree| {
+        let mut tree = tree.borrow_mut();
+        if tree.get(label.as_bytes()).is_none() {
+            tree.insert(label, RbTree::new());
+        }
+        tree.modify(label.as_bytes(), |store| store.insert(id.to_be_bytes().to_vec(), hash));
+        publish_root(&tree);
+    });
+}
+
+#[cfg(not(test))]
+fn publish_root(tree: &CertifiedTree) {
+    ic_cdk::api::set_certified_data(&tree.root_hash());
+}
+
+// Unit tests run outside a canister, see modules::testing
+#[cfg(test)]
+fn publish_root(_tree: &CertifiedTree) {}
+
+fn build_witness(label: &'static str, id: u64) -> Result<Vec<u8>, String> {
+    let witness: HashTree = CERTIFIED_TREE.with(|tree| {
+        tree.borrow().nested_witness(label.as_bytes(), |store| store.witness(&id.to_be_bytes()))
+    });
+
+    let mut serializer = serde_cbor::Serializer::new(Vec::new());
+    serializer.self_describe()
+        .and_then(|_| witness.serialize(&mut serializer))
+        .map_err(|e| format!("Failed to encode witness: {}", e))?;
+    Ok(serializer.into_inner())
+}
+
+fn current_certificate() -> Result<Vec<u8>, String> {
+    data_certificate().ok_or_else(|| "Certificates are only available in query calls".to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ic_certification::{LookupResult, SubtreeLookupResult};
+    use crate::modules::testing::{artifact_request, create_artifact_as, principal};
+
+    fn root_hash() -> Hash {
+        CERTIFIED_TREE.with(|tree| tree.borrow().root_hash())
+    }
+
+    fn decode_witness(bytes: &[u8]) -> HashTree {
+        serde_cbor::from_slice(bytes).expect("Witness is not a CBOR hash tree")
+    }
+
+    #[test]
+    fn witness_proves_the_stored_record_under_the_root() {
+        let artifact_id = create_artifact_as(principal(1), artifact_request("Jade seal"));
+        create_artifact_as(principal(1), artifact_request("Jade bowl"));
+        let artifact = ARTIFACTS.with(|artifacts| artifacts.borrow().get(&artifact_id)).unwrap();
+
+        let witness = decode_witness(&build_witness(ARTIFACTS_LABEL, artifact_id).unwrap());
+        assert_eq!(witness.digest(), root_hash());
+        let path = [ARTIFACTS_LABEL.as_bytes(), &artifact_id.to_be_bytes()[..]];
+        assert_eq!(witness.lookup_path(&path), LookupResult::Found(&hash_record(&artifact)[..]));
+        // Only the requested leaf is revealed
+        assert!(!matches!(witness.lookup_subtree(&[ARTIFACTS_LABEL.as_bytes(), &(artifact_id + 1).to_be_bytes()[..]]), SubtreeLookupResult::Found(_)));
+    }
+
+    #[test]
+    fn incremental_updates_match_a_full_rebuild() {
+        // As on init
+        rebuild_certified_tree();
+        let first = create_artifact_as(principal(1), artifact_request("Jade seal"));
+        create_artifact_as(principal(2), artifact_request("Bronze bell"));
+        let updated_root = root_hash();
+
+        rebuild_certified_tree();
+        assert_eq!(root_hash(), updated_root);
+
+        // Changing a record changes the root
+        let mut artifact = ARTIFACTS.with(|artifacts| artifacts.borrow().get(&first)).unwrap();
+        artifact.name = "Jade seal of office".to_string();
+        certify_artifact(&artifact);
+        assert_ne!(root_hash(), updated_root);
+    }
+}
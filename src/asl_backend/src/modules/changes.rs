@@ -0,0 +1,243 @@
+use candid::CandidType;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+
+use crate::modules::types::*;
+
+// ============================================================================
+// ARTIFACT CHANGE DIFFS
+// ============================================================================
+//
+// Edits record which fields changed, with their values before and after, in
+// `HistoryEntry.details` as JSON:
+//
+//   {"note":"...","changes":[{"field":"name","before":"Old","after":"New"}, ...]}
+//
+// Values are the JSON encoding of the field, so the entry is readable on its
+// own and can be replayed in either direction. Entries written before diffs
+// were recorded hold plain text and are skipped when replaying.
+
+#[derive(CandidType, Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct FieldChange {
+    pub field: String,
+    // JSON encodings of the field's value
+    pub before: String,
+    pub after: String,
+}
+
+fn to_json<T: Serialize>(value: &T) -> Value {
+    serde_json::to_value(value).unwrap_or(Value::Null)
+}
+
+// Every field an edit may touch, as (name, JSON value). Identity, authorship,
+// timestamps and the history itself are not part of the diff.
+pub fn tracked_fields(artifact: &Artifact) -> Vec<(&'static str, Value)> {
+    vec![
+        ("name", to_json(&artifact.name)),
+        ("description", to_json(&artifact.description)),
+        ("metadata", to_json(&artifact.metadata)),
+        ("images", to_json(&artifact.images)),
+        ("media", to_json(&artifact.media)),
+        ("status", to_json(&artifact.status)),
+        ("heritage_proof", to_json(&artifact.heritage_proof)),
+        ("authenticity_score", to_json(&artifact.authenticity_score)),
+        ("verification_level", to_json(&artifact.verification_level)),
+        ("cultural_significance", to_json(&artifact.cultural_significance)),
+        ("geographic_origin", to_json(&artifact.geographic_origin)),
+        ("dating_information", to_json(&artifact.dating_information)),
+        ("physical_properties", to_json(&artifact.physical_properties)),
+        ("conservation_status", to_json(&artifact.conservation_status)),
+    ]
+}
+
+pub fn diff_artifacts(before: &Artifact, after: &Artifact) -> Vec<FieldChange> {
+    tracked_fields(before).into_iter()
+        .zip(tracked_fields(after))
+        .filter(|((_, old), (_, new))| old != new)
+        .map(|((field, old), (_, new))| FieldChange {
+            field: field.to_string(),
+            before: old.to_string(),
+            after: new.to_string(),
+        })
+        .collect()
+}
+
+pub fn encode_changes(note: &str, changes: &[FieldChange]) -> String {
+    let changes: Vec<Value> = changes.iter()
+        .map(|change| json!({
+            "field": change.field,
+            "before": serde_json::from_str::<Value>(&change.before).unwrap_or(Value::Null),
+            "after": serde_json::from_str::<Value>(&change.after).unwrap_or(Value::Null),
+        }))
+        .collect();
+    json!({ "note": note, "changes": changes }).to_string()
+}
+
+// None when `details` is not a diff (plain-text entries from older versions)
+pub fn decode_changes(details: &str) -> Option<Vec<FieldChange>> {
+    let value: Value = serde_json::from_str(details).ok()?;
+    value.get("changes")?.as_array()?.iter()
+        .map(|change| Some(FieldChange {
+            field: change.get("field")?.as_str()?.to_string(),
+            before: change.get("before")?.to_string(),
+            after: change.get("after")?.to_string(),
+        }))
+        .collect()
+}
+
+fn from_json<T: for<'de> Deserialize<'de>>(field: &str, value: &str) -> Result<T, String> {
+    serde_json::from_str(value)
+        .map_err(|e| format!("Invalid recorded value for {}: {}", field, e))
+}
+
+fn set_tracked_field(artifact: &mut Artifact, field: &str, value: &str) -> Result<(), String> {
+    match field {
+        "name" => artifact.name = from_json(field, value)?,
+        "description" => artifact.description = from_json(field, value)?,
+        "metadata" => artifact.metadata = from_json(field, value)?,
+        "images" => artifact.images = from_json(field, value)?,
+        "media" => artifact.media = from_json(field, value)?,
+        "status" => artifact.status = from_json(field, value)?,
+        "heritage_proof" => artifact.heritage_proof = from_json(field, value)?,
+        "authenticity_score" => artifact.authenticity_score = from_json(field, value)?,
+        "verification_level" => artifact.verification_level = from_json(field, value)?,
+        "cultural_significance" => artifact.cultural_significance = from_json(field, value)?,
+        "geographic_origin" => artifact.geographic_origin = from_json(field, value)?,
+        "dating_information" => artifact.dating_information = from_json(field, value)?,
+        "physical_properties" => artifact.physical_properties = from_json(field, value)?,
+        "conservation_status" => artifact.conservation_status = from_json(field, value)?,
+        _ => return Err(format!("Unknown artifact field: {}", field)),
+    }
+    Ok(())
+}
+
+// Rebuilds `current` as it was at `timestamp` by undoing, newest first, every
+// history entry recorded after it.
+pub fn artifact_at(current: &Artifact, timestamp: u64) -> Result<Artifact, String> {
+    if timestamp < current.created_at {
+        return Err("Artifact did not exist at that time".to_string());
+    }
+
+    let mut artifact = current.clone();
+    while artifact.history.last().is_some_and(|entry| entry.timestamp > timestamp) {
+        let Some(entry) = artifact.history.pop() else { break };
+        for change in decode_changes(&entry.details).unwrap_or_default().iter().rev() {
+            set_tracked_field(&mut artifact, &change.field, &change.before)?;
+        }
+    }
+
+    artifact.updated_at = artifact.history.last()
+        .map(|entry| entry.timestamp.max(artifact.created_at))
+        .unwrap_or(artifact.created_at);
+    Ok(artifact)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use base64::{engine::general_purpose::STANDARD, Engine as _};
+    use crate::modules::artifacts::{diff_artifact_versions, get_artifact, store_artifact, update_artifact_metadata, update_artifact_status};
+    use crate::modules::media::migrate_inline_images;
+    use crate::modules::storage::ARTIFACTS;
+    use crate::modules::testing::{advance_time, artifact_request, create_artifact_as, now, principal, HOUR};
+
+    fn last_changes(artifact_id: u64) -> Vec<FieldChange> {
+        let artifact = get_artifact(artifact_id).unwrap();
+        decode_changes(&artifact.history.last().unwrap().details).unwrap()
+    }
+
+    #[test]
+    fn diffs_hold_only_changed_fields_as_json() {
+        let artifact_id = create_artifact_as(principal(1), artifact_request("Scarab"));
+        let before = get_artifact(artifact_id).unwrap();
+        let mut after = before.clone();
+        after.name = "Heart scarab".to_string();
+        after.status = ArtifactStatus::Verified;
+        after.updated_at += 1;
+
+        assert_eq!(diff_artifacts(&before, &after), vec![
+            FieldChange { field: "name".to_string(), before: "\"Scarab\"".to_string(), after: "\"Heart scarab\"".to_string() },
+            FieldChange { field: "status".to_string(), before: "\"PendingVerification\"".to_string(), after: "\"Verified\"".to_string() },
+        ]);
+        assert!(diff_artifacts(&before, &before).is_empty());
+    }
+
+    #[test]
+    fn encoded_changes_decode_to_the_same_changes() {
+        let changes = vec![
+            FieldChange { field: "metadata".to_string(), before: "[]".to_string(), after: r#"[["site","Saqqara"]]"#.to_string() },
+            FieldChange { field: "heritage_proof".to_string(), before: "null".to_string(), after: "\"proof\"".to_string() },
+        ];
+        let details = encode_changes("Edited", &changes);
+        assert_eq!(decode_changes(&details), Some(changes));
+        assert_eq!(decode_changes(&encode_changes("Created", &[])), Some(Vec::new()));
+
+        // Plain-text details from before diffs were recorded
+        assert_eq!(decode_changes("Artifact created"), None);
+        assert_eq!(decode_changes(r#"{"note":"no changes key"}"#), None);
+    }
+
+    #[test]
+    fn replay_rejects_unknown_fields_and_bad_values() {
+        let artifact_id = create_artifact_as(principal(1), artifact_request("Scarab"));
+        let mut artifact = get_artifact(artifact_id).unwrap();
+        assert!(set_tracked_field(&mut artifact, "creator", "null").is_err());
+        assert!(set_tracked_field(&mut artifact, "status", "\"Misplaced\"").is_err());
+        assert!(set_tracked_field(&mut artifact, "name", "\"Renamed\"").is_ok());
+        assert_eq!(artifact.name, "Renamed");
+    }
+
+    #[test]
+    fn past_versions_are_rebuilt_from_the_history() {
+        let artifact_id = create_artifact_as(principal(1), artifact_request("Scarab"));
+        let created = get_artifact(artifact_id).unwrap();
+        let t0 = now();
+
+        advance_time(HOUR);
+        update_artifact_metadata(artifact_id, vec![("site".to_string(), "Saqqara".to_string())]).unwrap();
+        let t1 = now();
+        let with_metadata = get_artifact(artifact_id).unwrap();
+
+        advance_time(HOUR);
+        update_artifact_status(artifact_id, ArtifactStatus::Verified, "Reviewed".to_string()).unwrap();
+        let t2 = now();
+        let current = get_artifact(artifact_id).unwrap();
+
+        assert_eq!(tracked_fields(&artifact_at(&current, t0).unwrap()), tracked_fields(&created));
+        assert_eq!(artifact_at(&current, t0).unwrap().updated_at, created.updated_at);
+        assert_eq!(tracked_fields(&artifact_at(&current, t1 + 1).unwrap()), tracked_fields(&with_metadata));
+        assert_eq!(tracked_fields(&artifact_at(&current, t2).unwrap()), tracked_fields(&current));
+        assert!(artifact_at(&current, created.created_at - 1).is_err());
+
+        let changed: Vec<String> = diff_artifact_versions(artifact_id, t0, t2).unwrap()
+            .into_iter().map(|change| change.field).collect();
+        assert_eq!(changed, vec!["metadata", "status"]);
+    }
+
+    #[test]
+    fn media_migration_records_the_images_change() {
+        let artifact_id = create_artifact_as(principal(1), artifact_request("Stela"));
+        let png = [&[0x89, b'P', b'N', b'G', 0x0D, 0x0A, 0x1A, 0x0A][..], &[0u8; 16]].concat();
+        let inline = format!("data:image/png;base64,{}", STANDARD.encode(&png));
+        // Inline images are only found in records written before the media store
+        ARTIFACTS.with(|artifacts| {
+            let mut artifacts = artifacts.borrow_mut();
+            let mut artifact = artifacts.get(&artifact_id).unwrap();
+            artifact.images = vec![inline.clone(), "https://example.org/stela.jpg".to_string()];
+            store_artifact(&mut artifacts, artifact);
+        });
+        let before_migration = now();
+
+        advance_time(HOUR);
+        assert_eq!(migrate_inline_images(), 1);
+        let migrated = get_artifact(artifact_id).unwrap();
+        assert_eq!(migrated.images, vec!["https://example.org/stela.jpg".to_string()]);
+        assert_eq!(migrated.media.len(), 1);
+
+        let changed: Vec<String> = last_changes(artifact_id).into_iter().map(|change| change.field).collect();
+        assert_eq!(changed, vec!["images", "media"]);
+        let restored = artifact_at(&migrated, before_migration).unwrap();
+        assert_eq!(restored.images[0], inline);
+        assert!(restored.media.is_empty());
+    }
+}
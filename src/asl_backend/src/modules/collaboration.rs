@@ -2,7 +2,7 @@ use ic_cdk::{query, update};
 use candid::{CandidType, Principal};
 use serde::{Deserialize, Serialize};
 use crate::modules::types::*;
-use crate::modules::storage::{COLLABORATION_ROOMS, MESSAGES, VIRTUAL_EVENTS, get_next_id};
+use crate::modules::storage::{COLLABORATION_ROOMS, MESSAGES, VIRTUAL_EVENTS, get_next_id, IdKind};
 use crate::modules::auth::*;
 use crate::modules::utils::*;
 use crate::modules::audit::log_audit_event;
@@ -111,7 +111,7 @@ pub fn create_collaboration_room(
         return Err("Room title cannot be empty".to_string());
     }
 
-    let room_id = get_next_id(11); // Collaboration room counter
+    let room_id = get_next_id(IdKind::CollaborationRoom);
     let now = get_time();
 
     let room = CollaborationRoom {
@@ -168,7 +168,7 @@ pub fn send_message(
         return Err("You don't have access to this room".to_string());
     }
 
-    let message_id = get_next_id(12); // Message counter
+    let message_id = get_next_id(IdKind::CollaborationMessage);
     let now = get_time();
 
     let message = Message {
@@ -325,7 +325,7 @@ pub fn create_virtual_event(
         return Err("Event end time must be after start time".to_string());
     }
 
-    let event_id = get_next_id(13); // Event counter
+    let event_id = get_next_id(IdKind::VirtualEvent);
 
     let event = VirtualEvent {
         event_id,
@@ -1,11 +1,11 @@
 use ic_cdk::{query, update};
 use candid::{CandidType, Principal};
 use serde::{Deserialize, Serialize};
-use crate::modules::storage::{COMMUNITY_POSTS, COMMUNITY_STATS, get_next_id, USERS};
+use crate::modules::storage::{COMMUNITY_ACTIVE_POSTS, COMMUNITY_POSTS, COMMUNITY_STATS, get_next_id, IdKind, USERS};
 use crate::modules::auth::*;
 use crate::modules::utils::*;
 use crate::modules::audit::{log_audit_event};
-use crate::modules::types::{AuditEventType, AuditSeverity};
+use crate::modules::types::{AuditEventType, AuditSeverity, Page, PageRequest};
 
 // ============================================================================
 // COMMUNITY MANAGEMENT SYSTEM
@@ -114,7 +114,7 @@ pub fn create_community_post(request: CreatePostRequest) -> Result<u64, String>
     }
 
     let now = get_time();
-    let post_id = get_next_id(8); // Community posts counter
+    let post_id = get_next_id(IdKind::CommunityPost);
 
     // Generate author name from principal or institution
     let author_name = if let Some(institution) = &user.institution {
@@ -144,6 +144,7 @@ pub fn create_community_post(request: CreatePostRequest) -> Result<u64, String>
     COMMUNITY_POSTS.with(|posts| {
         posts.borrow_mut().insert(post_id, post);
     });
+    adjust_active_post_count(1);
 
     // Update community stats
     update_community_stats();
@@ -174,7 +175,7 @@ pub fn create_community_reply(request: CreateReplyRequest) -> Result<u64, String
     }
 
     let now = get_time();
-    let reply_id = get_next_id(9); // Community replies counter
+    let reply_id = get_next_id(IdKind::CommunityReply);
 
     // Generate author name from principal or institution
     let author_name = if let Some(institution) = &user.institution {
@@ -286,6 +287,16 @@ pub fn get_all_community_posts() -> Vec<CommunityPost> {
     })
 }
 
+pub fn list_community_posts(page: PageRequest) -> Page<CommunityPost> {
+    let total = COMMUNITY_ACTIVE_POSTS.with(|count| *count.borrow().get());
+    COMMUNITY_POSTS.with(|posts| {
+        let posts = posts.borrow();
+        paginate_newest_first(&posts, &page, total, |post| {
+            matches!(post.status, PostStatus::Active).then_some(post)
+        })
+    })
+}
+
 #[query]
 pub fn get_community_posts_by_category(category: CommunityCategory) -> Vec<CommunityPost> {
     COMMUNITY_POSTS.with(|posts| {
@@ -385,6 +396,14 @@ pub fn moderate_post(post_id: u64, action: ModerationAction) -> Result<String, S
     COMMUNITY_POSTS.with(|posts| {
         let mut posts_map = posts.borrow_mut();
         if let Some(mut post) = posts_map.get(&post_id) {
+            // Hiding, archiving and flagging all take a post out of the listing
+            let leaves_listing = matches!(post.status, PostStatus::Active) && matches!(
+                action,
+                ModerationAction::Hide | ModerationAction::Archive | ModerationAction::Flag
+            );
+            if leaves_listing {
+                adjust_active_post_count(-1);
+            }
             match action {
                 ModerationAction::Pin => {
                     post.is_pinned = true;
@@ -443,6 +462,27 @@ pub enum ModerationAction {
 // HELPER FUNCTIONS
 // ============================================================================
 
+fn adjust_active_post_count(delta: i64) {
+    COMMUNITY_ACTIVE_POSTS.with(|count| {
+        let mut count = count.borrow_mut();
+        let updated = count.get().saturating_add_signed(delta);
+        count.set(updated).expect("Failed to update community post count");
+    });
+}
+
+// Recounts Active posts from scratch, for canisters that predate the counter
+pub fn recount_active_posts() -> u64 {
+    let active = COMMUNITY_POSTS.with(|posts| {
+        posts.borrow().iter()
+            .filter(|(_, post)| matches!(post.status, PostStatus::Active))
+            .count() as u64
+    });
+    COMMUNITY_ACTIVE_POSTS.with(|count| {
+        count.borrow_mut().set(active).expect("Failed to update community post count");
+    });
+    active
+}
+
 fn update_community_stats() {
     let now = get_time();
     let day_in_ms = 24 * 60 * 60 * 1000;
@@ -486,3 +526,63 @@ fn update_community_stats() {
         stats.active_this_week = (total_members as f64 * 0.3) as u64; // 30% of members active this week
     });
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::modules::testing::{principal, register, set_caller};
+    use crate::modules::types::UserRole;
+
+    fn post(title: &str) -> u64 {
+        create_community_post(CreatePostRequest {
+            title: title.to_string(),
+            content: format!("{} content", title),
+            category: CommunityCategory::General,
+            tags: Vec::new(),
+        }).unwrap()
+    }
+
+    fn page(cursor: Option<u64>, limit: u32) -> PageRequest {
+        PageRequest { cursor, limit: Some(limit) }
+    }
+
+    #[test]
+    fn listing_pages_newest_first_with_cursors() {
+        register(principal(1), UserRole::Institution, &[]);
+        set_caller(principal(1));
+        let ids: Vec<u64> = (0..5).map(|n| post(&format!("Post {}", n))).collect();
+
+        let first = list_community_posts(page(None, 2));
+        assert_eq!(first.items.iter().map(|p| p.post_id).collect::<Vec<_>>(), vec![ids[4], ids[3]]);
+        assert_eq!(first.next_cursor, Some(ids[3]));
+        assert_eq!(first.total, 5);
+
+        let second = list_community_posts(page(first.next_cursor, 2));
+        assert_eq!(second.items.iter().map(|p| p.post_id).collect::<Vec<_>>(), vec![ids[2], ids[1]]);
+
+        // The last page holds exactly the remaining post and hands out no cursor
+        let last = list_community_posts(page(second.next_cursor, 2));
+        assert_eq!(last.items.iter().map(|p| p.post_id).collect::<Vec<_>>(), vec![ids[0]]);
+        assert_eq!(last.next_cursor, None);
+    }
+
+    #[test]
+    fn moderated_posts_leave_the_listing_and_its_total() {
+        register(principal(1), UserRole::Institution, &[]);
+        set_caller(principal(1));
+        let kept = post("Kept");
+        let hidden = post("Hidden");
+        let flagged = post("Flagged");
+
+        moderate_post(hidden, ModerationAction::Hide).unwrap();
+        moderate_post(flagged, ModerationAction::Flag).unwrap();
+        // Moderating a post that already left the listing must not count it twice
+        moderate_post(flagged, ModerationAction::Archive).unwrap();
+        moderate_post(kept, ModerationAction::Pin).unwrap();
+
+        let listing = list_community_posts(page(None, 10));
+        assert_eq!(listing.items.iter().map(|p| p.post_id).collect::<Vec<_>>(), vec![kept]);
+        assert_eq!(listing.total, 1);
+        assert_eq!(recount_active_posts(), 1);
+    }
+}
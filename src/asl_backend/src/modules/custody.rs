@@ -0,0 +1,598 @@
+use candid::{CandidType, Principal};
+use ic_cdk::{query, update};
+use serde::{Deserialize, Serialize};
+
+use crate::modules::types::*;
+use crate::modules::storage::{get_next_id, IdKind, ARTIFACTS, CUSTODY_TRANSFERS, NFTS, USERS};
+use crate::modules::auth::{can_moderate, get_caller};
+use crate::modules::utils::get_time;
+use crate::modules::audit::log_audit_event;
+use crate::modules::ai_analysis::{append_provenance_entry, ProvenanceEventType};
+use crate::modules::icrc7::apply_transfer;
+use crate::modules::dao::create_proposal;
+use crate::modules::governance::rule_for;
+
+// ============================================================================
+// CUSTODY TRANSFERS
+// ============================================================================
+//
+// Heritage NFTs change hands through a two-phase workflow rather than through
+// `icrc7_transfer`:
+//
+//   offer_custody_transfer     the owner names the new custodian       -> Offered
+//   accept_custody_transfer    the new custodian accepts               -> Completed
+//                              or, for National significance and above,
+//                              an ApproveCustodyTransfer proposal opens -> AwaitingApproval
+//   executing the proposal     moves the NFT                           -> Completed
+//   the proposal is rejected or expires                                -> Rejected
+//
+// Until it completes, the owner can cancel and the recipient can decline an
+// offer. Offers lapse after OFFER_TTL_NANOS. Every step appends a
+// `ProvenanceEventType::Transfer` entry to the artifact's history, and
+// completing a transfer moves `owner` through `icrc7::apply_transfer`.
+
+const OFFER_TTL_NANOS: u64 = 30 * 24 * 60 * 60 * 1_000_000_000;
+// Raised to the ApproveCustodyTransfer governance rule's minimum if that is longer
+const APPROVAL_VOTING_HOURS: u64 = 72;
+const MAX_TERMS_LENGTH: usize = 2_000;
+
+#[derive(CandidType, Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub enum CustodyTransferReason {
+    Loan,
+    Repatriation,
+    Sale,
+    Donation,
+    Other,
+}
+
+#[derive(CandidType, Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub enum CustodyTransferStatus {
+    Offered,
+    AwaitingApproval,
+    Completed,
+    Declined,
+    Cancelled,
+    Expired,
+    // The approval proposal was rejected or expired
+    Rejected,
+}
+
+#[derive(CandidType, Serialize, Deserialize, Clone, Debug)]
+pub struct CustodyTransfer {
+    pub id: u64,
+    pub nft_id: u64,
+    pub artifact_id: u64,
+    pub from: Principal,
+    pub to: Principal,
+    pub reason: CustodyTransferReason,
+    pub terms: String,
+    pub documentation: Vec<String>,
+    pub status: CustodyTransferStatus,
+    pub created_at: u64,
+    pub expires_at: u64,
+    pub accepted_at: Option<u64>,
+    pub completed_at: Option<u64>,
+    // The ApproveCustodyTransfer proposal, for transfers that need one
+    pub proposal_id: Option<u64>,
+}
+
+#[derive(CandidType, Serialize, Deserialize, Clone, Debug)]
+pub struct CustodyTransferRequest {
+    pub nft_id: u64,
+    pub to: Principal,
+    pub reason: CustodyTransferReason,
+    pub terms: String,
+    pub documentation: Vec<String>,
+}
+
+impl CustodyTransfer {
+    fn is_open(&self, now: u64) -> bool {
+        match self.status {
+            CustodyTransferStatus::Offered => now <= self.expires_at,
+            CustodyTransferStatus::AwaitingApproval => true,
+            _ => false,
+        }
+    }
+}
+
+fn get_transfer(transfer_id: u64) -> Result<CustodyTransfer, String> {
+    CUSTODY_TRANSFERS.with(|transfers| transfers.borrow().get(&transfer_id))
+        .ok_or_else(|| "Custody transfer not found".to_string())
+}
+
+fn save_transfer(transfer: &CustodyTransfer) {
+    CUSTODY_TRANSFERS.with(|transfers| {
+        transfers.borrow_mut().insert(transfer.id, transfer.clone());
+    });
+}
+
+// Transfers of artifacts of national significance or above need DAO approval
+fn requires_dao_approval(artifact_id: u64) -> bool {
+    ARTIFACTS.with(|artifacts| artifacts.borrow().get(&artifact_id))
+        .is_some_and(|artifact| matches!(
+            artifact.cultural_significance.significance_level,
+            SignificanceLevel::National | SignificanceLevel::International | SignificanceLevel::WorldHeritage
+        ))
+}
+
+// Steps are recorded once they took effect, so a failure to append the
+// provenance entry is logged rather than reported as a failed step
+fn record_step(transfer: &CustodyTransfer, actor: Principal, step: &str) {
+    let details = format!(
+        "Custody transfer {} {}: {} -> {} ({:?})",
+        transfer.id, step, transfer.from.to_text(), transfer.to.to_text(), transfer.reason
+    );
+    let recorded = append_provenance_entry(transfer.artifact_id, ProvenanceEventType::Transfer, actor, details, &transfer.documentation);
+    if let Err(error) = recorded {
+        log_audit_event(
+            AuditEventType::SystemMaintenance,
+            Some(transfer.nft_id),
+            format!("Custody transfer {} {} but its provenance entry failed: {}", transfer.id, step, error),
+            AuditSeverity::Warning
+        );
+    }
+}
+
+// Marks a lapsed offer as expired; returns whether it had lapsed
+fn expire_if_lapsed(transfer: &mut CustodyTransfer, now: u64) -> bool {
+    if transfer.status != CustodyTransferStatus::Offered || now <= transfer.expires_at {
+        return false;
+    }
+    transfer.status = CustodyTransferStatus::Expired;
+    save_transfer(transfer);
+    record_step(transfer, get_caller(), "expired");
+    true
+}
+
+fn complete_transfer(transfer: &mut CustodyTransfer, actor: Principal, now: u64) -> Result<(), String> {
+    let owner = NFTS.with(|nfts| nfts.borrow().get(&transfer.nft_id))
+        .map(|nft| nft.owner)
+        .ok_or_else(|| "Heritage NFT not found".to_string())?;
+    if owner != transfer.from {
+        return Err("The Heritage NFT changed owner since the transfer was offered".to_string());
+    }
+
+    apply_transfer(transfer.nft_id, transfer.to, None, None, now)?;
+    transfer.status = CustodyTransferStatus::Completed;
+    transfer.completed_at = Some(now);
+    save_transfer(transfer);
+    record_step(transfer, actor, "completed");
+
+    log_audit_event(
+        AuditEventType::DataModification,
+        Some(transfer.nft_id),
+        format!("Custody transfer {} completed", transfer.id),
+        AuditSeverity::Info
+    );
+    Ok(())
+}
+
+// ============================================================================
+// WORKFLOW
+// ============================================================================
+
+#[update]
+pub fn offer_custody_transfer(request: CustodyTransferRequest) -> Result<u64, String> {
+    let caller = get_caller();
+    let now = get_time();
+
+    let nft = NFTS.with(|nfts| nfts.borrow().get(&request.nft_id))
+        .ok_or_else(|| "Heritage NFT not found".to_string())?;
+    if nft.owner != caller {
+        return Err("Only the owner of a Heritage NFT can offer its custody".to_string());
+    }
+    if request.to == caller || request.to == Principal::anonymous() {
+        return Err("Invalid recipient".to_string());
+    }
+    if USERS.with(|users| !users.borrow().contains_key(&request.to)) {
+        return Err("The recipient must be a registered user".to_string());
+    }
+    if request.terms.len() > MAX_TERMS_LENGTH {
+        return Err(format!("Transfer terms cannot exceed {} characters", MAX_TERMS_LENGTH));
+    }
+
+    let has_open_transfer = CUSTODY_TRANSFERS.with(|transfers| {
+        transfers.borrow().iter().any(|(_, transfer)| transfer.nft_id == request.nft_id && transfer.is_open(now))
+    });
+    if has_open_transfer {
+        return Err("This Heritage NFT already has an open custody transfer".to_string());
+    }
+
+    let transfer = CustodyTransfer {
+        id: get_next_id(IdKind::CustodyTransfer),
+        nft_id: request.nft_id,
+        artifact_id: nft.artifact_id,
+        from: caller,
+        to: request.to,
+        reason: request.reason,
+        terms: request.terms,
+        documentation: request.documentation,
+        status: CustodyTransferStatus::Offered,
+        created_at: now,
+        expires_at: now + OFFER_TTL_NANOS,
+        accepted_at: None,
+        completed_at: None,
+        proposal_id: None,
+    };
+    save_transfer(&transfer);
+    record_step(&transfer, caller, "offered");
+
+    log_audit_event(
+        AuditEventType::DataModification,
+        Some(transfer.nft_id),
+        format!("Custody transfer {} offered to {}", transfer.id, transfer.to.to_text()),
+        AuditSeverity::Info
+    );
+    Ok(transfer.id)
+}
+
+#[update]
+pub fn accept_custody_transfer(transfer_id: u64) -> Result<CustodyTransfer, String> {
+    let caller = get_caller();
+    let now = get_time();
+    let mut transfer = get_transfer(transfer_id)?;
+
+    if transfer.to != caller {
+        return Err("Only the recipient can accept a custody transfer".to_string());
+    }
+    if expire_if_lapsed(&mut transfer, now) {
+        return Err("The custody transfer offer has expired".to_string());
+    }
+    if transfer.status != CustodyTransferStatus::Offered {
+        return Err(format!("Custody transfer is {:?}", transfer.status));
+    }
+
+    transfer.accepted_at = Some(now);
+    if !requires_dao_approval(transfer.artifact_id) {
+        complete_transfer(&mut transfer, caller, now)?;
+        return Ok(transfer);
+    }
+
+    // The proposal validates against the AwaitingApproval state, so save it first
+    let offered = transfer.clone();
+    transfer.status = CustodyTransferStatus::AwaitingApproval;
+    save_transfer(&transfer);
+
+    let proposal = create_proposal(CreateProposalRequest {
+        proposal_type: ProposalType::ApproveCustodyTransfer,
+        artifact_id: Some(transfer.artifact_id),
+        title: format!("Approve custody transfer of Heritage NFT {}", transfer.nft_id),
+        description: format!(
+            "Transfer custody of artifact {} from {} to {} ({:?}). Terms: {}",
+            transfer.artifact_id, transfer.from.to_text(), transfer.to.to_text(), transfer.reason, transfer.terms
+        ),
+        evidence: Some(transfer.documentation.clone()),
+        voting_duration_hours: APPROVAL_VOTING_HOURS.max(rule_for(&ProposalType::ApproveCustodyTransfer).min_voting_hours),
+        execution_payload: Some(ProposalAction::ApproveCustodyTransfer { transfer_id }),
+        required_expertise: None,
+        urgency_level: None,
+        quorum_required: None,
+        voting_mode: None,
+    });
+
+    match proposal {
+        Ok(proposal_id) => {
+            transfer.proposal_id = Some(proposal_id);
+            save_transfer(&transfer);
+            record_step(&transfer, caller, &format!("accepted, awaiting approval by proposal {}", proposal_id));
+            Ok(transfer)
+        },
+        Err(error) => {
+            save_transfer(&offered);
+            Err(format!("Failed to open the approval proposal: {}", error))
+        }
+    }
+}
+
+#[update]
+pub fn decline_custody_transfer(transfer_id: u64) -> Result<CustodyTransfer, String> {
+    let caller = get_caller();
+    let mut transfer = get_transfer(transfer_id)?;
+    if transfer.to != caller {
+        return Err("Only the recipient can decline a custody transfer".to_string());
+    }
+    close_transfer(&mut transfer, caller, CustodyTransferStatus::Declined, "declined")?;
+    Ok(transfer)
+}
+
+#[update]
+pub fn cancel_custody_transfer(transfer_id: u64) -> Result<CustodyTransfer, String> {
+    let caller = get_caller();
+    let mut transfer = get_transfer(transfer_id)?;
+    if transfer.from != caller && !can_moderate(caller) {
+        return Err("Only the owner or a moderator can cancel a custody transfer".to_string());
+    }
+    close_transfer(&mut transfer, caller, CustodyTransferStatus::Cancelled, "cancelled")?;
+    Ok(transfer)
+}
+
+fn close_transfer(transfer: &mut CustodyTransfer, actor: Principal, status: CustodyTransferStatus, step: &str) -> Result<(), String> {
+    if expire_if_lapsed(transfer, get_time()) {
+        return Err("The custody transfer offer has expired".to_string());
+    }
+    if !matches!(transfer.status, CustodyTransferStatus::Offered | CustodyTransferStatus::AwaitingApproval) {
+        return Err(format!("Custody transfer is {:?}", transfer.status));
+    }
+    transfer.status = status;
+    save_transfer(transfer);
+    record_step(transfer, actor, step);
+    Ok(())
+}
+
+// Checks an ApproveCustodyTransfer payload when its proposal is created
+pub fn validate_approval_request(transfer_id: u64, artifact_id: Option<u64>) -> Result<(), String> {
+    let transfer = get_transfer(transfer_id)?;
+    if transfer.status != CustodyTransferStatus::AwaitingApproval || transfer.proposal_id.is_some() {
+        return Err("Custody transfer is not awaiting an approval proposal".to_string());
+    }
+    if artifact_id != Some(transfer.artifact_id) {
+        return Err("Proposal artifact does not match the custody transfer".to_string());
+    }
+    Ok(())
+}
+
+// Executes a passed ApproveCustodyTransfer proposal
+pub fn complete_approved_transfer(proposal: &Proposal, transfer_id: u64) -> Result<String, String> {
+    let mut transfer = get_transfer(transfer_id)?;
+    if transfer.status != CustodyTransferStatus::AwaitingApproval || transfer.proposal_id != Some(proposal.id) {
+        return Err("Custody transfer is not awaiting approval by this proposal".to_string());
+    }
+    complete_transfer(&mut transfer, get_caller(), get_time())?;
+    Ok(format!("Custody transfer {} completed", transfer_id))
+}
+
+// Called whenever a proposal is rejected or expires: closes the custody
+// transfer awaiting it, which could otherwise never complete nor be reoffered
+pub fn close_unapproved_transfer(proposal: &Proposal) {
+    let Some(ProposalAction::ApproveCustodyTransfer { transfer_id }) = proposal.execution_payload else { return };
+    if !matches!(proposal.status, ProposalStatus::Rejected | ProposalStatus::Expired) {
+        return;
+    }
+    let Ok(mut transfer) = get_transfer(transfer_id) else { return };
+    if transfer.status != CustodyTransferStatus::AwaitingApproval || transfer.proposal_id != Some(proposal.id) {
+        return;
+    }
+
+    transfer.status = CustodyTransferStatus::Rejected;
+    save_transfer(&transfer);
+    let step = format!("not approved, proposal {} is {:?}", proposal.id, proposal.status);
+    record_step(&transfer, get_caller(), &step);
+}
+
+// ============================================================================
+// QUERIES
+// ============================================================================
+
+#[query]
+pub fn get_custody_transfer(transfer_id: u64) -> Result<CustodyTransfer, String> {
+    get_transfer(transfer_id)
+}
+
+#[query]
+pub fn get_custody_transfers_for_nft(nft_id: u64) -> Vec<CustodyTransfer> {
+    CUSTODY_TRANSFERS.with(|transfers| {
+        transfers.borrow().iter()
+            .map(|(_, transfer)| transfer)
+            .filter(|transfer| transfer.nft_id == nft_id)
+            .collect()
+    })
+}
+
+// Open transfers the principal has offered or been offered
+#[query]
+pub fn get_pending_custody_transfers(principal: Principal) -> Vec<CustodyTransfer> {
+    let now = get_time();
+    CUSTODY_TRANSFERS.with(|transfers| {
+        transfers.borrow().iter()
+            .map(|(_, transfer)| transfer)
+            .filter(|transfer| (transfer.from == principal || transfer.to == principal) && transfer.is_open(now))
+            .collect()
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::modules::artifacts::store_artifact;
+    use crate::modules::dao::{execute_passed_proposal, get_proposal, store_proposal};
+    use crate::modules::nft::store_nft;
+    use crate::modules::scheduler::run_proposal_lifecycle;
+    use crate::modules::storage::PROPOSALS;
+    use crate::modules::testing::{advance_time, artifact_request, create_artifact_as, now, principal, register, set_caller, HOUR};
+
+    const NFT_ID: u64 = 1;
+
+    // An artifact of `level` owned by principal 1 through Heritage NFT 1, and a
+    // registered principal 2 to receive it
+    fn heritage_nft(level: SignificanceLevel) -> u64 {
+        let artifact_id = create_artifact_as(principal(1), artifact_request("Bronze mirror"));
+        ARTIFACTS.with(|artifacts| {
+            let mut artifacts = artifacts.borrow_mut();
+            let mut artifact = artifacts.get(&artifact_id).unwrap();
+            artifact.cultural_significance.significance_level = level;
+            store_artifact(&mut artifacts, artifact);
+        });
+        let nft = ProofOfHeritageNFT {
+            id: NFT_ID,
+            artifact_id,
+            owner: principal(1),
+            created_at: now(),
+            metadata: Vec::new(),
+            is_transferable: false,
+            verification_hash: String::new(),
+            issuing_authority: principal(1),
+            heritage_certificate: HeritageCertificate {
+                certificate_number: "HER-000001-0".to_string(),
+                issuer_name: "Test Museum".to_string(),
+                verification_date: now(),
+                authenticity_guarantees: Vec::new(),
+                scientific_analysis: Vec::new(),
+                expert_endorsements: Vec::new(),
+                digital_signature: String::new(),
+            },
+            access_rights: AccessRights {
+                can_view_detailed_metadata: true,
+                can_request_high_res_images: false,
+                can_access_research_data: false,
+                can_propose_studies: false,
+                special_permissions: Vec::new(),
+            },
+        };
+        NFTS.with(|nfts| store_nft(&mut nfts.borrow_mut(), nft));
+        register(principal(2), UserRole::Institution, &[]);
+        artifact_id
+    }
+
+    fn offer() -> Result<u64, String> {
+        set_caller(principal(1));
+        offer_custody_transfer(CustodyTransferRequest {
+            nft_id: NFT_ID,
+            to: principal(2),
+            reason: CustodyTransferReason::Loan,
+            terms: "Returned after the exhibition".to_string(),
+            documentation: vec!["loan-agreement.pdf".to_string()],
+        })
+    }
+
+    fn accept(transfer_id: u64) -> Result<CustodyTransfer, String> {
+        set_caller(principal(2));
+        accept_custody_transfer(transfer_id)
+    }
+
+    fn owner() -> Principal {
+        NFTS.with(|nfts| nfts.borrow().get(&NFT_ID).unwrap().owner)
+    }
+
+    fn custody_steps(artifact_id: u64) -> Vec<String> {
+        let history = ARTIFACTS.with(|artifacts| artifacts.borrow().get(&artifact_id).unwrap().history);
+        history.into_iter()
+            .filter(|entry| entry.action == "Provenance: Transfer")
+            .map(|entry| entry.details.split(':').next().unwrap().to_string())
+            .collect()
+    }
+
+    #[test]
+    fn accepted_offers_move_the_nft() {
+        let artifact_id = heritage_nft(SignificanceLevel::Local);
+        let transfer_id = offer().unwrap();
+        assert_eq!(offer().unwrap_err(), "This Heritage NFT already has an open custody transfer");
+
+        set_caller(principal(3));
+        assert!(accept_custody_transfer(transfer_id).is_err());
+        let transfer = accept(transfer_id).unwrap();
+        assert_eq!(transfer.status, CustodyTransferStatus::Completed);
+        assert_eq!(transfer.completed_at, Some(now()));
+        assert_eq!(owner(), principal(2));
+        assert_eq!(custody_steps(artifact_id), vec![
+            format!("Custody transfer {} offered", transfer_id),
+            format!("Custody transfer {} completed", transfer_id),
+        ]);
+
+        // The previous owner has nothing left to offer
+        assert!(offer().is_err());
+    }
+
+    #[test]
+    fn declined_and_cancelled_offers_close() {
+        heritage_nft(SignificanceLevel::Local);
+        let declined = offer().unwrap();
+        set_caller(principal(1));
+        assert!(decline_custody_transfer(declined).is_err());
+        set_caller(principal(2));
+        assert_eq!(decline_custody_transfer(declined).unwrap().status, CustodyTransferStatus::Declined);
+        assert_eq!(accept(declined).unwrap_err(), "Custody transfer is Declined");
+
+        let cancelled = offer().unwrap();
+        set_caller(principal(2));
+        assert!(cancel_custody_transfer(cancelled).is_err());
+        set_caller(principal(1));
+        assert_eq!(cancel_custody_transfer(cancelled).unwrap().status, CustodyTransferStatus::Cancelled);
+        assert!(get_pending_custody_transfers(principal(1)).is_empty());
+        assert_eq!(owner(), principal(1));
+    }
+
+    #[test]
+    fn lapsed_offers_expire() {
+        let artifact_id = heritage_nft(SignificanceLevel::Local);
+        let transfer_id = offer().unwrap();
+        assert_eq!(get_pending_custody_transfers(principal(2)).len(), 1);
+
+        advance_time(OFFER_TTL_NANOS + 1);
+        assert!(get_pending_custody_transfers(principal(2)).is_empty());
+        assert_eq!(accept(transfer_id).unwrap_err(), "The custody transfer offer has expired");
+        assert_eq!(get_custody_transfer(transfer_id).unwrap().status, CustodyTransferStatus::Expired);
+        assert_eq!(custody_steps(artifact_id).last().unwrap(), &format!("Custody transfer {} expired", transfer_id));
+
+        // A lapsed offer no longer blocks a new one
+        assert!(offer().is_ok());
+    }
+
+    #[test]
+    fn significant_artifacts_wait_for_approval() {
+        heritage_nft(SignificanceLevel::National);
+        let transfer_id = offer().unwrap();
+        let transfer = accept(transfer_id).unwrap();
+        assert_eq!(transfer.status, CustodyTransferStatus::AwaitingApproval);
+        assert_eq!(owner(), principal(1));
+
+        let proposal_id = transfer.proposal_id.unwrap();
+        let proposal = get_proposal(proposal_id).unwrap();
+        let hours = APPROVAL_VOTING_HOURS.max(rule_for(&ProposalType::ApproveCustodyTransfer).min_voting_hours);
+        assert_eq!(proposal.voting_deadline - proposal.created_at, hours * HOUR);
+        assert!(matches!(proposal.execution_payload, Some(ProposalAction::ApproveCustodyTransfer { transfer_id: id }) if id == transfer_id));
+        // Awaiting approval still blocks another offer
+        assert!(offer().is_err());
+
+        PROPOSALS.with(|proposals| {
+            let mut proposals = proposals.borrow_mut();
+            let mut proposal = proposals.get(&proposal_id).unwrap();
+            proposal.status = ProposalStatus::Passed;
+            store_proposal(&mut proposals, proposal);
+        });
+        execute_passed_proposal(proposal_id, now()).unwrap();
+        assert_eq!(get_custody_transfer(transfer_id).unwrap().status, CustodyTransferStatus::Completed);
+        assert_eq!(owner(), principal(2));
+    }
+
+    #[test]
+    fn unapproved_transfers_are_closed() {
+        let artifact_id = heritage_nft(SignificanceLevel::National);
+
+        // Nobody votes, so the proposal expires without quorum
+        let unvoted = accept(offer().unwrap()).unwrap();
+        let proposal = get_proposal(unvoted.proposal_id.unwrap()).unwrap();
+        advance_time(proposal.voting_deadline - now() + 1);
+        run_proposal_lifecycle(now());
+        assert_eq!(get_proposal(proposal.id).unwrap().status, ProposalStatus::Expired);
+        assert_eq!(get_custody_transfer(unvoted.id).unwrap().status, CustodyTransferStatus::Rejected);
+
+        // Passed but never executed in time
+        let unexecuted = accept(offer().unwrap()).unwrap();
+        let proposal_id = unexecuted.proposal_id.unwrap();
+        PROPOSALS.with(|proposals| {
+            let mut proposals = proposals.borrow_mut();
+            let mut proposal = proposals.get(&proposal_id).unwrap();
+            proposal.status = ProposalStatus::Passed;
+            store_proposal(&mut proposals, proposal);
+        });
+        let deadline = get_proposal(proposal_id).unwrap().execution_deadline.unwrap();
+        assert!(execute_passed_proposal(proposal_id, deadline + 1).is_err());
+        assert_eq!(get_custody_transfer(unexecuted.id).unwrap().status, CustodyTransferStatus::Rejected);
+
+        assert_eq!(owner(), principal(1));
+        assert_eq!(custody_steps(artifact_id).last().unwrap(), &format!("Custody transfer {} not approved, proposal {} is Expired", unexecuted.id, proposal_id));
+        assert!(offer().is_ok());
+    }
+
+    #[test]
+    fn completion_survives_a_missing_provenance_entry() {
+        let artifact_id = heritage_nft(SignificanceLevel::Local);
+        let transfer_id = offer().unwrap();
+        ARTIFACTS.with(|artifacts| artifacts.borrow_mut().remove(&artifact_id));
+
+        let transfer = accept(transfer_id).unwrap();
+        assert_eq!(transfer.status, CustodyTransferStatus::Completed);
+        assert_eq!(get_custody_transfer(transfer_id).unwrap().status, CustodyTransferStatus::Completed);
+        assert_eq!(owner(), principal(2));
+    }
+}
@@ -1,3 +1,4 @@
+use candid::Principal;
 use ic_cdk::{query, update};
 use std::collections::BTreeSet;
 
@@ -6,6 +7,12 @@ use crate::modules::storage::*;
 use crate::modules::auth::*;
 use crate::modules::utils::*;
 use crate::modules::audit::log_audit_event;
+use crate::modules::artifacts::{record_changes, store_artifact};
+use crate::modules::custody::{close_unapproved_transfer, complete_approved_transfer, validate_approval_request};
+use crate::modules::reviews::ensure_no_open_round;
+use crate::modules::tally::voting_mode_for;
+use crate::modules::governance::{quorum_for, rule_for, update_governance_rules, validate_rule_changes};
+use crate::modules::scheduler::{get_scheduler_config, proposal_due_at};
 
 // ============================================================================
 // DAO GOVERNANCE SYSTEM
@@ -34,14 +41,34 @@ pub fn create_proposal(request: CreateProposalRequest) -> Result<u64, String> {
         })?;
     }
 
-    let proposal_id = get_next_id(2); // Proposal ID counter
+    // Validate the executable payload against the proposal type
+    let execution_payload = resolve_proposal_action(&request)?;
+
+    // One verification round per artifact at a time
+    if let (ProposalType::VerifyArtifact, Some(artifact_id)) = (&request.proposal_type, request.artifact_id) {
+        ensure_no_open_round(artifact_id)?;
+    }
+
+    let rule = rule_for(&request.proposal_type);
+    if request.voting_duration_hours < rule.min_voting_hours {
+        return Err(format!(
+            "{:?} proposals need a voting period of at least {} hours",
+            request.proposal_type, rule.min_voting_hours
+        ));
+    }
+
+    let voting_mode = voting_mode_for(&request.proposal_type);
+    if request.voting_mode.as_ref().is_some_and(|requested| *requested != voting_mode) {
+        return Err(format!("{:?} proposals are tallied by {:?} voting", request.proposal_type, voting_mode));
+    }
+
+    let proposal_id = get_next_id(IdKind::Proposal);
     let now = get_time();
     let voting_deadline = calculate_voting_deadline(request.voting_duration_hours);
     
-    // Calculate appropriate quorum based on proposal type
-    let total_eligible_voters = count_eligible_voters();
-    let quorum_required = request.quorum_required
-        .unwrap_or_else(|| calculate_quorum(total_eligible_voters, &request.proposal_type));
+    // Calculate appropriate quorum based on proposal type; a proposer may only raise it
+    let rule_quorum = quorum_for(&request.proposal_type, count_eligible_voters());
+    let quorum_required = request.quorum_required.map_or(rule_quorum, |quorum| quorum.max(rule_quorum));
 
     let proposal = Proposal {
         id: proposal_id,
@@ -55,6 +82,8 @@ pub fn create_proposal(request: CreateProposalRequest) -> Result<u64, String> {
         voting_deadline,
         execution_deadline: Some(voting_deadline + (24 * 3600 * 1_000_000_000)), // 24 hours after voting
         quorum_required,
+        pass_threshold_percent: rule.pass_threshold_percent,
+        abstention: rule.abstention,
         status: ProposalStatus::Active,
         voting_results: VotingResults {
             total_votes: 0,
@@ -64,15 +93,17 @@ pub fn create_proposal(request: CreateProposalRequest) -> Result<u64, String> {
             weighted_score: 0.0,
             voter_principals: BTreeSet::new(),
             expert_consensus: None,
+            breakdown: None,
         },
-        execution_payload: request.execution_payload,
+        execution_payload: Some(execution_payload),
         discussion_thread: Vec::new(),
         required_expertise: request.required_expertise.unwrap_or_default(),
         urgency_level: request.urgency_level.unwrap_or(UrgencyLevel::Normal),
+        voting_mode,
     };
 
     PROPOSALS.with(|proposals| {
-        proposals.borrow_mut().insert(proposal_id, proposal);
+        store_proposal(&mut proposals.borrow_mut(), proposal);
     });
 
     // Update user stats
@@ -98,12 +129,22 @@ pub fn create_proposal(request: CreateProposalRequest) -> Result<u64, String> {
 #[update]
 pub fn execute_proposal(proposal_id: u64) -> Result<String, String> {
     let caller = get_caller();
-    
+    let proposal = get_proposal(proposal_id)?;
+
+    // Permission check for execution
+    if !can_moderate(caller) && proposal.proposer != caller {
+        return Err("Only the proposer or a moderator can execute this proposal".to_string());
+    }
+
+    execute_passed_proposal(proposal_id, get_time())
+}
+
+// Applies a passed proposal's payload. Callers check who may execute it; the
+// proposal scheduler calls this directly for auto-executed types.
+pub fn execute_passed_proposal(proposal_id: u64, now: u64) -> Result<String, String> {
     PROPOSALS.with(|proposals| {
         let mut proposals = proposals.borrow_mut();
         if let Some(mut proposal) = proposals.get(&proposal_id) {
-            let now = get_time();
-            
             // Enhanced execution checks
             if proposal.status != ProposalStatus::Passed {
                 return Err("Proposal must be passed before execution".to_string());
@@ -113,45 +154,27 @@ pub fn execute_proposal(proposal_id: u64) -> Result<String, String> {
             if let Some(deadline) = proposal.execution_deadline {
                 if now > deadline {
                     proposal.status = ProposalStatus::Expired;
-                    proposals.insert(proposal_id, proposal);
+                    close_unapproved_transfer(&proposal);
+                    store_proposal(&mut proposals, proposal);
                     return Err("Proposal execution deadline has passed".to_string());
                 }
             }
 
-            // Permission check for execution
-            if !can_moderate(caller) && proposal.proposer != caller {
-                return Err("Only the proposer or a moderator can execute this proposal".to_string());
-            }
+            // Execute the typed payload; legacy proposals without one only support verification
+            let action = proposal.execution_payload.clone().or_else(|| {
+                matches!(proposal.proposal_type, ProposalType::VerifyArtifact)
+                    .then_some(ProposalAction::VerifyArtifact)
+            });
 
-            // Execute based on proposal type
-            let execution_result = match proposal.proposal_type {
-                ProposalType::VerifyArtifact => {
-                    execute_verify_artifact(&proposal)
-                },
-                ProposalType::DisputeArtifact => {
-                    execute_dispute_artifact(&proposal)
-                },
-                ProposalType::UpdateArtifactStatus => {
-                    execute_update_artifact_status(&proposal)
-                },
-                ProposalType::GrantUserRole => {
-                    execute_grant_user_role(&proposal)
-                },
-                ProposalType::RevokeUserRole => {
-                    execute_revoke_user_role(&proposal)
-                },
-                ProposalType::UpdateArtifactMetadata => {
-                    execute_update_artifact_metadata(&proposal)
-                },
-                _ => {
-                    Ok("Proposal type requires manual execution".to_string())
-                }
+            let execution_result = match action {
+                Some(action) => apply_proposal_action(&proposal, action),
+                None => Err("Proposal has no execution payload".to_string()),
             };
 
             match execution_result {
                 Ok(result) => {
                     proposal.status = ProposalStatus::Executed;
-                    proposals.insert(proposal_id, proposal);
+                    store_proposal(&mut proposals, proposal);
                     
                     log_audit_event(
                         AuditEventType::SystemMaintenance,
@@ -164,7 +187,7 @@ pub fn execute_proposal(proposal_id: u64) -> Result<String, String> {
                 },
                 Err(error) => {
                     proposal.status = ProposalStatus::FailedExecution;
-                    proposals.insert(proposal_id, proposal);
+                    store_proposal(&mut proposals, proposal);
                     
                     log_audit_event(
                         AuditEventType::SystemMaintenance,
@@ -197,7 +220,7 @@ pub fn add_comment_to_proposal(proposal_id: u64, content: String, reply_to: Opti
     PROPOSALS.with(|proposals| {
         let mut proposals = proposals.borrow_mut();
         if let Some(mut proposal) = proposals.get(&proposal_id) {
-            let comment_id = get_next_id(7); // Comment ID counter
+            let comment_id = get_next_id(IdKind::Comment);
             
             let comment = Comment {
                 id: comment_id,
@@ -209,7 +232,7 @@ pub fn add_comment_to_proposal(proposal_id: u64, content: String, reply_to: Opti
             };
             
             proposal.discussion_thread.push(comment);
-            proposals.insert(proposal_id, proposal);
+            store_proposal(&mut proposals, proposal);
             
             Ok(comment_id)
         } else {
@@ -218,66 +241,270 @@ pub fn add_comment_to_proposal(proposal_id: u64, content: String, reply_to: Opti
     })
 }
 
+// Helper functions for proposal validation
+fn resolve_proposal_action(request: &CreateProposalRequest) -> Result<ProposalAction, String> {
+    let action = match (&request.execution_payload, &request.proposal_type) {
+        (Some(action), _) => action.clone(),
+        (None, ProposalType::VerifyArtifact) => ProposalAction::VerifyArtifact,
+        (None, proposal_type) => {
+            return Err(format!("Proposal type {:?} requires an execution payload", proposal_type));
+        }
+    };
+
+    validate_proposal_action(&request.proposal_type, request.artifact_id, &action)?;
+    Ok(action)
+}
+
+fn validate_proposal_action(proposal_type: &ProposalType, artifact_id: Option<u64>, action: &ProposalAction) -> Result<(), String> {
+    let matches_type = matches!(
+        (proposal_type, action),
+        (ProposalType::VerifyArtifact, ProposalAction::VerifyArtifact) |
+        (ProposalType::DisputeArtifact, ProposalAction::DisputeArtifact { .. }) |
+        (ProposalType::UpdateArtifactStatus, ProposalAction::SetArtifactStatus { .. }) |
+        (ProposalType::GrantUserRole, ProposalAction::GrantRole { .. }) |
+        (ProposalType::RevokeUserRole, ProposalAction::RevokeRole { .. }) |
+        (ProposalType::UpdateArtifactMetadata, ProposalAction::PatchMetadata { .. }) |
+        (ProposalType::RequestAdditionalEvidence, ProposalAction::RequestEvidence { .. }) |
+        (ProposalType::ProposeConservationAction, ProposalAction::SetConservationStatus { .. }) |
+        (ProposalType::RequestExpertReview, ProposalAction::RequestExpertReview { .. }) |
+        (ProposalType::UpdateVerificationCriteria, ProposalAction::UpdateVerificationCriteria { .. }) |
+        (ProposalType::EmergencyIntervention, ProposalAction::EmergencyIntervention { .. }) |
+        (ProposalType::ApproveCustodyTransfer, ProposalAction::ApproveCustodyTransfer { .. })
+    );
+
+    if !matches_type {
+        return Err(format!("Execution payload does not match proposal type {:?}", proposal_type));
+    }
+
+    if action_targets_artifact(action) && artifact_id.is_none() {
+        return Err("No artifact ID specified in proposal".to_string());
+    }
+
+    match action {
+        ProposalAction::DisputeArtifact { reason } |
+        ProposalAction::SetArtifactStatus { reason, .. } |
+        ProposalAction::EmergencyIntervention { reason, .. } => {
+            if reason.trim().is_empty() {
+                return Err("A reason is required for this proposal".to_string());
+            }
+        },
+        ProposalAction::GrantRole { principal, .. } => {
+            if USERS.with(|users| !users.borrow().contains_key(principal)) {
+                return Err("Target user not found".to_string());
+            }
+        },
+        ProposalAction::RevokeRole { principal, role } => {
+            let current_role = USERS.with(|users| users.borrow().get(principal).map(|user| user.role))
+                .ok_or_else(|| "Target user not found".to_string())?;
+            if std::mem::discriminant(&current_role) != std::mem::discriminant(role) {
+                return Err(format!("Target user does not hold the {:?} role", role));
+            }
+        },
+        ProposalAction::PatchMetadata { set, remove } => {
+            if set.is_empty() && remove.is_empty() {
+                return Err("Metadata patch cannot be empty".to_string());
+            }
+            if set.iter().any(|(key, _)| key.trim().is_empty()) {
+                return Err("Metadata keys cannot be empty".to_string());
+            }
+        },
+        ProposalAction::RequestEvidence { requested_evidence } => {
+            if requested_evidence.is_empty() {
+                return Err("At least one evidence request is required".to_string());
+            }
+        },
+        ProposalAction::RequestExpertReview { expertise_areas } => {
+            if expertise_areas.is_empty() {
+                return Err("At least one expertise area is required".to_string());
+            }
+        },
+        ProposalAction::UpdateVerificationCriteria { criteria } => {
+            if criteria.is_empty() {
+                return Err("At least one verification criterion is required".to_string());
+            }
+            validate_rule_changes(criteria)?;
+        },
+        ProposalAction::ApproveCustodyTransfer { transfer_id } => {
+            validate_approval_request(*transfer_id, artifact_id)?;
+        },
+        ProposalAction::VerifyArtifact | ProposalAction::SetConservationStatus { .. } => {}
+    }
+
+    Ok(())
+}
+
+fn action_targets_artifact(action: &ProposalAction) -> bool {
+    !matches!(
+        action,
+        ProposalAction::GrantRole { .. } |
+        ProposalAction::RevokeRole { .. } |
+        ProposalAction::UpdateVerificationCriteria { .. }
+    )
+}
+
 // Helper functions for proposal execution
+fn apply_proposal_action(proposal: &Proposal, action: ProposalAction) -> Result<String, String> {
+    match action {
+        ProposalAction::VerifyArtifact => execute_verify_artifact(proposal),
+        ProposalAction::DisputeArtifact { reason } => execute_dispute_artifact(proposal, reason),
+        ProposalAction::SetArtifactStatus { status, reason } => execute_update_artifact_status(proposal, status, reason),
+        ProposalAction::GrantRole { principal, role } => execute_grant_user_role(principal, role),
+        ProposalAction::RevokeRole { principal, role } => execute_revoke_user_role(principal, role),
+        ProposalAction::PatchMetadata { set, remove } => execute_update_artifact_metadata(proposal, set, remove),
+        ProposalAction::RequestEvidence { requested_evidence } => execute_request_evidence(proposal, requested_evidence),
+        ProposalAction::SetConservationStatus { status, notes } => execute_conservation_action(proposal, status, notes),
+        ProposalAction::RequestExpertReview { expertise_areas } => execute_request_expert_review(proposal, expertise_areas),
+        ProposalAction::UpdateVerificationCriteria { criteria } => execute_update_verification_criteria(proposal, criteria),
+        ProposalAction::EmergencyIntervention { status, reason } => execute_emergency_intervention(proposal, status, reason),
+        ProposalAction::ApproveCustodyTransfer { transfer_id } => complete_approved_transfer(proposal, transfer_id),
+    }
+}
+
+fn update_proposal_artifact<F>(proposal: &Proposal, action: &str, details: String, apply: F) -> Result<u64, String>
+where
+    F: FnOnce(&mut Artifact),
+{
+    let artifact_id = proposal.artifact_id
+        .ok_or_else(|| "No artifact ID specified in proposal".to_string())?;
+
+    ARTIFACTS.with(|artifacts| {
+        let mut artifacts = artifacts.borrow_mut();
+        if let Some(mut artifact) = artifacts.get(&artifact_id) {
+            let before = artifact.clone();
+            apply(&mut artifact);
+            let note = format!("Proposal {}: {}", proposal.id, details);
+            record_changes(&mut artifact, &before, action, &note, None);
+            store_artifact(&mut artifacts, artifact);
+            Ok(artifact_id)
+        } else {
+            Err("Artifact not found".to_string())
+        }
+    })
+}
+
 fn execute_verify_artifact(proposal: &Proposal) -> Result<String, String> {
-    if let Some(artifact_id) = proposal.artifact_id {
-        ARTIFACTS.with(|artifacts| {
-            let mut artifacts = artifacts.borrow_mut();
-            if let Some(mut artifact) = artifacts.get(&artifact_id) {
-                artifact.status = ArtifactStatus::Verified;
-                artifact.verification_level = VerificationLevel::DaoVerified;
-                artifact.updated_at = get_time();
-                artifacts.insert(artifact_id, artifact);
-                Ok(format!("Artifact {} verified successfully", artifact_id))
-            } else {
-                Err("Artifact not found".to_string())
+    let artifact_id = update_proposal_artifact(proposal, "StatusChanged", "Verified through DAO vote".to_string(), |artifact| {
+        artifact.status = ArtifactStatus::Verified;
+        artifact.verification_level = VerificationLevel::DaoVerified;
+    })?;
+    Ok(format!("Artifact {} verified successfully", artifact_id))
+}
+
+fn execute_dispute_artifact(proposal: &Proposal, reason: String) -> Result<String, String> {
+    let artifact_id = update_proposal_artifact(proposal, "StatusChanged", format!("Disputed through DAO vote: {}", reason), |artifact| {
+        artifact.status = ArtifactStatus::Disputed;
+    })?;
+    Ok(format!("Artifact {} marked as disputed", artifact_id))
+}
+
+fn execute_update_artifact_status(proposal: &Proposal, status: ArtifactStatus, reason: String) -> Result<String, String> {
+    let details = format!("Status changed to {:?}: {}", status, reason);
+    let artifact_id = update_proposal_artifact(proposal, "StatusChanged", details, |artifact| {
+        artifact.status = status.clone();
+    })?;
+    Ok(format!("Artifact {} status updated to {:?}", artifact_id, status))
+}
+
+fn execute_grant_user_role(principal: Principal, role: UserRole) -> Result<String, String> {
+    USERS.with(|users| {
+        let mut users = users.borrow_mut();
+        if let Some(mut user) = users.get(&principal) {
+            let was_verified = user.verified_at.is_some();
+            user.role = role.clone();
+            user.permissions = default_permissions_for_role(&role);
+            // Verified users keep the voting rights granted at verification
+            user.permissions.can_vote |= was_verified;
+            users.insert(principal, user);
+            Ok(format!("Role {:?} granted to {}", role, principal))
+        } else {
+            Err("Target user not found".to_string())
+        }
+    })
+}
+
+fn execute_revoke_user_role(principal: Principal, role: UserRole) -> Result<String, String> {
+    USERS.with(|users| {
+        let mut users = users.borrow_mut();
+        if let Some(mut user) = users.get(&principal) {
+            if std::mem::discriminant(&user.role) != std::mem::discriminant(&role) {
+                return Err(format!("Target user no longer holds the {:?} role", role));
             }
-        })
-    } else {
-        Err("No artifact ID specified in proposal".to_string())
-    }
+            user.role = UserRole::Community;
+            user.permissions = default_permissions_for_role(&UserRole::Community);
+            users.insert(principal, user);
+            Ok(format!("Role {:?} revoked from {}", role, principal))
+        } else {
+            Err("Target user not found".to_string())
+        }
+    })
 }
 
-fn execute_dispute_artifact(proposal: &Proposal) -> Result<String, String> {
-    if let Some(artifact_id) = proposal.artifact_id {
-        ARTIFACTS.with(|artifacts| {
-            let mut artifacts = artifacts.borrow_mut();
-            if let Some(mut artifact) = artifacts.get(&artifact_id) {
-                artifact.status = ArtifactStatus::Disputed;
-                artifact.updated_at = get_time();
-                artifacts.insert(artifact_id, artifact);
-                Ok(format!("Artifact {} marked as disputed", artifact_id))
-            } else {
-                Err("Artifact not found".to_string())
+fn execute_update_artifact_metadata(proposal: &Proposal, set: Vec<(String, String)>, remove: Vec<String>) -> Result<String, String> {
+    let details = format!("Metadata patched ({} set, {} removed)", set.len(), remove.len());
+    let artifact_id = update_proposal_artifact(proposal, "MetadataUpdated", details, |artifact| {
+        artifact.metadata.retain(|(key, _)| !remove.contains(key));
+        for (key, value) in set {
+            match artifact.metadata.iter_mut().find(|(existing, _)| *existing == key) {
+                Some(entry) => entry.1 = value,
+                None => artifact.metadata.push((key, value)),
             }
-        })
-    } else {
-        Err("No artifact ID specified in proposal".to_string())
-    }
+        }
+    })?;
+    Ok(format!("Artifact {} metadata updated", artifact_id))
 }
 
-fn execute_update_artifact_status(_proposal: &Proposal) -> Result<String, String> {
-    // This would parse the execution payload to determine the new status
-    // For now, just return success
-    Ok("Artifact status updated".to_string())
+fn execute_request_evidence(proposal: &Proposal, requested_evidence: Vec<String>) -> Result<String, String> {
+    let details = format!("Additional evidence requested: {}", requested_evidence.join(", "));
+    let artifact_id = update_proposal_artifact(proposal, "StatusChanged", details, |artifact| {
+        artifact.status = ArtifactStatus::RequiresAdditionalEvidence;
+    })?;
+    Ok(format!("Additional evidence requested for artifact {}", artifact_id))
 }
 
-fn execute_grant_user_role(_proposal: &Proposal) -> Result<String, String> {
-    // This would parse the execution payload to determine user and role
-    // For now, just return success
-    Ok("User role granted".to_string())
+fn execute_conservation_action(proposal: &Proposal, status: ConservationStatus, notes: Vec<String>) -> Result<String, String> {
+    let details = format!("Conservation status set to {:?}", status);
+    let artifact_id = update_proposal_artifact(proposal, "ConservationUpdated", details, |artifact| {
+        artifact.conservation_status = status;
+        if let Some(properties) = artifact.physical_properties.as_mut() {
+            properties.conservation_notes.extend(notes);
+        }
+    })?;
+    Ok(format!("Conservation action recorded for artifact {}", artifact_id))
 }
 
-fn execute_revoke_user_role(_proposal: &Proposal) -> Result<String, String> {
-    // This would parse the execution payload to determine user and role
-    // For now, just return success
-    Ok("User role revoked".to_string())
+fn execute_request_expert_review(proposal: &Proposal, expertise_areas: Vec<String>) -> Result<String, String> {
+    let details = format!("Expert review requested: {}", expertise_areas.join(", "));
+    let artifact_id = update_proposal_artifact(proposal, "StatusChanged", details, |artifact| {
+        artifact.status = ArtifactStatus::UnderReview;
+    })?;
+    Ok(format!("Artifact {} placed under expert review", artifact_id))
 }
 
-fn execute_update_artifact_metadata(_proposal: &Proposal) -> Result<String, String> {
-    // This would parse the execution payload to update metadata
-    // For now, just return success
-    Ok("Artifact metadata updated".to_string())
+fn execute_update_verification_criteria(proposal: &Proposal, criteria: Vec<(String, String)>) -> Result<String, String> {
+    // Rules may have changed since the proposal was created, so they are checked again
+    let rules_changed = update_governance_rules(&criteria)?;
+
+    let summary = criteria.iter()
+        .map(|(key, value)| format!("{}={}", key, value))
+        .collect::<Vec<_>>()
+        .join(", ");
+
+    log_audit_event(
+        AuditEventType::SystemMaintenance,
+        Some(proposal.id),
+        format!("Verification criteria updated: {}", summary),
+        AuditSeverity::Info
+    );
+
+    Ok(format!("{} verification criteria recorded, {} governance rules updated", criteria.len(), rules_changed))
+}
+
+fn execute_emergency_intervention(proposal: &Proposal, status: ArtifactStatus, reason: String) -> Result<String, String> {
+    let details = format!("Emergency intervention, status set to {:?}: {}", status, reason);
+    let artifact_id = update_proposal_artifact(proposal, "EmergencyIntervention", details, |artifact| {
+        artifact.status = status.clone();
+    })?;
+    Ok(format!("Emergency intervention applied to artifact {}", artifact_id))
 }
 
 fn count_eligible_voters() -> u32 {
@@ -288,6 +515,41 @@ fn count_eligible_voters() -> u32 {
     })
 }
 
+// Every write to PROPOSALS goes through here so PROPOSAL_SCHEDULE follows
+// status changes
+pub fn store_proposal(proposals: &mut ProposalStore, proposal: Proposal) {
+    let config = get_scheduler_config();
+    let proposal_id = proposal.id;
+    let due_at = proposal_due_at(&proposal, &config);
+    let previous = proposals.insert(proposal_id, proposal);
+    PROPOSAL_SCHEDULE.with(|schedule| {
+        let mut schedule = schedule.borrow_mut();
+        if let Some(previous_due_at) = previous.and_then(|previous| proposal_due_at(&previous, &config)) {
+            schedule.remove(&(previous_due_at, proposal_id));
+        }
+        if let Some(due_at) = due_at {
+            schedule.insert((due_at, proposal_id), ());
+        }
+    });
+}
+
+// Recomputes the schedule from PROPOSALS; used after upgrades and when the
+// auto-execution settings change
+pub fn rebuild_proposal_schedule() {
+    let config = get_scheduler_config();
+    PROPOSAL_SCHEDULE.with(|schedule| {
+        let mut schedule = schedule.borrow_mut();
+        schedule.clear_new();
+        PROPOSALS.with(|proposals| {
+            for (proposal_id, proposal) in proposals.borrow().iter() {
+                if let Some(due_at) = proposal_due_at(&proposal, &config) {
+                    schedule.insert((due_at, proposal_id), ());
+                }
+            }
+        });
+    });
+}
+
 #[query]
 pub fn get_proposal(proposal_id: u64) -> Result<Proposal, String> {
     PROPOSALS.with(|proposals| {
@@ -301,22 +563,7 @@ pub fn get_all_proposals() -> Vec<ProposalResponse> {
     let mut proposals = Vec::new();
     PROPOSALS.with(|proposal_store| {
         for (_, proposal) in proposal_store.borrow().iter() {
-            let response = ProposalResponse {
-                id: proposal.id,
-                status: proposal.status.clone(),
-                title: proposal.title.clone(),
-                voting_deadline: proposal.voting_deadline,
-                artifact_id: proposal.artifact_id,
-                description: proposal.description.clone(),
-                voters: proposal.voting_results.voter_principals.iter().cloned().collect(),
-                created_at: proposal.created_at,
-                proposer: proposal.proposer,
-                votes_for: proposal.voting_results.votes_for,
-                execution_payload: proposal.execution_payload.clone(),
-                proposal_type: proposal.proposal_type.clone(),
-                votes_against: proposal.voting_results.votes_against,
-            };
-            proposals.push(response);
+            proposals.push(proposal_response(&proposal));
         }
     });
     
@@ -325,6 +572,33 @@ pub fn get_all_proposals() -> Vec<ProposalResponse> {
     proposals
 }
 
+pub fn list_proposals(page: PageRequest) -> Page<ProposalResponse> {
+    PROPOSALS.with(|proposal_store| {
+        let proposal_store = proposal_store.borrow();
+        paginate_newest_first(&proposal_store, &page, proposal_store.len(), |proposal| {
+            Some(proposal_response(&proposal))
+        })
+    })
+}
+
+pub fn proposal_response(proposal: &Proposal) -> ProposalResponse {
+    ProposalResponse {
+        id: proposal.id,
+        status: proposal.status.clone(),
+        title: proposal.title.clone(),
+        voting_deadline: proposal.voting_deadline,
+        artifact_id: proposal.artifact_id,
+        description: proposal.description.clone(),
+        voters: proposal.voting_results.voter_principals.iter().cloned().collect(),
+        created_at: proposal.created_at,
+        proposer: proposal.proposer,
+        votes_for: proposal.voting_results.votes_for,
+        execution_payload: proposal.execution_payload.clone(),
+        proposal_type: proposal.proposal_type.clone(),
+        votes_against: proposal.voting_results.votes_against,
+    }
+}
+
 #[query]
 pub fn get_active_proposals() -> Vec<ProposalResponse> {
     get_all_proposals()
@@ -340,3 +614,129 @@ pub fn get_proposals_by_status(status: ProposalStatus) -> Vec<ProposalResponse>
         .filter(|p| p.status == status)
         .collect()
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::modules::testing::{artifact_request, create_artifact_as, principal, register, set_caller};
+
+    fn request(proposal_type: ProposalType, artifact_id: Option<u64>, payload: Option<ProposalAction>) -> CreateProposalRequest {
+        CreateProposalRequest {
+            proposal_type,
+            artifact_id,
+            title: "Test proposal".to_string(),
+            description: "A proposal long enough to pass the description length validation.".to_string(),
+            evidence: None,
+            voting_duration_hours: 72,
+            execution_payload: payload,
+            required_expertise: None,
+            urgency_level: None,
+            quorum_required: None,
+            voting_mode: None,
+        }
+    }
+
+    // Creates the proposal and marks it Passed, as a successful vote would
+    fn passed_proposal(request: CreateProposalRequest) -> u64 {
+        let proposal_id = create_proposal(request).unwrap();
+        PROPOSALS.with(|proposals| {
+            let mut proposals = proposals.borrow_mut();
+            let mut proposal = proposals.get(&proposal_id).unwrap();
+            proposal.status = ProposalStatus::Passed;
+            store_proposal(&mut proposals, proposal);
+        });
+        proposal_id
+    }
+
+    #[test]
+    fn resolves_the_payload_against_the_proposal_type() {
+        let verify = request(ProposalType::VerifyArtifact, Some(1), None);
+        assert!(matches!(resolve_proposal_action(&verify), Ok(ProposalAction::VerifyArtifact)));
+
+        let missing = request(ProposalType::DisputeArtifact, Some(1), None);
+        assert!(resolve_proposal_action(&missing).unwrap_err().contains("requires an execution payload"));
+
+        let mismatched = request(ProposalType::DisputeArtifact, Some(1), Some(ProposalAction::VerifyArtifact));
+        assert!(resolve_proposal_action(&mismatched).unwrap_err().contains("does not match"));
+
+        let no_artifact = request(ProposalType::DisputeArtifact, None, Some(ProposalAction::DisputeArtifact { reason: "Forgery".to_string() }));
+        assert!(resolve_proposal_action(&no_artifact).is_err());
+
+        let no_reason = request(ProposalType::DisputeArtifact, Some(1), Some(ProposalAction::DisputeArtifact { reason: " ".to_string() }));
+        assert!(resolve_proposal_action(&no_reason).is_err());
+    }
+
+    #[test]
+    fn role_payloads_must_match_the_target_user() {
+        let target = principal(7);
+        let grant = |role| request(ProposalType::GrantUserRole, None, Some(ProposalAction::GrantRole { principal: target, role }));
+        let revoke = |role| request(ProposalType::RevokeUserRole, None, Some(ProposalAction::RevokeRole { principal: target, role }));
+        assert_eq!(resolve_proposal_action(&grant(UserRole::Expert)).unwrap_err(), "Target user not found");
+
+        register(target, UserRole::Community, &[]);
+        assert!(resolve_proposal_action(&grant(UserRole::Expert)).is_ok());
+        assert!(resolve_proposal_action(&revoke(UserRole::Expert)).is_err());
+        assert!(resolve_proposal_action(&revoke(UserRole::Community)).is_ok());
+    }
+
+    #[test]
+    fn executing_a_passed_proposal_applies_its_payload() {
+        let artifact_id = create_artifact_as(principal(1), artifact_request("Bronze mirror"));
+        let patch = ProposalAction::PatchMetadata { set: vec![("era".to_string(), "Han".to_string())], remove: Vec::new() };
+        let proposal_id = passed_proposal(request(ProposalType::UpdateArtifactMetadata, Some(artifact_id), Some(patch)));
+
+        assert!(execute_passed_proposal(proposal_id, get_time()).is_ok());
+        let artifact = ARTIFACTS.with(|artifacts| artifacts.borrow().get(&artifact_id)).unwrap();
+        assert_eq!(artifact.metadata, vec![("era".to_string(), "Han".to_string())]);
+        assert_eq!(artifact.history.last().unwrap().action, "MetadataUpdated");
+        assert_eq!(get_proposal(proposal_id).unwrap().status, ProposalStatus::Executed);
+
+        // A proposal executes once
+        assert!(execute_passed_proposal(proposal_id, get_time()).is_err());
+    }
+
+    #[test]
+    fn failed_and_late_executions_are_recorded() {
+        let target = principal(7);
+        register(target, UserRole::Community, &[]);
+        set_caller(principal(1));
+        let revoke = ProposalAction::RevokeRole { principal: target, role: UserRole::Community };
+        let proposal_id = passed_proposal(request(ProposalType::RevokeUserRole, None, Some(revoke.clone())));
+
+        // The target's role changed after the proposal was created
+        USERS.with(|users| {
+            let mut user = users.borrow().get(&target).unwrap();
+            user.role = UserRole::Expert;
+            users.borrow_mut().insert(target, user);
+        });
+        assert!(execute_passed_proposal(proposal_id, get_time()).is_err());
+        assert_eq!(get_proposal(proposal_id).unwrap().status, ProposalStatus::FailedExecution);
+
+        let grant = ProposalAction::GrantRole { principal: target, role: UserRole::Curator };
+        let late = passed_proposal(request(ProposalType::GrantUserRole, None, Some(grant)));
+        let deadline = get_proposal(late).unwrap().execution_deadline.unwrap();
+        assert!(execute_passed_proposal(late, deadline + 1).is_err());
+        assert_eq!(get_proposal(late).unwrap().status, ProposalStatus::Expired);
+    }
+
+    #[test]
+    fn the_proposal_type_decides_the_voting_mode() {
+        let artifact_id = create_artifact_as(principal(1), artifact_request("Bronze mirror"));
+        let dispute = || request(ProposalType::DisputeArtifact, Some(artifact_id), Some(ProposalAction::DisputeArtifact {
+            reason: "Modern replica".to_string(),
+        }));
+        set_caller(principal(1));
+
+        let mut linear = dispute();
+        linear.voting_mode = Some(VotingMode::Linear);
+        assert_eq!(create_proposal(linear).unwrap_err(), "DisputeArtifact proposals are tallied by Quadratic voting");
+
+        let mut quadratic = dispute();
+        quadratic.voting_mode = Some(VotingMode::Quadratic);
+        let explicit = create_proposal(quadratic).unwrap();
+        let implicit = create_proposal(dispute()).unwrap();
+        for proposal_id in [explicit, implicit] {
+            assert_eq!(get_proposal(proposal_id).unwrap().voting_mode, VotingMode::Quadratic);
+        }
+    }
+}
@@ -0,0 +1,470 @@
+use candid::{CandidType, Principal};
+use ic_cdk::{query, update};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet};
+
+use crate::modules::types::*;
+use crate::modules::storage::{DELEGATE_INDEX, DELEGATIONS, PROPOSALS, USERS};
+use crate::modules::auth::{expertise_matches, get_caller, get_voting_weight, has_required_expertise};
+use crate::modules::utils::get_time;
+use crate::modules::audit::log_audit_event;
+use crate::modules::tally::{tally_votes, weighting, FREE_CREDITS};
+use crate::modules::voting::refresh_vote_breakdowns;
+
+// ============================================================================
+// VOTE DELEGATION
+// ============================================================================
+//
+// A registered user may hand their voting weight to another user, either for
+// every proposal (Global) or for proposals requiring one expertise area
+// (Expertise), in which case the delegate must list that area in their
+// `User.specialization`. For a given proposal a user's expertise delegation
+// matching one of its `required_expertise` wins over their global one.
+//
+// Delegation is liquid: it is resolved when votes are tallied, not when they
+// are cast. A delegator's weight follows their chain of delegates to the first
+// one who voted directly and counts as that vote. Voting directly overrides the
+// delegation for that proposal. Chains that loop back on themselves or end
+// with nobody voting carry no weight. Delegated weight is the delegator's own,
+// as the proposal's voting mode would weigh it without spending credits.
+
+#[derive(CandidType, Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub enum DelegationScope {
+    Global,
+    Expertise(String),
+}
+
+#[derive(CandidType, Serialize, Deserialize, Clone, Debug)]
+pub struct Delegation {
+    pub delegate: Principal,
+    pub scope: DelegationScope,
+    pub created_at: u64,
+}
+
+// All delegations of one delegator, keyed by the delegator
+#[derive(CandidType, Serialize, Deserialize, Clone, Debug, Default)]
+pub struct UserDelegations {
+    pub delegations: Vec<Delegation>,
+}
+
+impl UserDelegations {
+    // The delegate this user's weight goes to on a proposal requiring `required_expertise`
+    pub fn delegate_for(&self, required_expertise: &[String]) -> Option<Principal> {
+        let expertise = self.delegations.iter().find(|delegation| match &delegation.scope {
+            DelegationScope::Expertise(area) => required_expertise.iter().any(|required| expertise_matches(area, required)),
+            DelegationScope::Global => false,
+        });
+        expertise
+            .or_else(|| self.delegations.iter().find(|delegation| delegation.scope == DelegationScope::Global))
+            .map(|delegation| delegation.delegate)
+    }
+}
+
+// Follows each delegator's chain to the first direct voter. Returns the voter
+// each delegator's weight goes to; delegators who voted themselves, whose chain
+// loops or whose chain reaches nobody who voted are left out.
+pub fn resolve_delegations<F>(
+    delegators: impl IntoIterator<Item = Principal>,
+    direct_voters: &BTreeSet<Principal>,
+    delegate_of: F,
+) -> BTreeMap<Principal, Principal>
+where
+    F: Fn(Principal) -> Option<Principal>,
+{
+    let mut resolved = BTreeMap::new();
+    for delegator in delegators {
+        if direct_voters.contains(&delegator) {
+            continue;
+        }
+        let mut visited = BTreeSet::from([delegator]);
+        let mut current = delegator;
+        while let Some(next) = delegate_of(current) {
+            if direct_voters.contains(&next) {
+                resolved.insert(delegator, next);
+                break;
+            }
+            if !visited.insert(next) {
+                break;
+            }
+            current = next;
+        }
+    }
+    resolved
+}
+
+// The delegation links on a proposal requiring `required_expertise` that lead
+// to one of the direct voters, found by walking the delegate index backwards
+// from them. Delegators who voted themselves are left out.
+fn chains_into(direct_voters: &BTreeSet<Principal>, required_expertise: &[String]) -> BTreeMap<Principal, Principal> {
+    let mut chains = BTreeMap::new();
+    let mut pending: Vec<Principal> = direct_voters.iter().cloned().collect();
+    while let Some(delegate) = pending.pop() {
+        for delegator in delegators_of(delegate) {
+            if direct_voters.contains(&delegator) || chains.contains_key(&delegator) {
+                continue;
+            }
+            if load_delegations(delegator).delegate_for(required_expertise) == Some(delegate) {
+                chains.insert(delegator, delegate);
+                pending.push(delegator);
+            }
+        }
+    }
+    chains
+}
+
+// Votes carrying the delegated weight on a proposal, one per delegator, each a
+// copy of the direct vote it follows
+pub fn delegated_votes(proposal: &Proposal, direct: &[Vote]) -> Vec<Vote> {
+    let direct_votes: BTreeMap<Principal, &Vote> = direct.iter().map(|vote| (vote.voter, vote)).collect();
+    let direct_voters: BTreeSet<Principal> = direct_votes.keys().cloned().collect();
+    let chains = chains_into(&direct_voters, &proposal.required_expertise);
+
+    let weighting = weighting(&proposal.voting_mode);
+    resolve_delegations(chains.keys().cloned(), &direct_voters, |principal| chains.get(&principal).cloned())
+        .into_iter()
+        // Delegators must be able to vote on the proposal themselves
+        .filter(|(delegator, _)| has_required_expertise(*delegator, &proposal.required_expertise))
+        .map(|(delegator, voter)| {
+            let followed = direct_votes[&voter];
+            Vote {
+                voter: delegator,
+                weight: weighting.cast_weight(get_voting_weight(delegator), FREE_CREDITS),
+                rationale: None,
+                expertise_relevance: 0,
+                ..followed.clone()
+            }
+        })
+        .collect()
+}
+
+fn load_delegations(delegator: Principal) -> UserDelegations {
+    DELEGATIONS.with(|delegations| delegations.borrow().get(&delegator).unwrap_or_default())
+}
+
+// Every write to DELEGATIONS goes through here so DELEGATE_INDEX stays in step
+fn save_delegations(delegator: Principal, user_delegations: UserDelegations) {
+    DELEGATE_INDEX.with(|index| {
+        let mut index = index.borrow_mut();
+        for delegation in load_delegations(delegator).delegations {
+            index.remove(&(delegation.delegate, delegator));
+        }
+        for delegation in &user_delegations.delegations {
+            index.insert((delegation.delegate, delegator), ());
+        }
+    });
+    DELEGATIONS.with(|delegations| {
+        let mut delegations = delegations.borrow_mut();
+        if user_delegations.delegations.is_empty() {
+            delegations.remove(&delegator);
+        } else {
+            delegations.insert(delegator, user_delegations);
+        }
+    });
+}
+
+// Users with at least one delegation to `delegate`
+fn delegators_of(delegate: Principal) -> Vec<Principal> {
+    DELEGATE_INDEX.with(|index| {
+        index.borrow()
+            .range((delegate, Principal::management_canister())..)
+            .take_while(|((to, _), _)| *to == delegate)
+            .map(|((_, delegator), _)| delegator)
+            .collect()
+    })
+}
+
+// Recomputes the delegate index from DELEGATIONS; used after upgrades
+pub fn rebuild_delegate_index() {
+    DELEGATE_INDEX.with(|index| {
+        let mut index = index.borrow_mut();
+        index.clear_new();
+        DELEGATIONS.with(|delegations| {
+            for (delegator, user_delegations) in delegations.borrow().iter() {
+                for delegation in user_delegations.delegations {
+                    index.insert((delegation.delegate, delegator), ());
+                }
+            }
+        });
+    });
+}
+
+// ============================================================================
+// ENDPOINTS
+// ============================================================================
+
+#[update]
+pub fn delegate_vote(delegate: Principal, scope: DelegationScope) -> Result<String, String> {
+    let caller = get_caller();
+    if delegate == caller {
+        return Err("You cannot delegate to yourself".to_string());
+    }
+    if USERS.with(|users| !users.borrow().contains_key(&caller)) {
+        return Err("Register before delegating your vote".to_string());
+    }
+    let specialization = USERS.with(|users| users.borrow().get(&delegate).map(|user| user.specialization))
+        .ok_or_else(|| "Delegate is not a registered user".to_string())?;
+
+    let required_expertise = match &scope {
+        DelegationScope::Global => Vec::new(),
+        DelegationScope::Expertise(area) => {
+            if area.trim().is_empty() {
+                return Err("Expertise area cannot be empty".to_string());
+            }
+            if !specialization.iter().any(|spec| expertise_matches(spec, area)) {
+                return Err(format!("Delegate does not specialize in {}", area));
+            }
+            vec![area.clone()]
+        },
+    };
+
+    let mut user_delegations = load_delegations(caller);
+    user_delegations.delegations.retain(|delegation| delegation.scope != scope);
+    user_delegations.delegations.push(Delegation { delegate, scope: scope.clone(), created_at: get_time() });
+
+    // Refuse a chain that would lead back to the caller on proposals in this scope
+    let loops = DELEGATIONS.with(|delegations| {
+        let delegations = delegations.borrow();
+        let mut visited = BTreeSet::new();
+        let mut current = Some(delegate);
+        while let Some(principal) = current {
+            if principal == caller {
+                return true;
+            }
+            if !visited.insert(principal) {
+                return false;
+            }
+            current = delegations.get(&principal).and_then(|other| other.delegate_for(&required_expertise));
+        }
+        false
+    });
+    if loops {
+        return Err("Delegation would create a cycle".to_string());
+    }
+
+    save_delegations(caller, user_delegations);
+    refresh_vote_breakdowns(caller);
+
+    log_audit_event(
+        AuditEventType::DataModification,
+        None,
+        format!("{} delegated {:?} voting weight to {}", caller.to_text(), scope, delegate.to_text()),
+        AuditSeverity::Info
+    );
+    Ok("Delegation recorded".to_string())
+}
+
+#[update]
+pub fn revoke_delegation(scope: DelegationScope) -> Result<String, String> {
+    let caller = get_caller();
+    let mut user_delegations = load_delegations(caller);
+    let before = user_delegations.delegations.len();
+    user_delegations.delegations.retain(|delegation| delegation.scope != scope);
+    if user_delegations.delegations.len() == before {
+        return Err("No delegation for this scope".to_string());
+    }
+    save_delegations(caller, user_delegations);
+    refresh_vote_breakdowns(caller);
+
+    log_audit_event(
+        AuditEventType::DataModification,
+        None,
+        format!("{} revoked their {:?} delegation", caller.to_text(), scope),
+        AuditSeverity::Info
+    );
+    Ok("Delegation revoked".to_string())
+}
+
+#[query]
+pub fn get_my_delegations() -> Vec<Delegation> {
+    load_delegations(get_caller()).delegations
+}
+
+// Direct and delegated weight on a proposal, tallied as of now
+#[query]
+pub fn get_vote_breakdown(proposal_id: u64) -> Result<VoteBreakdown, String> {
+    let proposal = PROPOSALS.with(|proposals| proposals.borrow().get(&proposal_id))
+        .ok_or_else(|| "Proposal not found".to_string())?;
+    Ok(tally_votes(&proposal, get_time()).breakdown)
+}
+
+// Users who delegated directly to `delegate`, with the delegation they made
+#[query]
+pub fn get_delegators(delegate: Principal) -> Vec<(Principal, Delegation)> {
+    delegators_of(delegate).into_iter()
+        .flat_map(|delegator| {
+            load_delegations(delegator).delegations.into_iter()
+                .filter(|delegation| delegation.delegate == delegate)
+                .map(move |delegation| (delegator, delegation))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::modules::dao::create_proposal;
+    use crate::modules::storage::VOTES;
+    use crate::modules::testing::{artifact_request, create_artifact_as, register, set_caller};
+    use crate::modules::voting::store_vote;
+
+    fn user(id: u8) -> Principal {
+        Principal::from_slice(&[id])
+    }
+
+    fn resolve(chains: &[(u8, u8)], voters: &[u8]) -> Vec<(u8, u8)> {
+        let chains: BTreeMap<Principal, Principal> = chains.iter().map(|(from, to)| (user(*from), user(*to))).collect();
+        let voters: BTreeSet<Principal> = voters.iter().map(|id| user(*id)).collect();
+        resolve_delegations(chains.keys().cloned(), &voters, |principal| chains.get(&principal).cloned())
+            .into_iter()
+            .map(|(delegator, voter)| (delegator.as_slice()[0], voter.as_slice()[0]))
+            .collect()
+    }
+
+    #[test]
+    fn weight_follows_chains_to_the_first_direct_voter() {
+        // 1 -> 2 -> 3 -> 4, with 3 and 4 voting
+        assert_eq!(resolve(&[(1, 2), (2, 3), (3, 4)], &[3, 4]), vec![(1, 3), (2, 3)]);
+    }
+
+    #[test]
+    fn voting_directly_overrides_a_delegation() {
+        assert_eq!(resolve(&[(1, 2)], &[1, 2]), vec![]);
+    }
+
+    #[test]
+    fn cycles_and_dead_ends_carry_no_weight() {
+        // 1 -> 2 -> 3 -> 1 never reaches a voter; 4 -> 5 ends with nobody voting
+        assert_eq!(resolve(&[(1, 2), (2, 3), (3, 1), (4, 5)], &[6]), vec![]);
+        // A loop behind a voter does not matter
+        assert_eq!(resolve(&[(1, 2), (2, 3), (3, 1)], &[3]), vec![(1, 3), (2, 3)]);
+    }
+
+    #[test]
+    fn expertise_delegation_wins_over_global() {
+        let delegations = UserDelegations {
+            delegations: vec![
+                Delegation { delegate: user(1), scope: DelegationScope::Global, created_at: 0 },
+                Delegation { delegate: user(2), scope: DelegationScope::Expertise("Egyptology".to_string()), created_at: 0 },
+            ],
+        };
+        assert_eq!(delegations.delegate_for(&["egyptology".to_string()]), Some(user(2)));
+        assert_eq!(delegations.delegate_for(&["Numismatics".to_string()]), Some(user(1)));
+        assert_eq!(delegations.delegate_for(&[]), Some(user(1)));
+    }
+
+    fn delegate_as(delegator: u8, delegate: u8, scope: DelegationScope) {
+        set_caller(user(delegator));
+        delegate_vote(user(delegate), scope).unwrap();
+    }
+
+    #[test]
+    fn the_delegate_index_follows_delegations() {
+        for id in 1..=6 {
+            register(user(id), UserRole::Expert, &["Egyptology"]);
+        }
+        // 1 -> 3 -> 4 and 2 -> 4 lead to voter 4; 5 -> 6 leads nowhere
+        delegate_as(1, 3, DelegationScope::Global);
+        delegate_as(3, 4, DelegationScope::Global);
+        delegate_as(2, 4, DelegationScope::Expertise("Egyptology".to_string()));
+        delegate_as(5, 6, DelegationScope::Global);
+
+        let delegators = |delegate: u8| -> Vec<Principal> {
+            get_delegators(user(delegate)).into_iter().map(|(delegator, _)| delegator).collect()
+        };
+        assert_eq!(delegators(4), vec![user(2), user(3)]);
+
+        let chains = chains_into(&BTreeSet::from([user(4)]), &["Egyptology".to_string()]);
+        assert_eq!(chains, BTreeMap::from([(user(1), user(3)), (user(2), user(4)), (user(3), user(4))]));
+        // Off the expertise, 2 has no delegation at all
+        let chains = chains_into(&BTreeSet::from([user(4)]), &["Numismatics".to_string()]);
+        assert_eq!(chains, BTreeMap::from([(user(1), user(3)), (user(3), user(4))]));
+
+        // Redelegating moves the delegator, revoking removes them
+        delegate_as(3, 6, DelegationScope::Global);
+        assert_eq!(delegators(4), vec![user(2)]);
+        assert_eq!(delegators(6), vec![user(3), user(5)]);
+        set_caller(user(2));
+        revoke_delegation(DelegationScope::Expertise("Egyptology".to_string())).unwrap();
+        assert!(delegators(4).is_empty());
+
+        DELEGATE_INDEX.with(|index| index.borrow_mut().clear_new());
+        rebuild_delegate_index();
+        assert_eq!(delegators(3), vec![user(1)]);
+        assert_eq!(delegators(6), vec![user(3), user(5)]);
+    }
+
+    #[test]
+    fn refuses_cycles_and_delegates_outside_the_expertise() {
+        register(user(1), UserRole::Expert, &["Egyptology"]);
+        register(user(2), UserRole::Expert, &["Egyptology"]);
+        register(user(3), UserRole::Community, &[]);
+        delegate_as(1, 2, DelegationScope::Global);
+        delegate_as(2, 3, DelegationScope::Global);
+
+        set_caller(user(3));
+        assert_eq!(delegate_vote(user(1), DelegationScope::Global).unwrap_err(), "Delegation would create a cycle");
+        assert_eq!(
+            delegate_vote(user(2), DelegationScope::Expertise("Numismatics".to_string())).unwrap_err(),
+            "Delegate does not specialize in Numismatics",
+        );
+        assert!(get_my_delegations().is_empty());
+        assert!(delegators_of(user(1)).is_empty());
+
+        // 1's global delegation also covers Egyptology proposals
+        set_caller(user(2));
+        revoke_delegation(DelegationScope::Global).unwrap();
+        assert_eq!(
+            delegate_vote(user(1), DelegationScope::Expertise("Egyptology".to_string())).unwrap_err(),
+            "Delegation would create a cycle",
+        );
+    }
+
+    #[test]
+    fn delegated_votes_follow_chains_on_a_stored_proposal() {
+        // 1 -> 2 -> 3 with 3 voting; 4 delegates to 3 but lacks the expertise;
+        // 5 -> 6 leads to nobody who voted
+        for id in [1, 2, 3, 5, 6] {
+            register(user(id), UserRole::Community, &["Egyptology"]);
+        }
+        register(user(4), UserRole::Community, &[]);
+        delegate_as(1, 2, DelegationScope::Global);
+        delegate_as(2, 3, DelegationScope::Global);
+        delegate_as(4, 3, DelegationScope::Global);
+        delegate_as(5, 6, DelegationScope::Global);
+
+        let artifact_id = create_artifact_as(user(7), artifact_request("Canopic jar"));
+        let proposal_id = create_proposal(CreateProposalRequest {
+            proposal_type: ProposalType::VerifyArtifact,
+            artifact_id: Some(artifact_id),
+            title: "Verify the canopic jar".to_string(),
+            description: "A verification round open to Egyptologists and those who delegate to them.".to_string(),
+            evidence: None,
+            voting_duration_hours: 72,
+            execution_payload: None,
+            required_expertise: Some(vec!["Egyptology".to_string()]),
+            urgency_level: None,
+            quorum_required: None,
+            voting_mode: None,
+        }).unwrap();
+        let proposal = PROPOSALS.with(|proposals| proposals.borrow().get(&proposal_id)).unwrap();
+        let direct = Vote {
+            id: 1,
+            proposal_id: proposal.id,
+            voter: user(3),
+            vote_type: VoteType::For,
+            weight: 1,
+            timestamp: 0,
+            rationale: Some("Consistent with the excavation record".to_string()),
+            expertise_relevance: 90,
+        };
+        VOTES.with(|votes| store_vote(&mut votes.borrow_mut(), direct.clone()));
+
+        let delegated = delegated_votes(&proposal, &[direct]);
+        let voters: Vec<Principal> = delegated.iter().map(|vote| vote.voter).collect();
+        assert_eq!(voters, vec![user(1), user(2)]);
+        assert!(delegated.iter().all(|vote| matches!(vote.vote_type, VoteType::For) && vote.rationale.is_none() && vote.weight == 1));
+
+        let breakdown = get_vote_breakdown(proposal.id).unwrap();
+        assert_eq!((breakdown.direct_for, breakdown.delegated_for, breakdown.delegators), (1, 2, 2));
+    }
+}
@@ -2,7 +2,7 @@ use ic_cdk::{query, update};
 use candid::{CandidType, Principal};
 use serde::{Deserialize, Serialize};
 use crate::modules::types::*;
-use crate::modules::storage::{ENHANCED_NFTS, USER_PROGRESS, QUESTS, ARTIFACTS, get_next_id};
+use crate::modules::storage::{ENHANCED_NFTS, USER_PROGRESS, QUESTS, ARTIFACTS, get_next_id, IdKind};
 use crate::modules::auth::*;
 use crate::modules::utils::*;
 use crate::modules::audit::log_audit_event;
@@ -322,7 +322,7 @@ pub fn mint_enhanced_nft(
         return Err("Only experts, institutions, or moderators can mint NFTs".to_string());
     }
 
-    let token_id = get_next_id(15); // NFT counter
+    let token_id = get_next_id(IdKind::GamificationNft);
     let now = get_time();
 
     let nft = EnhancedNFT {
@@ -358,7 +358,7 @@ pub fn mint_enhanced_nft(
 
 #[update]
 pub fn award_achievement(user: Principal, achievement_title: String, progress: f64) -> Result<String, String> {
-    let achievement_id = get_next_id(16); // Achievement counter
+    let achievement_id = get_next_id(IdKind::Achievement);
     let now = get_time();
 
     // Define achievement rewards based on title
@@ -462,7 +462,7 @@ pub fn create_quest(
         return Err("Only moderators can create quests".to_string());
     }
 
-    let quest_id = get_next_id(17); // Quest counter
+    let quest_id = get_next_id(IdKind::Quest);
     let now = get_time();
     let duration_ns = duration_days * 24 * 60 * 60 * 1000_000_000; // Convert days to nanoseconds
 
@@ -0,0 +1,611 @@
+use candid::CandidType;
+use ic_stable_structures::Storable;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::borrow::Cow;
+use std::collections::BTreeSet;
+
+use crate::modules::types::*;
+use crate::modules::storage::{ARTIFACTS, GEO_INDEX};
+use crate::modules::utils::page_limit;
+
+// ============================================================================
+// GEOSPATIAL INDEX
+// ============================================================================
+//
+// Artifacts with coordinates are indexed by geohash:
+//
+//   GEO_INDEX: geohash (GEOHASH_PRECISION chars) | artifact_id (u64 BE) -> GeoPoint
+//
+// Geohashes of nearby points share prefixes, so a query covers its bounding box
+// with a handful of coarser cells, range-scans each cell prefix and then checks
+// the stored point exactly. Coordinates are (latitude, longitude) in degrees.
+
+const GEOHASH_ALPHABET: &[u8; 32] = b"0123456789bcdefghjkmnpqrstuvwxyz";
+// ~4.8m x 4.8m cells
+const GEOHASH_PRECISION: usize = 9;
+const MAX_COVER_CELLS: usize = 64;
+const EARTH_RADIUS_KM: f64 = 6371.0088;
+const KM_PER_DEGREE_LATITUDE: f64 = 111.32;
+const MAX_POLYGON_VERTICES: usize = 1000;
+// Bump when the key layout or precision changes so the next upgrade rebuilds the index
+pub const GEO_INDEX_VERSION: u32 = 1;
+
+#[derive(CandidType, Serialize, Deserialize, Clone, Copy, Debug, PartialEq)]
+pub struct BoundingBox {
+    pub min_lat: f64,
+    pub min_lon: f64,
+    pub max_lat: f64,
+    // May be smaller than min_lon for boxes crossing the antimeridian
+    pub max_lon: f64,
+}
+
+#[derive(CandidType, Serialize, Deserialize, Clone, Debug)]
+pub struct NearbyArtifact {
+    pub artifact: Artifact,
+    pub distance_km: f64,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub struct GeoKey(Vec<u8>);
+
+impl Storable for GeoKey {
+    const BOUND: ic_stable_structures::storable::Bound = ic_stable_structures::storable::Bound::Bounded {
+        max_size: (GEOHASH_PRECISION + 8) as u32,
+        is_fixed_size: false,
+    };
+
+    fn to_bytes(&self) -> Cow<'_, [u8]> {
+        Cow::Borrowed(&self.0)
+    }
+
+    fn from_bytes(bytes: Cow<[u8]>) -> Self {
+        GeoKey(bytes.into_owned())
+    }
+}
+
+impl GeoKey {
+    fn new(point: GeoPoint, artifact_id: u64) -> Self {
+        let mut key = geohash(point.lat, point.lon, GEOHASH_PRECISION).into_bytes();
+        key.extend_from_slice(&artifact_id.to_be_bytes());
+        GeoKey(key)
+    }
+
+    fn artifact_id(&self) -> u64 {
+        let mut id_bytes = [0u8; 8];
+        id_bytes.copy_from_slice(&self.0[self.0.len() - 8..]);
+        u64::from_be_bytes(id_bytes)
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct GeoPoint {
+    pub lat: f64,
+    pub lon: f64,
+}
+
+impl Storable for GeoPoint {
+    const BOUND: ic_stable_structures::storable::Bound = ic_stable_structures::storable::Bound::Bounded {
+        max_size: 16,
+        is_fixed_size: true,
+    };
+
+    fn to_bytes(&self) -> Cow<'_, [u8]> {
+        let mut bytes = Vec::with_capacity(16);
+        bytes.extend_from_slice(&self.lat.to_be_bytes());
+        bytes.extend_from_slice(&self.lon.to_be_bytes());
+        Cow::Owned(bytes)
+    }
+
+    fn from_bytes(bytes: Cow<[u8]>) -> Self {
+        let mut lat = [0u8; 8];
+        let mut lon = [0u8; 8];
+        lat.copy_from_slice(&bytes[..8]);
+        lon.copy_from_slice(&bytes[8..16]);
+        GeoPoint {
+            lat: f64::from_be_bytes(lat),
+            lon: f64::from_be_bytes(lon),
+        }
+    }
+}
+
+// ============================================================================
+// VALIDATION
+// ============================================================================
+
+pub fn validate_coordinates(lat: f64, lon: f64) -> Result<(), String> {
+    if !lat.is_finite() || !lon.is_finite() {
+        return Err("Coordinates must be finite numbers".to_string());
+    }
+    if !(-90.0..=90.0).contains(&lat) {
+        return Err(format!("Latitude {} is outside [-90, 90]", lat));
+    }
+    if !(-180.0..=180.0).contains(&lon) {
+        return Err(format!("Longitude {} is outside [-180, 180]", lon));
+    }
+    Ok(())
+}
+
+pub fn validate_geographic_origin(origin: &GeographicOrigin) -> Result<(), String> {
+    if origin.country.trim().is_empty() {
+        return Err("Country of origin cannot be empty".to_string());
+    }
+    if let Some((lat, lon)) = origin.coordinates {
+        validate_coordinates(lat, lon)?;
+    }
+    Ok(())
+}
+
+fn validate_bounding_box(bbox: &BoundingBox) -> Result<(), String> {
+    validate_coordinates(bbox.min_lat, bbox.min_lon)?;
+    validate_coordinates(bbox.max_lat, bbox.max_lon)?;
+    if bbox.min_lat > bbox.max_lat {
+        return Err("min_lat must not exceed max_lat".to_string());
+    }
+    Ok(())
+}
+
+// ============================================================================
+// GEOMETRY
+// ============================================================================
+
+pub fn geohash(lat: f64, lon: f64, precision: usize) -> String {
+    let (mut lat_range, mut lon_range) = ((-90.0, 90.0), (-180.0, 180.0));
+    let mut hash = String::with_capacity(precision);
+    let mut bits = 0u8;
+    let mut bit_count = 0;
+    let mut even = true;
+
+    while hash.len() < precision {
+        let (range, value) = if even { (&mut lon_range, lon) } else { (&mut lat_range, lat) };
+        let mid = (range.0 + range.1) / 2.0;
+        bits <<= 1;
+        if value >= mid {
+            bits |= 1;
+            range.0 = mid;
+        } else {
+            range.1 = mid;
+        }
+        even = !even;
+        bit_count += 1;
+        if bit_count == 5 {
+            hash.push(GEOHASH_ALPHABET[bits as usize] as char);
+            bits = 0;
+            bit_count = 0;
+        }
+    }
+
+    hash
+}
+
+// (height, width) in degrees of a geohash cell of the given precision
+fn cell_size(precision: usize) -> (f64, f64) {
+    let total_bits = 5 * precision as i32;
+    let lon_bits = (total_bits + 1) / 2;
+    let lat_bits = total_bits / 2;
+    (180.0 / 2f64.powi(lat_bits), 360.0 / 2f64.powi(lon_bits))
+}
+
+// Splits a box crossing the antimeridian into two ordinary boxes
+fn split_antimeridian(bbox: &BoundingBox) -> Vec<BoundingBox> {
+    if bbox.min_lon <= bbox.max_lon {
+        return vec![*bbox];
+    }
+    vec![
+        BoundingBox { max_lon: 180.0, ..*bbox },
+        BoundingBox { min_lon: -180.0, ..*bbox },
+    ]
+}
+
+// Geohash prefixes whose cells together cover `bbox`, as fine as MAX_COVER_CELLS allows
+fn covering_cells(bbox: &BoundingBox) -> BTreeSet<String> {
+    let boxes = split_antimeridian(bbox);
+    let mut best = BTreeSet::from([String::new()]);
+
+    for precision in 1..=GEOHASH_PRECISION {
+        let (height, width) = cell_size(precision);
+        let mut cells = BTreeSet::new();
+        for part in &boxes {
+            let rows = ((part.max_lat - part.min_lat) / height).ceil() as usize + 1;
+            let columns = ((part.max_lon - part.min_lon) / width).ceil() as usize + 1;
+            if rows * columns > MAX_COVER_CELLS {
+                return best;
+            }
+            for row in 0..=rows {
+                let lat = (part.min_lat + row as f64 * height).min(part.max_lat);
+                for column in 0..=columns {
+                    let lon = (part.min_lon + column as f64 * width).min(part.max_lon);
+                    cells.insert(geohash(lat, lon, precision));
+                }
+            }
+        }
+        if cells.len() > MAX_COVER_CELLS {
+            return best;
+        }
+        best = cells;
+    }
+
+    best
+}
+
+fn bbox_contains(bbox: &BoundingBox, point: GeoPoint) -> bool {
+    let within_lon = if bbox.min_lon <= bbox.max_lon {
+        point.lon >= bbox.min_lon && point.lon <= bbox.max_lon
+    } else {
+        point.lon >= bbox.min_lon || point.lon <= bbox.max_lon
+    };
+    point.lat >= bbox.min_lat && point.lat <= bbox.max_lat && within_lon
+}
+
+pub fn haversine_km(a: GeoPoint, b: GeoPoint) -> f64 {
+    let (lat1, lat2) = (a.lat.to_radians(), b.lat.to_radians());
+    let dlat = lat2 - lat1;
+    let dlon = (b.lon - a.lon).to_radians();
+    let h = (dlat / 2.0).sin().powi(2) + lat1.cos() * lat2.cos() * (dlon / 2.0).sin().powi(2);
+    2.0 * EARTH_RADIUS_KM * h.sqrt().min(1.0).asin()
+}
+
+fn radius_bounding_box(center: GeoPoint, radius_km: f64) -> BoundingBox {
+    let dlat = radius_km / KM_PER_DEGREE_LATITUDE;
+    let min_lat = (center.lat - dlat).max(-90.0);
+    let max_lat = (center.lat + dlat).min(90.0);
+
+    // Near the poles (or for huge radii) every longitude is in range
+    let cos_lat = center.lat.to_radians().cos();
+    let dlon = if cos_lat > 1e-9 { radius_km / (KM_PER_DEGREE_LATITUDE * cos_lat) } else { 180.0 };
+    if min_lat <= -90.0 || max_lat >= 90.0 || dlon >= 180.0 {
+        return BoundingBox { min_lat, min_lon: -180.0, max_lat, max_lon: 180.0 };
+    }
+
+    let wrap = |lon: f64| if lon < -180.0 { lon + 360.0 } else if lon > 180.0 { lon - 360.0 } else { lon };
+    BoundingBox { min_lat, min_lon: wrap(center.lon - dlon), max_lat, max_lon: wrap(center.lon + dlon) }
+}
+
+// Ray casting in plain lat/lon space; polygons must not cross the antimeridian
+fn polygon_contains(polygon: &[(f64, f64)], point: GeoPoint) -> bool {
+    let mut inside = false;
+    let mut previous = polygon[polygon.len() - 1];
+    for &current in polygon {
+        let ((lat_i, lon_i), (lat_j, lon_j)) = (current, previous);
+        if (lat_i > point.lat) != (lat_j > point.lat) {
+            let crossing_lon = lon_i + (point.lat - lat_i) / (lat_j - lat_i) * (lon_j - lon_i);
+            if point.lon < crossing_lon {
+                inside = !inside;
+            }
+        }
+        previous = current;
+    }
+    inside
+}
+
+// ============================================================================
+// INDEX MAINTENANCE
+// ============================================================================
+
+pub fn artifact_point(artifact: &Artifact) -> Option<GeoPoint> {
+    let (lat, lon) = artifact.geographic_origin.as_ref()?.coordinates?;
+    // Points that fail validation are never indexed
+    validate_coordinates(lat, lon).ok()?;
+    Some(GeoPoint { lat, lon })
+}
+
+pub fn update_geo_index(artifact_id: u64, previous: Option<GeoPoint>, current: Option<GeoPoint>) {
+    if previous == current {
+        return;
+    }
+    GEO_INDEX.with(|index| {
+        let mut index = index.borrow_mut();
+        if let Some(point) = previous {
+            index.remove(&GeoKey::new(point, artifact_id));
+        }
+        if let Some(point) = current {
+            index.insert(GeoKey::new(point, artifact_id), point);
+        }
+    });
+}
+
+// Recomputes the index from ARTIFACTS; used after upgrades
+pub fn rebuild_geo_index() {
+    GEO_INDEX.with(|index| index.borrow_mut().clear_new());
+    ARTIFACTS.with(|artifacts| {
+        for (artifact_id, artifact) in artifacts.borrow().iter() {
+            update_geo_index(artifact_id, None, artifact_point(&artifact));
+        }
+    });
+}
+
+// Indexed points inside `bbox`, as (artifact_id, point)
+fn points_in_bounding_box(bbox: &BoundingBox) -> Vec<(u64, GeoPoint)> {
+    let mut points = Vec::new();
+    GEO_INDEX.with(|index| {
+        let index = index.borrow();
+        for cell in covering_cells(bbox) {
+            let prefix = cell.as_bytes();
+            for (key, point) in index.range(GeoKey(prefix.to_vec())..) {
+                if !key.0.starts_with(prefix) {
+                    break;
+                }
+                if bbox_contains(bbox, point) {
+                    points.push((key.artifact_id(), point));
+                }
+            }
+        }
+    });
+    points
+}
+
+// ============================================================================
+// QUERIES
+// ============================================================================
+
+fn paginate<T>(mut items: Vec<T>, page: &PageRequest) -> Page<T> {
+    let total = items.len();
+    let offset = (page.cursor.unwrap_or(0) as usize).min(total);
+    let end = (offset + page_limit(page)).min(total);
+    Page {
+        items: items.drain(offset..end).collect(),
+        next_cursor: (end < total).then_some(end as u64),
+        total: total as u64,
+    }
+}
+
+// Orders the matching ids newest first and decodes only the requested page
+fn newest_first_page(mut artifact_ids: Vec<u64>, page: &PageRequest) -> Page<Artifact> {
+    artifact_ids.sort_unstable_by(|a, b| b.cmp(a));
+    artifact_ids.dedup();
+    let page = paginate(artifact_ids, page);
+    let items = ARTIFACTS.with(|artifacts| {
+        let artifacts = artifacts.borrow();
+        page.items.iter().filter_map(|id| artifacts.get(id)).collect()
+    });
+    Page { items, next_cursor: page.next_cursor, total: page.total }
+}
+
+// Closest first; the cursor is an offset into that ordering
+pub fn find_artifacts_near(lat: f64, lon: f64, radius_km: f64, page: &PageRequest) -> Result<Page<NearbyArtifact>, String> {
+    validate_coordinates(lat, lon)?;
+    if !radius_km.is_finite() || radius_km <= 0.0 {
+        return Err("Radius must be a positive number of kilometres".to_string());
+    }
+
+    let center = GeoPoint { lat, lon };
+    let mut nearby: Vec<(u64, f64)> = points_in_bounding_box(&radius_bounding_box(center, radius_km))
+        .into_iter()
+        .map(|(artifact_id, point)| (artifact_id, haversine_km(center, point)))
+        .filter(|(_, distance)| *distance <= radius_km)
+        .collect();
+    nearby.sort_by(|a, b| a.1.total_cmp(&b.1).then(b.0.cmp(&a.0)));
+    nearby.dedup_by_key(|(artifact_id, _)| *artifact_id);
+
+    let page = paginate(nearby, page);
+    let items = ARTIFACTS.with(|artifacts| {
+        let artifacts = artifacts.borrow();
+        page.items.iter()
+            .filter_map(|(artifact_id, distance_km)| {
+                artifacts.get(artifact_id).map(|artifact| NearbyArtifact { artifact, distance_km: *distance_km })
+            })
+            .collect()
+    });
+    Ok(Page { items, next_cursor: page.next_cursor, total: page.total })
+}
+
+pub fn find_artifacts_in_bounding_box(bbox: BoundingBox, page: &PageRequest) -> Result<Page<Artifact>, String> {
+    validate_bounding_box(&bbox)?;
+    let ids = points_in_bounding_box(&bbox).into_iter().map(|(artifact_id, _)| artifact_id).collect();
+    Ok(newest_first_page(ids, page))
+}
+
+// `polygon` is a ring of (latitude, longitude) vertices; closing it is optional
+pub fn find_artifacts_in_polygon(polygon: Vec<(f64, f64)>, page: &PageRequest) -> Result<Page<Artifact>, String> {
+    if polygon.len() < 3 {
+        return Err("A polygon needs at least three vertices".to_string());
+    }
+    if polygon.len() > MAX_POLYGON_VERTICES {
+        return Err(format!("A polygon may have at most {} vertices", MAX_POLYGON_VERTICES));
+    }
+    for &(lat, lon) in &polygon {
+        validate_coordinates(lat, lon)?;
+    }
+
+    let bbox = BoundingBox {
+        min_lat: polygon.iter().map(|(lat, _)| *lat).fold(f64::INFINITY, f64::min),
+        min_lon: polygon.iter().map(|(_, lon)| *lon).fold(f64::INFINITY, f64::min),
+        max_lat: polygon.iter().map(|(lat, _)| *lat).fold(f64::NEG_INFINITY, f64::max),
+        max_lon: polygon.iter().map(|(_, lon)| *lon).fold(f64::NEG_INFINITY, f64::max),
+    };
+    if bbox.max_lon - bbox.min_lon > 180.0 {
+        return Err("Polygons spanning more than 180 degrees of longitude are not supported".to_string());
+    }
+
+    let ids = points_in_bounding_box(&bbox).into_iter()
+        .filter(|(_, point)| polygon_contains(&polygon, *point))
+        .map(|(artifact_id, _)| artifact_id)
+        .collect();
+    Ok(newest_first_page(ids, page))
+}
+
+// ============================================================================
+// GEOJSON EXPORT
+// ============================================================================
+
+fn artifact_feature(artifact: &Artifact, point: GeoPoint) -> Value {
+    let origin = artifact.geographic_origin.as_ref();
+    json!({
+        "type": "Feature",
+        "id": artifact.id,
+        // GeoJSON positions are [longitude, latitude]
+        "geometry": { "type": "Point", "coordinates": [point.lon, point.lat] },
+        "properties": {
+            "name": artifact.name,
+            "status": format!("{:?}", artifact.status),
+            "verification_level": format!("{:?}", artifact.verification_level),
+            "country": origin.map(|origin| origin.country.clone()),
+            "region": origin.and_then(|origin| origin.region.clone()),
+            "site_name": origin.and_then(|origin| origin.site_name.clone()),
+        },
+    })
+}
+
+// One page of located artifacts, optionally limited to `bbox`, in geohash
+// order. The FeatureCollection carries "next_cursor" and "total" as foreign
+// members; the cursor is an offset into that ordering.
+pub fn export_artifacts_geojson(bbox: Option<BoundingBox>, page: &PageRequest) -> Result<String, String> {
+    let points: Page<(u64, GeoPoint)> = match bbox {
+        Some(bbox) => {
+            validate_bounding_box(&bbox)?;
+            paginate(points_in_bounding_box(&bbox), page)
+        },
+        // Without a box only the requested slice of the index is read
+        None => GEO_INDEX.with(|index| {
+            let index = index.borrow();
+            let total = index.len();
+            let offset = page.cursor.unwrap_or(0).min(total);
+            let items: Vec<(u64, GeoPoint)> = index.iter()
+                .skip(offset as usize)
+                .take(page_limit(page))
+                .map(|(key, point)| (key.artifact_id(), point))
+                .collect();
+            let end = offset + items.len() as u64;
+            Page { items, next_cursor: (end < total).then_some(end), total }
+        }),
+    };
+
+    let features: Vec<Value> = ARTIFACTS.with(|artifacts| {
+        let artifacts = artifacts.borrow();
+        points.items.into_iter()
+            .filter_map(|(artifact_id, point)| {
+                artifacts.get(&artifact_id).map(|artifact| artifact_feature(&artifact, point))
+            })
+            .collect()
+    });
+
+    serde_json::to_string(&json!({
+        "type": "FeatureCollection",
+        "features": features,
+        "next_cursor": points.next_cursor,
+        "total": points.total,
+    }))
+    .map_err(|e| format!("Failed to encode GeoJSON: {}", e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::modules::testing::{artifact_request, create_artifact_as, principal};
+
+    fn located(name: &str, lat: f64, lon: f64) -> u64 {
+        let mut request = artifact_request(name);
+        request.geographic_origin = Some(GeographicOrigin {
+            country: "Unknown".to_string(),
+            region: None,
+            city: None,
+            site_name: None,
+            coordinates: Some((lat, lon)),
+            discovery_context: None,
+        });
+        create_artifact_as(principal(1), request)
+    }
+
+    fn page(cursor: Option<u64>, limit: u32) -> PageRequest {
+        PageRequest { cursor, limit: Some(limit) }
+    }
+
+    fn ids(page: &Page<Artifact>) -> Vec<u64> {
+        page.items.iter().map(|artifact| artifact.id).collect()
+    }
+
+    #[test]
+    fn geohash_matches_reference_values() {
+        assert_eq!(geohash(57.64911, 10.40744, 11), "u4pruydqqvj");
+        assert_eq!(geohash(-90.0, -180.0, 4), "0000");
+        assert_eq!(geohash(90.0, 180.0, 4), "zzzz");
+        // Nearby points share a prefix
+        assert_eq!(&geohash(30.0444, 31.2357, 9)[..5], &geohash(30.0450, 31.2360, 9)[..5]);
+        assert_eq!(cell_size(1), (45.0, 45.0));
+        assert_eq!(cell_size(2), (5.625, 11.25));
+    }
+
+    #[test]
+    fn bounding_boxes_may_cross_the_antimeridian() {
+        let pacific = BoundingBox { min_lat: -25.0, min_lon: 170.0, max_lat: -10.0, max_lon: -170.0 };
+        assert!(bbox_contains(&pacific, GeoPoint { lat: -17.7, lon: 178.0 }));
+        assert!(bbox_contains(&pacific, GeoPoint { lat: -13.8, lon: -172.1 }));
+        assert!(!bbox_contains(&pacific, GeoPoint { lat: -17.7, lon: 0.0 }));
+        assert_eq!(split_antimeridian(&pacific).len(), 2);
+
+        let fiji = located("Fiji tapa", -17.7, 178.0);
+        let samoa = located("Samoan adze", -13.8, -172.1);
+        located("Sydney shell", -33.9, 151.2);
+        let found = find_artifacts_in_bounding_box(pacific, &page(None, 10)).unwrap();
+        assert_eq!(ids(&found), vec![samoa, fiji]);
+    }
+
+    #[test]
+    fn bounding_box_results_page_newest_first() {
+        let found: Vec<u64> = (0..5).map(|n| located(&format!("Sherd {}", n), 30.0 + n as f64 * 0.01, 31.0)).collect();
+        let bbox = BoundingBox { min_lat: 29.0, min_lon: 30.0, max_lat: 31.0, max_lon: 32.0 };
+
+        let first = find_artifacts_in_bounding_box(bbox, &page(None, 2)).unwrap();
+        assert_eq!(ids(&first), vec![found[4], found[3]]);
+        assert_eq!(first.total, 5);
+        let last = find_artifacts_in_bounding_box(bbox, &page(Some(4), 2)).unwrap();
+        assert_eq!(ids(&last), vec![found[0]]);
+        assert_eq!(last.next_cursor, None);
+
+        assert!(validate_bounding_box(&BoundingBox { min_lat: 31.5, ..bbox }).is_err());
+    }
+
+    #[test]
+    fn polygons_match_by_ray_casting() {
+        // An L-shaped ring: the notch at its north-east corner is outside
+        let ring = vec![(0.0, 0.0), (0.0, 10.0), (5.0, 10.0), (5.0, 5.0), (10.0, 5.0), (10.0, 0.0)];
+        assert!(polygon_contains(&ring, GeoPoint { lat: 2.0, lon: 8.0 }));
+        assert!(polygon_contains(&ring, GeoPoint { lat: 8.0, lon: 2.0 }));
+        assert!(!polygon_contains(&ring, GeoPoint { lat: 8.0, lon: 8.0 }));
+
+        let inside = located("Inside", 8.0, 2.0);
+        located("Notch", 8.0, 8.0);
+        let found = find_artifacts_in_polygon(ring, &page(None, 10)).unwrap();
+        assert_eq!(ids(&found), vec![inside]);
+
+        assert!(find_artifacts_in_polygon(vec![(0.0, 0.0), (1.0, 1.0)], &page(None, 10)).is_err());
+        assert!(find_artifacts_in_polygon(vec![(0.0, -100.0), (1.0, 100.0), (2.0, 0.0)], &page(None, 10)).is_err());
+    }
+
+    #[test]
+    fn nearby_search_orders_by_distance_across_the_antimeridian() {
+        let paris = GeoPoint { lat: 48.8566, lon: 2.3522 };
+        let london = GeoPoint { lat: 51.5074, lon: -0.1278 };
+        assert!((haversine_km(paris, london) - 343.5).abs() < 1.0);
+
+        let east = located("East", -17.0, 179.9);
+        let west = located("West", -17.0, -179.5);
+        located("Far", -17.0, 170.0);
+        let found = find_artifacts_near(-17.0, 179.95, 100.0, &page(None, 10)).unwrap();
+        assert_eq!(found.items.iter().map(|hit| hit.artifact.id).collect::<Vec<_>>(), vec![east, west]);
+        assert!(found.items[0].distance_km < found.items[1].distance_km);
+
+        assert!(find_artifacts_near(0.0, 0.0, -1.0, &page(None, 10)).is_err());
+    }
+
+    #[test]
+    fn geojson_export_is_paged() {
+        for n in 0..3 {
+            located(&format!("Coin {}", n), 37.9 + n as f64, 23.7);
+        }
+
+        let first: Value = serde_json::from_str(&export_artifacts_geojson(None, &page(None, 2)).unwrap()).unwrap();
+        assert_eq!(first["features"].as_array().unwrap().len(), 2);
+        assert_eq!(first["total"], 3);
+        assert_eq!(first["next_cursor"], 2);
+        assert_eq!(first["features"][0]["geometry"]["coordinates"], json!([23.7, 37.9]));
+
+        let rest: Value = serde_json::from_str(&export_artifacts_geojson(None, &page(Some(2), 2)).unwrap()).unwrap();
+        assert_eq!(rest["features"].as_array().unwrap().len(), 1);
+        assert_eq!(rest["next_cursor"], Value::Null);
+
+        let bbox = BoundingBox { min_lat: 38.5, min_lon: 23.0, max_lat: 40.0, max_lon: 24.0 };
+        let boxed: Value = serde_json::from_str(&export_artifacts_geojson(Some(bbox), &page(None, 10)).unwrap()).unwrap();
+        assert_eq!(boxed["total"], 2);
+    }
+}
@@ -0,0 +1,361 @@
+use candid::CandidType;
+use ic_cdk::query;
+use serde::{Deserialize, Serialize};
+
+use crate::modules::types::*;
+use crate::modules::storage::GOVERNANCE_RULES;
+use crate::modules::utils::calculate_quorum;
+use crate::modules::tally::{tally_votes, VoteTally};
+
+// ============================================================================
+// GOVERNANCE RULES
+// ============================================================================
+//
+// Every proposal type has a rule deciding when its vote counts and what it
+// takes to pass:
+//
+//   quorum_percent          share of eligible voters that must take part,
+//                           fixed into `Proposal.quorum_required` at creation
+//   pass_threshold_percent  the For weight must exceed this share of the
+//                           decisive (For + Against) weight; 50 is a simple
+//                           majority, 66 a two-thirds supermajority
+//   min_voting_hours        shortest voting period a proposal may ask for, and
+//                           the earliest a vote may be concluded early
+//   abstention              how Abstain and RequiresMoreEvidence votes count
+//
+// The rules live in stable memory and only change through an executed
+// UpdateVerificationCriteria proposal, with criteria keyed
+// `governance.<ProposalType>.<field>`, for example
+// `governance.GrantUserRole.pass_threshold_percent = 75`. The quorum, threshold
+// and abstention policy are copied onto each proposal at creation, so a change
+// only applies to proposals created after it.
+
+pub const RULE_KEY_PREFIX: &str = "governance.";
+const MAX_MIN_VOTING_HOURS: u64 = 30 * 24;
+
+pub const ALL_PROPOSAL_TYPES: &[ProposalType] = &[
+    ProposalType::VerifyArtifact,
+    ProposalType::DisputeArtifact,
+    ProposalType::UpdateArtifactStatus,
+    ProposalType::GrantUserRole,
+    ProposalType::RevokeUserRole,
+    ProposalType::UpdateArtifactMetadata,
+    ProposalType::RequestAdditionalEvidence,
+    ProposalType::ProposeConservationAction,
+    ProposalType::RequestExpertReview,
+    ProposalType::UpdateVerificationCriteria,
+    ProposalType::EmergencyIntervention,
+    ProposalType::ApproveCustodyTransfer,
+];
+
+#[derive(CandidType, Serialize, Deserialize, Clone, Copy, Debug, PartialEq)]
+pub enum AbstentionPolicy {
+    // Abstainers make up quorum but do not weigh on the outcome
+    CountTowardQuorum,
+    // Abstentions are disregarded entirely
+    Ignore,
+    // Abstainers make up quorum and weigh against the proposal
+    CountAsAgainst,
+}
+
+#[derive(CandidType, Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct GovernanceRule {
+    pub quorum_percent: u32,
+    pub pass_threshold_percent: u32,
+    pub min_voting_hours: u64,
+    pub abstention: AbstentionPolicy,
+}
+
+#[derive(CandidType, Serialize, Deserialize, Clone, Debug, Default)]
+pub struct GovernanceRules {
+    pub rules: Vec<(ProposalType, GovernanceRule)>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum VotingOutcome {
+    QuorumNotMet,
+    Passed,
+    Rejected,
+}
+
+// Quorum percentages carried over from the original `calculate_quorum`
+pub fn default_rule(proposal_type: &ProposalType) -> GovernanceRule {
+    let (quorum_percent, pass_threshold_percent, min_voting_hours) = match proposal_type {
+        ProposalType::EmergencyIntervention => (75, 66, 1),
+        ProposalType::VerifyArtifact | ProposalType::DisputeArtifact => (60, 50, 24),
+        ProposalType::GrantUserRole | ProposalType::RevokeUserRole => (70, 66, 72),
+        ProposalType::UpdateVerificationCriteria | ProposalType::ApproveCustodyTransfer => (50, 66, 72),
+        _ => (50, 50, 24),
+    };
+    GovernanceRule { quorum_percent, pass_threshold_percent, min_voting_hours, abstention: AbstentionPolicy::CountTowardQuorum }
+}
+
+impl GovernanceRules {
+    pub fn rule(&self, proposal_type: &ProposalType) -> GovernanceRule {
+        self.rules.iter()
+            .find(|(rule_type, _)| rule_type == proposal_type)
+            .map(|(_, rule)| rule.clone())
+            .unwrap_or_else(|| default_rule(proposal_type))
+    }
+
+    fn set_rule(&mut self, proposal_type: ProposalType, rule: GovernanceRule) {
+        match self.rules.iter_mut().find(|(rule_type, _)| *rule_type == proposal_type) {
+            Some((_, existing)) => *existing = rule,
+            None => self.rules.push((proposal_type, rule)),
+        }
+    }
+}
+
+pub fn rule_for(proposal_type: &ProposalType) -> GovernanceRule {
+    GOVERNANCE_RULES.with(|rules| rules.borrow().get().rule(proposal_type))
+}
+
+pub fn quorum_for(proposal_type: &ProposalType, eligible_voters: u32) -> u32 {
+    calculate_quorum(eligible_voters, rule_for(proposal_type).quorum_percent).max(1)
+}
+
+// ============================================================================
+// DECIDING A VOTE
+// ============================================================================
+
+pub fn decide(pass_threshold_percent: u32, abstention: AbstentionPolicy, tally: &VoteTally, quorum_required: u32) -> VotingOutcome {
+    let turnout = match abstention {
+        AbstentionPolicy::Ignore => tally.voters - tally.abstaining_voters,
+        AbstentionPolicy::CountTowardQuorum | AbstentionPolicy::CountAsAgainst => tally.voters,
+    };
+    if turnout == 0 || turnout < quorum_required {
+        return VotingOutcome::QuorumNotMet;
+    }
+
+    let against = match abstention {
+        AbstentionPolicy::CountAsAgainst => tally.weight_against + tally.weight_abstaining,
+        AbstentionPolicy::CountTowardQuorum | AbstentionPolicy::Ignore => tally.weight_against,
+    };
+    let decisive = tally.weight_for + against;
+    if decisive > 0 && tally.weight_for * 100 > pass_threshold_percent as u64 * decisive {
+        VotingOutcome::Passed
+    } else {
+        VotingOutcome::Rejected
+    }
+}
+
+// Votes are weighed by the proposal's voting mode as of `now`, see modules::tally,
+// and decided by the rule fixed into the proposal at creation
+pub fn voting_outcome(proposal: &Proposal, now: u64) -> VotingOutcome {
+    decide(proposal.pass_threshold_percent, proposal.abstention, &tally_votes(proposal, now), proposal.quorum_required)
+}
+
+pub fn min_voting_period_elapsed(proposal: &Proposal, now: u64) -> bool {
+    let min_voting_nanos = rule_for(&proposal.proposal_type).min_voting_hours * 3600 * 1_000_000_000;
+    now >= proposal.created_at + min_voting_nanos
+}
+
+// ============================================================================
+// CHANGING THE RULES
+// ============================================================================
+
+fn parse_proposal_type(name: &str) -> Option<ProposalType> {
+    ALL_PROPOSAL_TYPES.iter().find(|proposal_type| format!("{:?}", proposal_type) == name).cloned()
+}
+
+fn parse_abstention(name: &str) -> Option<AbstentionPolicy> {
+    [AbstentionPolicy::CountTowardQuorum, AbstentionPolicy::Ignore, AbstentionPolicy::CountAsAgainst]
+        .into_iter()
+        .find(|policy| format!("{:?}", policy) == name)
+}
+
+fn parse_number<T: std::str::FromStr>(key: &str, value: &str) -> Result<T, String> {
+    value.trim().parse().map_err(|_| format!("{} must be a whole number, got '{}'", key, value))
+}
+
+fn validate_rule(proposal_type: &ProposalType, rule: &GovernanceRule) -> Result<(), String> {
+    if !(1..=100).contains(&rule.quorum_percent) {
+        return Err(format!("{:?} quorum must be between 1 and 100 percent", proposal_type));
+    }
+    if !(50..=100).contains(&rule.pass_threshold_percent) {
+        return Err(format!("{:?} pass threshold must be between 50 and 100 percent", proposal_type));
+    }
+    if rule.min_voting_hours > MAX_MIN_VOTING_HOURS {
+        return Err(format!("{:?} minimum voting period cannot exceed {} hours", proposal_type, MAX_MIN_VOTING_HOURS));
+    }
+    Ok(())
+}
+
+// Applies the `governance.*` criteria to `rules`; other criteria are ignored
+pub fn apply_rule_changes(mut rules: GovernanceRules, criteria: &[(String, String)]) -> Result<GovernanceRules, String> {
+    for (key, value) in criteria {
+        let Some(path) = key.strip_prefix(RULE_KEY_PREFIX) else { continue };
+        let (type_name, field) = path.split_once('.')
+            .ok_or_else(|| format!("Governance rule key '{}' must be governance.<ProposalType>.<field>", key))?;
+        let proposal_type = parse_proposal_type(type_name)
+            .ok_or_else(|| format!("Unknown proposal type '{}' in '{}'", type_name, key))?;
+
+        let mut rule = rules.rule(&proposal_type);
+        match field {
+            "quorum_percent" => rule.quorum_percent = parse_number(key, value)?,
+            "pass_threshold_percent" => rule.pass_threshold_percent = parse_number(key, value)?,
+            "min_voting_hours" => rule.min_voting_hours = parse_number(key, value)?,
+            "abstention" => {
+                rule.abstention = parse_abstention(value.trim())
+                    .ok_or_else(|| format!("Unknown abstention policy '{}' in '{}'", value, key))?;
+            },
+            _ => return Err(format!("Unknown governance rule field '{}' in '{}'", field, key)),
+        }
+        validate_rule(&proposal_type, &rule)?;
+        rules.set_rule(proposal_type, rule);
+    }
+    Ok(rules)
+}
+
+// Checked when an UpdateVerificationCriteria proposal is created
+pub fn validate_rule_changes(criteria: &[(String, String)]) -> Result<(), String> {
+    let rules = GOVERNANCE_RULES.with(|rules| rules.borrow().get().clone());
+    apply_rule_changes(rules, criteria).map(|_| ())
+}
+
+// Returns how many rule fields changed
+pub fn update_governance_rules(criteria: &[(String, String)]) -> Result<usize, String> {
+    GOVERNANCE_RULES.with(|cell| {
+        let mut cell = cell.borrow_mut();
+        let rules = apply_rule_changes(cell.get().clone(), criteria)?;
+        cell.set(rules).expect("Failed to save governance rules");
+        Ok(criteria.iter().filter(|(key, _)| key.starts_with(RULE_KEY_PREFIX)).count())
+    })
+}
+
+// ============================================================================
+// ENDPOINTS
+// ============================================================================
+
+#[query]
+pub fn get_governance_rules() -> Vec<(ProposalType, GovernanceRule)> {
+    GOVERNANCE_RULES.with(|rules| {
+        let rules = rules.borrow();
+        ALL_PROPOSAL_TYPES.iter()
+            .map(|proposal_type| (proposal_type.clone(), rules.get().rule(proposal_type)))
+            .collect()
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::modules::dao::{create_proposal, get_proposal};
+    use crate::modules::testing::{artifact_request, create_artifact_as, now, principal, register, set_caller};
+    use crate::modules::voting::vote_on_proposal;
+
+    fn tally(for_against_abstain: [(u32, u64); 3]) -> VoteTally {
+        let [(voters_for, weight_for), (voters_against, weight_against), (abstaining_voters, weight_abstaining)] = for_against_abstain;
+        VoteTally {
+            voters: voters_for + voters_against + abstaining_voters,
+            abstaining_voters,
+            weight_for,
+            weight_against,
+            weight_abstaining,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn rejects_votes_below_quorum() {
+        let counted = AbstentionPolicy::CountTowardQuorum;
+        assert_eq!(decide(50, counted, &tally([(2, 20), (0, 0), (0, 0)]), 3), VotingOutcome::QuorumNotMet);
+        assert_eq!(decide(50, counted, &tally([(2, 20), (1, 10), (0, 0)]), 3), VotingOutcome::Passed);
+        assert_eq!(decide(50, counted, &VoteTally::default(), 0), VotingOutcome::QuorumNotMet);
+    }
+
+    #[test]
+    fn supermajority_needs_more_than_the_threshold() {
+        let counted = AbstentionPolicy::CountTowardQuorum;
+        assert_eq!(decide(66, counted, &tally([(3, 60), (2, 40), (0, 0)]), 1), VotingOutcome::Rejected);
+        assert_eq!(decide(66, counted, &tally([(2, 20), (1, 10), (0, 0)]), 1), VotingOutcome::Passed);
+
+        // A tie never passes a simple majority
+        assert_eq!(decide(50, counted, &tally([(1, 10), (1, 10), (0, 0)]), 1), VotingOutcome::Rejected);
+    }
+
+    #[test]
+    fn abstention_policies() {
+        let votes = tally([(1, 10), (0, 0), (2, 20)]);
+
+        // Abstainers make up the quorum of 3, but only the For vote is decisive
+        assert_eq!(decide(50, AbstentionPolicy::CountTowardQuorum, &votes, 3), VotingOutcome::Passed);
+        assert_eq!(decide(50, AbstentionPolicy::Ignore, &votes, 3), VotingOutcome::QuorumNotMet);
+        assert_eq!(decide(50, AbstentionPolicy::CountAsAgainst, &votes, 3), VotingOutcome::Rejected);
+
+        // Only abstentions: quorum may be met but nothing passes
+        let abstained = tally([(0, 0), (0, 0), (3, 30)]);
+        assert_eq!(decide(50, AbstentionPolicy::CountTowardQuorum, &abstained, 3), VotingOutcome::Rejected);
+    }
+
+    fn verification_proposal(name: &str) -> u64 {
+        let artifact_id = create_artifact_as(principal(1), artifact_request(name));
+        set_caller(principal(1));
+        create_proposal(CreateProposalRequest {
+            proposal_type: ProposalType::VerifyArtifact,
+            artifact_id: Some(artifact_id),
+            title: format!("Verify the {}", name),
+            description: "A verification proposal decided under the rule it was created with.".to_string(),
+            evidence: None,
+            voting_duration_hours: 24,
+            execution_payload: None,
+            required_expertise: None,
+            urgency_level: None,
+            quorum_required: None,
+            voting_mode: None,
+        }).unwrap()
+    }
+
+    #[test]
+    fn rule_changes_leave_open_proposals_alone() {
+        for expert in 2..=4 {
+            register(principal(expert), UserRole::Expert, &["ceramics"]);
+        }
+        let open = verification_proposal("amphora");
+        for (expert, vote_type) in [(2, VoteType::For), (3, VoteType::For), (4, VoteType::Against)] {
+            set_caller(principal(expert));
+            vote_on_proposal(VoteRequest { proposal_id: open, vote_type, rationale: None, expertise_relevance: None, credits: None }).unwrap();
+        }
+        assert_eq!(voting_outcome(&get_proposal(open).unwrap(), now()), VotingOutcome::Passed);
+
+        update_governance_rules(&[
+            ("governance.VerifyArtifact.pass_threshold_percent".to_string(), "75".to_string()),
+            ("governance.VerifyArtifact.abstention".to_string(), "CountAsAgainst".to_string()),
+        ]).unwrap();
+
+        // Two thirds still passes the proposal opened under the simple majority
+        let proposal = get_proposal(open).unwrap();
+        assert_eq!((proposal.pass_threshold_percent, proposal.abstention), (50, AbstentionPolicy::CountTowardQuorum));
+        assert_eq!(voting_outcome(&proposal, now()), VotingOutcome::Passed);
+
+        let later = get_proposal(verification_proposal("oil lamp")).unwrap();
+        assert_eq!((later.pass_threshold_percent, later.abstention), (75, AbstentionPolicy::CountAsAgainst));
+    }
+
+    #[test]
+    fn rule_changes_are_validated_and_applied() {
+        let criteria = vec![
+            ("governance.GrantUserRole.pass_threshold_percent".to_string(), "75".to_string()),
+            ("governance.GrantUserRole.abstention".to_string(), "CountAsAgainst".to_string()),
+            ("minimum_evidence_items".to_string(), "3".to_string()),
+        ];
+        let rules = apply_rule_changes(GovernanceRules::default(), &criteria).unwrap();
+        let granted = rules.rule(&ProposalType::GrantUserRole);
+        assert_eq!(granted.pass_threshold_percent, 75);
+        assert_eq!(granted.abstention, AbstentionPolicy::CountAsAgainst);
+        assert_eq!(granted.quorum_percent, default_rule(&ProposalType::GrantUserRole).quorum_percent);
+        assert_eq!(rules.rule(&ProposalType::RevokeUserRole), default_rule(&ProposalType::RevokeUserRole));
+
+        for (key, value) in [
+            ("governance.GrantUserRole.pass_threshold_percent", "40"),
+            ("governance.GrantUserRole.quorum_percent", "0"),
+            ("governance.GrantUserRole.min_voting_hours", "many"),
+            ("governance.NoSuchType.quorum_percent", "50"),
+            ("governance.GrantUserRole.color", "blue"),
+            ("governance.GrantUserRole", "50"),
+        ] {
+            let criteria = vec![(key.to_string(), value.to_string())];
+            assert!(apply_rule_changes(GovernanceRules::default(), &criteria).is_err(), "{} = {}", key, value);
+        }
+    }
+}
@@ -0,0 +1,395 @@
+use candid::{define_function, CandidType, Principal};
+use ic_cdk::query;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use sha2::{Digest, Sha256};
+
+use crate::modules::artifacts::get_artifact;
+use crate::modules::nft::{get_nft, get_nft_by_certificate_number};
+use crate::modules::dao::{get_proposal, proposal_response};
+use crate::modules::media::{get_media_chunk, get_media_info, MediaAsset};
+use crate::modules::certificates::{get_certificate_document, CertificateDocument};
+
+// ============================================================================
+// HTTP GATEWAY
+// ============================================================================
+//
+// `http_request` serves read-only canister content through the HTTP gateway,
+// so links can be shared without a Candid client:
+//
+//   GET /artifacts/<id>             artifact as JSON
+//   GET /nfts/<id>                  Proof of Heritage NFT as JSON
+//   GET /proposals/<id>             proposal as JSON
+//   GET /certificates/<number>      the NFT holding that heritage certificate, as JSON
+//   GET /certificates/<number>.jsonld|.html|.svg
+//                                   the sealed certificate document, see modules::certificates
+//   GET /media/<id>                 a committed media file, see modules::media
+//
+// JSON bodies are the records returned by the matching Candid queries, errors
+// are `{"error": "..."}`. Every response carries an ETag (SHA-256 of the body,
+// or of the file for media) and honours If-None-Match. Records can change, so
+// JSON is cached briefly; media are immutable and cached for a year.
+//
+// Media larger than one chunk are streamed: the first response carries chunk 0
+// and a callback token, and the gateway fetches the remaining chunks through
+// `http_request_streaming_callback`. Responses are not certified, so they must
+// be fetched through the raw domain; clients needing proof should use the
+// certified Candid queries (see modules::certification).
+
+const IMMUTABLE_CACHE_CONTROL: &str = "public, max-age=31536000, immutable";
+const RECORD_CACHE_CONTROL: &str = "public, max-age=60";
+
+#[derive(CandidType, Deserialize, Clone, Debug)]
+pub struct HttpRequest {
+    pub method: String,
+    pub url: String,
+    pub headers: Vec<(String, String)>,
+    pub body: Vec<u8>,
+}
+
+#[derive(CandidType, Deserialize, Clone, Debug)]
+pub struct HttpResponse {
+    pub status_code: u16,
+    pub headers: Vec<(String, String)>,
+    pub body: Vec<u8>,
+    pub streaming_strategy: Option<StreamingStrategy>,
+}
+
+#[derive(CandidType, Deserialize, Clone, Debug)]
+pub struct StreamingCallbackToken {
+    pub media_id: u64,
+    pub index: u32,
+}
+
+#[derive(CandidType, Deserialize, Clone, Debug)]
+pub struct StreamingCallbackHttpResponse {
+    pub body: Vec<u8>,
+    pub token: Option<StreamingCallbackToken>,
+}
+
+define_function!(pub StreamingCallback : (StreamingCallbackToken) -> (StreamingCallbackHttpResponse) query);
+
+#[derive(CandidType, Deserialize, Clone, Debug)]
+pub enum StreamingStrategy {
+    Callback {
+        callback: StreamingCallback,
+        token: StreamingCallbackToken,
+    },
+}
+
+fn text_response(status_code: u16, message: &str) -> HttpResponse {
+    HttpResponse {
+        status_code,
+        headers: vec![("Content-Type".to_string(), "text/plain; charset=utf-8".to_string())],
+        body: message.as_bytes().to_vec(),
+        streaming_strategy: None,
+    }
+}
+
+fn not_modified(request: &HttpRequest, etag: &str) -> bool {
+    request_header(request, "If-None-Match")
+        .is_some_and(|value| value.split(',').any(|candidate| candidate.trim() == etag))
+}
+
+fn json_response<T: Serialize>(request: &HttpRequest, status_code: u16, value: &T) -> HttpResponse {
+    let body = match serde_json::to_vec(value) {
+        Ok(body) => body,
+        Err(e) => return text_response(500, &format!("Failed to encode response: {}", e)),
+    };
+    let etag = format!("\"{}\"", hex(&Sha256::digest(&body)));
+    let headers = vec![
+        ("Content-Type".to_string(), "application/json; charset=utf-8".to_string()),
+        ("Cache-Control".to_string(), RECORD_CACHE_CONTROL.to_string()),
+        ("ETag".to_string(), etag.clone()),
+        ("Access-Control-Allow-Origin".to_string(), "*".to_string()),
+        ("X-Content-Type-Options".to_string(), "nosniff".to_string()),
+    ];
+
+    if status_code == 200 && not_modified(request, &etag) {
+        return HttpResponse { status_code: 304, headers, body: Vec::new(), streaming_strategy: None };
+    }
+    let body = if request.method == "HEAD" { Vec::new() } else { body };
+    HttpResponse { status_code, headers, body, streaming_strategy: None }
+}
+
+// 200 with the record, or 404 with the lookup error
+fn json_record<T: Serialize>(request: &HttpRequest, record: Result<T, String>) -> HttpResponse {
+    match record {
+        Ok(record) => json_response(request, 200, &record),
+        Err(error) => json_response(request, 404, &json!({ "error": error })),
+    }
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+fn request_header<'a>(request: &'a HttpRequest, name: &str) -> Option<&'a str> {
+    request.headers.iter()
+        .find(|(key, _)| key.eq_ignore_ascii_case(name))
+        .map(|(_, value)| value.as_str())
+}
+
+#[cfg(not(test))]
+fn canister_id() -> Principal {
+    ic_cdk::id()
+}
+
+#[cfg(test)]
+fn canister_id() -> Principal {
+    crate::modules::testing::canister_id()
+}
+
+// Token for the chunk after `index`, if there is one
+fn next_chunk_token(asset: &MediaAsset, index: u32) -> Option<StreamingCallbackToken> {
+    (index + 1 < asset.chunk_count).then_some(StreamingCallbackToken {
+        media_id: asset.id,
+        index: index + 1,
+    })
+}
+
+#[query]
+pub fn http_request(request: HttpRequest) -> HttpResponse {
+    if request.method != "GET" && request.method != "HEAD" {
+        return text_response(405, "Method not allowed");
+    }
+
+    let path = request.url.split(['?', '#']).next().unwrap_or_default();
+    let segments: Vec<&str> = path.trim_matches('/').split('/').collect();
+    match segments.as_slice() {
+        ["artifacts", id] => match id.parse::<u64>() {
+            Ok(artifact_id) => json_record(&request, get_artifact(artifact_id)),
+            Err(_) => json_response(&request, 400, &json!({ "error": "Invalid artifact id" })),
+        },
+        ["nfts", id] => match id.parse::<u64>() {
+            Ok(nft_id) => json_record(&request, get_nft(nft_id)),
+            Err(_) => json_response(&request, 400, &json!({ "error": "Invalid NFT id" })),
+        },
+        ["proposals", id] => match id.parse::<u64>() {
+            Ok(proposal_id) => json_record(&request, get_proposal(proposal_id).map(|proposal| proposal_response(&proposal))),
+            Err(_) => json_response(&request, 400, &json!({ "error": "Invalid proposal id" })),
+        },
+        ["certificates", name] => match name.rsplit_once('.') {
+            Some((number, "jsonld")) => serve_certificate(&request, number, "application/ld+json", |document| document.json_ld),
+            Some((number, "html")) => serve_certificate(&request, number, "text/html; charset=utf-8", |document| document.html),
+            Some((number, "svg")) => serve_certificate(&request, number, "image/svg+xml", |document| document.svg),
+            _ => json_record(&request, get_nft_by_certificate_number(name.to_string())),
+        },
+        ["media", media_id] => match media_id.parse::<u64>() {
+            Ok(media_id) => serve_media(&request, media_id),
+            Err(_) => text_response(400, "Invalid media id"),
+        },
+        _ => json_response(&request, 404, &json!({ "error": "Not found" })),
+    }
+}
+
+fn serve_certificate<F>(request: &HttpRequest, number: &str, content_type: &str, rendering: F) -> HttpResponse
+where
+    F: FnOnce(CertificateDocument) -> String,
+{
+    let document = match get_certificate_document(number.to_string()) {
+        Ok(document) => document,
+        Err(error) => return json_response(request, 404, &json!({ "error": error })),
+    };
+
+    // The ETag is the sealed digest, shared by every rendering of the document
+    let etag = format!("\"{}\"", document.sha256);
+    let headers = vec![
+        ("Content-Type".to_string(), content_type.to_string()),
+        ("Cache-Control".to_string(), RECORD_CACHE_CONTROL.to_string()),
+        ("ETag".to_string(), etag.clone()),
+        ("Access-Control-Allow-Origin".to_string(), "*".to_string()),
+        ("X-Content-Type-Options".to_string(), "nosniff".to_string()),
+    ];
+
+    if not_modified(request, &etag) {
+        return HttpResponse { status_code: 304, headers, body: Vec::new(), streaming_strategy: None };
+    }
+    let body = if request.method == "HEAD" { Vec::new() } else { rendering(document).into_bytes() };
+    HttpResponse { status_code: 200, headers, body, streaming_strategy: None }
+}
+
+fn serve_media(request: &HttpRequest, media_id: u64) -> HttpResponse {
+    let Ok(asset) = get_media_info(media_id) else {
+        return text_response(404, "Media not found");
+    };
+
+    let etag = format!("\"{}\"", asset.sha256);
+    let mut headers = vec![
+        ("Content-Type".to_string(), asset.content_type.clone()),
+        ("Content-Length".to_string(), asset.size.to_string()),
+        ("Cache-Control".to_string(), IMMUTABLE_CACHE_CONTROL.to_string()),
+        ("ETag".to_string(), etag.clone()),
+        ("X-Content-Type-Options".to_string(), "nosniff".to_string()),
+    ];
+
+    if not_modified(request, &etag) {
+        headers.retain(|(key, _)| key != "Content-Length");
+        return HttpResponse { status_code: 304, headers, body: Vec::new(), streaming_strategy: None };
+    }
+    if request.method == "HEAD" {
+        return HttpResponse { status_code: 200, headers, body: Vec::new(), streaming_strategy: None };
+    }
+
+    let Some(body) = get_media_chunk(media_id, 0) else {
+        return text_response(500, "Media content missing");
+    };
+    let streaming_strategy = next_chunk_token(&asset, 0).map(|token| StreamingStrategy::Callback {
+        callback: StreamingCallback::new(canister_id(), "http_request_streaming_callback".to_string()),
+        token,
+    });
+
+    HttpResponse { status_code: 200, headers, body, streaming_strategy }
+}
+
+#[query]
+pub fn http_request_streaming_callback(token: StreamingCallbackToken) -> StreamingCallbackHttpResponse {
+    let chunk = get_media_info(token.media_id).ok()
+        .and_then(|asset| get_media_chunk(asset.id, token.index).map(|body| (asset, body)));
+
+    match chunk {
+        Some((asset, body)) => StreamingCallbackHttpResponse {
+            body,
+            token: next_chunk_token(&asset, token.index),
+        },
+        None => ic_cdk::trap("Invalid streaming token"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::modules::media::{begin_media_upload, commit_media_upload, put_media_chunk};
+    use crate::modules::testing::{artifact_request, create_artifact_as, principal, register, set_caller};
+    use crate::modules::types::UserRole;
+    use serde_json::Value;
+
+    fn get(url: &str, headers: &[(&str, &str)]) -> HttpResponse {
+        http_request(HttpRequest {
+            method: "GET".to_string(),
+            url: url.to_string(),
+            headers: headers.iter().map(|(key, value)| (key.to_string(), value.to_string())).collect(),
+            body: Vec::new(),
+        })
+    }
+
+    fn header<'a>(response: &'a HttpResponse, name: &str) -> Option<&'a str> {
+        response.headers.iter().find(|(key, _)| key == name).map(|(_, value)| value.as_str())
+    }
+
+    fn json(response: &HttpResponse) -> Value {
+        serde_json::from_slice(&response.body).unwrap()
+    }
+
+    #[test]
+    fn records_are_served_as_json_with_an_etag() {
+        let artifact_id = create_artifact_as(principal(1), artifact_request("Scarab"));
+        let response = get(&format!("/artifacts/{}?view=full#top", artifact_id), &[]);
+
+        assert_eq!(response.status_code, 200);
+        assert_eq!(header(&response, "Content-Type"), Some("application/json; charset=utf-8"));
+        assert_eq!(json(&response)["name"], "Scarab");
+        let etag = header(&response, "ETag").unwrap();
+        assert_eq!(etag, format!("\"{}\"", hex(&Sha256::digest(&response.body))));
+
+        let cached = get(&format!("/artifacts/{}", artifact_id), &[("If-None-Match", &format!("\"other\", {}", etag))]);
+        assert_eq!(cached.status_code, 304);
+        assert!(cached.body.is_empty());
+    }
+
+    #[test]
+    fn lookup_failures_are_json_errors() {
+        let missing = get("/artifacts/42", &[]);
+        assert_eq!(missing.status_code, 404);
+        assert_eq!(json(&missing)["error"], "Artifact not found");
+        // Errors never answer 304, even for a matching ETag
+        let etag = header(&missing, "ETag").unwrap().to_string();
+        assert_eq!(get("/artifacts/42", &[("If-None-Match", &etag)]).status_code, 404);
+
+        assert_eq!(get("/nfts/x", &[]).status_code, 400);
+        assert_eq!(json(&get("/proposals/-1", &[]))["error"], "Invalid proposal id");
+        assert_eq!(get("/unknown/path", &[]).status_code, 404);
+    }
+
+    #[test]
+    fn only_get_and_head_are_served() {
+        let artifact_id = create_artifact_as(principal(1), artifact_request("Scarab"));
+        let request = |method: &str| http_request(HttpRequest {
+            method: method.to_string(),
+            url: format!("/artifacts/{}", artifact_id),
+            headers: Vec::new(),
+            body: Vec::new(),
+        });
+
+        assert_eq!(request("POST").status_code, 405);
+        let head = request("HEAD");
+        assert_eq!(head.status_code, 200);
+        assert!(head.body.is_empty());
+        assert_eq!(header(&head, "ETag"), header(&request("GET"), "ETag"));
+    }
+
+    // A PNG uploaded in three chunks
+    fn upload_media() -> (MediaAsset, Vec<Vec<u8>>) {
+        register(principal(1), UserRole::Institution, &[]);
+        set_caller(principal(1));
+        let chunks = vec![
+            vec![0x89, b'P', b'N', b'G', 0x0D, 0x0A, 0x1A, 0x0A],
+            vec![1; 8],
+            vec![2; 4],
+        ];
+        let media_id = begin_media_upload(20).unwrap();
+        for (index, chunk) in chunks.iter().enumerate() {
+            put_media_chunk(media_id, index as u32, chunk.clone()).unwrap();
+        }
+        (commit_media_upload(media_id).unwrap(), chunks)
+    }
+
+    #[test]
+    fn media_stream_through_the_callback() {
+        let (asset, chunks) = upload_media();
+        let response = get(&format!("/media/{}", asset.id), &[]);
+
+        assert_eq!(response.status_code, 200);
+        assert_eq!(response.body, chunks[0]);
+        assert_eq!(header(&response, "Content-Length"), Some("20"));
+        assert_eq!(header(&response, "ETag"), Some(format!("\"{}\"", asset.sha256).as_str()));
+        let Some(StreamingStrategy::Callback { callback, token }) = response.streaming_strategy else {
+            panic!("multi-chunk media must stream");
+        };
+        assert_eq!(callback.0.method, "http_request_streaming_callback");
+
+        let mut body = response.body;
+        let mut next = Some(token);
+        while let Some(token) = next {
+            let part = http_request_streaming_callback(token);
+            body.extend(part.body);
+            next = part.token;
+        }
+        assert_eq!(body, chunks.concat());
+    }
+
+    #[test]
+    fn cached_and_single_chunk_media_do_not_stream() {
+        let (asset, _) = upload_media();
+        let etag = format!("\"{}\"", asset.sha256);
+        let cached = get(&format!("/media/{}", asset.id), &[("if-none-match", &etag)]);
+        assert_eq!(cached.status_code, 304);
+        assert!(cached.body.is_empty() && cached.streaming_strategy.is_none());
+        assert_eq!(header(&cached, "Content-Length"), None);
+
+        let media_id = begin_media_upload(8).unwrap();
+        put_media_chunk(media_id, 0, vec![0x89, b'P', b'N', b'G', 0x0D, 0x0A, 0x1A, 0x0A]).unwrap();
+        commit_media_upload(media_id).unwrap();
+        assert!(get(&format!("/media/{}", media_id), &[]).streaming_strategy.is_none());
+
+        assert_eq!(get("/media/999", &[]).status_code, 404);
+        assert_eq!(get("/media/abc", &[]).status_code, 400);
+    }
+
+    #[test]
+    #[should_panic]
+    fn unknown_streaming_tokens_trap() {
+        let (asset, _) = upload_media();
+        http_request_streaming_callback(StreamingCallbackToken { media_id: asset.id, index: 7 });
+    }
+}
@@ -0,0 +1,311 @@
+use candid::{define_function, CandidType, Int, Nat, Principal};
+use ic_cdk::query;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use crate::modules::storage::ICRC3_BLOCKS;
+
+// ============================================================================
+// ICRC-3 TRANSACTION LOG
+// ============================================================================
+//
+// Ledger transactions are recorded as an append-only chain of ICRC-3 blocks:
+//
+//   { "btype": <block type>, "ts": <ledger time>, "phash": <parent hash>, "tx": { ... } }
+//
+// `phash` is the ICRC-3 hash of the previous block and is absent from block 0.
+// Block hashes follow the representation-independent hashing of the ICRC-3
+// standard, so any client can re-verify the chain from `icrc3_get_blocks`.
+// Blocks are never rewritten and the log is not archived: `icrc3_get_archives`
+// is always empty. The tip is not yet part of the certified tree, so
+// `icrc3_get_tip_certificate` is not offered.
+
+pub const MAX_BLOCKS_PER_REQUEST: u64 = 100;
+
+#[derive(CandidType, Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub enum Icrc3Value {
+    Blob(Vec<u8>),
+    Text(String),
+    Nat(Nat),
+    Int(Int),
+    Array(Vec<Icrc3Value>),
+    Map(Vec<(String, Icrc3Value)>),
+}
+
+impl Icrc3Value {
+    pub fn nat(value: u64) -> Self {
+        Icrc3Value::Nat(Nat::from(value))
+    }
+
+    pub fn text(value: impl Into<String>) -> Self {
+        Icrc3Value::Text(value.into())
+    }
+
+    // Accounts are `[owner, subaccount]`, without the subaccount if there is none
+    pub fn account(owner: Principal, subaccount: Option<&[u8]>) -> Self {
+        let mut parts = vec![Icrc3Value::Blob(owner.as_slice().to_vec())];
+        if let Some(subaccount) = subaccount {
+            parts.push(Icrc3Value::Blob(subaccount.to_vec()));
+        }
+        Icrc3Value::Array(parts)
+    }
+
+    pub fn field(&self, name: &str) -> Option<&Icrc3Value> {
+        match self {
+            Icrc3Value::Map(entries) => entries.iter().find(|(key, _)| key == name).map(|(_, value)| value),
+            _ => None,
+        }
+    }
+
+    pub fn as_u64(&self) -> Option<u64> {
+        match self {
+            Icrc3Value::Nat(n) => u64::try_from(&n.0).ok(),
+            _ => None,
+        }
+    }
+}
+
+pub fn hash_value(value: &Icrc3Value) -> [u8; 32] {
+    match value {
+        Icrc3Value::Blob(bytes) => Sha256::digest(bytes).into(),
+        Icrc3Value::Text(text) => Sha256::digest(text.as_bytes()).into(),
+        Icrc3Value::Nat(n) => {
+            let mut leb = Vec::new();
+            n.encode(&mut leb).expect("writing to a Vec cannot fail");
+            Sha256::digest(leb).into()
+        },
+        Icrc3Value::Int(i) => {
+            let mut sleb = Vec::new();
+            i.encode(&mut sleb).expect("writing to a Vec cannot fail");
+            Sha256::digest(sleb).into()
+        },
+        Icrc3Value::Array(values) => {
+            let mut hasher = Sha256::new();
+            for value in values {
+                hasher.update(hash_value(value));
+            }
+            hasher.finalize().into()
+        },
+        Icrc3Value::Map(entries) => {
+            let mut pairs: Vec<[u8; 64]> = entries.iter()
+                .map(|(key, value)| {
+                    let mut pair = [0u8; 64];
+                    pair[..32].copy_from_slice(&Sha256::digest(key.as_bytes()));
+                    pair[32..].copy_from_slice(&hash_value(value));
+                    pair
+                })
+                .collect();
+            pairs.sort();
+            let mut hasher = Sha256::new();
+            for pair in pairs {
+                hasher.update(pair);
+            }
+            hasher.finalize().into()
+        },
+    }
+}
+
+// ============================================================================
+// BLOCK LOG
+// ============================================================================
+
+pub fn log_length() -> u64 {
+    ICRC3_BLOCKS.with(|blocks| blocks.borrow().len())
+}
+
+// Appends a block holding `tx` and returns its index
+pub fn append_block(btype: &str, tx: Vec<(String, Icrc3Value)>, now: u64) -> u64 {
+    ICRC3_BLOCKS.with(|blocks| {
+        let mut blocks = blocks.borrow_mut();
+        let parent = blocks.last_key_value();
+        let index = parent.as_ref().map(|(index, _)| index + 1).unwrap_or(0);
+
+        let mut block = vec![
+            ("btype".to_string(), Icrc3Value::text(btype)),
+            ("ts".to_string(), Icrc3Value::nat(now)),
+        ];
+        if let Some((_, parent)) = parent {
+            block.push(("phash".to_string(), Icrc3Value::Blob(hash_value(&parent).to_vec())));
+        }
+        block.push(("tx".to_string(), Icrc3Value::Map(tx)));
+
+        blocks.insert(index, Icrc3Value::Map(block));
+        index
+    })
+}
+
+// Newest block at or after `since` whose type and transaction equal the given
+// ones, for deduplicating transactions that carry a `created_at_time`
+pub fn find_duplicate(btype: &str, tx: &[(String, Icrc3Value)], since: u64) -> Option<u64> {
+    let btype = Icrc3Value::text(btype);
+    let tx = Icrc3Value::Map(tx.to_vec());
+    ICRC3_BLOCKS.with(|blocks| {
+        blocks.borrow().iter().rev()
+            .take_while(|(_, block)| block.field("ts").and_then(Icrc3Value::as_u64).unwrap_or(0) >= since)
+            .find(|(_, block)| block.field("btype") == Some(&btype) && block.field("tx") == Some(&tx))
+            .map(|(index, _)| index)
+    })
+}
+
+// ============================================================================
+// ENDPOINTS
+// ============================================================================
+
+#[derive(CandidType, Serialize, Deserialize, Clone, Debug)]
+pub struct GetBlocksArgs {
+    pub start: Nat,
+    pub length: Nat,
+}
+
+#[derive(CandidType, Serialize, Deserialize, Clone, Debug)]
+pub struct BlockWithId {
+    pub id: Nat,
+    pub block: Icrc3Value,
+}
+
+define_function!(pub GetBlocksCallback : (Vec<GetBlocksArgs>) -> (GetBlocksResult) query);
+
+#[derive(CandidType, Deserialize, Clone, Debug)]
+pub struct ArchivedBlocks {
+    pub args: Vec<GetBlocksArgs>,
+    pub callback: GetBlocksCallback,
+}
+
+#[derive(CandidType, Deserialize, Clone, Debug)]
+pub struct GetBlocksResult {
+    pub log_length: Nat,
+    pub blocks: Vec<BlockWithId>,
+    pub archived_blocks: Vec<ArchivedBlocks>,
+}
+
+#[derive(CandidType, Serialize, Deserialize, Clone, Debug)]
+pub struct GetArchivesArgs {
+    pub from: Option<Principal>,
+}
+
+#[derive(CandidType, Serialize, Deserialize, Clone, Debug)]
+pub struct ArchiveInfo {
+    pub canister_id: Principal,
+    pub start: Nat,
+    pub end: Nat,
+}
+
+#[derive(CandidType, Serialize, Deserialize, Clone, Debug)]
+pub struct SupportedBlockType {
+    pub block_type: String,
+    pub url: String,
+}
+
+pub const ICRC7_URL: &str = "https://github.com/dfinity/ICRC/blob/main/ICRCs/ICRC-7/ICRC-7.md";
+
+// Ranges are served in order until MAX_BLOCKS_PER_REQUEST blocks are returned
+#[query]
+pub fn icrc3_get_blocks(args: Vec<GetBlocksArgs>) -> GetBlocksResult {
+    let log_length = log_length();
+    let mut remaining = MAX_BLOCKS_PER_REQUEST;
+    let mut blocks = Vec::new();
+
+    ICRC3_BLOCKS.with(|store| {
+        let store = store.borrow();
+        for range in args {
+            let Ok(start) = u64::try_from(&range.start.0) else { continue };
+            let length = u64::try_from(&range.length.0).unwrap_or(u64::MAX).min(remaining);
+            let end = start.saturating_add(length).min(log_length);
+            for (id, block) in store.range(start..end) {
+                blocks.push(BlockWithId { id: Nat::from(id), block });
+            }
+            remaining -= end.saturating_sub(start).min(remaining);
+            if remaining == 0 {
+                break;
+            }
+        }
+    });
+
+    GetBlocksResult {
+        log_length: Nat::from(log_length),
+        blocks,
+        archived_blocks: Vec::new(),
+    }
+}
+
+#[query]
+pub fn icrc3_get_archives(_args: GetArchivesArgs) -> Vec<ArchiveInfo> {
+    Vec::new()
+}
+
+#[query]
+pub fn icrc3_supported_block_types() -> Vec<SupportedBlockType> {
+    ["7mint", "7xfer"].iter()
+        .map(|block_type| SupportedBlockType { block_type: block_type.to_string(), url: ICRC7_URL.to_string() })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn get_block(index: u64) -> Option<Icrc3Value> {
+        ICRC3_BLOCKS.with(|blocks| blocks.borrow().get(&index))
+    }
+
+    fn hex(bytes: &[u8]) -> String {
+        bytes.iter().map(|b| format!("{:02x}", b)).collect()
+    }
+
+    // Scalar examples from the ICRC-3 specification
+    #[test]
+    fn hashes_match_the_specification_examples() {
+        assert_eq!(
+            hex(&hash_value(&Icrc3Value::nat(42))),
+            "684888c0ebb17f374298b65ee2807526c066094c701bcc7ebbe1c1095f494fc1"
+        );
+        assert_eq!(
+            hex(&hash_value(&Icrc3Value::Int(Int::from(-42)))),
+            "de5a6f78116eca62d7fc5ce159d23ae6b889b365a1739ad2cf36f925a140d0cc"
+        );
+        assert_eq!(
+            hex(&hash_value(&Icrc3Value::text("Hello, World!"))),
+            "dffd6021bb2bd5b0af676290809ec3a53191dd81c7f70a4b28688a362182986f"
+        );
+    }
+
+    #[test]
+    fn array_hash_covers_element_hashes_in_order() {
+        let elements = vec![Icrc3Value::nat(3), Icrc3Value::text("foo"), Icrc3Value::Blob(vec![0x6f, 0x6f])];
+        let concatenated: Vec<u8> = elements.iter().flat_map(hash_value).collect();
+        assert_eq!(hash_value(&Icrc3Value::Array(elements.clone())), <[u8; 32]>::from(Sha256::digest(concatenated)));
+
+        let reversed: Vec<Icrc3Value> = elements.into_iter().rev().collect();
+        assert_ne!(hash_value(&Icrc3Value::Array(reversed)), hash_value(&Icrc3Value::Array(vec![])));
+    }
+
+    #[test]
+    fn map_hash_ignores_field_order() {
+        let forward = Icrc3Value::Map(vec![
+            ("tid".to_string(), Icrc3Value::nat(1)),
+            ("memo".to_string(), Icrc3Value::Blob(vec![1, 2, 3])),
+        ]);
+        let reversed = Icrc3Value::Map(vec![
+            ("memo".to_string(), Icrc3Value::Blob(vec![1, 2, 3])),
+            ("tid".to_string(), Icrc3Value::nat(1)),
+        ]);
+        assert_eq!(hash_value(&forward), hash_value(&reversed));
+    }
+
+    #[test]
+    fn blocks_chain_to_their_parent() {
+        let first = append_block("7mint", vec![("tid".to_string(), Icrc3Value::nat(1))], 10);
+        let second = append_block("7xfer", vec![("tid".to_string(), Icrc3Value::nat(1))], 20);
+
+        let parent = get_block(first).unwrap();
+        let child = get_block(second).unwrap();
+        assert_eq!(second, first + 1);
+        assert_eq!(parent.field("phash"), None);
+        assert_eq!(child.field("phash"), Some(&Icrc3Value::Blob(hash_value(&parent).to_vec())));
+
+        let tx = vec![("tid".to_string(), Icrc3Value::nat(1))];
+        assert_eq!(find_duplicate("7xfer", &tx, 15), Some(second));
+        assert_eq!(find_duplicate("7xfer", &tx, 21), None);
+        assert_eq!(find_duplicate("7mint", &tx, 15), None);
+    }
+}
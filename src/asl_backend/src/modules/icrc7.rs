@@ -0,0 +1,619 @@
+use candid::{CandidType, Nat, Principal};
+use ic_cdk::{query, update};
+use serde::{Deserialize, Serialize};
+use std::ops::Bound;
+
+use crate::modules::types::*;
+use crate::modules::storage::{ARTIFACTS, NFTS};
+use crate::modules::auth::get_caller;
+use crate::modules::utils::{get_time, DEFAULT_PAGE_SIZE, MAX_PAGE_SIZE};
+use crate::modules::audit::log_audit_event;
+use crate::modules::certification::certify_nft;
+use crate::modules::certificates::seal_certificate;
+use crate::modules::nft::{count_owned_nfts, owned_nft_ids, store_nft};
+use crate::modules::icrc3::{append_block, find_duplicate, log_length, Icrc3Value, ICRC7_URL};
+
+// ============================================================================
+// ICRC-7 HERITAGE NFT LEDGER
+// ============================================================================
+//
+// Exposes the Proof of Heritage NFTs in NFTS as an ICRC-7 collection. Token ids
+// are NFT ids. Mints and transfers are recorded as "7mint" and "7xfer" blocks
+// in the ICRC-3 log (see modules::icrc3).
+//
+// Heritage NFTs are held by principals, so only default subaccounts (none, or
+// 32 zero bytes) can own tokens: transfers to any other subaccount are rejected
+// with InvalidRecipient and other subaccounts have a balance of 0.
+// `icrc7_transfer` only moves NFTs whose `is_transferable` flag is set; batches
+// are not atomic. Moving an NFT reseals its certificate for the new holder.
+
+const COLLECTION_NAME: &str = "ASL Proof of Heritage";
+const COLLECTION_SYMBOL: &str = "ASLPOH";
+const COLLECTION_DESCRIPTION: &str = "Certificates of authenticity for cultural heritage artifacts verified by the ASL Heritage community";
+
+const MAX_QUERY_BATCH_SIZE: u64 = 100;
+const MAX_UPDATE_BATCH_SIZE: u64 = 20;
+const MAX_MEMO_SIZE: usize = 32;
+const TX_WINDOW_NANOS: u64 = 24 * 60 * 60 * 1_000_000_000;
+const PERMITTED_DRIFT_NANOS: u64 = 2 * 60 * 1_000_000_000;
+
+// GenericError codes
+pub const ERROR_MEMO_TOO_LONG: u64 = 1;
+pub const ERROR_NOT_TRANSFERABLE: u64 = 2;
+pub const ERROR_BATCH_TOO_LARGE: u64 = 3;
+
+#[derive(CandidType, Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct Account {
+    pub owner: Principal,
+    pub subaccount: Option<Vec<u8>>,
+}
+
+#[derive(CandidType, Serialize, Deserialize, Clone, Debug)]
+pub struct TransferArg {
+    pub from_subaccount: Option<Vec<u8>>,
+    pub to: Account,
+    pub token_id: Nat,
+    pub memo: Option<Vec<u8>>,
+    pub created_at_time: Option<u64>,
+}
+
+#[derive(CandidType, Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub enum TransferError {
+    NonExistingTokenId,
+    InvalidRecipient,
+    Unauthorized,
+    TooOld,
+    CreatedInFuture { ledger_time: u64 },
+    Duplicate { duplicate_of: Nat },
+    GenericError { error_code: Nat, message: String },
+    GenericBatchError { error_code: Nat, message: String },
+}
+
+pub type TransferResult = Result<Nat, TransferError>;
+
+#[derive(CandidType, Serialize, Deserialize, Clone, Debug)]
+pub struct SupportedStandard {
+    pub name: String,
+    pub url: String,
+}
+
+fn to_u64(n: &Nat) -> Option<u64> {
+    u64::try_from(&n.0).ok()
+}
+
+fn is_default_subaccount(subaccount: &Option<Vec<u8>>) -> bool {
+    match subaccount {
+        None => true,
+        Some(bytes) => bytes.len() == 32 && bytes.iter().all(|b| *b == 0),
+    }
+}
+
+fn check_query_batch(len: usize) {
+    if len as u64 > MAX_QUERY_BATCH_SIZE {
+        ic_cdk::trap(&format!("At most {} items can be queried at once", MAX_QUERY_BATCH_SIZE));
+    }
+}
+
+fn take_limit(take: Option<Nat>) -> usize {
+    take.as_ref().and_then(to_u64)
+        .unwrap_or(DEFAULT_PAGE_SIZE as u64)
+        .clamp(1, MAX_PAGE_SIZE as u64) as usize
+}
+
+// Token ids after `prev` in ascending order, read from `ids(prev, limit)`
+fn token_page<F>(prev: Option<Nat>, take: Option<Nat>, ids: F) -> Vec<Nat>
+where
+    F: FnOnce(Option<u64>, usize) -> Vec<u64>,
+{
+    let prev = match prev {
+        Some(prev) => match to_u64(&prev) {
+            Some(prev) => Some(prev),
+            None => return Vec::new(),
+        },
+        None => None,
+    };
+    ids(prev, take_limit(take)).into_iter().map(Nat::from).collect()
+}
+
+fn all_token_ids(prev: Option<u64>, limit: usize) -> Vec<u64> {
+    let start = prev.map_or(Bound::Unbounded, Bound::Excluded);
+    NFTS.with(|nfts| {
+        nfts.borrow().range((start, Bound::Unbounded))
+            .take(limit)
+            .map(|(id, _)| id)
+            .collect()
+    })
+}
+
+pub fn token_metadata(nft: &ProofOfHeritageNFT) -> Vec<(String, Icrc3Value)> {
+    let certificate_number = &nft.heritage_certificate.certificate_number;
+    let name = nft.metadata.iter()
+        .find(|(key, _)| key == "artifact_name")
+        .map(|(_, name)| name.clone())
+        .unwrap_or_else(|| format!("Proof of Heritage #{}", nft.id));
+
+    let mut metadata = vec![
+        ("name".to_string(), Icrc3Value::text(name)),
+        ("asl:artifact_id".to_string(), Icrc3Value::nat(nft.artifact_id)),
+        ("asl:issuing_authority".to_string(), Icrc3Value::Blob(nft.issuing_authority.as_slice().to_vec())),
+        ("asl:issued_at".to_string(), Icrc3Value::nat(nft.created_at)),
+        ("asl:transferable".to_string(), Icrc3Value::text(nft.is_transferable.to_string())),
+        ("asl:verification_hash".to_string(), Icrc3Value::text(nft.verification_hash.clone())),
+        ("asl:certificate_sha256".to_string(), Icrc3Value::text(nft.heritage_certificate.digital_signature.clone())),
+        ("asl:certificate".to_string(), Icrc3Value::text(format!("/certificates/{}.jsonld", certificate_number))),
+    ];
+    for (key, value) in &nft.metadata {
+        metadata.push((format!("asl:{}", key), Icrc3Value::text(value.clone())));
+    }
+    metadata
+}
+
+// ============================================================================
+// LEDGER WRITES
+// ============================================================================
+
+// Records the issuance of `nft` and returns the block index
+pub fn record_mint(nft: &ProofOfHeritageNFT, now: u64) -> u64 {
+    append_block("7mint", vec![
+        ("tid".to_string(), Icrc3Value::nat(nft.id)),
+        ("from".to_string(), Icrc3Value::account(nft.issuing_authority, None)),
+        ("to".to_string(), Icrc3Value::account(nft.owner, None)),
+        ("meta".to_string(), Icrc3Value::Map(token_metadata(nft))),
+    ], now)
+}
+
+fn transfer_tx(nft_id: u64, from: Principal, to: Principal, memo: &Option<Vec<u8>>, created_at_time: Option<u64>) -> Vec<(String, Icrc3Value)> {
+    let mut tx = vec![
+        ("tid".to_string(), Icrc3Value::nat(nft_id)),
+        ("from".to_string(), Icrc3Value::account(from, None)),
+        ("to".to_string(), Icrc3Value::account(to, None)),
+    ];
+    if let Some(memo) = memo {
+        tx.push(("memo".to_string(), Icrc3Value::Blob(memo.clone())));
+    }
+    if let Some(created_at_time) = created_at_time {
+        tx.push(("ts".to_string(), Icrc3Value::nat(created_at_time)));
+    }
+    tx
+}
+
+// Moves `nft_id` to `to`, reseals its certificate for the new holder and
+// records the transfer. The resealed certificate is not re-signed here, as
+// any holder can transfer; see signing::sign_record. Callers check ownership
+// and transferability.
+pub fn apply_transfer(nft_id: u64, to: Principal, memo: Option<Vec<u8>>, created_at_time: Option<u64>, now: u64) -> Result<u64, String> {
+    let from = NFTS.with(|nfts| {
+        let mut nfts = nfts.borrow_mut();
+        let mut nft = nfts.get(&nft_id).ok_or_else(|| "Heritage NFT not found".to_string())?;
+        let from = nft.owner;
+        nft.owner = to;
+        if let Some(artifact) = ARTIFACTS.with(|artifacts| artifacts.borrow().get(&nft.artifact_id)) {
+            seal_certificate(&mut nft, &artifact);
+        }
+        certify_nft(&nft);
+        store_nft(&mut nfts, nft);
+        Ok::<_, String>(from)
+    })?;
+
+    let block_index = append_block("7xfer", transfer_tx(nft_id, from, to, &memo, created_at_time), now);
+
+    log_audit_event(
+        AuditEventType::DataModification,
+        Some(nft_id),
+        format!("Heritage NFT {} transferred from {} to {}", nft_id, from.to_text(), to.to_text()),
+        AuditSeverity::Info
+    );
+    Ok(block_index)
+}
+
+fn transfer(caller: Principal, arg: TransferArg, now: u64) -> TransferResult {
+    let nft_id = to_u64(&arg.token_id).ok_or(TransferError::NonExistingTokenId)?;
+
+    if arg.memo.as_ref().is_some_and(|memo| memo.len() > MAX_MEMO_SIZE) {
+        return Err(TransferError::GenericError {
+            error_code: Nat::from(ERROR_MEMO_TOO_LONG),
+            message: format!("Memo exceeds {} bytes", MAX_MEMO_SIZE),
+        });
+    }
+    if let Some(created_at_time) = arg.created_at_time {
+        if created_at_time.saturating_add(TX_WINDOW_NANOS + PERMITTED_DRIFT_NANOS) < now {
+            return Err(TransferError::TooOld);
+        }
+        if created_at_time > now.saturating_add(PERMITTED_DRIFT_NANOS) {
+            return Err(TransferError::CreatedInFuture { ledger_time: now });
+        }
+        let tx = transfer_tx(nft_id, caller, arg.to.owner, &arg.memo, arg.created_at_time);
+        let window_start = now.saturating_sub(TX_WINDOW_NANOS + PERMITTED_DRIFT_NANOS);
+        if let Some(duplicate_of) = find_duplicate("7xfer", &tx, window_start) {
+            return Err(TransferError::Duplicate { duplicate_of: Nat::from(duplicate_of) });
+        }
+    }
+
+    if !is_default_subaccount(&arg.from_subaccount) {
+        return Err(TransferError::Unauthorized);
+    }
+    if !is_default_subaccount(&arg.to.subaccount) || arg.to.owner == caller || arg.to.owner == Principal::anonymous() {
+        return Err(TransferError::InvalidRecipient);
+    }
+
+    let nft = NFTS.with(|nfts| nfts.borrow().get(&nft_id)).ok_or(TransferError::NonExistingTokenId)?;
+    if nft.owner != caller {
+        return Err(TransferError::Unauthorized);
+    }
+    if !nft.is_transferable {
+        return Err(TransferError::GenericError {
+            error_code: Nat::from(ERROR_NOT_TRANSFERABLE),
+            message: "This Heritage NFT is not transferable".to_string(),
+        });
+    }
+
+    apply_transfer(nft_id, arg.to.owner, arg.memo, arg.created_at_time, now)
+        .map(Nat::from)
+        .map_err(|message| TransferError::GenericError { error_code: Nat::from(0u64), message })
+}
+
+#[update]
+pub fn icrc7_transfer(args: Vec<TransferArg>) -> Vec<Option<TransferResult>> {
+    if args.len() as u64 > MAX_UPDATE_BATCH_SIZE {
+        return vec![Some(Err(TransferError::GenericBatchError {
+            error_code: Nat::from(ERROR_BATCH_TOO_LARGE),
+            message: format!("At most {} transfers can be submitted at once", MAX_UPDATE_BATCH_SIZE),
+        }))];
+    }
+
+    let caller = get_caller();
+    let now = get_time();
+    args.into_iter().map(|arg| Some(transfer(caller, arg, now))).collect()
+}
+
+// Records a mint block for every NFT issued before the ledger existed. Only
+// runs while the log is empty; returns the number of blocks written.
+pub fn backfill_mint_blocks() -> u64 {
+    if log_length() > 0 {
+        return 0;
+    }
+    let nfts: Vec<ProofOfHeritageNFT> = NFTS.with(|nfts| nfts.borrow().iter().map(|(_, nft)| nft).collect());
+    for nft in &nfts {
+        record_mint(nft, nft.created_at);
+    }
+    nfts.len() as u64
+}
+
+// ============================================================================
+// COLLECTION QUERIES
+// ============================================================================
+
+#[query]
+pub fn icrc7_name() -> String {
+    COLLECTION_NAME.to_string()
+}
+
+#[query]
+pub fn icrc7_symbol() -> String {
+    COLLECTION_SYMBOL.to_string()
+}
+
+#[query]
+pub fn icrc7_description() -> Option<String> {
+    Some(COLLECTION_DESCRIPTION.to_string())
+}
+
+#[query]
+pub fn icrc7_logo() -> Option<String> {
+    None
+}
+
+#[query]
+pub fn icrc7_total_supply() -> Nat {
+    Nat::from(NFTS.with(|nfts| nfts.borrow().len()))
+}
+
+#[query]
+pub fn icrc7_supply_cap() -> Option<Nat> {
+    None
+}
+
+#[query]
+pub fn icrc7_max_query_batch_size() -> Option<Nat> {
+    Some(Nat::from(MAX_QUERY_BATCH_SIZE))
+}
+
+#[query]
+pub fn icrc7_max_update_batch_size() -> Option<Nat> {
+    Some(Nat::from(MAX_UPDATE_BATCH_SIZE))
+}
+
+#[query]
+pub fn icrc7_default_take_value() -> Option<Nat> {
+    Some(Nat::from(DEFAULT_PAGE_SIZE))
+}
+
+#[query]
+pub fn icrc7_max_take_value() -> Option<Nat> {
+    Some(Nat::from(MAX_PAGE_SIZE))
+}
+
+#[query]
+pub fn icrc7_max_memo_size() -> Option<Nat> {
+    Some(Nat::from(MAX_MEMO_SIZE))
+}
+
+#[query]
+pub fn icrc7_atomic_batch_transfers() -> Option<bool> {
+    Some(false)
+}
+
+#[query]
+pub fn icrc7_tx_window() -> Option<Nat> {
+    Some(Nat::from(TX_WINDOW_NANOS))
+}
+
+#[query]
+pub fn icrc7_permitted_drift() -> Option<Nat> {
+    Some(Nat::from(PERMITTED_DRIFT_NANOS))
+}
+
+#[query]
+pub fn icrc7_collection_metadata() -> Vec<(String, Icrc3Value)> {
+    vec![
+        ("icrc7:name".to_string(), Icrc3Value::text(COLLECTION_NAME)),
+        ("icrc7:symbol".to_string(), Icrc3Value::text(COLLECTION_SYMBOL)),
+        ("icrc7:description".to_string(), Icrc3Value::text(COLLECTION_DESCRIPTION)),
+        ("icrc7:total_supply".to_string(), Icrc3Value::Nat(icrc7_total_supply())),
+        ("icrc7:max_query_batch_size".to_string(), Icrc3Value::nat(MAX_QUERY_BATCH_SIZE)),
+        ("icrc7:max_update_batch_size".to_string(), Icrc3Value::nat(MAX_UPDATE_BATCH_SIZE)),
+        ("icrc7:default_take_value".to_string(), Icrc3Value::nat(DEFAULT_PAGE_SIZE as u64)),
+        ("icrc7:max_take_value".to_string(), Icrc3Value::nat(MAX_PAGE_SIZE as u64)),
+        ("icrc7:max_memo_size".to_string(), Icrc3Value::nat(MAX_MEMO_SIZE as u64)),
+        ("icrc7:tx_window".to_string(), Icrc3Value::nat(TX_WINDOW_NANOS)),
+        ("icrc7:permitted_drift".to_string(), Icrc3Value::nat(PERMITTED_DRIFT_NANOS)),
+    ]
+}
+
+#[query]
+pub fn icrc10_supported_standards() -> Vec<SupportedStandard> {
+    vec![
+        SupportedStandard { name: "ICRC-3".to_string(), url: "https://github.com/dfinity/ICRC-1/tree/main/standards/ICRC-3".to_string() },
+        SupportedStandard { name: "ICRC-7".to_string(), url: ICRC7_URL.to_string() },
+        SupportedStandard { name: "ICRC-10".to_string(), url: "https://github.com/dfinity/ICRC/blob/main/ICRCs/ICRC-10/ICRC-10.md".to_string() },
+    ]
+}
+
+// ============================================================================
+// TOKEN QUERIES
+// ============================================================================
+
+#[query]
+pub fn icrc7_token_metadata(token_ids: Vec<Nat>) -> Vec<Option<Vec<(String, Icrc3Value)>>> {
+    check_query_batch(token_ids.len());
+    NFTS.with(|nfts| {
+        let nfts = nfts.borrow();
+        token_ids.iter()
+            .map(|token_id| to_u64(token_id).and_then(|id| nfts.get(&id)).map(|nft| token_metadata(&nft)))
+            .collect()
+    })
+}
+
+#[query]
+pub fn icrc7_owner_of(token_ids: Vec<Nat>) -> Vec<Option<Account>> {
+    check_query_batch(token_ids.len());
+    NFTS.with(|nfts| {
+        let nfts = nfts.borrow();
+        token_ids.iter()
+            .map(|token_id| to_u64(token_id).and_then(|id| nfts.get(&id)))
+            .map(|nft| nft.map(|nft| Account { owner: nft.owner, subaccount: None }))
+            .collect()
+    })
+}
+
+#[query]
+pub fn icrc7_balance_of(accounts: Vec<Account>) -> Vec<Nat> {
+    check_query_batch(accounts.len());
+    accounts.iter()
+        .map(|account| match is_default_subaccount(&account.subaccount) {
+            true => Nat::from(count_owned_nfts(account.owner)),
+            false => Nat::from(0u64),
+        })
+        .collect()
+}
+
+#[query]
+pub fn icrc7_tokens(prev: Option<Nat>, take: Option<Nat>) -> Vec<Nat> {
+    token_page(prev, take, all_token_ids)
+}
+
+#[query]
+pub fn icrc7_tokens_of(account: Account, prev: Option<Nat>, take: Option<Nat>) -> Vec<Nat> {
+    if !is_default_subaccount(&account.subaccount) {
+        return Vec::new();
+    }
+    token_page(prev, take, |prev, limit| owned_nft_ids(account.owner, prev, limit))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::modules::nft::get_nfts_by_owner;
+    use crate::modules::testing::{now, principal, signature_requests};
+
+    fn mint(nft_id: u64, owner: Principal, is_transferable: bool) {
+        let nft = ProofOfHeritageNFT {
+            id: nft_id,
+            artifact_id: nft_id,
+            owner,
+            created_at: nft_id,
+            metadata: Vec::new(),
+            is_transferable,
+            verification_hash: String::new(),
+            issuing_authority: principal(9),
+            heritage_certificate: HeritageCertificate {
+                certificate_number: format!("HER-{:06}-0", nft_id),
+                issuer_name: "Test Museum".to_string(),
+                verification_date: 0,
+                authenticity_guarantees: Vec::new(),
+                scientific_analysis: Vec::new(),
+                expert_endorsements: Vec::new(),
+                digital_signature: String::new(),
+            },
+            access_rights: AccessRights {
+                can_view_detailed_metadata: true,
+                can_request_high_res_images: false,
+                can_access_research_data: false,
+                can_propose_studies: false,
+                special_permissions: Vec::new(),
+            },
+        };
+        NFTS.with(|nfts| store_nft(&mut nfts.borrow_mut(), nft));
+    }
+
+    fn account(owner: Principal) -> Account {
+        Account { owner, subaccount: None }
+    }
+
+    fn transfer_arg(token_id: u64, to: Principal) -> TransferArg {
+        TransferArg {
+            from_subaccount: None,
+            to: account(to),
+            token_id: Nat::from(token_id),
+            memo: None,
+            created_at_time: None,
+        }
+    }
+
+    fn nats(ids: &[u64]) -> Vec<Nat> {
+        ids.iter().copied().map(Nat::from).collect()
+    }
+
+    fn error_code(result: TransferResult) -> u64 {
+        match result {
+            Err(TransferError::GenericError { error_code, .. }) => to_u64(&error_code).unwrap(),
+            other => panic!("expected a generic error, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn transfer_moves_the_token_between_owners() {
+        let (alice, bob) = (principal(1), principal(2));
+        mint(1, alice, true);
+        mint(2, alice, true);
+
+        assert_eq!(transfer(alice, transfer_arg(1, bob), now()), Ok(Nat::from(0u64)));
+        assert_eq!(icrc7_owner_of(nats(&[1, 2, 3])), vec![Some(account(bob)), Some(account(alice)), None]);
+        assert_eq!(icrc7_balance_of(vec![account(alice), account(bob), account(principal(3))]), nats(&[1, 1, 0]));
+        assert_eq!(icrc7_tokens_of(account(alice), None, None), nats(&[2]));
+        assert_eq!(icrc7_tokens_of(account(bob), None, None), nats(&[1]));
+        assert_eq!(get_nfts_by_owner(bob).iter().map(|nft| nft.id).collect::<Vec<_>>(), vec![1]);
+        // Holders can transfer freely, so the resealed certificate is not re-signed
+        assert!(signature_requests().is_empty());
+
+        // The previous owner can no longer move it
+        assert_eq!(transfer(alice, transfer_arg(1, principal(3)), now()), Err(TransferError::Unauthorized));
+        assert_eq!(transfer(alice, transfer_arg(7, bob), now()), Err(TransferError::NonExistingTokenId));
+    }
+
+    #[test]
+    fn created_at_time_must_fall_in_the_window() {
+        let alice = principal(1);
+        mint(1, alice, true);
+        let now = now();
+
+        let mut arg = transfer_arg(1, principal(2));
+        arg.created_at_time = Some(now - TX_WINDOW_NANOS - PERMITTED_DRIFT_NANOS - 1);
+        assert_eq!(transfer(alice, arg.clone(), now), Err(TransferError::TooOld));
+
+        arg.created_at_time = Some(now + PERMITTED_DRIFT_NANOS + 1);
+        assert_eq!(transfer(alice, arg.clone(), now), Err(TransferError::CreatedInFuture { ledger_time: now }));
+
+        // Both edges of the window are accepted
+        arg.created_at_time = Some(now + PERMITTED_DRIFT_NANOS);
+        assert!(transfer(alice, arg, now).is_ok());
+    }
+
+    #[test]
+    fn resubmitted_transfers_are_duplicates() {
+        let (alice, bob) = (principal(1), principal(2));
+        mint(1, alice, true);
+        mint(2, alice, true);
+        let mut arg = transfer_arg(1, bob);
+        arg.memo = Some(b"loan".to_vec());
+        arg.created_at_time = Some(now());
+
+        let block = transfer(alice, arg.clone(), now()).unwrap();
+        assert_eq!(transfer(alice, arg.clone(), now()), Err(TransferError::Duplicate { duplicate_of: block.clone() }));
+
+        // Any difference in the transaction makes it a new one
+        arg.token_id = Nat::from(2u64);
+        assert!(transfer(alice, arg.clone(), now()).is_ok());
+        arg.memo = Some(b"gift".to_vec());
+        assert_eq!(transfer(alice, arg, now()), Err(TransferError::Unauthorized));
+
+        // Without created_at_time there is no deduplication
+        let arg = transfer_arg(1, alice);
+        assert!(transfer(bob, arg.clone(), now()).is_ok());
+        assert_eq!(transfer(bob, arg, now()), Err(TransferError::Unauthorized));
+    }
+
+    #[test]
+    fn only_default_subaccounts_hold_tokens() {
+        let (alice, bob) = (principal(1), principal(2));
+        mint(1, alice, true);
+        let other = Some(vec![1u8; 32]);
+
+        let mut arg = transfer_arg(1, bob);
+        arg.from_subaccount = other.clone();
+        assert_eq!(transfer(alice, arg, now()), Err(TransferError::Unauthorized));
+
+        let mut arg = transfer_arg(1, bob);
+        arg.to.subaccount = other.clone();
+        assert_eq!(transfer(alice, arg, now()), Err(TransferError::InvalidRecipient));
+
+        // 32 zero bytes are the default subaccount
+        let mut arg = transfer_arg(1, bob);
+        arg.from_subaccount = Some(vec![0u8; 32]);
+        arg.to.subaccount = Some(vec![0u8; 32]);
+        assert!(transfer(alice, arg, now()).is_ok());
+
+        let bob_other = Account { owner: bob, subaccount: other };
+        assert_eq!(icrc7_balance_of(vec![account(bob), bob_other.clone()]), nats(&[1, 0]));
+        assert!(icrc7_tokens_of(bob_other, None, None).is_empty());
+    }
+
+    #[test]
+    fn heritage_nfts_and_self_transfers_are_refused() {
+        let alice = principal(1);
+        mint(1, alice, false);
+        mint(2, alice, true);
+
+        assert_eq!(error_code(transfer(alice, transfer_arg(1, principal(2)), now())), ERROR_NOT_TRANSFERABLE);
+        assert_eq!(transfer(alice, transfer_arg(2, alice), now()), Err(TransferError::InvalidRecipient));
+        assert_eq!(transfer(alice, transfer_arg(2, Principal::anonymous()), now()), Err(TransferError::InvalidRecipient));
+
+        let mut arg = transfer_arg(2, principal(2));
+        arg.memo = Some(vec![0u8; MAX_MEMO_SIZE + 1]);
+        assert_eq!(error_code(transfer(alice, arg, now())), ERROR_MEMO_TOO_LONG);
+        assert_eq!(icrc7_balance_of(vec![account(alice)]), nats(&[2]));
+    }
+
+    #[test]
+    fn token_pages_follow_prev_and_take() {
+        let (alice, bob) = (principal(1), principal(2));
+        for nft_id in 1..=5 {
+            mint(nft_id, if nft_id == 3 { bob } else { alice }, true);
+        }
+
+        assert_eq!(icrc7_tokens(None, Some(Nat::from(2u64))), nats(&[1, 2]));
+        assert_eq!(icrc7_tokens(Some(Nat::from(2u64)), Some(Nat::from(2u64))), nats(&[3, 4]));
+        assert_eq!(icrc7_tokens(Some(Nat::from(4u64)), None), nats(&[5]));
+        assert!(icrc7_tokens(Some(Nat::from(5u64)), None).is_empty());
+        // take is clamped to at least one token
+        assert_eq!(icrc7_tokens(None, Some(Nat::from(0u64))), nats(&[1]));
+        // A prev beyond any u64 id has nothing after it
+        assert!(icrc7_tokens(Some(Nat::from(u128::MAX)), None).is_empty());
+
+        assert_eq!(icrc7_tokens_of(account(alice), None, None), nats(&[1, 2, 4, 5]));
+        assert_eq!(icrc7_tokens_of(account(alice), Some(Nat::from(1u64)), Some(Nat::from(2u64))), nats(&[2, 4]));
+        assert_eq!(icrc7_tokens_of(account(alice), Some(Nat::from(5u64)), None), Vec::<Nat>::new());
+        assert_eq!(icrc7_tokens_of(account(bob), Some(Nat::from(2u64)), None), nats(&[3]));
+        assert!(owned_nft_ids(alice, Some(u64::MAX), 10).is_empty());
+    }
+}
@@ -0,0 +1,289 @@
+use candid::Principal;
+use ic_stable_structures::Storable;
+use std::borrow::Cow;
+use std::collections::BTreeSet;
+
+use crate::modules::types::*;
+use crate::modules::storage::{ARTIFACTS, ARTIFACT_INDEX};
+
+// ============================================================================
+// ARTIFACT SECONDARY INDEXES
+// ============================================================================
+//
+// All indexes share one stable map of keys with unit values. A key is
+//
+//   index kind (1 byte) | term length (u16 BE) | term | artifact_id (u64 BE)
+//
+// so every artifact carrying a term sits in one contiguous key range and a
+// lookup only touches matching entries. The length prefix keeps "egy" from
+// matching "egypt".
+
+const MAX_TERM_BYTES: usize = 128;
+// Bump when the key layout changes so the next upgrade rebuilds the index
+pub const ARTIFACT_INDEX_VERSION: u32 = 1;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ArtifactIndex {
+    Status = 0,
+    Creator = 1,
+    Tag = 2,
+    Country = 3,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub struct IndexKey(Vec<u8>);
+
+impl Storable for IndexKey {
+    const BOUND: ic_stable_structures::storable::Bound = ic_stable_structures::storable::Bound::Bounded {
+        max_size: (1 + 2 + MAX_TERM_BYTES + 8) as u32,
+        is_fixed_size: false,
+    };
+
+    fn to_bytes(&self) -> Cow<'_, [u8]> {
+        Cow::Borrowed(&self.0)
+    }
+
+    fn from_bytes(bytes: Cow<[u8]>) -> Self {
+        IndexKey(bytes.into_owned())
+    }
+}
+
+impl IndexKey {
+    fn artifact_id(&self) -> u64 {
+        let mut id_bytes = [0u8; 8];
+        id_bytes.copy_from_slice(&self.0[self.0.len() - 8..]);
+        u64::from_be_bytes(id_bytes)
+    }
+}
+
+fn term_prefix(index: ArtifactIndex, term: &[u8]) -> Vec<u8> {
+    let mut prefix = Vec::with_capacity(3 + term.len() + 8);
+    prefix.push(index as u8);
+    prefix.extend_from_slice(&(term.len() as u16).to_be_bytes());
+    prefix.extend_from_slice(term);
+    prefix
+}
+
+fn index_key(index: ArtifactIndex, term: &[u8], artifact_id: u64) -> IndexKey {
+    let mut key = term_prefix(index, term);
+    key.extend_from_slice(&artifact_id.to_be_bytes());
+    IndexKey(key)
+}
+
+// Case-insensitive and capped at MAX_TERM_BYTES on a char boundary
+pub fn normalize_term(term: &str) -> Vec<u8> {
+    let normalized = term.trim().to_lowercase();
+    let mut end = normalized.len().min(MAX_TERM_BYTES);
+    while !normalized.is_char_boundary(end) {
+        end -= 1;
+    }
+    normalized.as_bytes()[..end].to_vec()
+}
+
+fn status_term(status: &ArtifactStatus) -> Vec<u8> {
+    normalize_term(&format!("{:?}", status))
+}
+
+pub fn artifact_index_keys(artifact: &Artifact) -> Vec<IndexKey> {
+    let mut keys = vec![
+        index_key(ArtifactIndex::Status, &status_term(&artifact.status), artifact.id),
+        index_key(ArtifactIndex::Creator, artifact.creator.as_slice(), artifact.id),
+    ];
+
+    for tag in &artifact.cultural_significance.cultural_tags {
+        let term = normalize_term(tag);
+        if !term.is_empty() {
+            keys.push(index_key(ArtifactIndex::Tag, &term, artifact.id));
+        }
+    }
+
+    if let Some(origin) = &artifact.geographic_origin {
+        let term = normalize_term(&origin.country);
+        if !term.is_empty() {
+            keys.push(index_key(ArtifactIndex::Country, &term, artifact.id));
+        }
+    }
+
+    keys.sort();
+    keys.dedup();
+    keys
+}
+
+// Only the difference between the two key sets is written
+pub fn update_artifact_index(old_keys: Vec<IndexKey>, new_keys: Vec<IndexKey>) {
+    ARTIFACT_INDEX.with(|index| {
+        let mut index = index.borrow_mut();
+        for key in old_keys.iter().filter(|key| !new_keys.contains(key)) {
+            index.remove(key);
+        }
+        for key in new_keys.into_iter().filter(|key| !old_keys.contains(key)) {
+            index.insert(key, ());
+        }
+    });
+}
+
+// Recomputes every index from ARTIFACTS; used after upgrades
+pub fn rebuild_artifact_index() {
+    ARTIFACT_INDEX.with(|index| index.borrow_mut().clear_new());
+    ARTIFACTS.with(|artifacts| {
+        for (_, artifact) in artifacts.borrow().iter() {
+            update_artifact_index(Vec::new(), artifact_index_keys(&artifact));
+        }
+    });
+}
+
+// Newest first, matching get_all_artifacts (ids are allocated in creation order)
+fn lookup_artifact_ids(index: ArtifactIndex, term: &[u8]) -> Vec<u64> {
+    let prefix = term_prefix(index, term);
+    let mut ids: Vec<u64> = ARTIFACT_INDEX.with(|artifact_index| {
+        artifact_index.borrow()
+            .range(IndexKey(prefix.clone())..)
+            .take_while(|(key, _)| key.0.starts_with(&prefix))
+            .map(|(key, _)| key.artifact_id())
+            .collect()
+    });
+    ids.reverse();
+    ids
+}
+
+fn load_artifacts(ids: Vec<u64>) -> Vec<Artifact> {
+    ARTIFACTS.with(|artifacts| {
+        let artifacts = artifacts.borrow();
+        ids.into_iter().filter_map(|id| artifacts.get(&id)).collect()
+    })
+}
+
+// Ids of artifacts carrying any of `terms` in one index, for narrowing
+// queries. None when a term normalizes to nothing and cannot be looked up.
+pub fn artifact_ids_with_any_term(index: ArtifactIndex, terms: &[String]) -> Option<BTreeSet<u64>> {
+    let mut ids = BTreeSet::new();
+    for term in terms {
+        let term = normalize_term(term);
+        if term.is_empty() {
+            return None;
+        }
+        ids.extend(lookup_artifact_ids(index, &term));
+    }
+    Some(ids)
+}
+
+// (term, artifact count, newest artifact id) for every term of one index.
+// Reads index keys only, no artifact is decoded.
+pub fn artifact_term_counts(index: ArtifactIndex) -> Vec<(Vec<u8>, u64, u64)> {
+    let mut counts: Vec<(Vec<u8>, u64, u64)> = Vec::new();
+    ARTIFACT_INDEX.with(|artifact_index| {
+        for (key, _) in artifact_index.borrow().range(IndexKey(vec![index as u8])..) {
+            if key.0[0] != index as u8 {
+                break;
+            }
+            let term = &key.0[3..key.0.len() - 8];
+            match counts.last_mut() {
+                Some((last, count, newest)) if last.as_slice() == term => {
+                    *count += 1;
+                    *newest = key.artifact_id();
+                },
+                _ => counts.push((term.to_vec(), 1, key.artifact_id())),
+            }
+        }
+    });
+    counts
+}
+
+pub fn find_artifacts_by_status(status: &ArtifactStatus) -> Vec<Artifact> {
+    load_artifacts(lookup_artifact_ids(ArtifactIndex::Status, &status_term(status)))
+}
+
+pub fn count_artifacts_by_status(status: &ArtifactStatus) -> u64 {
+    lookup_artifact_ids(ArtifactIndex::Status, &status_term(status)).len() as u64
+}
+
+pub fn find_artifacts_by_creator(creator: Principal) -> Vec<Artifact> {
+    load_artifacts(lookup_artifact_ids(ArtifactIndex::Creator, creator.as_slice()))
+}
+
+pub fn find_artifacts_by_tag(tag: &str) -> Vec<Artifact> {
+    load_artifacts(lookup_artifact_ids(ArtifactIndex::Tag, &normalize_term(tag)))
+}
+
+pub fn find_artifacts_by_country(country: &str) -> Vec<Artifact> {
+    load_artifacts(lookup_artifact_ids(ArtifactIndex::Country, &normalize_term(country)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::modules::artifacts::store_artifact;
+    use crate::modules::testing::{artifact_request, create_artifact_as, principal};
+
+    fn create(name: &str, creator: u8, tags: &[&str], country: &str) -> u64 {
+        let mut request = artifact_request(name);
+        request.cultural_significance = Some(CulturalSignificance {
+            historical_period: None,
+            cultural_group: None,
+            significance_level: SignificanceLevel::Local,
+            unesco_status: None,
+            cultural_tags: tags.iter().map(|tag| tag.to_string()).collect(),
+        });
+        request.geographic_origin = Some(GeographicOrigin {
+            country: country.to_string(),
+            region: None,
+            city: None,
+            site_name: None,
+            coordinates: None,
+            discovery_context: None,
+        });
+        create_artifact_as(principal(creator), request)
+    }
+
+    fn ids(artifacts: Vec<Artifact>) -> Vec<u64> {
+        artifacts.into_iter().map(|artifact| artifact.id).collect()
+    }
+
+    #[test]
+    fn looks_up_artifacts_newest_first() {
+        let scarab = create("Scarab", 1, &["Amulet", "Pharaonic"], "Egypt");
+        let coin = create("Coin", 2, &["coinage"], "Greece");
+        let ushabti = create("Ushabti", 1, &["pharaonic "], "egypt");
+
+        assert_eq!(ids(find_artifacts_by_creator(principal(1))), vec![ushabti, scarab]);
+        assert_eq!(ids(find_artifacts_by_tag("PHARAONIC")), vec![ushabti, scarab]);
+        assert_eq!(ids(find_artifacts_by_country("Greece")), vec![coin]);
+        assert_eq!(count_artifacts_by_status(&ArtifactStatus::PendingVerification), 3);
+    }
+
+    #[test]
+    fn terms_match_whole_not_by_prefix() {
+        create("Scarab", 1, &["egypt"], "Egypt");
+        assert!(find_artifacts_by_tag("egy").is_empty());
+        assert!(find_artifacts_by_country("Egyptian").is_empty());
+    }
+
+    #[test]
+    fn updates_move_index_entries() {
+        let scarab = create("Scarab", 1, &["amulet"], "Egypt");
+        ARTIFACTS.with(|artifacts| {
+            let mut artifacts = artifacts.borrow_mut();
+            let mut artifact = artifacts.get(&scarab).unwrap();
+            artifact.status = ArtifactStatus::Verified;
+            artifact.cultural_significance.cultural_tags = vec!["seal".to_string()];
+            store_artifact(&mut artifacts, artifact);
+        });
+
+        assert_eq!(count_artifacts_by_status(&ArtifactStatus::PendingVerification), 0);
+        assert_eq!(ids(find_artifacts_by_status(&ArtifactStatus::Verified)), vec![scarab]);
+        assert!(find_artifacts_by_tag("amulet").is_empty());
+        assert_eq!(ids(find_artifacts_by_tag("seal")), vec![scarab]);
+    }
+
+    #[test]
+    fn rebuild_reproduces_the_maintained_index() {
+        create("Scarab", 1, &["amulet", "seal"], "Egypt");
+        create("Coin", 2, &[], "Greece");
+        let maintained: Vec<IndexKey> = ARTIFACT_INDEX.with(|index| index.borrow().iter().map(|(key, _)| key).collect());
+
+        rebuild_artifact_index();
+        let rebuilt: Vec<IndexKey> = ARTIFACT_INDEX.with(|index| index.borrow().iter().map(|(key, _)| key).collect());
+        assert_eq!(rebuilt, maintained);
+        assert_eq!(rebuilt.len(), 8);
+    }
+}
@@ -0,0 +1,270 @@
+use candid::CandidType;
+use serde::{Deserialize, Serialize};
+
+use crate::modules::community::CommunityStats;
+use crate::modules::storage::{COMMUNITY_STATS, LIFECYCLE_STATE, MEMORY_LAYOUT};
+use crate::modules::utils::get_time;
+
+// ============================================================================
+// UPGRADE LIFECYCLE
+// ============================================================================
+//
+// Stable stores survive upgrades on their own; everything else lives on the
+// heap and is snapshotted into the lifecycle cell in pre_upgrade and restored
+// in post_upgrade. The cell also records the memory layout the stores were
+// written with, so a build that moves or reuses a memory id is rejected before
+// it can read another store's bytes.
+//
+// Upgrade work that is proportional to the size of the stores (rebuilding a
+// derived stable index, one-off data migrations) is an `UpgradeTask`. The
+// cell records the version each task last ran at and post_upgrade only runs a
+// task whose version changed, so routine upgrades stay cheap. Bump a task's
+// version when its index layout or migration changes.
+
+#[derive(CandidType, Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct MemoryAssignment {
+    pub memory_id: u8,
+    pub store: String,
+}
+
+#[derive(CandidType, Serialize, Deserialize, Clone, Debug, Default)]
+pub struct LifecycleState {
+    pub memory_layout: Vec<MemoryAssignment>,
+    pub community_stats: Option<CommunityStats>,
+    pub saved_at: u64,
+    // (task name, version it last ran at)
+    pub upgrade_tasks: Vec<(String, u32)>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum UpgradeTask {
+    ArtifactIndex,
+    GeoIndex,
+    IdCounters,
+    InlineImages,
+    CertificateDocuments,
+    CommunityPostCount,
+    NftOwnerIndex,
+    ProposalSchedule,
+    VoteIndex,
+    DelegateIndex,
+}
+
+impl UpgradeTask {
+    pub const ALL: [UpgradeTask; 10] = [
+        UpgradeTask::ArtifactIndex,
+        UpgradeTask::GeoIndex,
+        UpgradeTask::IdCounters,
+        UpgradeTask::InlineImages,
+        UpgradeTask::CertificateDocuments,
+        UpgradeTask::CommunityPostCount,
+        UpgradeTask::NftOwnerIndex,
+        UpgradeTask::ProposalSchedule,
+        UpgradeTask::VoteIndex,
+        UpgradeTask::DelegateIndex,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            UpgradeTask::ArtifactIndex => "artifact_index",
+            UpgradeTask::GeoIndex => "geo_index",
+            UpgradeTask::IdCounters => "id_counters",
+            UpgradeTask::InlineImages => "inline_images",
+            UpgradeTask::CertificateDocuments => "certificate_documents",
+            UpgradeTask::CommunityPostCount => "community_post_count",
+            UpgradeTask::NftOwnerIndex => "nft_owner_index",
+            UpgradeTask::ProposalSchedule => "proposal_schedule",
+            UpgradeTask::VoteIndex => "vote_index",
+            UpgradeTask::DelegateIndex => "delegate_index",
+        }
+    }
+
+    pub fn version(self) -> u32 {
+        match self {
+            UpgradeTask::ArtifactIndex => crate::modules::indexes::ARTIFACT_INDEX_VERSION,
+            UpgradeTask::GeoIndex => crate::modules::geo::GEO_INDEX_VERSION,
+            UpgradeTask::IdCounters
+            | UpgradeTask::InlineImages
+            | UpgradeTask::CertificateDocuments
+            | UpgradeTask::CommunityPostCount
+            | UpgradeTask::NftOwnerIndex
+            | UpgradeTask::ProposalSchedule
+            | UpgradeTask::VoteIndex
+            | UpgradeTask::DelegateIndex => 1,
+        }
+    }
+}
+
+pub fn current_memory_layout() -> Vec<MemoryAssignment> {
+    MEMORY_LAYOUT.iter()
+        .map(|(memory_id, store)| MemoryAssignment {
+            memory_id: *memory_id,
+            store: store.to_string(),
+        })
+        .collect()
+}
+
+pub fn get_recorded_memory_layout() -> Vec<MemoryAssignment> {
+    LIFECYCLE_STATE.with(|state| state.borrow().get().memory_layout.clone())
+}
+
+// Every recorded assignment must still exist unchanged; new ids may be appended
+pub fn check_memory_layout(recorded: &[MemoryAssignment], current: &[MemoryAssignment]) -> Result<(), String> {
+    for (index, assignment) in current.iter().enumerate() {
+        if current[..index].iter().any(|other| other.store == assignment.store) {
+            return Err(format!("Store '{}' is assigned more than one memory id", assignment.store));
+        }
+    }
+
+    for previous in recorded {
+        match current.iter().find(|assignment| assignment.memory_id == previous.memory_id) {
+            Some(assignment) if assignment.store == previous.store => {},
+            Some(assignment) => return Err(format!(
+                "Memory id {} belongs to '{}' but this build assigns it to '{}'",
+                previous.memory_id, previous.store, assignment.store
+            )),
+            None => return Err(format!(
+                "Memory id {} ('{}') was dropped from the layout; ids must stay reserved",
+                previous.memory_id, previous.store
+            )),
+        }
+    }
+
+    Ok(())
+}
+
+// Called first in post_upgrade: trapping here rolls the upgrade back
+pub fn verify_memory_layout() {
+    let recorded = get_recorded_memory_layout();
+    if let Err(error) = check_memory_layout(&recorded, &current_memory_layout()) {
+        ic_cdk::trap(&format!("Refusing upgrade: {}", error));
+    }
+}
+
+pub fn record_memory_layout() {
+    update_lifecycle_state(|state| state.memory_layout = current_memory_layout());
+}
+
+pub fn save_heap_state() {
+    let community_stats = COMMUNITY_STATS.with(|stats| stats.borrow().clone());
+    update_lifecycle_state(|state| {
+        state.memory_layout = current_memory_layout();
+        state.community_stats = Some(community_stats);
+        state.saved_at = get_time();
+    });
+}
+
+// Canisters upgraded from a build without pre_upgrade snapshots keep the defaults
+pub fn restore_heap_state() {
+    let state = LIFECYCLE_STATE.with(|state| state.borrow().get().clone());
+    if let Some(community_stats) = state.community_stats {
+        COMMUNITY_STATS.with(|stats| *stats.borrow_mut() = community_stats);
+    }
+    record_memory_layout();
+}
+
+fn recorded_task_version(task: UpgradeTask) -> Option<u32> {
+    LIFECYCLE_STATE.with(|state| {
+        state.borrow().get().upgrade_tasks.iter()
+            .find(|(name, _)| name == task.name())
+            .map(|(_, version)| *version)
+    })
+}
+
+fn record_task_version(task: UpgradeTask) {
+    update_lifecycle_state(|state| {
+        state.upgrade_tasks.retain(|(name, _)| name != task.name());
+        state.upgrade_tasks.push((task.name().to_string(), task.version()));
+    });
+}
+
+// Runs `run` unless `task` already ran at its current version. Skipped tasks
+// return the default result.
+pub fn run_upgrade_task<T: Default>(task: UpgradeTask, run: impl FnOnce() -> T) -> T {
+    if recorded_task_version(task) == Some(task.version()) {
+        return T::default();
+    }
+    let result = run();
+    record_task_version(task);
+    result
+}
+
+// A fresh install has nothing to rebuild or migrate
+pub fn mark_upgrade_tasks_current() {
+    for task in UpgradeTask::ALL {
+        record_task_version(task);
+    }
+}
+
+fn update_lifecycle_state(update: impl FnOnce(&mut LifecycleState)) {
+    LIFECYCLE_STATE.with(|cell| {
+        let mut cell = cell.borrow_mut();
+        let mut state = cell.get().clone();
+        update(&mut state);
+        if let Err(error) = cell.set(state) {
+            ic_cdk::trap(&format!("Failed to persist lifecycle state: {:?}", error));
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn layout(assignments: &[(u8, &str)]) -> Vec<MemoryAssignment> {
+        assignments.iter()
+            .map(|(memory_id, store)| MemoryAssignment { memory_id: *memory_id, store: store.to_string() })
+            .collect()
+    }
+
+    #[test]
+    fn current_layout_is_consistent() {
+        let current = current_memory_layout();
+        assert!(check_memory_layout(&current, &current).is_ok());
+        for (index, assignment) in current.iter().enumerate() {
+            assert!(!current[..index].iter().any(|other| other.memory_id == assignment.memory_id));
+        }
+    }
+
+    #[test]
+    fn only_appending_memory_ids_is_allowed() {
+        let recorded = layout(&[(0, "artifacts"), (1, "nfts")]);
+        assert!(check_memory_layout(&recorded, &layout(&[(0, "artifacts"), (1, "nfts"), (2, "votes")])).is_ok());
+        assert!(check_memory_layout(&[], &recorded).is_ok());
+
+        let moved = check_memory_layout(&recorded, &layout(&[(0, "artifacts"), (1, "votes"), (2, "nfts")]));
+        assert!(moved.unwrap_err().contains("belongs to 'nfts'"));
+        let dropped = check_memory_layout(&recorded, &layout(&[(0, "artifacts")]));
+        assert!(dropped.unwrap_err().contains("dropped"));
+        let duplicated = check_memory_layout(&[], &layout(&[(0, "artifacts"), (1, "artifacts")]));
+        assert!(duplicated.unwrap_err().contains("more than one memory id"));
+    }
+
+    #[test]
+    fn heap_state_survives_a_save_and_restore() {
+        COMMUNITY_STATS.with(|stats| stats.borrow_mut().total_posts = 42);
+        save_heap_state();
+        COMMUNITY_STATS.with(|stats| stats.borrow_mut().total_posts = 0);
+
+        restore_heap_state();
+        assert_eq!(COMMUNITY_STATS.with(|stats| stats.borrow().total_posts), 42);
+        assert_eq!(get_recorded_memory_layout(), current_memory_layout());
+    }
+
+    #[test]
+    fn upgrade_tasks_run_once_per_version() {
+        let mut runs = 0;
+        assert_eq!(run_upgrade_task(UpgradeTask::GeoIndex, || { runs += 1; 7 }), 7);
+        assert_eq!(run_upgrade_task(UpgradeTask::GeoIndex, || { runs += 1; 7 }), 0);
+        assert_eq!(runs, 1);
+
+        // A version bump runs the task again
+        update_lifecycle_state(|state| state.upgrade_tasks = vec![("geo_index".to_string(), 0)]);
+        run_upgrade_task(UpgradeTask::GeoIndex, || runs += 1);
+        assert_eq!(runs, 2);
+
+        mark_upgrade_tasks_current();
+        run_upgrade_task(UpgradeTask::ArtifactIndex, || runs += 1);
+        assert_eq!(runs, 2);
+    }
+}
@@ -0,0 +1,525 @@
+use base64::{engine::general_purpose::STANDARD, Engine as _};
+use candid::{CandidType, Principal};
+use ic_cdk::{query, update};
+use ic_stable_structures::Storable;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::borrow::Cow;
+
+use crate::modules::types::*;
+use crate::modules::storage::{ARTIFACTS, MEDIA_ASSETS, MEDIA_CHUNKS, MEDIA_UPLOADS, get_next_id, IdKind};
+use crate::modules::auth::{can_moderate, can_submit_artifacts, get_caller};
+use crate::modules::utils::get_time;
+use crate::modules::audit::log_audit_event;
+use crate::modules::artifacts::{record_changes, store_artifact};
+
+// ============================================================================
+// MEDIA STORE
+// ============================================================================
+//
+// Files are uploaded in chunks so that every call stays under the ingress
+// message limit:
+//
+//   begin_media_upload(total_size) -> media_id
+//   put_media_chunk(media_id, 0, bytes), put_media_chunk(media_id, 1, bytes), ...
+//   commit_media_upload(media_id) -> MediaAsset
+//
+// Chunks live in MEDIA_CHUNKS under (media_id, index) both during the upload
+// and after it; MEDIA_UPLOADS tracks uploads in progress and MEDIA_ASSETS the
+// committed files. Commit checks the size, hashes the content and sniffs its
+// type from the leading bytes. Committed media are immutable, are referenced
+// from `Artifact.media` and are served by `http_request` at /media/<id>.
+
+// Stays under the 2 MiB ingress limit and the 3 MiB query reply limit
+pub const MAX_CHUNK_BYTES: usize = 1_900_000;
+pub const MAX_MEDIA_BYTES: u64 = 64 * 1024 * 1024;
+const MAX_OPEN_UPLOADS_PER_CALLER: usize = 4;
+// Abandoned uploads are dropped after a day
+const UPLOAD_TTL_NS: u64 = 24 * 60 * 60 * 1_000_000_000;
+const MAX_IMAGE_URL_LEN: usize = 2048;
+const SNIFF_BYTES: usize = 16;
+
+#[derive(CandidType, Serialize, Deserialize, Clone, Debug)]
+pub struct MediaAsset {
+    pub id: u64,
+    pub owner: Principal,
+    pub content_type: String,
+    pub size: u64,
+    // Hex SHA-256 of the content, also served as the ETag
+    pub sha256: String,
+    pub chunk_count: u32,
+    pub created_at: u64,
+}
+
+#[derive(CandidType, Serialize, Deserialize, Clone, Debug)]
+pub struct MediaUpload {
+    pub id: u64,
+    pub owner: Principal,
+    pub total_size: u64,
+    pub received_bytes: u64,
+    // Chunks received so far, which is also the index of the next chunk
+    pub chunk_count: u32,
+    pub started_at: u64,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub struct MediaChunkKey {
+    pub media_id: u64,
+    pub index: u32,
+}
+
+impl Storable for MediaChunkKey {
+    const BOUND: ic_stable_structures::storable::Bound = ic_stable_structures::storable::Bound::Bounded {
+        max_size: 12,
+        is_fixed_size: true,
+    };
+
+    fn to_bytes(&self) -> Cow<'_, [u8]> {
+        let mut bytes = Vec::with_capacity(12);
+        bytes.extend_from_slice(&self.media_id.to_be_bytes());
+        bytes.extend_from_slice(&self.index.to_be_bytes());
+        Cow::Owned(bytes)
+    }
+
+    fn from_bytes(bytes: Cow<[u8]>) -> Self {
+        let mut media_id = [0u8; 8];
+        let mut index = [0u8; 4];
+        media_id.copy_from_slice(&bytes[..8]);
+        index.copy_from_slice(&bytes[8..12]);
+        MediaChunkKey {
+            media_id: u64::from_be_bytes(media_id),
+            index: u32::from_be_bytes(index),
+        }
+    }
+}
+
+// Raw content bytes, stored without any envelope
+#[derive(Clone, Debug)]
+pub struct MediaChunk(pub Vec<u8>);
+
+impl Storable for MediaChunk {
+    const BOUND: ic_stable_structures::storable::Bound = ic_stable_structures::storable::Bound::Unbounded;
+
+    fn to_bytes(&self) -> Cow<'_, [u8]> {
+        Cow::Borrowed(&self.0)
+    }
+
+    fn from_bytes(bytes: Cow<[u8]>) -> Self {
+        MediaChunk(bytes.into_owned())
+    }
+}
+
+// ============================================================================
+// CONTENT CHECKS
+// ============================================================================
+
+// Content type from the file's magic bytes; None for anything not accepted
+pub fn sniff_content_type(head: &[u8]) -> Option<&'static str> {
+    match head {
+        [0xFF, 0xD8, 0xFF, ..] => Some("image/jpeg"),
+        [0x89, b'P', b'N', b'G', 0x0D, 0x0A, 0x1A, 0x0A, ..] => Some("image/png"),
+        [b'G', b'I', b'F', b'8', b'7' | b'9', b'a', ..] => Some("image/gif"),
+        [b'R', b'I', b'F', b'F', _, _, _, _, b'W', b'E', b'B', b'P', ..] => Some("image/webp"),
+        [b'I', b'I', 0x2A, 0x00, ..] | [b'M', b'M', 0x00, 0x2A, ..] => Some("image/tiff"),
+        [_, _, _, _, b'f', b't', b'y', b'p', b'a', b'v', b'i', b'f', ..] => Some("image/avif"),
+        [_, _, _, _, b'f', b't', b'y', b'p', ..] => Some("video/mp4"),
+        [b'%', b'P', b'D', b'F', b'-', ..] => Some("application/pdf"),
+        [b'g', b'l', b'T', b'F', ..] => Some("model/gltf-binary"),
+        _ => None,
+    }
+}
+
+// Images stored inline by older clients: `data:` URIs and bare base64 payloads
+pub fn inline_image_bytes(image: &str) -> Option<Vec<u8>> {
+    let payload = match image.strip_prefix("data:") {
+        Some(rest) => rest.split_once(";base64,")?.1,
+        None if image.contains("://") || image.starts_with('/') => return None,
+        None => image,
+    };
+    let bytes = STANDARD.decode(payload.trim()).ok()?;
+    sniff_content_type(&bytes).map(|_| bytes)
+}
+
+// `Artifact.images` only holds links; file contents go through the media store
+pub fn validate_image_url(image: &str) -> Result<(), String> {
+    if image.starts_with("data:") || inline_image_bytes(image).is_some() {
+        return Err("Inline image data is not accepted; upload the file with begin_media_upload and attach the media id".to_string());
+    }
+    if image.trim().is_empty() || image.len() > MAX_IMAGE_URL_LEN {
+        return Err(format!("Image URLs must be between 1 and {} characters", MAX_IMAGE_URL_LEN));
+    }
+    Ok(())
+}
+
+// Media may be attached by their uploader or by a moderator
+pub fn validate_media_ids(media_ids: &[u64], caller: Principal) -> Result<(), String> {
+    MEDIA_ASSETS.with(|assets| {
+        let assets = assets.borrow();
+        for media_id in media_ids {
+            let asset = assets.get(media_id)
+                .ok_or_else(|| format!("Media {} not found", media_id))?;
+            if asset.owner != caller && !can_moderate(caller) {
+                return Err(format!("You don't have permission to attach media {}", media_id));
+            }
+        }
+        Ok(())
+    })
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+// ============================================================================
+// UPLOADS
+// ============================================================================
+
+#[update]
+pub fn begin_media_upload(total_size: u64) -> Result<u64, String> {
+    let caller = get_caller();
+    if !can_submit_artifacts(caller) {
+        return Err("You don't have permission to upload media".to_string());
+    }
+    if total_size == 0 || total_size > MAX_MEDIA_BYTES {
+        return Err(format!("Media size must be between 1 and {} bytes", MAX_MEDIA_BYTES));
+    }
+
+    let now = get_time();
+    purge_expired_uploads(now);
+
+    let open_uploads = MEDIA_UPLOADS.with(|uploads| {
+        uploads.borrow().iter().filter(|(_, upload)| upload.owner == caller).count()
+    });
+    if open_uploads >= MAX_OPEN_UPLOADS_PER_CALLER {
+        return Err("Too many uploads in progress; commit or abort one first".to_string());
+    }
+
+    let media_id = get_next_id(IdKind::Media);
+    MEDIA_UPLOADS.with(|uploads| {
+        uploads.borrow_mut().insert(media_id, MediaUpload {
+            id: media_id,
+            owner: caller,
+            total_size,
+            received_bytes: 0,
+            chunk_count: 0,
+            started_at: now,
+        });
+    });
+
+    Ok(media_id)
+}
+
+// Chunks must arrive in order; returns the number of bytes received so far
+#[update]
+pub fn put_media_chunk(media_id: u64, index: u32, data: Vec<u8>) -> Result<u64, String> {
+    let mut upload = owned_upload(media_id)?;
+
+    if data.is_empty() || data.len() > MAX_CHUNK_BYTES {
+        return Err(format!("Chunks must be between 1 and {} bytes", MAX_CHUNK_BYTES));
+    }
+    if index != upload.chunk_count {
+        return Err(format!("Expected chunk {}, got {}", upload.chunk_count, index));
+    }
+    if upload.received_bytes + data.len() as u64 > upload.total_size {
+        return Err(format!("Upload exceeds its declared size of {} bytes", upload.total_size));
+    }
+
+    upload.received_bytes += data.len() as u64;
+    upload.chunk_count += 1;
+    let received_bytes = upload.received_bytes;
+
+    MEDIA_CHUNKS.with(|chunks| {
+        chunks.borrow_mut().insert(MediaChunkKey { media_id, index }, MediaChunk(data));
+    });
+    MEDIA_UPLOADS.with(|uploads| {
+        uploads.borrow_mut().insert(media_id, upload);
+    });
+
+    Ok(received_bytes)
+}
+
+#[update]
+pub fn commit_media_upload(media_id: u64) -> Result<MediaAsset, String> {
+    let upload = owned_upload(media_id)?;
+    if upload.received_bytes != upload.total_size {
+        return Err(format!(
+            "Upload incomplete: received {} of {} bytes",
+            upload.received_bytes, upload.total_size
+        ));
+    }
+
+    let mut hasher = Sha256::new();
+    let mut head = Vec::with_capacity(SNIFF_BYTES);
+    MEDIA_CHUNKS.with(|chunks| {
+        let chunks = chunks.borrow();
+        for index in 0..upload.chunk_count {
+            if let Some(chunk) = chunks.get(&MediaChunkKey { media_id, index }) {
+                let missing = SNIFF_BYTES.saturating_sub(head.len());
+                head.extend_from_slice(&chunk.0[..missing.min(chunk.0.len())]);
+                hasher.update(&chunk.0);
+            }
+        }
+    });
+
+    let Some(content_type) = sniff_content_type(&head) else {
+        discard_upload(media_id, upload.chunk_count);
+        return Err("Unsupported media type; accepted are JPEG, PNG, GIF, WebP, TIFF, AVIF, MP4, PDF and glTF".to_string());
+    };
+
+    let asset = MediaAsset {
+        id: media_id,
+        owner: upload.owner,
+        content_type: content_type.to_string(),
+        size: upload.total_size,
+        sha256: hex(&hasher.finalize()),
+        chunk_count: upload.chunk_count,
+        created_at: get_time(),
+    };
+
+    MEDIA_ASSETS.with(|assets| {
+        assets.borrow_mut().insert(media_id, asset.clone());
+    });
+    MEDIA_UPLOADS.with(|uploads| {
+        uploads.borrow_mut().remove(&media_id);
+    });
+
+    log_audit_event(
+        AuditEventType::DataModification,
+        None,
+        format!("Media {} uploaded ({}, {} bytes)", media_id, asset.content_type, asset.size),
+        AuditSeverity::Info
+    );
+
+    Ok(asset)
+}
+
+#[update]
+pub fn abort_media_upload(media_id: u64) -> Result<String, String> {
+    let upload = owned_upload(media_id)?;
+    discard_upload(media_id, upload.chunk_count);
+    Ok("Upload aborted".to_string())
+}
+
+#[query]
+pub fn get_media_info(media_id: u64) -> Result<MediaAsset, String> {
+    MEDIA_ASSETS.with(|assets| {
+        assets.borrow().get(&media_id)
+            .ok_or_else(|| "Media not found".to_string())
+    })
+}
+
+pub fn get_media_chunk(media_id: u64, index: u32) -> Option<Vec<u8>> {
+    MEDIA_CHUNKS.with(|chunks| {
+        chunks.borrow().get(&MediaChunkKey { media_id, index }).map(|chunk| chunk.0)
+    })
+}
+
+fn owned_upload(media_id: u64) -> Result<MediaUpload, String> {
+    let upload = MEDIA_UPLOADS.with(|uploads| uploads.borrow().get(&media_id))
+        .ok_or_else(|| "Upload not found or already committed".to_string())?;
+    if upload.owner != get_caller() {
+        return Err("You don't own this upload".to_string());
+    }
+    Ok(upload)
+}
+
+fn discard_upload(media_id: u64, chunk_count: u32) {
+    MEDIA_CHUNKS.with(|chunks| {
+        let mut chunks = chunks.borrow_mut();
+        for index in 0..chunk_count {
+            chunks.remove(&MediaChunkKey { media_id, index });
+        }
+    });
+    MEDIA_UPLOADS.with(|uploads| {
+        uploads.borrow_mut().remove(&media_id);
+    });
+}
+
+fn purge_expired_uploads(now: u64) {
+    let expired: Vec<MediaUpload> = MEDIA_UPLOADS.with(|uploads| {
+        uploads.borrow().iter()
+            .filter(|(_, upload)| now.saturating_sub(upload.started_at) > UPLOAD_TTL_NS)
+            .map(|(_, upload)| upload)
+            .collect()
+    });
+    for upload in expired {
+        discard_upload(upload.id, upload.chunk_count);
+    }
+}
+
+// Stores a complete file in one go, splitting it into chunks
+fn store_media(owner: Principal, bytes: &[u8]) -> Result<MediaAsset, String> {
+    if bytes.is_empty() || bytes.len() as u64 > MAX_MEDIA_BYTES {
+        return Err(format!("Media size must be between 1 and {} bytes", MAX_MEDIA_BYTES));
+    }
+    let content_type = sniff_content_type(bytes)
+        .ok_or_else(|| "Unsupported media type".to_string())?;
+
+    let media_id = get_next_id(IdKind::Media);
+    let mut chunk_count = 0;
+    MEDIA_CHUNKS.with(|chunks| {
+        let mut chunks = chunks.borrow_mut();
+        for (index, chunk) in bytes.chunks(MAX_CHUNK_BYTES).enumerate() {
+            chunks.insert(MediaChunkKey { media_id, index: index as u32 }, MediaChunk(chunk.to_vec()));
+            chunk_count += 1;
+        }
+    });
+
+    let asset = MediaAsset {
+        id: media_id,
+        owner,
+        content_type: content_type.to_string(),
+        size: bytes.len() as u64,
+        sha256: hex(&Sha256::digest(bytes)),
+        chunk_count,
+        created_at: get_time(),
+    };
+    MEDIA_ASSETS.with(|assets| {
+        assets.borrow_mut().insert(media_id, asset.clone());
+    });
+    Ok(asset)
+}
+
+// ============================================================================
+// MIGRATION
+// ============================================================================
+
+// Moves images stored inline in `Artifact.images` into the media store and
+// references them from `Artifact.media`. Returns the number of images moved.
+//
+// Only the current record is rewritten. Inline payloads that reached the
+// history (as evidence, in older diffs, or as the "before" value of the
+// MediaMigrated diff itself) stay there: entries are hash-chained, so rewriting
+// them would make verify_provenance_integrity report tampering.
+pub fn migrate_inline_images() -> u64 {
+    let candidates: Vec<u64> = ARTIFACTS.with(|artifacts| {
+        artifacts.borrow().iter()
+            .filter(|(_, artifact)| artifact.images.iter().any(|image| inline_image_bytes(image).is_some()))
+            .map(|(artifact_id, _)| artifact_id)
+            .collect()
+    });
+
+    let mut moved = 0;
+    for artifact_id in candidates {
+        ARTIFACTS.with(|artifacts| {
+            let mut artifacts = artifacts.borrow_mut();
+            let Some(mut artifact) = artifacts.get(&artifact_id) else { return };
+            let before = artifact.clone();
+
+            let mut kept = Vec::new();
+            for image in std::mem::take(&mut artifact.images) {
+                let stored = inline_image_bytes(&image)
+                    .and_then(|bytes| store_media(artifact.creator, &bytes).ok());
+                match stored {
+                    Some(asset) => {
+                        artifact.media.push(asset.id);
+                        moved += 1;
+                    },
+                    None => kept.push(image),
+                }
+            }
+            artifact.images = kept;
+
+            record_changes(&mut artifact, &before, "MediaMigrated", "Inline images moved to the media store", None);
+            store_artifact(&mut artifacts, artifact);
+        });
+    }
+    moved
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::modules::testing::{advance_time, principal, register, set_caller};
+
+    const PNG_MAGIC: [u8; 8] = [0x89, b'P', b'N', b'G', 0x0D, 0x0A, 0x1A, 0x0A];
+
+    fn uploader(id: u8) -> Principal {
+        register(principal(id), UserRole::Institution, &[]);
+        set_caller(principal(id));
+        principal(id)
+    }
+
+    fn png(size: usize) -> Vec<u8> {
+        let mut bytes = PNG_MAGIC.to_vec();
+        bytes.extend((0..size - PNG_MAGIC.len()).map(|n| n as u8));
+        bytes
+    }
+
+    fn upload(content: &[u8], chunk_size: usize) -> Result<MediaAsset, String> {
+        let media_id = begin_media_upload(content.len() as u64)?;
+        for (index, chunk) in content.chunks(chunk_size).enumerate() {
+            put_media_chunk(media_id, index as u32, chunk.to_vec())?;
+        }
+        commit_media_upload(media_id)
+    }
+
+    #[test]
+    fn chunked_uploads_commit_with_their_hash_and_type() {
+        uploader(1);
+        let content = png(1000);
+        let asset = upload(&content, 300).unwrap();
+
+        assert_eq!(asset.content_type, "image/png");
+        assert_eq!(asset.size, 1000);
+        assert_eq!(asset.chunk_count, 4);
+        assert_eq!(asset.sha256, hex(&Sha256::digest(&content)));
+        let stored: Vec<u8> = (0..asset.chunk_count).flat_map(|index| get_media_chunk(asset.id, index).unwrap()).collect();
+        assert_eq!(stored, content);
+        // A committed upload can no longer be written to
+        assert!(put_media_chunk(asset.id, 4, vec![0]).is_err());
+    }
+
+    #[test]
+    fn chunks_must_arrive_in_order_and_fit_the_declared_size() {
+        uploader(1);
+        let media_id = begin_media_upload(10).unwrap();
+        assert!(put_media_chunk(media_id, 1, vec![0; 4]).is_err());
+        assert_eq!(put_media_chunk(media_id, 0, PNG_MAGIC.to_vec()), Ok(8));
+        assert!(put_media_chunk(media_id, 1, vec![0; 3]).is_err());
+        assert!(put_media_chunk(media_id, 1, Vec::new()).is_err());
+        assert!(commit_media_upload(media_id).unwrap_err().contains("incomplete"));
+
+        // Only the owner may continue an upload
+        uploader(2);
+        assert!(put_media_chunk(media_id, 1, vec![0; 2]).is_err());
+        set_caller(principal(1));
+        put_media_chunk(media_id, 1, vec![0; 2]).unwrap();
+        assert!(commit_media_upload(media_id).is_ok());
+
+        assert!(begin_media_upload(0).is_err());
+        assert!(begin_media_upload(MAX_MEDIA_BYTES + 1).is_err());
+    }
+
+    #[test]
+    fn unrecognised_content_is_discarded_on_commit() {
+        uploader(1);
+        let media_id = begin_media_upload(6).unwrap();
+        put_media_chunk(media_id, 0, b"<html>".to_vec()).unwrap();
+        assert!(commit_media_upload(media_id).is_err());
+        assert!(get_media_chunk(media_id, 0).is_none());
+        assert!(get_media_info(media_id).is_err());
+    }
+
+    #[test]
+    fn open_uploads_are_limited_and_expire() {
+        uploader(1);
+        for _ in 0..MAX_OPEN_UPLOADS_PER_CALLER {
+            begin_media_upload(10).unwrap();
+        }
+        assert!(begin_media_upload(10).is_err());
+
+        advance_time(UPLOAD_TTL_NS + 1);
+        assert!(begin_media_upload(10).is_ok());
+    }
+
+    #[test]
+    fn inline_images_are_recognised_and_refused_as_urls() {
+        let encoded = STANDARD.encode(png(32));
+        assert_eq!(inline_image_bytes(&format!("data:image/png;base64,{}", encoded)), Some(png(32)));
+        assert_eq!(inline_image_bytes(&encoded), Some(png(32)));
+        assert_eq!(inline_image_bytes("https://example.org/a.png"), None);
+        assert!(validate_image_url(&encoded).is_err());
+        assert!(validate_image_url("https://example.org/a.png").is_ok());
+    }
+}
@@ -0,0 +1,550 @@
+use candid::CandidType;
+use ic_stable_structures::{StableBTreeMap, Storable};
+use serde::{Deserialize, Serialize};
+use std::borrow::Cow;
+use std::cell::RefCell;
+use std::collections::{HashMap, HashSet};
+
+use crate::modules::types::*;
+use crate::modules::storage::*;
+use crate::modules::ai_analysis::AIAnalysisResult;
+use crate::modules::community::CommunityPost;
+use crate::modules::media::{MediaAsset, MediaUpload};
+use crate::modules::signing::SignatureRecord;
+use crate::modules::icrc3::Icrc3Value;
+use crate::modules::custody::CustodyTransfer;
+use crate::modules::delegation::UserDelegations;
+use crate::modules::utils::{get_time, hash_history_entry};
+use crate::modules::artifacts::store_artifact;
+use crate::modules::certification::certify_nft;
+use crate::modules::certificates::seal_certificate;
+use crate::modules::nft::store_nft;
+use crate::modules::signing::{request_signature, SignatureKey, SignedSubject};
+
+// ============================================================================
+// SCHEMA MIGRATIONS
+// ============================================================================
+
+#[derive(CandidType, Serialize, Deserialize, Clone, Debug)]
+pub struct MigrationReport {
+    pub store: String,
+    pub schema_version: u16,
+    pub total_records: u64,
+    pub migrated: u64,
+    pub up_to_date: u64,
+    pub failed: u64,
+}
+
+// A record that could not be upgraded, kept byte-for-byte so it can be recovered
+#[derive(CandidType, Serialize, Deserialize, Clone, Debug)]
+pub struct QuarantinedRecord {
+    pub store: String,
+    pub key: Vec<u8>,
+    pub schema_version: u16,
+    pub bytes: Vec<u8>,
+    pub error: String,
+    pub quarantined_at: u64,
+}
+
+// Stored value bytes as-is, used to inspect records without decoding them
+struct RawRecord(Vec<u8>);
+
+impl Storable for RawRecord {
+    const BOUND: ic_stable_structures::storable::Bound = ic_stable_structures::storable::Bound::Unbounded;
+
+    fn to_bytes(&self) -> Cow<'_, [u8]> {
+        Cow::Borrowed(&self.0)
+    }
+
+    fn from_bytes(bytes: Cow<[u8]>) -> Self {
+        RawRecord(bytes.into_owned())
+    }
+}
+
+thread_local! {
+    static LAST_MIGRATION_REPORTS: RefCell<Vec<MigrationReport>> = const { RefCell::new(Vec::new()) };
+}
+
+// Must run before any typed store is touched after an upgrade: it rewrites the
+// underlying memories directly, which would invalidate an already-open map.
+pub fn run_schema_migrations() -> Vec<MigrationReport> {
+    let reports = vec![
+        migrate_store::<u64, Artifact>(ARTIFACTS_MEMORY_ID, "artifacts"),
+        migrate_store::<u64, ProofOfHeritageNFT>(NFTS_MEMORY_ID, "nfts"),
+        migrate_store::<u64, Proposal>(PROPOSALS_MEMORY_ID, "proposals"),
+        migrate_store::<candid::Principal, User>(USERS_MEMORY_ID, "users"),
+        migrate_store::<u64, Vote>(VOTES_MEMORY_ID, "votes"),
+        migrate_store::<u64, AuditEntry>(AUDIT_LOG_MEMORY_ID, "audit_log"),
+        migrate_store::<u64, AIAnalysisResult>(AI_ANALYSES_MEMORY_ID, "ai_analyses"),
+        migrate_store::<u64, CommunityPost>(COMMUNITY_POSTS_MEMORY_ID, "community_posts"),
+        migrate_store::<u64, MediaAsset>(MEDIA_ASSETS_MEMORY_ID, "media_assets"),
+        migrate_store::<u64, MediaUpload>(MEDIA_UPLOADS_MEMORY_ID, "media_uploads"),
+        migrate_store::<SignatureKey, SignatureRecord>(SIGNATURES_MEMORY_ID, "signatures"),
+        migrate_store::<u64, Icrc3Value>(ICRC3_BLOCKS_MEMORY_ID, "icrc3_blocks"),
+        migrate_store::<u64, CustodyTransfer>(CUSTODY_TRANSFERS_MEMORY_ID, "custody_transfers"),
+        migrate_store::<candid::Principal, UserDelegations>(DELEGATIONS_MEMORY_ID, "delegations"),
+    ];
+
+    LAST_MIGRATION_REPORTS.with(|last| *last.borrow_mut() = reports.clone());
+    reports
+}
+
+pub fn get_last_migration_reports() -> Vec<MigrationReport> {
+    LAST_MIGRATION_REPORTS.with(|last| last.borrow().clone())
+}
+
+fn migrate_store<K, T>(memory_id: u8, store: &str) -> MigrationReport
+where
+    K: Storable + Ord + Clone,
+    T: VersionedRecord,
+{
+    let mut raw_store: StableBTreeMap<K, RawRecord, Memory> = StableBTreeMap::init(get_memory(memory_id));
+    let mut report = MigrationReport {
+        store: store.to_string(),
+        schema_version: T::SCHEMA_VERSION,
+        total_records: raw_store.len(),
+        migrated: 0,
+        up_to_date: 0,
+        failed: 0,
+    };
+
+    // Collect outdated keys first; values are re-read one at a time below
+    let mut outdated_keys = Vec::new();
+    for (key, record) in raw_store.iter() {
+        let (version, _) = split_envelope(&record.0);
+        if version == T::SCHEMA_VERSION {
+            report.up_to_date += 1;
+        } else if version > T::SCHEMA_VERSION {
+            // Trapping rolls the upgrade back instead of quarantining newer data
+            ic_cdk::trap(&format!(
+                "Refusing to downgrade: {} contains {} schema version {} but this build supports {}",
+                store, T::RECORD_NAME, version, T::SCHEMA_VERSION
+            ));
+        } else {
+            outdated_keys.push(key);
+        }
+    }
+
+    for key in outdated_keys {
+        let Some(record) = raw_store.get(&key) else { continue };
+        match decode_versioned::<T>(&record.0) {
+            Ok(upgraded) => {
+                raw_store.insert(key, RawRecord(encode_versioned(&upgraded)));
+                report.migrated += 1;
+            },
+            Err(error) => {
+                quarantine_record(store, key.to_bytes().into_owned(), record.0, error);
+                raw_store.remove(&key);
+                report.failed += 1;
+            },
+        }
+    }
+
+    report
+}
+
+fn quarantine_record(store: &str, key: Vec<u8>, bytes: Vec<u8>, error: String) {
+    let (schema_version, _) = split_envelope(&bytes);
+    QUARANTINED_RECORDS.with(|quarantine| {
+        let mut quarantine = quarantine.borrow_mut();
+        let quarantine_id = quarantine.len() + 1;
+        quarantine.insert(quarantine_id, QuarantinedRecord {
+            store: store.to_string(),
+            key,
+            schema_version,
+            bytes,
+            error,
+            quarantined_at: get_time(),
+        });
+    });
+}
+
+pub fn get_quarantined_records() -> Vec<QuarantinedRecord> {
+    QUARANTINED_RECORDS.with(|quarantine| {
+        quarantine.borrow().iter().map(|(_, record)| record).collect()
+    })
+}
+
+// ============================================================================
+// ID COUNTER MIGRATION
+// ============================================================================
+//
+// Provenance entries used to draw `HistoryEntry.id` from their own counter (10)
+// while every other history entry drew from counter 8, which community posts
+// also shared. `migrate_id_counters` moves the affected counters past every id
+// already handed out and renumbers provenance entries whose id collides with
+// another history entry. Renumbered entries are rehashed, later entries are
+// relinked to the new hashes, certificates sealed over the old chain are
+// resealed, and the affected signatures are dropped and requested again.
+// Media ids used to come from counter 11, which belongs to the disabled
+// collaboration module; the media counter moves to its own key.
+// Running it again finds nothing left to do.
+
+const RETIRED_PROVENANCE_COUNTER: u64 = 10;
+const LEGACY_MEDIA_COUNTER: u64 = 11;
+
+#[derive(CandidType, Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct RenumberedEntry {
+    pub artifact_id: u64,
+    pub old_id: u64,
+    pub new_id: u64,
+}
+
+fn is_provenance_entry(entry: &HistoryEntry) -> bool {
+    entry.action.starts_with("Provenance: ")
+}
+
+// Gives every history entry a unique id. Entries drawn from the history counter
+// keep theirs; provenance entries (and any later duplicate) get a fresh id from
+// `allocate`. Rehashes and relinks the chains that changed.
+pub fn renumber_history_ids<F>(histories: &mut [(u64, Vec<HistoryEntry>)], mut allocate: F) -> Vec<RenumberedEntry>
+where
+    F: FnMut() -> u64,
+{
+    let mut taken = HashSet::new();
+    let mut colliding = Vec::new();
+    for provenance_pass in [false, true] {
+        for (a, (_, history)) in histories.iter().enumerate() {
+            for (i, entry) in history.iter().enumerate() {
+                if is_provenance_entry(entry) == provenance_pass && !taken.insert(entry.id) {
+                    colliding.push((a, i));
+                }
+            }
+        }
+    }
+    colliding.sort();
+
+    let mut renumbered = Vec::new();
+    let mut changed: HashMap<usize, HashSet<usize>> = HashMap::new();
+    for (a, i) in colliding {
+        let new_id = std::iter::repeat_with(&mut allocate).find(|id| !taken.contains(id)).unwrap_or_default();
+        taken.insert(new_id);
+        let (artifact_id, history) = &mut histories[a];
+        renumbered.push(RenumberedEntry { artifact_id: *artifact_id, old_id: history[i].id, new_id });
+        history[i].id = new_id;
+        changed.entry(a).or_default().insert(i);
+    }
+
+    for (a, entries) in changed {
+        let (artifact_id, history) = &mut histories[a];
+        rehash_history(*artifact_id, history, &entries);
+    }
+    renumbered
+}
+
+// Recomputes the hashes of `changed` entries and of every entry linking to a
+// rehashed one. Links that were already broken stay broken.
+fn rehash_history(artifact_id: u64, history: &mut [HistoryEntry], changed: &HashSet<usize>) {
+    let mut replaced: HashMap<String, String> = HashMap::new();
+    for (i, entry) in history.iter_mut().enumerate() {
+        let relinked = match entry.previous_hash.as_ref().and_then(|previous| replaced.get(previous)) {
+            Some(new_previous) => {
+                entry.previous_hash = Some(new_previous.clone());
+                true
+            },
+            None => false,
+        };
+        if relinked || changed.contains(&i) {
+            let new_hash = hash_history_entry(artifact_id, entry);
+            replaced.insert(std::mem::replace(&mut entry.immutable_hash, new_hash.clone()), new_hash);
+        }
+    }
+}
+
+pub fn migrate_id_counters() -> Vec<RenumberedEntry> {
+    // Community posts used to share the history counter: start theirs past it
+    let history_counter = IdKind::HistoryEntry.counter_key();
+    if get_id_counter(IdKind::CommunityPost.counter_key()).is_none() {
+        set_id_counter(IdKind::CommunityPost.counter_key(), get_id_counter(history_counter).unwrap_or(0));
+    }
+    // Provenance entries now draw from the history counter: move it past every provenance id
+    let provenance_counter = get_id_counter(RETIRED_PROVENANCE_COUNTER).unwrap_or(0);
+    if provenance_counter > get_id_counter(history_counter).unwrap_or(0) {
+        set_id_counter(history_counter, provenance_counter);
+    }
+    if get_id_counter(IdKind::Media.counter_key()).is_none() {
+        if let Some(media_counter) = get_id_counter(LEGACY_MEDIA_COUNTER) {
+            set_id_counter(IdKind::Media.counter_key(), media_counter);
+        }
+    }
+
+    let mut histories: Vec<(u64, Vec<HistoryEntry>)> = ARTIFACTS.with(|artifacts| {
+        artifacts.borrow().iter().map(|(id, artifact)| (id, artifact.history)).collect()
+    });
+    let renumbered = renumber_history_ids(&mut histories, || get_next_id(IdKind::HistoryEntry));
+    if renumbered.is_empty() {
+        return renumbered;
+    }
+
+    let affected: HashSet<u64> = renumbered.iter().map(|entry| entry.artifact_id).collect();
+    let mut resign = Vec::new();
+    for (artifact_id, history) in histories.into_iter().filter(|(id, _)| affected.contains(id)) {
+        let Some(artifact) = ARTIFACTS.with(|artifacts| {
+            let mut artifacts = artifacts.borrow_mut();
+            let mut artifact = artifacts.get(&artifact_id)?;
+            artifact.history = history;
+            store_artifact(&mut artifacts, artifact.clone());
+            Some(artifact)
+        }) else { continue };
+
+        // Every signed provenance entry of the artifact may have been rehashed.
+        // Provenance is signed on demand, so only entries that had a
+        // signature are signed again.
+        let moved: HashMap<u64, u64> = renumbered.iter()
+            .filter(|entry| entry.artifact_id == artifact_id)
+            .map(|entry| (entry.old_id, entry.new_id))
+            .collect();
+        let stale: HashSet<u64> = moved.keys().copied()
+            .chain(artifact.history.iter().filter(|entry| is_provenance_entry(entry)).map(|entry| entry.id))
+            .collect();
+        let signed: Vec<u64> = SIGNATURES.with(|signatures| {
+            let mut signatures = signatures.borrow_mut();
+            stale.into_iter()
+                .filter(|entry_id| signatures.remove(&SignatureKey::new(SignedSubject::ProvenanceEntry { artifact_id, entry_id: *entry_id })).is_some())
+                .collect()
+        });
+        resign.extend(artifact.history.iter()
+            .filter(|entry| is_provenance_entry(entry))
+            .filter(|entry| {
+                let old_id = moved.iter().find(|(_, new_id)| **new_id == entry.id).map_or(entry.id, |(old_id, _)| *old_id);
+                signed.contains(&old_id)
+            })
+            .map(|entry| SignedSubject::ProvenanceEntry { artifact_id, entry_id: entry.id }));
+
+        // Certificates quote the verification chain, so they are resealed
+        let nft_ids: Vec<u64> = NFTS.with(|nfts| {
+            nfts.borrow().iter().filter(|(_, nft)| nft.artifact_id == artifact_id).map(|(id, _)| id).collect()
+        });
+        for nft_id in nft_ids {
+            NFTS.with(|nfts| {
+                let mut nfts = nfts.borrow_mut();
+                let Some(mut nft) = nfts.get(&nft_id) else { return };
+                seal_certificate(&mut nft, &artifact);
+                certify_nft(&nft);
+                store_nft(&mut nfts, nft);
+            });
+            resign.push(SignedSubject::Certificate { nft_id });
+        }
+    }
+
+    request_signatures_after_upgrade(resign);
+    renumbered
+}
+
+// Signing needs inter-canister calls, which post_upgrade cannot make
+#[cfg(not(test))]
+fn request_signatures_after_upgrade(subjects: Vec<SignedSubject>) {
+    ic_cdk_timers::set_timer(std::time::Duration::ZERO, move || {
+        for subject in subjects {
+            request_signature(subject);
+        }
+    });
+}
+
+#[cfg(test)]
+fn request_signatures_after_upgrade(subjects: Vec<SignedSubject>) {
+    subjects.into_iter().for_each(request_signature);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use candid::{Encode, Principal};
+    use crate::modules::artifacts::append_history_entry;
+    use crate::modules::certificates::{get_certificate_document, verify_certificate};
+    use crate::modules::nft::{get_nft, issue_heritage_nft};
+    use crate::modules::signing::{get_signature, SignatureRecord, SignatureScheme};
+    use crate::modules::testing::{artifact_request, create_artifact_as, now, principal, register, set_caller, signature_requests};
+
+    fn entry(id: u64, action: &str) -> HistoryEntry {
+        HistoryEntry {
+            id,
+            timestamp: id * 10,
+            action: action.to_string(),
+            actor: Principal::anonymous(),
+            details: String::new(),
+            evidence: None,
+            previous_hash: None,
+            immutable_hash: String::new(),
+        }
+    }
+
+    fn chain(artifact_id: u64, entries: Vec<HistoryEntry>) -> (u64, Vec<HistoryEntry>) {
+        let mut history: Vec<HistoryEntry> = Vec::new();
+        for mut entry in entries {
+            entry.previous_hash = history.last().map(|last| last.immutable_hash.clone());
+            entry.immutable_hash = hash_history_entry(artifact_id, &entry);
+            history.push(entry);
+        }
+        (artifact_id, history)
+    }
+
+    fn chain_is_intact(artifact_id: u64, history: &[HistoryEntry]) -> bool {
+        history.iter().enumerate().all(|(i, entry)| {
+            entry.immutable_hash == hash_history_entry(artifact_id, entry)
+                && entry.previous_hash == i.checked_sub(1).map(|p| history[p].immutable_hash.clone())
+        })
+    }
+
+    fn vote(id: u64) -> Vote {
+        Vote {
+            id,
+            proposal_id: 1,
+            voter: Principal::anonymous(),
+            vote_type: VoteType::For,
+            weight: 1,
+            timestamp: 0,
+            rationale: None,
+            expertise_relevance: 0,
+        }
+    }
+
+    #[test]
+    fn envelope_round_trips_and_refuses_newer_versions() {
+        let bytes = encode_versioned(&vote(1));
+        assert_eq!(split_envelope(&bytes).0, <Vote as VersionedRecord>::SCHEMA_VERSION);
+        assert_eq!(decode_versioned::<Vote>(&bytes).unwrap().id, 1);
+
+        // Records from before the envelope are bare Candid
+        let legacy = Encode!(&vote(2)).unwrap();
+        assert_eq!(split_envelope(&legacy).0, LEGACY_SCHEMA_VERSION);
+        assert_eq!(decode_versioned::<Vote>(&legacy).unwrap().id, 2);
+
+        let mut newer = bytes.clone();
+        newer[4..6].copy_from_slice(&99u16.to_be_bytes());
+        assert!(decode_versioned::<Vote>(&newer).unwrap_err().contains("newer than supported"));
+    }
+
+    #[test]
+    fn migrates_outdated_records_and_quarantines_undecodable_ones() {
+        let mut raw: StableBTreeMap<u64, RawRecord, Memory> = StableBTreeMap::init(get_memory(VOTES_MEMORY_ID));
+        raw.insert(1, RawRecord(Encode!(&vote(1)).unwrap()));
+        raw.insert(2, RawRecord(b"not a vote".to_vec()));
+        raw.insert(3, RawRecord(encode_versioned(&vote(3))));
+
+        let report = migrate_store::<u64, Vote>(VOTES_MEMORY_ID, "votes");
+        assert_eq!((report.total_records, report.migrated, report.up_to_date, report.failed), (3, 1, 1, 1));
+
+        let raw: StableBTreeMap<u64, RawRecord, Memory> = StableBTreeMap::init(get_memory(VOTES_MEMORY_ID));
+        assert_eq!(split_envelope(&raw.get(&1).unwrap().0).0, <Vote as VersionedRecord>::SCHEMA_VERSION);
+        assert!(!raw.contains_key(&2));
+
+        let quarantined = get_quarantined_records();
+        assert_eq!(quarantined.len(), 1);
+        assert_eq!((quarantined[0].store.as_str(), quarantined[0].bytes.as_slice()), ("votes", &b"not a vote"[..]));
+
+        // A second pass has nothing left to do
+        let again = migrate_store::<u64, Vote>(VOTES_MEMORY_ID, "votes");
+        assert_eq!((again.migrated, again.up_to_date, again.failed), (0, 2, 0));
+    }
+
+    #[test]
+    fn renumbers_provenance_entries_colliding_with_history_ids() {
+        let mut histories = vec![
+            chain(1, vec![entry(1, "Created"), entry(1, "Provenance: Acquisition"), entry(2, "FieldsUpdated")]),
+            chain(2, vec![entry(3, "Created"), entry(2, "Provenance: Transfer")]),
+        ];
+        let mut next = 3;
+        let renumbered = renumber_history_ids(&mut histories, || { next += 1; next });
+
+        assert_eq!(renumbered, vec![
+            RenumberedEntry { artifact_id: 1, old_id: 1, new_id: 4 },
+            RenumberedEntry { artifact_id: 2, old_id: 2, new_id: 5 },
+        ]);
+        let ids: Vec<u64> = histories.iter().flat_map(|(_, history)| history.iter().map(|entry| entry.id)).collect();
+        assert_eq!(ids, vec![1, 4, 2, 3, 5]);
+        assert!(histories.iter().all(|(artifact_id, history)| chain_is_intact(*artifact_id, history)));
+    }
+
+    #[test]
+    fn skips_ids_already_in_use() {
+        let mut histories = vec![chain(1, vec![entry(1, "Created"), entry(2, "Created"), entry(1, "Provenance: Discovery")])];
+        let mut next = 0;
+        renumber_history_ids(&mut histories, || { next += 1; next });
+        assert_eq!(histories[0].1[2].id, 3);
+    }
+
+    #[test]
+    fn leaves_unique_histories_untouched() {
+        let mut histories = vec![chain(1, vec![entry(1, "Created"), entry(7, "Provenance: Discovery")])];
+        let before = histories.clone();
+        assert!(renumber_history_ids(&mut histories, || unreachable!()).is_empty());
+        assert_eq!(histories[0].1.iter().map(|e| &e.immutable_hash).collect::<Vec<_>>(),
+                   before[0].1.iter().map(|e| &e.immutable_hash).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn migrating_counters_renumbers_rehashes_and_reseals() {
+        let artifact_id = create_artifact_as(principal(1), artifact_request("Scarab"));
+        let created_id = ARTIFACTS.with(|artifacts| artifacts.borrow().get(&artifact_id).unwrap().history[0].id);
+        // A provenance entry drawn from the retired counter, colliding with the creation entry
+        ARTIFACTS.with(|artifacts| {
+            let mut artifacts = artifacts.borrow_mut();
+            let mut artifact = artifacts.get(&artifact_id).unwrap();
+            append_history_entry(&mut artifact, HistoryEntry { id: created_id, ..entry(0, "Provenance: Acquisition") });
+            artifact.status = ArtifactStatus::Verified;
+            store_artifact(&mut artifacts, artifact);
+        });
+        set_id_counter(RETIRED_PROVENANCE_COUNTER, 40);
+        set_id_counter(LEGACY_MEDIA_COUNTER, 7);
+
+        register(principal(2), UserRole::Institution, &[]);
+        USERS.with(|users| {
+            let mut users = users.borrow_mut();
+            let mut user = users.get(&principal(2)).unwrap();
+            user.verified_at = Some(now());
+            user.verification_level = UserVerificationLevel::InstitutionVerified;
+            users.insert(principal(2), user);
+        });
+        set_caller(principal(2));
+        let nft_id = issue_heritage_nft(artifact_id).unwrap();
+        let number = get_nft(nft_id).unwrap().heritage_certificate.certificate_number;
+
+        let old_subject = SignedSubject::ProvenanceEntry { artifact_id, entry_id: created_id };
+        SIGNATURES.with(|signatures| signatures.borrow_mut().insert(SignatureKey::new(old_subject), SignatureRecord {
+            subject: old_subject,
+            scheme: SignatureScheme::EcdsaSecp256k1,
+            key_name: "test_key".to_string(),
+            message_hash: vec![0; 32],
+            signature: vec![1; 64],
+            signed_at: now(),
+        }));
+        let requested_before = signature_requests().len();
+
+        let renumbered = migrate_id_counters();
+        assert_eq!(renumbered.len(), 1);
+        let new_id = renumbered[0].new_id;
+        assert_eq!((renumbered[0].artifact_id, renumbered[0].old_id), (artifact_id, created_id));
+        assert!(new_id > 40);
+        assert_eq!(get_id_counter(IdKind::Media.counter_key()), Some(7));
+
+        let history = ARTIFACTS.with(|artifacts| artifacts.borrow().get(&artifact_id).unwrap().history);
+        assert_eq!(history[1].id, new_id);
+        assert!(chain_is_intact(artifact_id, &history));
+
+        // The certificate was resealed over the new chain
+        let document = get_certificate_document(number.clone()).unwrap();
+        let verification = verify_certificate(number, document.json_ld.into_bytes()).unwrap();
+        assert!(verification.valid, "{:?}", verification.issues);
+        assert!(verification.chain_intact);
+
+        // The stale signature is dropped and both subjects are signed again
+        assert!(get_signature(SignedSubject::ProvenanceEntry { artifact_id, entry_id: created_id }).is_none());
+        assert_eq!(signature_requests()[requested_before..], [
+            SignedSubject::ProvenanceEntry { artifact_id, entry_id: new_id },
+            SignedSubject::Certificate { nft_id },
+        ]);
+
+        assert!(migrate_id_counters().is_empty());
+        assert_eq!(signature_requests().len(), requested_before + 2);
+    }
+
+    #[test]
+    fn keeps_already_broken_links_broken() {
+        let (artifact_id, mut history) = chain(1, vec![entry(1, "Created"), entry(1, "Provenance: Discovery"), entry(2, "Updated")]);
+        history[2].previous_hash = Some("tampered".to_string());
+        let mut histories = vec![(artifact_id, history)];
+        renumber_history_ids(&mut histories, || 9);
+        assert_eq!(histories[0].1[2].previous_hash.as_deref(), Some("tampered"));
+    }
+}
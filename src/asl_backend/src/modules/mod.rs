@@ -8,6 +8,28 @@ pub mod voting;
 pub mod nft;
 pub mod audit;
 pub mod utils;
+pub mod certification;
+pub mod migrations;
+pub mod lifecycle;
+pub mod indexes;
+pub mod search;
+pub mod query;
+pub mod geo;
+pub mod changes;
+pub mod media;
+pub mod http;
+pub mod certificates;
+pub mod signing;
+pub mod icrc3;
+pub mod icrc7;
+pub mod custody;
+pub mod scheduler;
+pub mod governance;
+pub mod tally;
+pub mod delegation;
+pub mod reviews;
+#[cfg(test)]
+pub mod testing;
 
 // Amazing new features modules
 pub mod ai_analysis;
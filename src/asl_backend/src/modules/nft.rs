@@ -1,3 +1,4 @@
+use candid::Principal;
 use ic_cdk::{query, update};
 
 use crate::modules::types::*;
@@ -5,6 +6,10 @@ use crate::modules::storage::*;
 use crate::modules::auth::*;
 use crate::modules::utils::*;
 use crate::modules::audit::log_audit_event;
+use crate::modules::certification::certify_nft;
+use crate::modules::certificates::seal_certificate;
+use crate::modules::signing::{request_signature, SignedSubject};
+use crate::modules::icrc7::record_mint;
 
 // ============================================================================
 // NFT HERITAGE CERTIFICATE SYSTEM
@@ -47,7 +52,7 @@ pub fn issue_heritage_nft(artifact_id: u64) -> Result<u64, String> {
         return Err("Heritage NFT already exists for this artifact".to_string());
     }
 
-    let nft_id = get_next_id(3); // NFT ID counter
+    let nft_id = get_next_id(IdKind::Nft);
     let now = get_time();
     
     // Get issuer information
@@ -78,7 +83,7 @@ pub fn issue_heritage_nft(artifact_id: u64) -> Result<u64, String> {
             "Provenance verification".to_string(),
         ],
         expert_endorsements: Vec::new(), // To be populated separately
-        digital_signature: verification_hash.clone(),
+        digital_signature: String::new(), // Set when the document is sealed below
     };
 
     let access_rights = AccessRights {
@@ -89,7 +94,7 @@ pub fn issue_heritage_nft(artifact_id: u64) -> Result<u64, String> {
         special_permissions: Vec::new(),
     };
 
-    let nft = ProofOfHeritageNFT {
+    let mut nft = ProofOfHeritageNFT {
         id: nft_id,
         artifact_id,
         owner: artifact.creator, // Initial owner is the artifact creator
@@ -107,9 +112,13 @@ pub fn issue_heritage_nft(artifact_id: u64) -> Result<u64, String> {
         access_rights,
     };
 
+    seal_certificate(&mut nft, &artifact);
+    record_mint(&nft, now);
     NFTS.with(|nfts| {
-        nfts.borrow_mut().insert(nft_id, nft);
+        certify_nft(&nft);
+        store_nft(&mut nfts.borrow_mut(), nft);
     });
+    request_signature(SignedSubject::Certificate { nft_id });
 
     log_audit_event(
         AuditEventType::NftIssued,
@@ -134,6 +143,7 @@ pub fn add_expert_endorsement(nft_id: u64, notes: String) -> Result<String, Stri
         return Err("Endorsement notes must be at least 20 characters".to_string());
     }
 
+    // The resealed certificate is re-signed on demand, see signing::sign_record
     NFTS.with(|nfts| {
         let mut nfts = nfts.borrow_mut();
         if let Some(mut nft) = nfts.get(&nft_id) {
@@ -172,7 +182,11 @@ pub fn add_expert_endorsement(nft_id: u64, notes: String) -> Result<String, Stri
             };
 
             nft.heritage_certificate.expert_endorsements.push(endorsement);
-            nfts.insert(nft_id, nft);
+            if let Some(artifact) = ARTIFACTS.with(|artifacts| artifacts.borrow().get(&nft.artifact_id)) {
+                seal_certificate(&mut nft, &artifact);
+            }
+            certify_nft(&nft);
+            store_nft(&mut nfts, nft);
 
             log_audit_event(
                 AuditEventType::DataModification,
@@ -207,7 +221,8 @@ pub fn update_nft_access_rights(nft_id: u64, new_rights: AccessRights) -> Result
         let mut nfts = nfts.borrow_mut();
         if let Some(mut nft) = nfts.get(&nft_id) {
             nft.access_rights = new_rights;
-            nfts.insert(nft_id, nft);
+            certify_nft(&nft);
+            store_nft(&mut nfts, nft);
 
             log_audit_event(
                 AuditEventType::AccessGranted,
@@ -244,14 +259,21 @@ pub fn get_nft_by_artifact(artifact_id: u64) -> Result<ProofOfHeritageNFT, Strin
 }
 
 #[query]
-pub fn get_nfts_by_owner(owner: candid::Principal) -> Vec<ProofOfHeritageNFT> {
-    let mut nfts = Vec::new();
-    NFTS.with(|nft_store| {
-        for (_, nft) in nft_store.borrow().iter() {
-            if nft.owner == owner {
-                nfts.push(nft.clone());
-            }
-        }
+pub fn get_nft_by_certificate_number(certificate_number: String) -> Result<ProofOfHeritageNFT, String> {
+    NFTS.with(|nfts| {
+        nfts.borrow().iter()
+            .map(|(_, nft)| nft)
+            .find(|nft| nft.heritage_certificate.certificate_number == certificate_number)
+            .ok_or_else(|| "Heritage certificate not found".to_string())
+    })
+}
+
+#[query]
+pub fn get_nfts_by_owner(owner: Principal) -> Vec<ProofOfHeritageNFT> {
+    let nft_ids = owned_nft_ids(owner, None, usize::MAX);
+    let mut nfts: Vec<ProofOfHeritageNFT> = NFTS.with(|nft_store| {
+        let nft_store = nft_store.borrow();
+        nft_ids.into_iter().filter_map(|nft_id| nft_store.get(&nft_id)).collect()
     });
     
     // Sort by creation date (newest first)
@@ -273,6 +295,65 @@ pub fn get_all_nfts() -> Vec<ProofOfHeritageNFT> {
     nfts
 }
 
+pub fn list_nfts(page: PageRequest) -> Page<ProofOfHeritageNFT> {
+    NFTS.with(|nft_store| {
+        let nft_store = nft_store.borrow();
+        paginate_newest_first(&nft_store, &page, nft_store.len(), Some)
+    })
+}
+
+// ============================================================================
+// OWNER INDEX
+// ============================================================================
+
+// Every write to NFTS goes through here so NFT_OWNER_INDEX follows transfers
+pub fn store_nft(nfts: &mut NFTStore, nft: ProofOfHeritageNFT) {
+    let (nft_id, owner) = (nft.id, nft.owner);
+    let previous = nfts.insert(nft_id, nft);
+    NFT_OWNER_INDEX.with(|index| {
+        let mut index = index.borrow_mut();
+        if let Some(previous) = previous.filter(|previous| previous.owner != owner) {
+            index.remove(&(previous.owner, nft_id));
+        }
+        index.insert((owner, nft_id), ());
+    });
+}
+
+// Recomputes the owner index from NFTS; used after upgrades
+pub fn rebuild_nft_owner_index() {
+    NFT_OWNER_INDEX.with(|index| {
+        let mut index = index.borrow_mut();
+        index.clear_new();
+        NFTS.with(|nfts| {
+            for (nft_id, nft) in nfts.borrow().iter() {
+                index.insert((nft.owner, nft_id), ());
+            }
+        });
+    });
+}
+
+// Ids of the NFTs held by `owner` after `prev`, in ascending order
+pub fn owned_nft_ids(owner: Principal, prev: Option<u64>, limit: usize) -> Vec<u64> {
+    let start = match prev {
+        Some(prev) => match prev.checked_add(1) {
+            Some(start) => start,
+            None => return Vec::new(),
+        },
+        None => 0,
+    };
+    NFT_OWNER_INDEX.with(|index| {
+        index.borrow()
+            .range((owner, start)..=(owner, u64::MAX))
+            .take(limit)
+            .map(|((_, nft_id), _)| nft_id)
+            .collect()
+    })
+}
+
+pub fn count_owned_nfts(owner: Principal) -> u64 {
+    NFT_OWNER_INDEX.with(|index| index.borrow().range((owner, 0)..=(owner, u64::MAX)).count() as u64)
+}
+
 fn get_user_role(principal: candid::Principal) -> Option<UserRole> {
     USERS.with(|users| {
         users.borrow().get(&principal)
@@ -0,0 +1,549 @@
+use candid::CandidType;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet};
+
+use crate::modules::types::*;
+use crate::modules::storage::ARTIFACTS;
+use crate::modules::indexes::{artifact_ids_with_any_term, artifact_term_counts, normalize_term, ArtifactIndex};
+use crate::modules::search::{parse_query, rank_artifacts};
+use crate::modules::utils::page_limit;
+
+// ============================================================================
+// FACETED ARTIFACT QUERIES
+// ============================================================================
+//
+// Every filter dimension is a list of accepted values (empty = no constraint);
+// values within a dimension are OR-ed and dimensions are AND-ed. Facet counts
+// are disjunctive: the counts of a dimension ignore that dimension's own
+// filter, so a catalogue can show how many results each alternative value
+// would give without the user first clearing their selection.
+//
+// Status, country and tag filters are answered from modules::indexes, so only
+// artifacts that can land in the results or in a facet count are decoded.
+
+const DATE_BUCKET_YEARS: i64 = 100;
+// Dating years are validated on write, but records predating that check may
+// hold anything; an artifact never spreads over more than this many buckets.
+const MAX_DATE_BUCKETS: usize = 200;
+const DATE_FACET_LIMIT_YEARS: i64 = 20_000;
+
+#[derive(CandidType, Serialize, Deserialize, Clone, Debug, Default)]
+pub struct ArtifactFilter {
+    // Full-text query, see modules::search for the syntax
+    pub text: Option<String>,
+    pub statuses: Vec<ArtifactStatus>,
+    pub verification_levels: Vec<VerificationLevel>,
+    pub significance_levels: Vec<SignificanceLevel>,
+    pub conservation_statuses: Vec<ConservationStatus>,
+    pub countries: Vec<String>,
+    pub regions: Vec<String>,
+    // Artifacts whose dating range overlaps [date_from, date_to]; negative years are BCE
+    pub date_from: Option<i64>,
+    pub date_to: Option<i64>,
+    pub materials: Vec<String>,
+    pub tags: Vec<String>,
+}
+
+#[derive(CandidType, Serialize, Deserialize, Clone, Debug, Default)]
+pub enum ArtifactSort {
+    // Only meaningful with a text query; falls back to Newest otherwise
+    Relevance,
+    #[default]
+    Newest,
+    Oldest,
+    RecentlyUpdated,
+    Name,
+    AuthenticityScore,
+}
+
+#[derive(CandidType, Serialize, Deserialize, Clone, Debug)]
+pub struct FacetCount {
+    pub value: String,
+    pub count: u64,
+}
+
+#[derive(CandidType, Serialize, Deserialize, Clone, Debug, Default)]
+pub struct ArtifactFacets {
+    pub statuses: Vec<FacetCount>,
+    pub verification_levels: Vec<FacetCount>,
+    pub significance_levels: Vec<FacetCount>,
+    pub conservation_statuses: Vec<FacetCount>,
+    pub countries: Vec<FacetCount>,
+    pub regions: Vec<FacetCount>,
+    // Centuries, as "start..end" years
+    pub date_ranges: Vec<FacetCount>,
+    pub materials: Vec<FacetCount>,
+    pub tags: Vec<FacetCount>,
+}
+
+#[derive(CandidType, Serialize, Deserialize, Clone, Debug)]
+pub struct ArtifactQueryResult {
+    // The cursor of this page is an offset into the sorted results
+    pub page: Page<Artifact>,
+    pub facets: ArtifactFacets,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+enum Dimension {
+    Status,
+    VerificationLevel,
+    SignificanceLevel,
+    ConservationStatus,
+    Country,
+    Region,
+    Date,
+    Material,
+    Tag,
+}
+
+const DIMENSIONS: [Dimension; 9] = [
+    Dimension::Status,
+    Dimension::VerificationLevel,
+    Dimension::SignificanceLevel,
+    Dimension::ConservationStatus,
+    Dimension::Country,
+    Dimension::Region,
+    Dimension::Date,
+    Dimension::Material,
+    Dimension::Tag,
+];
+
+fn normalize(value: &str) -> String {
+    value.trim().to_lowercase()
+}
+
+fn variant_name<T: std::fmt::Debug>(value: &T) -> String {
+    format!("{:?}", value)
+}
+
+fn dating_range(artifact: &Artifact) -> Option<(i64, i64)> {
+    let dating = artifact.dating_information.as_ref()?;
+    match (dating.date_range_start, dating.date_range_end) {
+        (Some(start), Some(end)) => Some((start.min(end), start.max(end))),
+        (Some(year), None) | (None, Some(year)) => Some((year, year)),
+        (None, None) => None,
+    }
+}
+
+fn date_bucket(year: i64) -> String {
+    let start = year.div_euclid(DATE_BUCKET_YEARS) * DATE_BUCKET_YEARS;
+    format!("{}..{}", start, start + DATE_BUCKET_YEARS - 1)
+}
+
+// Display values of an artifact for one dimension
+fn dimension_values(artifact: &Artifact, dimension: Dimension) -> Vec<String> {
+    let origin = artifact.geographic_origin.as_ref();
+    match dimension {
+        Dimension::Status => vec![variant_name(&artifact.status)],
+        Dimension::VerificationLevel => vec![variant_name(&artifact.verification_level)],
+        Dimension::SignificanceLevel => vec![variant_name(&artifact.cultural_significance.significance_level)],
+        Dimension::ConservationStatus => vec![variant_name(&artifact.conservation_status)],
+        Dimension::Country => origin.map(|origin| vec![origin.country.clone()]).unwrap_or_default(),
+        Dimension::Region => origin.and_then(|origin| origin.region.clone()).into_iter().collect(),
+        Dimension::Date => match dating_range(artifact) {
+            Some((start, end)) => {
+                let clamp = |year: i64| year.clamp(-DATE_FACET_LIMIT_YEARS, DATE_FACET_LIMIT_YEARS);
+                let first = clamp(start).div_euclid(DATE_BUCKET_YEARS);
+                let last = clamp(end).div_euclid(DATE_BUCKET_YEARS);
+                (first..=last)
+                    .take(MAX_DATE_BUCKETS)
+                    .map(|bucket| date_bucket(bucket * DATE_BUCKET_YEARS))
+                    .collect()
+            },
+            None => Vec::new(),
+        },
+        Dimension::Material => artifact.physical_properties.as_ref()
+            .map(|properties| properties.material.clone())
+            .unwrap_or_default(),
+        Dimension::Tag => artifact.cultural_significance.cultural_tags.clone(),
+    }
+}
+
+fn matches_any<T: std::fmt::Debug>(accepted: &[T], value: &T) -> bool {
+    accepted.is_empty() || accepted.iter().any(|candidate| variant_name(candidate) == variant_name(value))
+}
+
+fn matches_any_text(accepted: &[String], values: &[String]) -> bool {
+    accepted.is_empty() || accepted.iter().any(|candidate| {
+        let candidate = normalize(candidate);
+        values.iter().any(|value| normalize(value) == candidate)
+    })
+}
+
+fn matches_dimension(filter: &ArtifactFilter, artifact: &Artifact, dimension: Dimension) -> bool {
+    match dimension {
+        Dimension::Status => matches_any(&filter.statuses, &artifact.status),
+        Dimension::VerificationLevel => matches_any(&filter.verification_levels, &artifact.verification_level),
+        Dimension::SignificanceLevel => matches_any(&filter.significance_levels, &artifact.cultural_significance.significance_level),
+        Dimension::ConservationStatus => matches_any(&filter.conservation_statuses, &artifact.conservation_status),
+        Dimension::Country => matches_any_text(&filter.countries, &dimension_values(artifact, dimension)),
+        Dimension::Region => matches_any_text(&filter.regions, &dimension_values(artifact, dimension)),
+        Dimension::Date => {
+            if filter.date_from.is_none() && filter.date_to.is_none() {
+                return true;
+            }
+            dating_range(artifact).is_some_and(|(start, end)| {
+                filter.date_to.is_none_or(|to| start <= to) && filter.date_from.is_none_or(|from| end >= from)
+            })
+        },
+        Dimension::Material => matches_any_text(&filter.materials, &dimension_values(artifact, dimension)),
+        Dimension::Tag => matches_any_text(&filter.tags, &dimension_values(artifact, dimension)),
+    }
+}
+
+// Counts keyed by normalized value, remembering the first spelling seen
+#[derive(Default)]
+struct FacetCounter {
+    counts: BTreeMap<Dimension, BTreeMap<String, (String, u64)>>,
+}
+
+impl FacetCounter {
+    fn add(&mut self, artifact: &Artifact, dimension: Dimension) {
+        let mut values = dimension_values(artifact, dimension);
+        values.sort_by_key(|value| normalize(value));
+        values.dedup_by_key(|value| normalize(value));
+
+        let counts = self.counts.entry(dimension).or_default();
+        for value in values {
+            counts.entry(normalize(&value)).or_insert((value, 0)).1 += 1;
+        }
+    }
+
+    // Replaces one dimension's counts with those of its index. Used when that
+    // dimension is the only constraint, so its alternatives were never loaded;
+    // the spelling of a value not among the results comes from its newest artifact.
+    fn count_from_index(&mut self, dimension: Dimension, index: ArtifactIndex) {
+        let seen = self.counts.remove(&dimension).unwrap_or_default();
+        let counts = self.counts.entry(dimension).or_default();
+        for (term, count, newest) in artifact_term_counts(index) {
+            let spelled = |value: &String| normalize_term(value) == term;
+            let value = seen.values().map(|(value, _)| value).find(|value| spelled(value)).cloned()
+                .or_else(|| {
+                    let artifact = ARTIFACTS.with(|artifacts| artifacts.borrow().get(&newest))?;
+                    dimension_values(&artifact, dimension).into_iter().find(spelled)
+                })
+                .unwrap_or_else(|| String::from_utf8_lossy(&term).into_owned());
+            counts.insert(normalize(&value), (value, count));
+        }
+    }
+
+    fn take(&mut self, dimension: Dimension) -> Vec<FacetCount> {
+        let mut facets: Vec<FacetCount> = self.counts.remove(&dimension).unwrap_or_default()
+            .into_values()
+            .map(|(value, count)| FacetCount { value, count })
+            .collect();
+        facets.sort_by(|a, b| b.count.cmp(&a.count).then_with(|| a.value.cmp(&b.value)));
+        facets
+    }
+
+    fn into_facets(mut self) -> ArtifactFacets {
+        ArtifactFacets {
+            statuses: self.take(Dimension::Status),
+            verification_levels: self.take(Dimension::VerificationLevel),
+            significance_levels: self.take(Dimension::SignificanceLevel),
+            conservation_statuses: self.take(Dimension::ConservationStatus),
+            countries: self.take(Dimension::Country),
+            regions: self.take(Dimension::Region),
+            date_ranges: self.take(Dimension::Date),
+            materials: self.take(Dimension::Material),
+            tags: self.take(Dimension::Tag),
+        }
+    }
+}
+
+fn constrained_dimensions(filter: &ArtifactFilter) -> Vec<Dimension> {
+    DIMENSIONS.iter().copied()
+        .filter(|dimension| match dimension {
+            Dimension::Status => !filter.statuses.is_empty(),
+            Dimension::VerificationLevel => !filter.verification_levels.is_empty(),
+            Dimension::SignificanceLevel => !filter.significance_levels.is_empty(),
+            Dimension::ConservationStatus => !filter.conservation_statuses.is_empty(),
+            Dimension::Country => !filter.countries.is_empty(),
+            Dimension::Region => !filter.regions.is_empty(),
+            Dimension::Date => filter.date_from.is_some() || filter.date_to.is_some(),
+            Dimension::Material => !filter.materials.is_empty(),
+            Dimension::Tag => !filter.tags.is_empty(),
+        })
+        .collect()
+}
+
+// Ids accepted by each indexed dimension the filter constrains
+fn indexed_lookups(filter: &ArtifactFilter) -> Vec<(Dimension, ArtifactIndex, BTreeSet<u64>)> {
+    let lookups = [
+        (Dimension::Status, ArtifactIndex::Status, filter.statuses.iter().map(variant_name).collect()),
+        (Dimension::Country, ArtifactIndex::Country, filter.countries.clone()),
+        (Dimension::Tag, ArtifactIndex::Tag, filter.tags.clone()),
+    ];
+    lookups.into_iter()
+        .filter(|(_, _, terms): &(Dimension, ArtifactIndex, Vec<String>)| !terms.is_empty())
+        .filter_map(|(dimension, index, terms)| {
+            artifact_ids_with_any_term(index, &terms).map(|ids| (dimension, index, ids))
+        })
+        .collect()
+}
+
+pub fn query_artifacts(filter: ArtifactFilter, sort: ArtifactSort, page: PageRequest) -> ArtifactQueryResult {
+    // A text query narrows the candidates to its hits and provides relevance
+    let relevance: Option<BTreeMap<u64, f64>> = filter.text.as_ref()
+        .filter(|text| !text.trim().is_empty())
+        .map(|text| rank_artifacts(&parse_query(text)).into_iter().collect());
+
+    // An artifact matters when it fails at most one dimension, so with k
+    // indexed filters it must appear in at least k - 1 of their lookups. A
+    // single indexed filter narrows nothing that way, but when it is the only
+    // constraint its own facet can be counted from the index instead.
+    let lookups = indexed_lookups(&filter);
+    let mut facet_from_index = None;
+    let mut candidate_ids: Option<BTreeSet<u64>> = match lookups.as_slice() {
+        [] => None,
+        [(dimension, index, ids)] => {
+            let only_constraint = relevance.is_none() && constrained_dimensions(&filter) == [*dimension];
+            only_constraint.then(|| {
+                facet_from_index = Some((*dimension, *index));
+                ids.clone()
+            })
+        },
+        _ => {
+            let mut appearances: BTreeMap<u64, usize> = BTreeMap::new();
+            for (_, _, ids) in &lookups {
+                for artifact_id in ids {
+                    *appearances.entry(*artifact_id).or_default() += 1;
+                }
+            }
+            Some(appearances.into_iter()
+                .filter(|(_, count)| *count + 1 >= lookups.len())
+                .map(|(artifact_id, _)| artifact_id)
+                .collect())
+        },
+    };
+    if let Some(scores) = &relevance {
+        candidate_ids = Some(match candidate_ids {
+            Some(ids) => ids.into_iter().filter(|artifact_id| scores.contains_key(artifact_id)).collect(),
+            None => scores.keys().copied().collect(),
+        });
+    }
+
+    let mut facets = FacetCounter::default();
+    let mut results = Vec::new();
+
+    ARTIFACTS.with(|artifacts| {
+        let artifacts = artifacts.borrow();
+        let candidates: Box<dyn Iterator<Item = Artifact>> = match &candidate_ids {
+            Some(ids) => Box::new(ids.iter().filter_map(|artifact_id| artifacts.get(artifact_id))),
+            None => Box::new(artifacts.iter().map(|(_, artifact)| artifact)),
+        };
+
+        for artifact in candidates {
+            let failed: Vec<Dimension> = DIMENSIONS.iter().copied()
+                .filter(|dimension| !matches_dimension(&filter, &artifact, *dimension))
+                .collect();
+
+            match failed.as_slice() {
+                [] => {
+                    for dimension in DIMENSIONS {
+                        facets.add(&artifact, dimension);
+                    }
+                    results.push(artifact);
+                },
+                // Excluded only by its own dimension: still counts as an alternative there
+                [dimension] => facets.add(&artifact, *dimension),
+                _ => {},
+            }
+        }
+    });
+    if let Some((dimension, index)) = facet_from_index {
+        facets.count_from_index(dimension, index);
+    }
+
+    sort_artifacts(&mut results, &sort, relevance.as_ref());
+
+    let total = results.len() as u64;
+    let offset = (page.cursor.unwrap_or(0) as usize).min(results.len());
+    let end = (offset + page_limit(&page)).min(results.len());
+    let items = results.drain(offset..end).collect();
+
+    ArtifactQueryResult {
+        page: Page {
+            items,
+            next_cursor: (end < total as usize).then_some(end as u64),
+            total,
+        },
+        facets: facets.into_facets(),
+    }
+}
+
+fn sort_artifacts(artifacts: &mut [Artifact], sort: &ArtifactSort, relevance: Option<&BTreeMap<u64, f64>>) {
+    // Every ordering falls back to newest first so pages stay stable
+    let newest = |a: &Artifact, b: &Artifact| b.created_at.cmp(&a.created_at).then(b.id.cmp(&a.id));
+    match (sort, relevance) {
+        (ArtifactSort::Relevance, Some(scores)) => artifacts.sort_by(|a, b| {
+            let score = |artifact: &Artifact| scores.get(&artifact.id).copied().unwrap_or(0.0);
+            score(b).total_cmp(&score(a)).then_with(|| newest(a, b))
+        }),
+        (ArtifactSort::Relevance, None) | (ArtifactSort::Newest, _) => artifacts.sort_by(newest),
+        (ArtifactSort::Oldest, _) => artifacts.sort_by(|a, b| newest(b, a)),
+        (ArtifactSort::RecentlyUpdated, _) => artifacts.sort_by(|a, b| {
+            b.updated_at.cmp(&a.updated_at).then_with(|| newest(a, b))
+        }),
+        (ArtifactSort::Name, _) => artifacts.sort_by(|a, b| {
+            a.name.to_lowercase().cmp(&b.name.to_lowercase()).then_with(|| newest(a, b))
+        }),
+        (ArtifactSort::AuthenticityScore, _) => artifacts.sort_by(|a, b| {
+            b.authenticity_score.cmp(&a.authenticity_score).then_with(|| newest(a, b))
+        }),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::modules::artifacts::store_artifact;
+    use crate::modules::testing::{artifact_request, create_artifact_as, principal};
+
+    fn create(name: &str, country: &str, tags: &[&str], status: ArtifactStatus) -> u64 {
+        let mut request = artifact_request(name);
+        request.cultural_significance = Some(CulturalSignificance {
+            historical_period: None,
+            cultural_group: None,
+            significance_level: SignificanceLevel::Local,
+            unesco_status: None,
+            cultural_tags: tags.iter().map(|tag| tag.to_string()).collect(),
+        });
+        request.geographic_origin = Some(GeographicOrigin {
+            country: country.to_string(),
+            region: None,
+            city: None,
+            site_name: None,
+            coordinates: None,
+            discovery_context: None,
+        });
+        let artifact_id = create_artifact_as(principal(1), request);
+        update(artifact_id, |artifact| artifact.status = status);
+        artifact_id
+    }
+
+    // Writes straight to the store, bypassing request validation like a legacy record
+    fn update(artifact_id: u64, change: impl FnOnce(&mut Artifact)) {
+        ARTIFACTS.with(|artifacts| {
+            let mut artifacts = artifacts.borrow_mut();
+            let mut artifact = artifacts.get(&artifact_id).unwrap();
+            change(&mut artifact);
+            store_artifact(&mut artifacts, artifact);
+        });
+    }
+
+    fn run(filter: ArtifactFilter) -> ArtifactQueryResult {
+        query_artifacts(filter, ArtifactSort::Newest, PageRequest { cursor: None, limit: None })
+    }
+
+    fn ids(result: &ArtifactQueryResult) -> Vec<u64> {
+        result.page.items.iter().map(|artifact| artifact.id).collect()
+    }
+
+    fn counts(facets: &[FacetCount]) -> Vec<(&str, u64)> {
+        facets.iter().map(|facet| (facet.value.as_str(), facet.count)).collect()
+    }
+
+    // Egypt/Greece x Verified/Pending, newest last
+    fn catalogue() -> [u64; 4] {
+        [
+            create("Scarab", "Egypt", &["amulet"], ArtifactStatus::Verified),
+            create("Seal", "Egypt", &["seal"], ArtifactStatus::PendingVerification),
+            create("Eye amulet", "Greece", &["amulet"], ArtifactStatus::Verified),
+            create("Drachma", "Greece", &["coin"], ArtifactStatus::PendingVerification),
+        ]
+    }
+
+    #[test]
+    fn facet_counts_ignore_their_own_dimension() {
+        let [scarab, seal, ..] = catalogue();
+        let result = run(ArtifactFilter {
+            statuses: vec![ArtifactStatus::Verified],
+            countries: vec!["egypt".to_string()],
+            ..Default::default()
+        });
+
+        assert_eq!(ids(&result), vec![scarab]);
+        assert_eq!(counts(&result.facets.statuses), vec![("PendingVerification", 1), ("Verified", 1)]);
+        assert_eq!(counts(&result.facets.countries), vec![("Egypt", 1), ("Greece", 1)]);
+        assert_eq!(counts(&result.facets.tags), vec![("amulet", 1)]);
+
+        // Moving the seal out of Egypt drops it from the status alternatives
+        update(seal, |artifact| artifact.geographic_origin.as_mut().unwrap().country = "Nubia".to_string());
+        let result = run(ArtifactFilter {
+            statuses: vec![ArtifactStatus::Verified],
+            countries: vec!["Egypt".to_string()],
+            ..Default::default()
+        });
+        assert_eq!(counts(&result.facets.statuses), vec![("Verified", 1)]);
+    }
+
+    #[test]
+    fn a_lone_indexed_filter_counts_its_facet_from_the_index() {
+        let [scarab, _, eye, _] = catalogue();
+        let result = run(ArtifactFilter { statuses: vec![ArtifactStatus::Verified], ..Default::default() });
+
+        assert_eq!(ids(&result), vec![eye, scarab]);
+        assert_eq!(result.page.total, 2);
+        assert_eq!(counts(&result.facets.statuses), vec![("PendingVerification", 2), ("Verified", 2)]);
+        assert_eq!(counts(&result.facets.countries), vec![("Egypt", 1), ("Greece", 1)]);
+
+        // Values never loaded keep the spelling stored on their artifacts
+        let result = run(ArtifactFilter { tags: vec!["SEAL".to_string()], ..Default::default() });
+        assert_eq!(counts(&result.facets.tags), vec![("amulet", 2), ("coin", 1), ("seal", 1)]);
+        let result = run(ArtifactFilter { countries: vec!["greece".to_string()], ..Default::default() });
+        assert_eq!(counts(&result.facets.countries), vec![("Egypt", 2), ("Greece", 2)]);
+    }
+
+    #[test]
+    fn indexed_and_unindexed_filters_combine() {
+        let [scarab, _, eye, _] = catalogue();
+        update(eye, |artifact| artifact.geographic_origin.as_mut().unwrap().region = Some("Attica".to_string()));
+
+        let result = run(ArtifactFilter {
+            statuses: vec![ArtifactStatus::Verified],
+            regions: vec!["attica".to_string()],
+            ..Default::default()
+        });
+        assert_eq!(ids(&result), vec![eye]);
+        assert_eq!(counts(&result.facets.statuses), vec![("Verified", 1)]);
+        assert_eq!(counts(&result.facets.regions), vec![("Attica", 1)]);
+
+        let result = run(ArtifactFilter {
+            statuses: vec![ArtifactStatus::Verified],
+            countries: vec!["Egypt".to_string(), "Greece".to_string()],
+            tags: vec!["amulet".to_string()],
+            text: Some("scarab".to_string()),
+            ..Default::default()
+        });
+        assert_eq!(ids(&result), vec![scarab]);
+    }
+
+    #[test]
+    fn extreme_dating_ranges_produce_bounded_buckets() {
+        let [scarab, seal, ..] = catalogue();
+        update(scarab, |artifact| artifact.dating_information = Some(DatingInformation {
+            estimated_age: None,
+            dating_method: Vec::new(),
+            confidence_level: 0,
+            date_range_start: Some(i64::MIN),
+            date_range_end: Some(i64::MAX),
+        }));
+        update(seal, |artifact| artifact.dating_information = Some(DatingInformation {
+            estimated_age: None,
+            dating_method: Vec::new(),
+            confidence_level: 0,
+            date_range_start: Some(-1350),
+            date_range_end: Some(-1330),
+        }));
+
+        let result = run(ArtifactFilter::default());
+        let buckets = &result.facets.date_ranges;
+        assert_eq!(buckets.len(), MAX_DATE_BUCKETS);
+        assert!(buckets.iter().any(|bucket| bucket.value == "-20000..-19901"));
+        assert!(buckets.iter().any(|bucket| bucket.value == "-1400..-1301" && bucket.count == 2));
+
+        let dated = run(ArtifactFilter { date_from: Some(-1340), date_to: Some(-1340), ..Default::default() });
+        assert_eq!(ids(&dated), vec![seal, scarab]);
+    }
+}
@@ -0,0 +1,253 @@
+use ic_cdk::query;
+
+use crate::modules::types::*;
+use crate::modules::storage::{ARTIFACTS, PROPOSALS};
+use crate::modules::dao::{create_proposal, proposal_response};
+use crate::modules::governance::rule_for;
+use crate::modules::voting::{validate_ballot, vote_on_proposal};
+
+// ============================================================================
+// ARTIFACT VERIFICATION REVIEWS
+// ============================================================================
+//
+// An artifact is verified through a round: a VerifyArtifact proposal on that
+// artifact. Voting on an artifact votes in its open round, opening one first
+// if there is none, so the first reviewer to vote starts the round. An artifact
+// has at most one open round at a time; `create_proposal` refuses a second
+// one. A round is open while its proposal is Active or UnderReview. The ballot
+// is checked before a round is opened, so a refused vote opens nothing, and a
+// Verified artifact takes no more votes.
+
+const ROUND_VOTING_HOURS: u64 = 7 * 24;
+const MAX_TITLE_CHARS: usize = 200;
+
+fn is_open_round(proposal: &Proposal, artifact_id: u64) -> bool {
+    proposal.proposal_type == ProposalType::VerifyArtifact
+        && proposal.artifact_id == Some(artifact_id)
+        && matches!(proposal.status, ProposalStatus::Active | ProposalStatus::UnderReview)
+}
+
+pub fn open_verification_round(artifact_id: u64) -> Option<u64> {
+    PROPOSALS.with(|proposals| {
+        proposals.borrow().iter()
+            .find(|(_, proposal)| is_open_round(proposal, artifact_id))
+            .map(|(id, _)| id)
+    })
+}
+
+// Checked by `create_proposal` for every VerifyArtifact proposal
+pub fn ensure_no_open_round(artifact_id: u64) -> Result<(), String> {
+    match open_verification_round(artifact_id) {
+        Some(proposal_id) => Err(format!(
+            "Artifact {} already has an open verification round (proposal {})",
+            artifact_id, proposal_id
+        )),
+        None => Ok(()),
+    }
+}
+
+fn start_verification_round(artifact_id: u64) -> Result<u64, String> {
+    let name = ARTIFACTS.with(|artifacts| artifacts.borrow().get(&artifact_id).map(|artifact| artifact.name))
+        .ok_or_else(|| "Artifact not found".to_string())?;
+
+    create_proposal(CreateProposalRequest {
+        proposal_type: ProposalType::VerifyArtifact,
+        artifact_id: Some(artifact_id),
+        title: format!("Verify artifact {}: {}", artifact_id, name).chars().take(MAX_TITLE_CHARS).collect(),
+        description: format!(
+            "Community verification round for artifact {}, opened by the first review vote. \
+             If this proposal passes, the artifact is marked as DAO verified.",
+            artifact_id
+        ),
+        evidence: None,
+        voting_duration_hours: ROUND_VOTING_HOURS.max(rule_for(&ProposalType::VerifyArtifact).min_voting_hours),
+        execution_payload: Some(ProposalAction::VerifyArtifact),
+        required_expertise: None,
+        urgency_level: None,
+        quorum_required: None,
+        voting_mode: None,
+    })
+}
+
+// Votes in the artifact's open verification round and returns its proposal id
+pub fn vote_on_artifact(
+    artifact_id: u64,
+    vote_type: VoteType,
+    rationale: Option<String>,
+    expertise_relevance: Option<u32>,
+) -> Result<u64, String> {
+    let status = ARTIFACTS.with(|artifacts| artifacts.borrow().get(&artifact_id).map(|artifact| artifact.status))
+        .ok_or_else(|| "Artifact not found".to_string())?;
+    if matches!(status, ArtifactStatus::Verified) {
+        return Err(format!("Artifact {} is already verified", artifact_id));
+    }
+    validate_ballot(&rationale, expertise_relevance)?;
+
+    let proposal_id = match open_verification_round(artifact_id) {
+        Some(proposal_id) => proposal_id,
+        None => start_verification_round(artifact_id)?,
+    };
+
+    vote_on_proposal(VoteRequest {
+        proposal_id,
+        vote_type,
+        rationale,
+        expertise_relevance,
+        credits: None,
+    })?;
+    Ok(proposal_id)
+}
+
+// ============================================================================
+// ENDPOINTS
+// ============================================================================
+
+// Every verification round of an artifact, newest first
+#[query]
+pub fn get_artifact_verification_rounds(artifact_id: u64) -> Vec<ProposalResponse> {
+    PROPOSALS.with(|proposals| {
+        proposals.borrow().iter().rev()
+            .filter(|(_, proposal)| {
+                proposal.proposal_type == ProposalType::VerifyArtifact && proposal.artifact_id == Some(artifact_id)
+            })
+            .map(|(_, proposal)| proposal_response(&proposal))
+            .collect()
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::modules::artifacts::store_artifact;
+    use crate::modules::dao::{get_proposal, store_proposal};
+    use crate::modules::storage::VOTES;
+    use crate::modules::testing::{artifact_request, create_artifact_as, principal, register, set_caller, HOUR};
+
+    fn reviewed_artifact() -> u64 {
+        let artifact_id = create_artifact_as(principal(1), artifact_request("Votive figurine"));
+        for reviewer in 2..=4 {
+            register(principal(reviewer), UserRole::Expert, &["ceramics"]);
+        }
+        artifact_id
+    }
+
+    fn vote_as(reviewer: u8, artifact_id: u64, rationale: Option<&str>) -> Result<u64, String> {
+        set_caller(principal(reviewer));
+        vote_on_artifact(artifact_id, VoteType::For, rationale.map(str::to_string), None)
+    }
+
+    fn round_request(artifact_id: u64) -> CreateProposalRequest {
+        CreateProposalRequest {
+            proposal_type: ProposalType::VerifyArtifact,
+            artifact_id: Some(artifact_id),
+            title: "Verify the votive figurine".to_string(),
+            description: "A verification round opened directly rather than by a review vote.".to_string(),
+            evidence: None,
+            voting_duration_hours: ROUND_VOTING_HOURS,
+            execution_payload: None,
+            required_expertise: None,
+            urgency_level: None,
+            quorum_required: None,
+            voting_mode: None,
+        }
+    }
+
+    fn close_round(proposal_id: u64) {
+        PROPOSALS.with(|proposals| {
+            let mut proposals = proposals.borrow_mut();
+            let mut proposal = proposals.get(&proposal_id).unwrap();
+            proposal.status = ProposalStatus::Rejected;
+            store_proposal(&mut proposals, proposal);
+        });
+    }
+
+    #[test]
+    fn first_vote_opens_the_round_and_later_votes_join_it() {
+        let artifact_id = reviewed_artifact();
+        assert_eq!(open_verification_round(artifact_id), None);
+
+        let round = vote_as(2, artifact_id, None).unwrap();
+        assert_eq!(open_verification_round(artifact_id), Some(round));
+        let proposal = get_proposal(round).unwrap();
+        assert_eq!(proposal.proposal_type, ProposalType::VerifyArtifact);
+        assert_eq!(proposal.artifact_id, Some(artifact_id));
+        assert!(matches!(proposal.execution_payload, Some(ProposalAction::VerifyArtifact)));
+        let hours = ROUND_VOTING_HOURS.max(rule_for(&ProposalType::VerifyArtifact).min_voting_hours);
+        assert_eq!(proposal.voting_deadline - proposal.created_at, hours * HOUR);
+
+        assert_eq!(vote_as(3, artifact_id, None), Ok(round));
+        let rounds = get_artifact_verification_rounds(artifact_id);
+        assert_eq!(rounds.len(), 1);
+        assert_eq!(get_proposal(round).unwrap().voting_results.total_votes, 2);
+    }
+
+    #[test]
+    fn refused_votes_open_no_round() {
+        let artifact_id = reviewed_artifact();
+        assert_eq!(vote_as(2, 999, None).unwrap_err(), "Artifact not found");
+
+        // The ballot is checked before a round is opened in the voter's name
+        assert!(vote_as(2, artifact_id, Some("short")).is_err());
+        set_caller(principal(2));
+        assert_eq!(
+            vote_on_artifact(artifact_id, VoteType::For, None, Some(101)).unwrap_err(),
+            "Expertise relevance cannot exceed 100%"
+        );
+        assert_eq!(open_verification_round(artifact_id), None);
+
+        let round = vote_as(3, artifact_id, None).unwrap();
+        assert_eq!(get_proposal(round).unwrap().proposer, principal(3));
+        assert_eq!(vote_as(3, artifact_id, None).unwrap_err(), "You have already voted on this proposal");
+        assert_eq!(get_artifact_verification_rounds(artifact_id).len(), 1);
+    }
+
+    #[test]
+    fn votes_keep_their_relevance_until_the_artifact_is_verified() {
+        let artifact_id = reviewed_artifact();
+        set_caller(principal(2));
+        let round = vote_on_artifact(artifact_id, VoteType::For, None, Some(80)).unwrap();
+        let relevance = VOTES.with(|votes| {
+            votes.borrow().iter()
+                .find(|(_, vote)| vote.proposal_id == round && vote.voter == principal(2))
+                .map(|(_, vote)| vote.expertise_relevance)
+        });
+        assert_eq!(relevance, Some(80));
+
+        ARTIFACTS.with(|artifacts| {
+            let mut artifacts = artifacts.borrow_mut();
+            let mut artifact = artifacts.get(&artifact_id).unwrap();
+            artifact.status = ArtifactStatus::Verified;
+            store_artifact(&mut artifacts, artifact);
+        });
+        close_round(round);
+        assert_eq!(vote_as(3, artifact_id, None).unwrap_err(), format!("Artifact {} is already verified", artifact_id));
+        assert_eq!(open_verification_round(artifact_id), None);
+    }
+
+    #[test]
+    fn an_artifact_has_one_open_round_at_a_time() {
+        let artifact_id = reviewed_artifact();
+        set_caller(principal(1));
+        let first = create_proposal(round_request(artifact_id)).unwrap();
+        assert_eq!(
+            create_proposal(round_request(artifact_id)).unwrap_err(),
+            format!("Artifact {} already has an open verification round (proposal {})", artifact_id, first)
+        );
+        // Votes go to the round opened through create_proposal
+        assert_eq!(vote_as(2, artifact_id, None), Ok(first));
+
+        // Other proposal types on the artifact are not rounds
+        let mut dispute = round_request(artifact_id);
+        dispute.proposal_type = ProposalType::DisputeArtifact;
+        dispute.execution_payload = Some(ProposalAction::DisputeArtifact { reason: "Modern replica".to_string() });
+        set_caller(principal(1));
+        assert!(create_proposal(dispute).is_ok());
+
+        // Once the round closes the next vote opens a new one
+        close_round(first);
+        let second = vote_as(3, artifact_id, None).unwrap();
+        assert_ne!(second, first);
+        let rounds: Vec<u64> = get_artifact_verification_rounds(artifact_id).iter().map(|round| round.id).collect();
+        assert_eq!(rounds, vec![second, first]);
+    }
+}
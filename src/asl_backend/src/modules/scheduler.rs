@@ -0,0 +1,460 @@
+use candid::CandidType;
+use ic_cdk::{query, update};
+use ic_cdk_timers::TimerId;
+use serde::{Deserialize, Serialize};
+use std::cell::RefCell;
+use std::time::Duration;
+
+use crate::modules::types::*;
+use crate::modules::storage::{PROPOSALS, PROPOSAL_SCHEDULE, SCHEDULER_CONFIG};
+use crate::modules::auth::get_caller;
+use crate::modules::utils::get_time;
+use crate::modules::audit::log_audit_event;
+use crate::modules::dao::{execute_passed_proposal, rebuild_proposal_schedule, store_proposal};
+use crate::modules::custody::close_unapproved_transfer;
+use crate::modules::voting::{effective_voting_deadline, finalize_proposal_voting};
+
+// ============================================================================
+// PROPOSAL SCHEDULER
+// ============================================================================
+//
+// Voting only finalizes a proposal when somebody votes on it, so without this
+// a proposal whose deadline passes quietly would stay Active forever. A timer
+// runs every `interval_seconds` and moves proposals along:
+//
+//   Active, voting period over        -> Passed, Rejected or Expired
+//   Passed, execution deadline over   -> Expired
+//   Passed, type in auto_execute_types -> Executed or FailedExecution
+//
+// Auto-execution is off by default and only covers proposal types whose
+// payload is reversible by a later proposal (AUTO_EXECUTABLE_TYPES). Role
+// changes, custody approvals, criteria changes and emergency interventions
+// always wait for the proposer or a moderator to call `execute_proposal`.
+//
+// PROPOSAL_SCHEDULE keys every open proposal by the time it next becomes due,
+// so a run reads only due proposals and never scans PROPOSALS. A run handles at
+// most MAX_PROPOSALS_PER_RUN of them; the rest stay due and the next run picks
+// them up.
+
+const DEFAULT_INTERVAL_SECONDS: u64 = 300;
+const MIN_INTERVAL_SECONDS: u64 = 60;
+pub const MAX_PROPOSALS_PER_RUN: usize = 50;
+
+pub const AUTO_EXECUTABLE_TYPES: &[ProposalType] = &[
+    ProposalType::VerifyArtifact,
+    ProposalType::UpdateArtifactMetadata,
+    ProposalType::RequestAdditionalEvidence,
+    ProposalType::ProposeConservationAction,
+    ProposalType::RequestExpertReview,
+];
+
+#[derive(CandidType, Serialize, Deserialize, Clone, Debug)]
+pub struct SchedulerConfig {
+    pub enabled: bool,
+    pub interval_seconds: u64,
+    pub auto_execute: bool,
+    pub auto_execute_types: Vec<ProposalType>,
+}
+
+impl Default for SchedulerConfig {
+    fn default() -> Self {
+        SchedulerConfig {
+            enabled: true,
+            interval_seconds: DEFAULT_INTERVAL_SECONDS,
+            auto_execute: false,
+            auto_execute_types: AUTO_EXECUTABLE_TYPES.to_vec(),
+        }
+    }
+}
+
+#[derive(CandidType, Serialize, Deserialize, Clone, Debug, Default)]
+pub struct SchedulerRun {
+    pub ran_at: u64,
+    pub finalized: Vec<u64>,
+    pub expired: Vec<u64>,
+    pub executed: Vec<u64>,
+    pub failed: Vec<u64>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum LifecycleStep {
+    FinalizeVoting,
+    ExpireExecution,
+    AutoExecute,
+}
+
+thread_local! {
+    static PROPOSAL_TIMER: RefCell<Option<TimerId>> = const { RefCell::new(None) };
+    static LAST_RUN: RefCell<Option<SchedulerRun>> = const { RefCell::new(None) };
+}
+
+// What the scheduler does next with a proposal, if anything
+pub fn lifecycle_step(proposal: &Proposal, config: &SchedulerConfig, now: u64) -> Option<LifecycleStep> {
+    match proposal.status {
+        ProposalStatus::Active if now > effective_voting_deadline(proposal) => Some(LifecycleStep::FinalizeVoting),
+        ProposalStatus::Passed => {
+            if proposal.execution_deadline.is_some_and(|deadline| now > deadline) {
+                Some(LifecycleStep::ExpireExecution)
+            } else if config.auto_execute && config.auto_execute_types.contains(&proposal.proposal_type) {
+                Some(LifecycleStep::AutoExecute)
+            } else {
+                None
+            }
+        },
+        _ => None,
+    }
+}
+
+// The earliest time `lifecycle_step` can return a step for the proposal, or
+// None once the scheduler is done with it
+pub fn proposal_due_at(proposal: &Proposal, config: &SchedulerConfig) -> Option<u64> {
+    match proposal.status {
+        ProposalStatus::Active => Some(effective_voting_deadline(proposal).saturating_add(1)),
+        ProposalStatus::Passed if config.auto_execute && config.auto_execute_types.contains(&proposal.proposal_type) => Some(0),
+        ProposalStatus::Passed => proposal.execution_deadline.map(|deadline| deadline.saturating_add(1)),
+        _ => None,
+    }
+}
+
+pub fn run_proposal_lifecycle(now: u64) -> SchedulerRun {
+    let config = get_scheduler_config();
+    let mut run = SchedulerRun { ran_at: now, ..Default::default() };
+
+    let due: Vec<(u64, u64)> = PROPOSAL_SCHEDULE.with(|schedule| {
+        schedule.borrow()
+            .range(..=(now, u64::MAX))
+            .take(MAX_PROPOSALS_PER_RUN)
+            .map(|(key, _)| key)
+            .collect()
+    });
+
+    for (due_at, proposal_id) in due {
+        let Some(mut proposal) = PROPOSALS.with(|proposals| proposals.borrow().get(&proposal_id)) else {
+            PROPOSAL_SCHEDULE.with(|schedule| schedule.borrow_mut().remove(&(due_at, proposal_id)));
+            continue;
+        };
+
+        match lifecycle_step(&proposal, &config, now) {
+            Some(LifecycleStep::FinalizeVoting) => {
+                finalize_proposal_voting(&mut proposal, now);
+                let next_step = lifecycle_step(&proposal, &config, now);
+                PROPOSALS.with(|proposals| store_proposal(&mut proposals.borrow_mut(), proposal));
+                run.finalized.push(proposal_id);
+                // Finalizing can pass a proposal, which may then be auto-executed in the same run
+                if next_step == Some(LifecycleStep::AutoExecute) {
+                    auto_execute(proposal_id, now, &mut run);
+                }
+            },
+            Some(LifecycleStep::ExpireExecution) => {
+                proposal.status = ProposalStatus::Expired;
+                close_unapproved_transfer(&proposal);
+                PROPOSALS.with(|proposals| store_proposal(&mut proposals.borrow_mut(), proposal));
+                run.expired.push(proposal_id);
+            },
+            Some(LifecycleStep::AutoExecute) => auto_execute(proposal_id, now, &mut run),
+            // Left over from an older configuration
+            None => PROPOSAL_SCHEDULE.with(|schedule| {
+                let mut schedule = schedule.borrow_mut();
+                schedule.remove(&(due_at, proposal_id));
+                if let Some(due_at) = proposal_due_at(&proposal, &config) {
+                    schedule.insert((due_at, proposal_id), ());
+                }
+            }),
+        }
+    }
+
+    if !run.finalized.is_empty() || !run.expired.is_empty() {
+        log_audit_event(
+            AuditEventType::SystemMaintenance,
+            None,
+            format!(
+                "Proposal scheduler finalized voting on {:?} and expired unexecuted proposals {:?}",
+                run.finalized, run.expired
+            ),
+            AuditSeverity::Info
+        );
+    }
+
+    LAST_RUN.with(|last| *last.borrow_mut() = Some(run.clone()));
+    run
+}
+
+// execute_passed_proposal logs the outcome itself
+fn auto_execute(proposal_id: u64, now: u64, run: &mut SchedulerRun) {
+    match execute_passed_proposal(proposal_id, now) {
+        Ok(_) => run.executed.push(proposal_id),
+        Err(_) => run.failed.push(proposal_id),
+    }
+}
+
+// Called from init and post_upgrade (timers do not survive upgrades) and
+// whenever the configuration changes
+pub fn start_proposal_scheduler() {
+    PROPOSAL_TIMER.with(|timer| {
+        if let Some(timer_id) = timer.borrow_mut().take() {
+            ic_cdk_timers::clear_timer(timer_id);
+        }
+
+        let config = get_scheduler_config();
+        if config.enabled {
+            let interval = Duration::from_secs(config.interval_seconds.max(MIN_INTERVAL_SECONDS));
+            let timer_id = ic_cdk_timers::set_timer_interval(interval, || {
+                run_proposal_lifecycle(get_time());
+            });
+            *timer.borrow_mut() = Some(timer_id);
+        }
+    });
+}
+
+// ============================================================================
+// ENDPOINTS
+// ============================================================================
+
+#[query]
+pub fn get_scheduler_config() -> SchedulerConfig {
+    SCHEDULER_CONFIG.with(|config| config.borrow().get().clone())
+}
+
+#[query]
+pub fn get_last_scheduler_run() -> Option<SchedulerRun> {
+    LAST_RUN.with(|last| last.borrow().clone())
+}
+
+// Controllers only
+#[update]
+pub fn set_scheduler_config(config: SchedulerConfig) -> Result<String, String> {
+    let caller = get_caller();
+    if !ic_cdk::api::is_controller(&caller) {
+        return Err("Only controllers can configure the proposal scheduler".to_string());
+    }
+    if config.interval_seconds < MIN_INTERVAL_SECONDS {
+        return Err(format!("Scheduler interval must be at least {} seconds", MIN_INTERVAL_SECONDS));
+    }
+    if let Some(unsafe_type) = config.auto_execute_types.iter().find(|t| !AUTO_EXECUTABLE_TYPES.contains(t)) {
+        return Err(format!("{:?} proposals cannot be executed automatically", unsafe_type));
+    }
+
+    let previous = get_scheduler_config();
+    SCHEDULER_CONFIG.with(|cell| {
+        cell.borrow_mut().set(config.clone()).expect("Failed to save scheduler config");
+    });
+    // Passed proposals are due at once only while they can be auto-executed
+    if previous.auto_execute != config.auto_execute || previous.auto_execute_types != config.auto_execute_types {
+        rebuild_proposal_schedule();
+    }
+    start_proposal_scheduler();
+
+    log_audit_event(
+        AuditEventType::SystemMaintenance,
+        None,
+        format!(
+            "Proposal scheduler {}, every {}s, auto-execution {} for {:?}",
+            if config.enabled { "enabled" } else { "disabled" },
+            config.interval_seconds,
+            if config.auto_execute { "on" } else { "off" },
+            config.auto_execute_types
+        ),
+        AuditSeverity::Info
+    );
+    Ok("Scheduler configuration updated".to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use candid::Principal;
+    use std::collections::BTreeSet;
+    use crate::modules::custody::{get_custody_transfer, CustodyTransfer, CustodyTransferReason, CustodyTransferStatus};
+    use crate::modules::dao::{create_proposal, get_proposal};
+    use crate::modules::governance::AbstentionPolicy;
+    use crate::modules::storage::{ARTIFACTS, CUSTODY_TRANSFERS};
+    use crate::modules::testing::{advance_time, artifact_request, create_artifact_as, now, principal, register, set_caller, HOUR};
+    use crate::modules::voting::vote_on_proposal;
+
+    fn proposal(proposal_type: ProposalType, status: ProposalStatus, urgency_level: UrgencyLevel) -> Proposal {
+        Proposal {
+            id: 1,
+            proposal_type,
+            artifact_id: Some(1),
+            proposer: Principal::anonymous(),
+            title: String::new(),
+            description: String::new(),
+            evidence: Vec::new(),
+            created_at: 0,
+            voting_deadline: 10 * HOUR,
+            execution_deadline: Some(34 * HOUR),
+            quorum_required: 1,
+            pass_threshold_percent: 50,
+            abstention: AbstentionPolicy::CountTowardQuorum,
+            status,
+            voting_results: VotingResults {
+                total_votes: 0,
+                votes_for: 0,
+                votes_against: 0,
+                abstentions: 0,
+                weighted_score: 0.0,
+                voter_principals: BTreeSet::new(),
+                expert_consensus: None,
+                breakdown: None,
+            },
+            execution_payload: None,
+            discussion_thread: Vec::new(),
+            required_expertise: Vec::new(),
+            urgency_level,
+            voting_mode: VotingMode::Linear,
+        }
+    }
+
+    fn store(proposal: Proposal) {
+        PROPOSALS.with(|proposals| store_proposal(&mut proposals.borrow_mut(), proposal));
+    }
+
+    fn status(proposal_id: u64) -> ProposalStatus {
+        get_proposal(proposal_id).unwrap().status
+    }
+
+    // A verification round on a fresh artifact, open to experts 2 to 4
+    fn open_round(name: &str) -> u64 {
+        let artifact_id = create_artifact_as(principal(1), artifact_request(name));
+        set_caller(principal(1));
+        create_proposal(CreateProposalRequest {
+            proposal_type: ProposalType::VerifyArtifact,
+            artifact_id: Some(artifact_id),
+            title: format!("Verify the {}", name),
+            description: "A verification round left for the scheduler to finalize.".to_string(),
+            evidence: None,
+            voting_duration_hours: 24,
+            execution_payload: None,
+            required_expertise: None,
+            urgency_level: None,
+            quorum_required: None,
+            voting_mode: None,
+        }).unwrap()
+    }
+
+    fn vote(voter: u8, proposal_id: u64, vote_type: VoteType) {
+        set_caller(principal(voter));
+        vote_on_proposal(VoteRequest { proposal_id, vote_type, rationale: None, expertise_relevance: None, credits: None }).unwrap();
+    }
+
+    #[test]
+    fn finalizes_stored_proposals_once_voting_ends() {
+        for expert in 2..=4 {
+            register(principal(expert), UserRole::Expert, &["ceramics"]);
+        }
+        let passing = open_round("amphora");
+        let failing = open_round("oil lamp");
+        let unvoted = open_round("figurine");
+        for expert in 2..=4 {
+            vote(expert, passing, VoteType::For);
+            vote(expert, failing, VoteType::Against);
+        }
+
+        assert!(run_proposal_lifecycle(now()).finalized.is_empty());
+        advance_time(24 * HOUR + 1);
+        let run = run_proposal_lifecycle(now());
+        assert_eq!(run.finalized, vec![passing, failing, unvoted]);
+        assert_eq!(status(passing), ProposalStatus::Passed);
+        assert_eq!(status(failing), ProposalStatus::Rejected);
+        assert_eq!(status(unvoted), ProposalStatus::Expired);
+
+        // Finalized proposals are no longer due
+        let again = run_proposal_lifecycle(now());
+        assert!(again.finalized.is_empty() && again.expired.is_empty());
+    }
+
+    #[test]
+    fn expires_stored_proposals_and_closes_their_custody_transfer() {
+        let mut approval = proposal(ProposalType::ApproveCustodyTransfer, ProposalStatus::Passed, UrgencyLevel::Normal);
+        approval.execution_payload = Some(ProposalAction::ApproveCustodyTransfer { transfer_id: 1 });
+        CUSTODY_TRANSFERS.with(|transfers| transfers.borrow_mut().insert(1, CustodyTransfer {
+            id: 1,
+            nft_id: 1,
+            artifact_id: 1,
+            from: principal(1),
+            to: principal(2),
+            reason: CustodyTransferReason::Loan,
+            terms: "Returned after the exhibition".to_string(),
+            documentation: Vec::new(),
+            status: CustodyTransferStatus::AwaitingApproval,
+            created_at: 0,
+            expires_at: 100 * HOUR,
+            accepted_at: Some(0),
+            completed_at: None,
+            proposal_id: Some(1),
+        }));
+        store(approval);
+
+        assert!(run_proposal_lifecycle(34 * HOUR).expired.is_empty());
+        assert_eq!(run_proposal_lifecycle(34 * HOUR + 1).expired, vec![1]);
+        assert_eq!(status(1), ProposalStatus::Expired);
+        assert_eq!(get_custody_transfer(1).unwrap().status, CustodyTransferStatus::Rejected);
+    }
+
+    #[test]
+    fn auto_executes_stored_proposals_once_enabled() {
+        let artifact_id = create_artifact_as(principal(1), artifact_request("Amphora"));
+        let mut verify = proposal(ProposalType::VerifyArtifact, ProposalStatus::Passed, UrgencyLevel::Normal);
+        verify.artifact_id = Some(artifact_id);
+        verify.execution_payload = Some(ProposalAction::VerifyArtifact);
+        store(verify);
+        assert!(run_proposal_lifecycle(11 * HOUR).executed.is_empty());
+
+        SCHEDULER_CONFIG.with(|config| {
+            config.borrow_mut().set(SchedulerConfig { auto_execute: true, ..Default::default() }).unwrap();
+        });
+        rebuild_proposal_schedule();
+        assert_eq!(run_proposal_lifecycle(11 * HOUR).executed, vec![1]);
+        assert_eq!(status(1), ProposalStatus::Executed);
+        let artifact = ARTIFACTS.with(|artifacts| artifacts.borrow().get(&artifact_id).unwrap());
+        assert!(matches!(artifact.status, ArtifactStatus::Verified));
+    }
+
+    #[test]
+    fn caps_each_run_and_resumes_on_the_next() {
+        let count = MAX_PROPOSALS_PER_RUN as u64 + 1;
+        for id in 1..=count {
+            store(Proposal { id, ..proposal(ProposalType::RequestExpertReview, ProposalStatus::Active, UrgencyLevel::Normal) });
+        }
+
+        let first = run_proposal_lifecycle(10 * HOUR + 1);
+        assert_eq!(first.finalized, (1..count).collect::<Vec<u64>>());
+        assert_eq!(run_proposal_lifecycle(10 * HOUR + 1).finalized, vec![count]);
+        assert!(run_proposal_lifecycle(10 * HOUR + 1).finalized.is_empty());
+    }
+
+    #[test]
+    fn finalizes_active_proposals_after_the_voting_period() {
+        let config = SchedulerConfig::default();
+        let active = proposal(ProposalType::VerifyArtifact, ProposalStatus::Active, UrgencyLevel::Normal);
+        assert_eq!(lifecycle_step(&active, &config, 10 * HOUR), None);
+        assert_eq!(lifecycle_step(&active, &config, 10 * HOUR + 1), Some(LifecycleStep::FinalizeVoting));
+
+        // Emergency proposals keep their two hour voting grace period
+        let emergency = proposal(ProposalType::EmergencyIntervention, ProposalStatus::Active, UrgencyLevel::Emergency);
+        assert_eq!(lifecycle_step(&emergency, &config, 11 * HOUR), None);
+        assert_eq!(lifecycle_step(&emergency, &config, 12 * HOUR + 1), Some(LifecycleStep::FinalizeVoting));
+    }
+
+    #[test]
+    fn expires_passed_proposals_after_the_execution_deadline() {
+        let config = SchedulerConfig { auto_execute: true, ..Default::default() };
+        let passed = proposal(ProposalType::VerifyArtifact, ProposalStatus::Passed, UrgencyLevel::Normal);
+        assert_eq!(lifecycle_step(&passed, &config, 34 * HOUR + 1), Some(LifecycleStep::ExpireExecution));
+
+        let executed = proposal(ProposalType::VerifyArtifact, ProposalStatus::Executed, UrgencyLevel::Normal);
+        assert_eq!(lifecycle_step(&executed, &config, 34 * HOUR + 1), None);
+    }
+
+    #[test]
+    fn auto_executes_only_configured_types() {
+        let passed = proposal(ProposalType::VerifyArtifact, ProposalStatus::Passed, UrgencyLevel::Normal);
+        assert_eq!(lifecycle_step(&passed, &SchedulerConfig::default(), 11 * HOUR), None);
+
+        let config = SchedulerConfig { auto_execute: true, ..Default::default() };
+        assert_eq!(lifecycle_step(&passed, &config, 11 * HOUR), Some(LifecycleStep::AutoExecute));
+
+        let role_change = proposal(ProposalType::GrantUserRole, ProposalStatus::Passed, UrgencyLevel::Normal);
+        assert_eq!(lifecycle_step(&role_change, &config, 11 * HOUR), None);
+    }
+}
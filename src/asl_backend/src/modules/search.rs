@@ -0,0 +1,796 @@
+use candid::CandidType;
+use ic_stable_structures::Storable;
+use serde::{Deserialize, Serialize};
+use std::borrow::Cow;
+use std::collections::{BTreeMap, BTreeSet};
+
+use crate::modules::types::*;
+use crate::modules::storage::{ARTIFACTS, SEARCH_DOCUMENTS, SEARCH_POSTINGS, SEARCH_STATS};
+use crate::modules::utils::page_limit;
+
+// ============================================================================
+// FULL-TEXT SEARCH
+// ============================================================================
+//
+// An inverted index over artifact text, kept in stable memory:
+//
+//   SEARCH_POSTINGS:  term | 0x00 | artifact_id (u64 BE) -> Posting
+//   SEARCH_DOCUMENTS: artifact_id -> document length in tokens
+//   SEARCH_STATS:     index version and total token count, for BM25
+//
+// Terms never contain 0x00, so all postings of a term are contiguous and all
+// terms sharing a prefix are contiguous too, which is what prefix queries scan.
+//
+// Text is folded before tokenizing: lowercase, Latin diacritics and
+// transliteration marks removed (Ḥatšepsut -> hatsepsut), Arabic harakat and
+// tatweel removed, alef/ya/ta marbuta variants unified and Arabic-Indic digits
+// mapped to ASCII, so queries match regardless of how a name was typed.
+
+// Bump whenever folding, tokenizing or field weights change; post_upgrade
+// rebuilds the index when the stored version differs.
+const SEARCH_INDEX_VERSION: u32 = 1;
+
+const MAX_TERM_BYTES: usize = 64;
+// Only the first MAX_POSITIONS_PER_TERM occurrences of a term in an artifact
+// keep their position, which bounds the size of a Posting. The weight still
+// counts every occurrence, so ranking is unaffected, but a phrase whose only
+// occurrence starts past that point is not found.
+const MAX_POSITIONS_PER_TERM: usize = 64;
+// Keeps phrases from matching across two fields
+const FIELD_POSITION_GAP: u32 = 16;
+const MIN_PREFIX_CHARS: usize = 2;
+const MAX_PREFIX_EXPANSIONS: usize = 50;
+const SNIPPET_CONTEXT_CHARS: usize = 40;
+const SNIPPET_MAX_CHARS: usize = 160;
+const MAX_SNIPPETS_PER_HIT: usize = 3;
+
+// BM25 parameters
+const BM25_K1: f64 = 1.2;
+const BM25_B: f64 = 0.75;
+
+#[derive(CandidType, Serialize, Deserialize, Clone, Debug)]
+pub struct SearchSnippet {
+    pub field: String,
+    pub text: String,
+    // Character offsets into `text` of each matched token
+    pub highlights: Vec<(u32, u32)>,
+}
+
+#[derive(CandidType, Serialize, Deserialize, Clone, Debug)]
+pub struct SearchHit {
+    pub artifact: Artifact,
+    pub score: f64,
+    pub snippets: Vec<SearchSnippet>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub struct PostingKey(Vec<u8>);
+
+impl Storable for PostingKey {
+    const BOUND: ic_stable_structures::storable::Bound = ic_stable_structures::storable::Bound::Bounded {
+        max_size: (MAX_TERM_BYTES + 1 + 8) as u32,
+        is_fixed_size: false,
+    };
+
+    fn to_bytes(&self) -> Cow<'_, [u8]> {
+        Cow::Borrowed(&self.0)
+    }
+
+    fn from_bytes(bytes: Cow<[u8]>) -> Self {
+        PostingKey(bytes.into_owned())
+    }
+}
+
+impl PostingKey {
+    fn new(term: &str, artifact_id: u64) -> Self {
+        let mut key = Vec::with_capacity(term.len() + 1 + 8);
+        key.extend_from_slice(term.as_bytes());
+        key.push(0);
+        key.extend_from_slice(&artifact_id.to_be_bytes());
+        PostingKey(key)
+    }
+
+    fn term(&self) -> &[u8] {
+        &self.0[..self.0.len() - 9]
+    }
+
+    fn artifact_id(&self) -> u64 {
+        let mut id_bytes = [0u8; 8];
+        id_bytes.copy_from_slice(&self.0[self.0.len() - 8..]);
+        u64::from_be_bytes(id_bytes)
+    }
+}
+
+// Occurrences of one term in one artifact. `weight` is the field-weighted
+// term frequency used for ranking; `positions` drive phrase matching.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Posting {
+    pub weight: u32,
+    pub positions: Vec<u32>,
+}
+
+impl Storable for Posting {
+    const BOUND: ic_stable_structures::storable::Bound = ic_stable_structures::storable::Bound::Bounded {
+        max_size: (4 + 4 * MAX_POSITIONS_PER_TERM) as u32,
+        is_fixed_size: false,
+    };
+
+    fn to_bytes(&self) -> Cow<'_, [u8]> {
+        let mut bytes = Vec::with_capacity(4 + 4 * self.positions.len());
+        bytes.extend_from_slice(&self.weight.to_be_bytes());
+        for position in &self.positions {
+            bytes.extend_from_slice(&position.to_be_bytes());
+        }
+        Cow::Owned(bytes)
+    }
+
+    fn from_bytes(bytes: Cow<[u8]>) -> Self {
+        let mut words = bytes.chunks_exact(4)
+            .map(|chunk| u32::from_be_bytes([chunk[0], chunk[1], chunk[2], chunk[3]]));
+        Posting {
+            weight: words.next().unwrap_or(0),
+            positions: words.collect(),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct SearchStats {
+    pub index_version: u32,
+    pub total_length: u64,
+}
+
+impl Storable for SearchStats {
+    const BOUND: ic_stable_structures::storable::Bound = ic_stable_structures::storable::Bound::Bounded {
+        max_size: 12,
+        is_fixed_size: true,
+    };
+
+    fn to_bytes(&self) -> Cow<'_, [u8]> {
+        let mut bytes = Vec::with_capacity(12);
+        bytes.extend_from_slice(&self.index_version.to_be_bytes());
+        bytes.extend_from_slice(&self.total_length.to_be_bytes());
+        Cow::Owned(bytes)
+    }
+
+    fn from_bytes(bytes: Cow<[u8]>) -> Self {
+        let mut version = [0u8; 4];
+        let mut total = [0u8; 8];
+        version.copy_from_slice(&bytes[..4]);
+        total.copy_from_slice(&bytes[4..12]);
+        SearchStats {
+            index_version: u32::from_be_bytes(version),
+            total_length: u64::from_be_bytes(total),
+        }
+    }
+}
+
+// ============================================================================
+// NORMALIZATION AND TOKENIZATION
+// ============================================================================
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Token {
+    pub term: String,
+    // Byte range of the token in the original text
+    pub start: usize,
+    pub end: usize,
+}
+
+enum Folded {
+    Separator,
+    // Diacritics and marks that belong to the surrounding word
+    Ignored,
+    Text(&'static str),
+    Char(char),
+}
+
+fn fold_char(c: char) -> Folded {
+    match c {
+        // Combining diacritics, Arabic harakat, superscript alef, Quranic marks, tatweel
+        '\u{0300}'..='\u{036F}' | '\u{064B}'..='\u{065F}' | '\u{0670}' | '\u{06D6}'..='\u{06ED}' | '\u{0640}' => Folded::Ignored,
+        // Ayin/hamza transliteration marks and apostrophes
+        '\u{02BB}'..='\u{02BF}' | '\'' | '\u{2019}' => Folded::Ignored,
+
+        // Arabic letter variants
+        'أ' | 'إ' | 'آ' | 'ٱ' | 'ٲ' | 'ٳ' => Folded::Char('ا'),
+        'ة' => Folded::Char('ه'),
+        'ى' | 'ئ' | 'ی' => Folded::Char('ي'),
+        'ؤ' => Folded::Char('و'),
+        'ک' => Folded::Char('ك'),
+        '٠'..='٩' => Folded::Char(char::from(b'0' + (c as u32 - '٠' as u32) as u8)),
+        '۰'..='۹' => Folded::Char(char::from(b'0' + (c as u32 - '۰' as u32) as u8)),
+
+        // Latin letters with diacritics (already lowercase)
+        'à' | 'á' | 'â' | 'ã' | 'ä' | 'å' | 'ā' | 'ă' | 'ą' | 'ǎ' | 'ạ' | 'ả' => Folded::Char('a'),
+        'ç' | 'ć' | 'ĉ' | 'ċ' | 'č' => Folded::Char('c'),
+        'ď' | 'đ' | 'ḍ' | 'ḏ' | 'ð' => Folded::Char('d'),
+        'è' | 'é' | 'ê' | 'ë' | 'ē' | 'ĕ' | 'ė' | 'ę' | 'ě' | 'ẹ' => Folded::Char('e'),
+        'ĝ' | 'ğ' | 'ġ' | 'ģ' | 'ǧ' => Folded::Char('g'),
+        'ĥ' | 'ħ' | 'ḥ' | 'ḫ' | 'ẖ' | 'ḩ' => Folded::Char('h'),
+        'ì' | 'í' | 'î' | 'ï' | 'ĩ' | 'ī' | 'ĭ' | 'į' | 'ı' | 'ǐ' | 'ị' => Folded::Char('i'),
+        'ĵ' | 'ǰ' => Folded::Char('j'),
+        'ķ' | 'ḳ' | 'ḱ' => Folded::Char('k'),
+        'ĺ' | 'ļ' | 'ľ' | 'ŀ' | 'ł' => Folded::Char('l'),
+        'ñ' | 'ń' | 'ņ' | 'ň' | 'ṅ' | 'ṇ' => Folded::Char('n'),
+        'ò' | 'ó' | 'ô' | 'õ' | 'ö' | 'ø' | 'ō' | 'ŏ' | 'ő' | 'ǒ' | 'ọ' => Folded::Char('o'),
+        'ŕ' | 'ŗ' | 'ř' | 'ṛ' => Folded::Char('r'),
+        'ś' | 'ŝ' | 'ş' | 'š' | 'ṣ' | 'ș' => Folded::Char('s'),
+        'ţ' | 'ť' | 'ŧ' | 'ṭ' | 'ṯ' | 'ț' => Folded::Char('t'),
+        'ù' | 'ú' | 'û' | 'ü' | 'ũ' | 'ū' | 'ŭ' | 'ů' | 'ű' | 'ų' | 'ǔ' | 'ụ' => Folded::Char('u'),
+        'ŵ' => Folded::Char('w'),
+        'ý' | 'ÿ' | 'ŷ' | 'ỳ' => Folded::Char('y'),
+        'ź' | 'ż' | 'ž' | 'ẓ' => Folded::Char('z'),
+        'ß' => Folded::Text("ss"),
+        'æ' => Folded::Text("ae"),
+        'œ' => Folded::Text("oe"),
+        'þ' => Folded::Text("th"),
+
+        c if c.is_alphanumeric() => Folded::Char(c),
+        _ => Folded::Separator,
+    }
+}
+
+// Splits `text` into folded terms, remembering where each came from
+pub fn tokenize(text: &str) -> Vec<Token> {
+    let mut tokens = Vec::new();
+    let mut term = String::new();
+    let mut start = None;
+
+    for (offset, c) in text.char_indices() {
+        let mut is_separator = false;
+        for lower in c.to_lowercase() {
+            match fold_char(lower) {
+                Folded::Separator => is_separator = true,
+                Folded::Ignored => {},
+                Folded::Text(folded) => term.push_str(folded),
+                Folded::Char(folded) => term.push(folded),
+            }
+        }
+
+        if is_separator {
+            push_token(&mut tokens, &mut term, start.take(), offset);
+        } else if start.is_none() {
+            start = Some(offset);
+        }
+    }
+    push_token(&mut tokens, &mut term, start, text.len());
+
+    tokens
+}
+
+fn push_token(tokens: &mut Vec<Token>, term: &mut String, start: Option<usize>, end: usize) {
+    let folded = std::mem::take(term);
+    let Some(start) = start else { return };
+    if folded.is_empty() {
+        return;
+    }
+
+    let mut term_end = folded.len().min(MAX_TERM_BYTES);
+    while !folded.is_char_boundary(term_end) {
+        term_end -= 1;
+    }
+    tokens.push(Token {
+        term: folded[..term_end].to_string(),
+        start,
+        end,
+    });
+}
+
+// ============================================================================
+// INDEXING
+// ============================================================================
+
+// (field, text, weight) for every searchable piece of an artifact
+fn searchable_fields(artifact: &Artifact) -> Vec<(&'static str, &str, u32)> {
+    let mut fields = vec![("name", artifact.name.as_str(), 3)];
+    for tag in &artifact.cultural_significance.cultural_tags {
+        fields.push(("tags", tag.as_str(), 2));
+    }
+    fields.push(("description", artifact.description.as_str(), 1));
+    for (_, value) in &artifact.metadata {
+        fields.push(("metadata", value.as_str(), 1));
+    }
+
+    let significance = &artifact.cultural_significance;
+    for text in [&significance.historical_period, &significance.cultural_group].into_iter().flatten() {
+        fields.push(("culture", text.as_str(), 1));
+    }
+
+    if let Some(origin) = &artifact.geographic_origin {
+        fields.push(("origin", origin.country.as_str(), 1));
+        for text in [&origin.region, &origin.city, &origin.site_name].into_iter().flatten() {
+            fields.push(("origin", text.as_str(), 1));
+        }
+    }
+
+    fields
+}
+
+// Postings and token count of one artifact, before they are written
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct SearchDocument {
+    pub postings: BTreeMap<String, Posting>,
+    pub length: u32,
+}
+
+pub fn search_document(artifact: &Artifact) -> SearchDocument {
+    let mut document = SearchDocument::default();
+    let mut position = 0u32;
+
+    for (_, text, weight) in searchable_fields(artifact) {
+        for token in tokenize(text) {
+            let posting = document.postings.entry(token.term).or_default();
+            posting.weight += weight;
+            if posting.positions.len() < MAX_POSITIONS_PER_TERM {
+                posting.positions.push(position);
+            }
+            position += 1;
+            document.length += 1;
+        }
+        position += FIELD_POSITION_GAP;
+    }
+
+    document
+}
+
+// Writes only the postings that changed between two versions of an artifact
+pub fn update_search_index(artifact_id: u64, previous: Option<SearchDocument>, current: SearchDocument) {
+    let previous = previous.unwrap_or_default();
+    if previous == current {
+        return;
+    }
+
+    SEARCH_POSTINGS.with(|postings| {
+        let mut postings = postings.borrow_mut();
+        for term in previous.postings.keys().filter(|term| !current.postings.contains_key(*term)) {
+            postings.remove(&PostingKey::new(term, artifact_id));
+        }
+        for (term, posting) in &current.postings {
+            if previous.postings.get(term) != Some(posting) {
+                postings.insert(PostingKey::new(term, artifact_id), posting.clone());
+            }
+        }
+    });
+
+    SEARCH_DOCUMENTS.with(|documents| {
+        documents.borrow_mut().insert(artifact_id, current.length);
+    });
+
+    update_search_stats(|stats| {
+        stats.total_length = (stats.total_length + current.length as u64).saturating_sub(previous.length as u64);
+    });
+}
+
+// Rebuilds the index when it was written by a different tokenizer version
+// (including never); cheap no-op otherwise.
+pub fn ensure_search_index() {
+    let stats = SEARCH_STATS.with(|stats| *stats.borrow().get());
+    if stats.index_version == SEARCH_INDEX_VERSION {
+        return;
+    }
+
+    SEARCH_POSTINGS.with(|postings| postings.borrow_mut().clear_new());
+    SEARCH_DOCUMENTS.with(|documents| documents.borrow_mut().clear_new());
+    update_search_stats(|stats| *stats = SearchStats::default());
+
+    ARTIFACTS.with(|artifacts| {
+        for (artifact_id, artifact) in artifacts.borrow().iter() {
+            update_search_index(artifact_id, None, search_document(&artifact));
+        }
+    });
+    update_search_stats(|stats| stats.index_version = SEARCH_INDEX_VERSION);
+}
+
+fn update_search_stats(update: impl FnOnce(&mut SearchStats)) {
+    SEARCH_STATS.with(|cell| {
+        let mut cell = cell.borrow_mut();
+        let mut stats = *cell.get();
+        update(&mut stats);
+        if let Err(error) = cell.set(stats) {
+            ic_cdk::trap(&format!("Failed to persist search stats: {:?}", error));
+        }
+    });
+}
+
+// ============================================================================
+// QUERYING
+// ============================================================================
+//
+// Query syntax: plain words are ranked with BM25 and any of them may match,
+// `word*` matches every term starting with `word`, and "quoted phrases" must
+// appear verbatim (after folding) in every result.
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum QueryClause {
+    Term(String),
+    Prefix(String),
+    Phrase(Vec<String>),
+}
+
+pub fn parse_query(query: &str) -> Vec<QueryClause> {
+    let mut clauses = Vec::new();
+
+    for (index, segment) in query.split('"').enumerate() {
+        let is_phrase = index % 2 == 1;
+        if is_phrase {
+            let terms: Vec<String> = tokenize(segment).into_iter().map(|token| token.term).collect();
+            match terms.len() {
+                0 => {},
+                1 => clauses.extend(terms.into_iter().map(QueryClause::Term)),
+                _ => clauses.push(QueryClause::Phrase(terms)),
+            }
+            continue;
+        }
+
+        for word in segment.split_whitespace() {
+            let is_prefix = word.ends_with('*');
+            let mut terms: Vec<String> = tokenize(word).into_iter().map(|token| token.term).collect();
+            let last = terms.pop();
+            clauses.extend(terms.into_iter().map(QueryClause::Term));
+            match last {
+                Some(term) if is_prefix && term.chars().count() >= MIN_PREFIX_CHARS => clauses.push(QueryClause::Prefix(term)),
+                Some(term) => clauses.push(QueryClause::Term(term)),
+                None => {},
+            }
+        }
+    }
+
+    clauses
+}
+
+pub fn bm25(weight: f64, document_frequency: u64, document_count: u64, document_length: f64, average_length: f64) -> f64 {
+    let n = document_count as f64;
+    let df = document_frequency as f64;
+    let idf = ((n - df + 0.5) / (df + 0.5) + 1.0).ln();
+    let length_norm = 1.0 - BM25_B + BM25_B * document_length / average_length.max(1.0);
+    idf * weight * (BM25_K1 + 1.0) / (weight + BM25_K1 * length_norm)
+}
+
+// All postings of one term, keyed by artifact id
+fn term_postings(term: &str) -> BTreeMap<u64, Posting> {
+    let start = PostingKey::new(term, 0);
+    SEARCH_POSTINGS.with(|postings| {
+        postings.borrow()
+            .range(start.clone()..)
+            .take_while(|(key, _)| key.term() == term.as_bytes())
+            .map(|(key, posting)| (key.artifact_id(), posting))
+            .collect()
+    })
+}
+
+// Distinct indexed terms starting with `prefix`, capped at MAX_PREFIX_EXPANSIONS
+fn expand_prefix(prefix: &str) -> Vec<String> {
+    let mut terms: Vec<String> = Vec::new();
+    SEARCH_POSTINGS.with(|postings| {
+        for (key, _) in postings.borrow().range(PostingKey(prefix.as_bytes().to_vec())..) {
+            if !key.0.starts_with(prefix.as_bytes()) {
+                break;
+            }
+            let term = String::from_utf8_lossy(key.term()).into_owned();
+            if terms.last() != Some(&term) {
+                if terms.len() == MAX_PREFIX_EXPANSIONS {
+                    break;
+                }
+                terms.push(term);
+            }
+        }
+    });
+    terms
+}
+
+fn contains_phrase(postings: &[&Posting]) -> bool {
+    let Some((first, rest)) = postings.split_first() else { return false };
+    first.positions.iter().any(|start| {
+        rest.iter().enumerate().all(|(offset, posting)| {
+            posting.positions.contains(&(start + offset as u32 + 1))
+        })
+    })
+}
+
+struct Scorer {
+    document_count: u64,
+    average_length: f64,
+    lengths: BTreeMap<u64, f64>,
+    scores: BTreeMap<u64, f64>,
+}
+
+impl Scorer {
+    fn new() -> Self {
+        let document_count = SEARCH_DOCUMENTS.with(|documents| documents.borrow().len());
+        let total_length = SEARCH_STATS.with(|stats| stats.borrow().get().total_length);
+        Scorer {
+            document_count,
+            average_length: total_length as f64 / document_count.max(1) as f64,
+            lengths: BTreeMap::new(),
+            scores: BTreeMap::new(),
+        }
+    }
+
+    fn score(&mut self, artifact_id: u64, posting: &Posting, document_frequency: u64) -> f64 {
+        let length = *self.lengths.entry(artifact_id).or_insert_with(|| {
+            SEARCH_DOCUMENTS.with(|documents| documents.borrow().get(&artifact_id).unwrap_or(0) as f64)
+        });
+        bm25(posting.weight as f64, document_frequency, self.document_count, length, self.average_length)
+    }
+
+    fn add_term(&mut self, postings: &BTreeMap<u64, Posting>, only: Option<&BTreeSet<u64>>) {
+        let document_frequency = postings.len() as u64;
+        for (artifact_id, posting) in postings {
+            if only.is_some_and(|only| !only.contains(artifact_id)) {
+                continue;
+            }
+            let score = self.score(*artifact_id, posting, document_frequency);
+            *self.scores.entry(*artifact_id).or_insert(0.0) += score;
+        }
+    }
+}
+
+// Ranked (artifact_id, score) pairs for `clauses`, best first
+pub fn rank_artifacts(clauses: &[QueryClause]) -> Vec<(u64, f64)> {
+    let mut scorer = Scorer::new();
+    if scorer.document_count == 0 {
+        return Vec::new();
+    }
+
+    // Phrases are filters: a result must contain every one of them
+    let mut required: Option<BTreeSet<u64>> = None;
+    for clause in clauses {
+        let QueryClause::Phrase(terms) = clause else { continue };
+        let term_postings: Vec<BTreeMap<u64, Posting>> = terms.iter().map(|term| term_postings(term)).collect();
+        let matches: BTreeSet<u64> = term_postings[0].keys()
+            .filter(|artifact_id| {
+                let postings: Option<Vec<&Posting>> = term_postings.iter()
+                    .map(|postings| postings.get(artifact_id))
+                    .collect();
+                postings.is_some_and(|postings| contains_phrase(&postings))
+            })
+            .copied()
+            .collect();
+
+        for postings in &term_postings {
+            scorer.add_term(postings, Some(&matches));
+        }
+        required = Some(match required {
+            Some(required) => required.intersection(&matches).copied().collect(),
+            None => matches,
+        });
+    }
+
+    for clause in clauses {
+        match clause {
+            QueryClause::Term(term) => scorer.add_term(&term_postings(term), required.as_ref()),
+            QueryClause::Prefix(prefix) => {
+                for term in expand_prefix(prefix) {
+                    scorer.add_term(&term_postings(&term), required.as_ref());
+                }
+            },
+            QueryClause::Phrase(_) => {},
+        }
+    }
+
+    let mut ranked: Vec<(u64, f64)> = scorer.scores.into_iter()
+        .filter(|(artifact_id, _)| required.as_ref().is_none_or(|required| required.contains(artifact_id)))
+        .collect();
+    // Ties go to the newest artifact, matching the other listings
+    ranked.sort_by(|a, b| b.1.total_cmp(&a.1).then(b.0.cmp(&a.0)));
+    ranked
+}
+
+fn clause_matches(clauses: &[QueryClause], term: &str) -> bool {
+    clauses.iter().any(|clause| match clause {
+        QueryClause::Term(query_term) => query_term == term,
+        QueryClause::Prefix(prefix) => term.starts_with(prefix.as_str()),
+        QueryClause::Phrase(terms) => terms.iter().any(|query_term| query_term == term),
+    })
+}
+
+// One snippet per matching field, centred on the first match in that field
+pub fn build_snippets(artifact: &Artifact, clauses: &[QueryClause]) -> Vec<SearchSnippet> {
+    let mut snippets = Vec::new();
+
+    for (field, text, _) in searchable_fields(artifact) {
+        if snippets.len() == MAX_SNIPPETS_PER_HIT {
+            break;
+        }
+        let matches: Vec<Token> = tokenize(text).into_iter()
+            .filter(|token| clause_matches(clauses, &token.term))
+            .collect();
+        let Some(first) = matches.first() else { continue };
+
+        let chars: Vec<(usize, char)> = text.char_indices().collect();
+        let first_char = chars.iter().position(|(offset, _)| *offset == first.start).unwrap_or(0);
+        let window_start = first_char.saturating_sub(SNIPPET_CONTEXT_CHARS);
+        let window_end = (window_start + SNIPPET_MAX_CHARS).min(chars.len());
+        let byte_start = chars[window_start].0;
+        let byte_end = chars.get(window_end).map_or(text.len(), |(offset, _)| *offset);
+
+        let mut snippet_text = String::new();
+        let mut prefix_chars = 0;
+        if window_start > 0 {
+            snippet_text.push('…');
+            prefix_chars = 1;
+        }
+        snippet_text.push_str(&text[byte_start..byte_end]);
+        if window_end < chars.len() {
+            snippet_text.push('…');
+        }
+
+        let to_char = |byte: usize| (text[byte_start..byte].chars().count() + prefix_chars) as u32;
+        let highlights = matches.iter()
+            .filter(|token| token.start >= byte_start && token.end <= byte_end)
+            .map(|token| (to_char(token.start), to_char(token.end)))
+            .collect();
+
+        snippets.push(SearchSnippet {
+            field: field.to_string(),
+            text: snippet_text,
+            highlights,
+        });
+    }
+
+    snippets
+}
+
+pub fn search(query: &str, page: &PageRequest) -> Page<SearchHit> {
+    let clauses = parse_query(query);
+    let ranked = rank_artifacts(&clauses);
+
+    let offset = page.cursor.unwrap_or(0) as usize;
+    let limit = page_limit(page);
+    let end = (offset + limit).min(ranked.len());
+
+    let items = ARTIFACTS.with(|artifacts| {
+        let artifacts = artifacts.borrow();
+        ranked.get(offset..end).unwrap_or_default().iter()
+            .filter_map(|(artifact_id, score)| {
+                artifacts.get(artifact_id).map(|artifact| SearchHit {
+                    snippets: build_snippets(&artifact, &clauses),
+                    artifact,
+                    score: *score,
+                })
+            })
+            .collect()
+    });
+
+    Page {
+        items,
+        // For search results the cursor is an offset into the ranking
+        next_cursor: (end < ranked.len()).then_some(end as u64),
+        total: ranked.len() as u64,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::modules::testing::{artifact_request, create_artifact_as, principal};
+
+    fn terms(text: &str) -> Vec<String> {
+        tokenize(text).into_iter().map(|token| token.term).collect()
+    }
+
+    fn ranked_ids(query: &str) -> Vec<u64> {
+        rank_artifacts(&parse_query(query)).into_iter().map(|(artifact_id, _)| artifact_id).collect()
+    }
+
+    fn create(name: &str, description: &str) -> u64 {
+        let mut request = artifact_request(name);
+        request.description = description.to_string();
+        create_artifact_as(principal(1), request)
+    }
+
+    #[test]
+    fn folding_removes_diacritics_and_unifies_arabic_variants() {
+        assert_eq!(terms("Ḥatšepsut’s STELA"), vec!["hatsepsuts", "stela"]);
+        assert_eq!(terms("Ṭūṭ-ʿanḫ-Amūn"), vec!["tut", "anh", "amun"]);
+        // Hamza forms of alef, ta marbuta and harakat all fold away
+        assert_eq!(terms("الإسكندرية"), terms("الاسكندريه"));
+        assert_eq!(terms("مَكْتَبَة"), vec!["مكتبه"]);
+        assert_eq!(terms("كتـــاب"), vec!["كتاب"]);
+        assert_eq!(terms("سنة ١٢٣٤ / ۱۲۳۴"), vec!["سنه", "1234", "1234"]);
+    }
+
+    #[test]
+    fn tokens_keep_their_byte_range_in_the_original_text() {
+        let text = "Ḥatšepsut temple";
+        let tokens = tokenize(text);
+        assert_eq!(&text[tokens[0].start..tokens[0].end], "Ḥatšepsut");
+        assert_eq!(&text[tokens[1].start..tokens[1].end], "temple");
+    }
+
+    #[test]
+    fn query_syntax_separates_terms_prefixes_and_phrases() {
+        assert_eq!(parse_query(r#"tomb "golden mask" ka* k* "stela""#), vec![
+            QueryClause::Term("tomb".to_string()),
+            QueryClause::Phrase(vec!["golden".to_string(), "mask".to_string()]),
+            QueryClause::Prefix("ka".to_string()),
+            // Too short to expand, and a one word phrase is a plain term
+            QueryClause::Term("k".to_string()),
+            QueryClause::Term("stela".to_string()),
+        ]);
+    }
+
+    #[test]
+    fn bm25_favours_frequent_terms_rare_terms_and_short_documents() {
+        let base = bm25(1.0, 1, 10, 10.0, 10.0);
+        assert!(bm25(3.0, 1, 10, 10.0, 10.0) > base);
+        assert!(bm25(1.0, 5, 10, 10.0, 10.0) < base);
+        assert!(bm25(1.0, 1, 10, 40.0, 10.0) < base);
+    }
+
+    #[test]
+    fn name_matches_outrank_description_matches() {
+        let in_description = create("Limestone block", "Carved with a scarab beetle");
+        let in_name = create("Scarab amulet", "Green faience");
+        create("Bronze mirror", "Polished disc");
+
+        assert_eq!(ranked_ids("scarab"), vec![in_name, in_description]);
+        // Folded queries find the same artifacts
+        assert_eq!(ranked_ids("SCARÁB"), vec![in_name, in_description]);
+    }
+
+    #[test]
+    fn phrases_require_adjacent_terms() {
+        let phrase = create("Golden mask", "Funerary mask");
+        let scattered = create("Mask", "Golden leaf over wood");
+
+        assert_eq!(ranked_ids(r#""golden mask""#), vec![phrase]);
+        let mut either = ranked_ids("golden mask");
+        either.sort();
+        assert_eq!(either, vec![phrase, scattered]);
+    }
+
+    #[test]
+    fn phrases_do_not_match_across_fields() {
+        // "mask" ends the name and "golden" starts the description
+        create("Funerary mask", "Golden leaf");
+        assert!(ranked_ids(r#""mask golden""#).is_empty());
+    }
+
+    #[test]
+    fn prefixes_expand_to_every_indexed_term() {
+        let mask = create("Mask", "Painted");
+        let masonry = create("Masonry fragment", "Painted");
+        create("Mirror", "Painted");
+
+        let mut matches = ranked_ids("mas*");
+        matches.sort();
+        assert_eq!(matches, vec![mask, masonry]);
+        assert_eq!(expand_prefix("mas"), vec!["mask", "masonry"]);
+    }
+
+    #[test]
+    fn positions_are_capped_but_weight_counts_every_occurrence() {
+        let mut artifact_text = vec!["ankh"; MAX_POSITIONS_PER_TERM + 10].join(" ");
+        artifact_text.push_str(" scarab");
+        let artifact_id = create("Ankh", &artifact_text);
+        let artifact = ARTIFACTS.with(|artifacts| artifacts.borrow().get(&artifact_id)).unwrap();
+
+        let document = search_document(&artifact);
+        let posting = &document.postings["ankh"];
+        assert_eq!(posting.positions.len(), MAX_POSITIONS_PER_TERM);
+        assert_eq!(posting.weight, 3 + (MAX_POSITIONS_PER_TERM as u32 + 10));
+        // The capped posting still round-trips through its bounded encoding
+        assert_eq!(Posting::from_bytes(posting.to_bytes()), *posting);
+        // The only "ankh scarab" pair lies past the cap
+        assert!(ranked_ids(r#""ankh scarab""#).is_empty());
+    }
+
+    #[test]
+    fn snippets_highlight_matches_within_a_window() {
+        let artifact_id = create("Scarab", &format!("{} a scarab of green faience", "Found in the tomb. ".repeat(5)));
+        let artifact = ARTIFACTS.with(|artifacts| artifacts.borrow().get(&artifact_id)).unwrap();
+
+        let snippets = build_snippets(&artifact, &parse_query("scarab"));
+        assert_eq!(snippets.iter().map(|s| s.field.as_str()).collect::<Vec<_>>(), vec!["name", "description"]);
+        assert_eq!(snippets[0].highlights, vec![(0, 6)]);
+
+        let description = &snippets[1];
+        assert!(description.text.starts_with('…'));
+        let highlighted: Vec<String> = description.highlights.iter()
+            .map(|(start, end)| description.text.chars().skip(*start as usize).take((end - start) as usize).collect())
+            .collect();
+        assert_eq!(highlighted, vec!["scarab"]);
+    }
+}
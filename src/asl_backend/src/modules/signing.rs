@@ -0,0 +1,591 @@
+use candid::{CandidType, Principal};
+use ic_cdk::api::management_canister::ecdsa::{
+    ecdsa_public_key, sign_with_ecdsa, EcdsaCurve, EcdsaKeyId, EcdsaPublicKeyArgument, SignWithEcdsaArgument,
+};
+use ic_cdk::api::management_canister::schnorr::{
+    schnorr_public_key, sign_with_schnorr, SchnorrAlgorithm, SchnorrKeyId, SchnorrPublicKeyArgument,
+    SignWithSchnorrArgument,
+};
+use ic_cdk::{query, update};
+use ic_stable_structures::Storable;
+use serde::{Deserialize, Serialize};
+use std::borrow::Cow;
+
+use crate::modules::types::*;
+use crate::modules::storage::{ARTIFACTS, NFTS, SIGNATURES, SIGNER_CONFIG};
+use crate::modules::auth::{can_moderate, get_caller};
+use crate::modules::utils::get_time;
+use crate::modules::audit::log_audit_event;
+
+// ============================================================================
+// THRESHOLD SIGNATURES
+// ============================================================================
+//
+// Heritage certificates and provenance entries are signed with the canister's
+// threshold key through the management canister. What is signed is the
+// record's SHA-256 digest, as 32 raw bytes:
+//
+//   certificate       the sealed document digest, `HeritageCertificate.digital_signature`
+//   provenance entry  the history entry's `immutable_hash`
+//
+// All signatures use the single derivation path SIGNING_DERIVATION_PATH, so
+// one public key (`get_signing_public_key`) verifies every signature offline.
+// ECDSA signatures are 64-byte (r, s) over secp256k1; Schnorr signatures
+// follow BIP-340 or Ed25519 depending on the configured scheme.
+//
+// Each signature costs the canister cycles, so nothing an ordinary user can
+// trigger signs. Certificates are signed in the background when an NFT is
+// issued (`request_signature`); provenance entries, and certificates resealed
+// by a transfer or an endorsement, are signed on demand through `sign_record`,
+// which only moderators and controllers may call. A signature is only stored
+// if the record's digest did not change in the meantime, and a stored
+// signature whose `message_hash` differs from the record's digest is stale.
+// The signing path goes through the `Signer` trait so it can be exercised
+// with a local signer in tests.
+
+pub const SIGNING_DERIVATION_PATH: &[u8] = b"asl_heritage_signatures";
+const DEFAULT_KEY_NAME: &str = "key_1";
+
+#[derive(CandidType, Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SignatureScheme {
+    EcdsaSecp256k1,
+    SchnorrBip340Secp256k1,
+    SchnorrEd25519,
+}
+
+#[derive(CandidType, Serialize, Deserialize, Clone, Debug)]
+pub struct SignerConfig {
+    pub scheme: SignatureScheme,
+    // "key_1" on mainnet, "test_key_1" for testing, "dfx_test_key" locally
+    pub key_name: String,
+    // Fetched on first use, cleared when the key changes
+    pub public_key: Option<Vec<u8>>,
+}
+
+impl Default for SignerConfig {
+    fn default() -> Self {
+        SignerConfig {
+            scheme: SignatureScheme::EcdsaSecp256k1,
+            key_name: DEFAULT_KEY_NAME.to_string(),
+            public_key: None,
+        }
+    }
+}
+
+#[derive(CandidType, Serialize, Deserialize, Clone, Debug)]
+pub struct SigningPublicKey {
+    pub scheme: SignatureScheme,
+    pub key_name: String,
+    pub derivation_path: Vec<Vec<u8>>,
+    pub public_key: Vec<u8>,
+}
+
+#[derive(CandidType, Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SignedSubject {
+    Certificate { nft_id: u64 },
+    ProvenanceEntry { artifact_id: u64, entry_id: u64 },
+}
+
+#[derive(CandidType, Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct SignatureRecord {
+    pub subject: SignedSubject,
+    pub scheme: SignatureScheme,
+    pub key_name: String,
+    // The signed 32-byte digest
+    pub message_hash: Vec<u8>,
+    pub signature: Vec<u8>,
+    pub signed_at: u64,
+}
+
+// kind (u8) | id (u64 BE) | sub id (u64 BE)
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub struct SignatureKey([u8; 17]);
+
+impl SignatureKey {
+    pub fn new(subject: SignedSubject) -> Self {
+        let (kind, id, sub_id) = match subject {
+            SignedSubject::Certificate { nft_id } => (0u8, nft_id, 0),
+            SignedSubject::ProvenanceEntry { artifact_id, entry_id } => (1u8, artifact_id, entry_id),
+        };
+        let mut key = [0u8; 17];
+        key[0] = kind;
+        key[1..9].copy_from_slice(&id.to_be_bytes());
+        key[9..].copy_from_slice(&sub_id.to_be_bytes());
+        SignatureKey(key)
+    }
+}
+
+impl Storable for SignatureKey {
+    const BOUND: ic_stable_structures::storable::Bound = ic_stable_structures::storable::Bound::Bounded {
+        max_size: 17,
+        is_fixed_size: true,
+    };
+
+    fn to_bytes(&self) -> Cow<'_, [u8]> {
+        Cow::Borrowed(&self.0)
+    }
+
+    fn from_bytes(bytes: Cow<[u8]>) -> Self {
+        let mut key = [0u8; 17];
+        key.copy_from_slice(&bytes);
+        SignatureKey(key)
+    }
+}
+
+// ============================================================================
+// SIGNERS
+// ============================================================================
+
+pub trait Signer {
+    fn scheme(&self) -> SignatureScheme;
+    fn key_name(&self) -> String;
+    async fn public_key(&self) -> Result<Vec<u8>, String>;
+    async fn sign(&self, message_hash: &[u8; 32]) -> Result<Vec<u8>, String>;
+}
+
+// The canister's threshold key, through the management canister
+pub struct ManagementCanisterSigner {
+    pub scheme: SignatureScheme,
+    pub key_name: String,
+}
+
+impl ManagementCanisterSigner {
+    pub fn from_config() -> Self {
+        let config = get_signer_config();
+        ManagementCanisterSigner { scheme: config.scheme, key_name: config.key_name }
+    }
+
+    fn schnorr_key_id(&self, algorithm: SchnorrAlgorithm) -> SchnorrKeyId {
+        SchnorrKeyId { algorithm, name: self.key_name.clone() }
+    }
+
+    fn ecdsa_key_id(&self) -> EcdsaKeyId {
+        EcdsaKeyId { curve: EcdsaCurve::Secp256k1, name: self.key_name.clone() }
+    }
+}
+
+fn derivation_path() -> Vec<Vec<u8>> {
+    vec![SIGNING_DERIVATION_PATH.to_vec()]
+}
+
+impl Signer for ManagementCanisterSigner {
+    fn scheme(&self) -> SignatureScheme {
+        self.scheme
+    }
+
+    fn key_name(&self) -> String {
+        self.key_name.clone()
+    }
+
+    async fn public_key(&self) -> Result<Vec<u8>, String> {
+        let algorithm = match self.scheme {
+            SignatureScheme::EcdsaSecp256k1 => {
+                let (response,) = ecdsa_public_key(EcdsaPublicKeyArgument {
+                    canister_id: None,
+                    derivation_path: derivation_path(),
+                    key_id: self.ecdsa_key_id(),
+                }).await.map_err(|(code, message)| format!("ecdsa_public_key failed ({:?}): {}", code, message))?;
+                return Ok(response.public_key);
+            },
+            SignatureScheme::SchnorrBip340Secp256k1 => SchnorrAlgorithm::Bip340secp256k1,
+            SignatureScheme::SchnorrEd25519 => SchnorrAlgorithm::Ed25519,
+        };
+        let (response,) = schnorr_public_key(SchnorrPublicKeyArgument {
+            canister_id: None,
+            derivation_path: derivation_path(),
+            key_id: self.schnorr_key_id(algorithm),
+        }).await.map_err(|(code, message)| format!("schnorr_public_key failed ({:?}): {}", code, message))?;
+        Ok(response.public_key)
+    }
+
+    async fn sign(&self, message_hash: &[u8; 32]) -> Result<Vec<u8>, String> {
+        let algorithm = match self.scheme {
+            SignatureScheme::EcdsaSecp256k1 => {
+                let (response,) = sign_with_ecdsa(SignWithEcdsaArgument {
+                    message_hash: message_hash.to_vec(),
+                    derivation_path: derivation_path(),
+                    key_id: self.ecdsa_key_id(),
+                }).await.map_err(|(code, message)| format!("sign_with_ecdsa failed ({:?}): {}", code, message))?;
+                return Ok(response.signature);
+            },
+            SignatureScheme::SchnorrBip340Secp256k1 => SchnorrAlgorithm::Bip340secp256k1,
+            SignatureScheme::SchnorrEd25519 => SchnorrAlgorithm::Ed25519,
+        };
+        let (response,) = sign_with_schnorr(SignWithSchnorrArgument {
+            message: message_hash.to_vec(),
+            derivation_path: derivation_path(),
+            key_id: self.schnorr_key_id(algorithm),
+        }).await.map_err(|(code, message)| format!("sign_with_schnorr failed ({:?}): {}", code, message))?;
+        Ok(response.signature)
+    }
+}
+
+// ============================================================================
+// SIGNING
+// ============================================================================
+
+fn decode_digest(hex: &str) -> Option<[u8; 32]> {
+    if hex.len() != 64 || !hex.is_ascii() {
+        return None;
+    }
+    let mut digest = [0u8; 32];
+    for (i, byte) in digest.iter_mut().enumerate() {
+        *byte = u8::from_str_radix(&hex[i * 2..i * 2 + 2], 16).ok()?;
+    }
+    Some(digest)
+}
+
+// The digest a subject's signature has to cover right now
+pub fn current_digest(subject: SignedSubject) -> Result<[u8; 32], String> {
+    let hex = match subject {
+        SignedSubject::Certificate { nft_id } => NFTS.with(|nfts| nfts.borrow().get(&nft_id))
+            .map(|nft| nft.heritage_certificate.digital_signature)
+            .ok_or_else(|| "Heritage NFT not found".to_string())?,
+        SignedSubject::ProvenanceEntry { artifact_id, entry_id } => ARTIFACTS.with(|artifacts| artifacts.borrow().get(&artifact_id))
+            .ok_or_else(|| "Artifact not found".to_string())?
+            .history.into_iter()
+            .find(|entry| entry.id == entry_id)
+            .map(|entry| entry.immutable_hash)
+            .ok_or_else(|| "History entry not found".to_string())?,
+    };
+    decode_digest(&hex).ok_or_else(|| "Record has no SHA-256 digest to sign".to_string())
+}
+
+pub fn get_signature(subject: SignedSubject) -> Option<SignatureRecord> {
+    SIGNATURES.with(|signatures| signatures.borrow().get(&SignatureKey::new(subject)))
+}
+
+// Signs the subject's current digest unless a signature of it by the same key
+// already exists. Fails without storing anything if the record changed while
+// the signature was being produced.
+pub async fn sign_subject<S: Signer>(signer: &S, subject: SignedSubject, now: u64) -> Result<SignatureRecord, String> {
+    let digest = current_digest(subject)?;
+    if let Some(existing) = get_signature(subject) {
+        if existing.message_hash == digest && existing.scheme == signer.scheme() && existing.key_name == signer.key_name() {
+            return Ok(existing);
+        }
+    }
+
+    let signature = signer.sign(&digest).await?;
+
+    if current_digest(subject)? != digest {
+        return Err("Record changed while it was being signed".to_string());
+    }
+
+    let record = SignatureRecord {
+        subject,
+        scheme: signer.scheme(),
+        key_name: signer.key_name(),
+        message_hash: digest.to_vec(),
+        signature,
+        signed_at: now,
+    };
+    SIGNATURES.with(|signatures| {
+        signatures.borrow_mut().insert(SignatureKey::new(subject), record.clone());
+    });
+    Ok(record)
+}
+
+// Signs `subject` in the background with the canister's key. Must not be called
+// while a store is borrowed: the signing call starts immediately.
+#[cfg(not(test))]
+pub fn request_signature(subject: SignedSubject) {
+    ic_cdk::spawn(async move {
+        let signer = ManagementCanisterSigner::from_config();
+        if let Err(error) = sign_subject(&signer, subject, get_time()).await {
+            log_audit_event(
+                AuditEventType::SystemMaintenance,
+                None,
+                format!("Signing {:?} failed: {}", subject, error),
+                AuditSeverity::Warning
+            );
+        }
+    });
+}
+
+#[cfg(test)]
+pub fn request_signature(subject: SignedSubject) {
+    crate::modules::testing::record_signature_request(subject);
+}
+
+// ============================================================================
+// ENDPOINTS
+// ============================================================================
+
+pub fn get_signer_config() -> SignerConfig {
+    SIGNER_CONFIG.with(|config| config.borrow().get().clone())
+}
+
+fn save_signer_config(config: SignerConfig) {
+    SIGNER_CONFIG.with(|cell| {
+        cell.borrow_mut().set(config).expect("Failed to save signer config");
+    });
+}
+
+// Controllers only; existing signatures stay valid under the key that made them
+#[update]
+pub fn set_signer_config(scheme: SignatureScheme, key_name: String) -> Result<String, String> {
+    let caller = get_caller();
+    if !ic_cdk::api::is_controller(&caller) {
+        return Err("Only controllers can change the signing key".to_string());
+    }
+    if key_name.trim().is_empty() {
+        return Err("Key name cannot be empty".to_string());
+    }
+
+    save_signer_config(SignerConfig { scheme, key_name: key_name.clone(), public_key: None });
+
+    log_audit_event(
+        AuditEventType::SystemMaintenance,
+        None,
+        format!("Signing key set to {} ({:?})", key_name, scheme),
+        AuditSeverity::Info
+    );
+    Ok("Signer configuration updated".to_string())
+}
+
+fn signing_public_key(config: SignerConfig, public_key: Vec<u8>) -> SigningPublicKey {
+    SigningPublicKey {
+        scheme: config.scheme,
+        key_name: config.key_name,
+        derivation_path: derivation_path(),
+        public_key,
+    }
+}
+
+// The cached key; `fetch_signing_public_key` fills the cache
+#[query]
+pub fn get_signing_public_key() -> Result<SigningPublicKey, String> {
+    let config = get_signer_config();
+    match config.public_key.clone() {
+        Some(public_key) => Ok(signing_public_key(config, public_key)),
+        None => Err("Public key not fetched yet; call fetch_signing_public_key".to_string()),
+    }
+}
+
+#[update]
+pub async fn fetch_signing_public_key() -> Result<SigningPublicKey, String> {
+    if let Ok(public_key) = get_signing_public_key() {
+        return Ok(public_key);
+    }
+
+    let signer = ManagementCanisterSigner::from_config();
+    let public_key = signer.public_key().await?;
+
+    // Only cache the key if the configuration did not change in the meantime
+    let mut config = get_signer_config();
+    if config.scheme == signer.scheme && config.key_name == signer.key_name {
+        config.public_key = Some(public_key.clone());
+        save_signer_config(config.clone());
+    }
+    Ok(signing_public_key(config, public_key))
+}
+
+// Signing spends cycles, so only moderators and controllers may ask for it
+fn can_request_signature(caller: Principal) -> bool {
+    can_moderate(caller) || is_controller(caller)
+}
+
+#[cfg(not(test))]
+fn is_controller(caller: Principal) -> bool {
+    ic_cdk::api::is_controller(&caller)
+}
+
+#[cfg(test)]
+fn is_controller(_caller: Principal) -> bool {
+    false
+}
+
+// Signs (or re-signs, after a change or a key change) a certificate or
+// provenance entry
+#[update]
+pub async fn sign_record(subject: SignedSubject) -> Result<SignatureRecord, String> {
+    if !can_request_signature(get_caller()) {
+        return Err("Only moderators and controllers can request signatures".to_string());
+    }
+    let signer = ManagementCanisterSigner::from_config();
+    sign_subject(&signer, subject, get_time()).await
+}
+
+#[query]
+pub fn get_record_signature(subject: SignedSubject) -> Result<SignatureRecord, String> {
+    get_signature(subject).ok_or_else(|| "No signature recorded".to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use sha2::{Digest, Sha256};
+    use std::cell::Cell;
+
+    // Deterministic stand-in for the threshold key: the "signature" is
+    // SHA-256(secret || message_hash), with SHA-256(secret) as public key
+    struct MockSigner {
+        secret: Vec<u8>,
+        calls: Cell<u32>,
+        // Runs while the signature is "in flight"
+        during_sign: Option<Box<dyn Fn()>>,
+    }
+
+    impl MockSigner {
+        fn new() -> Self {
+            MockSigner { secret: b"mock-secret".to_vec(), calls: Cell::new(0), during_sign: None }
+        }
+
+        fn expected_signature(&self, message_hash: &[u8]) -> Vec<u8> {
+            Sha256::new().chain_update(&self.secret).chain_update(message_hash).finalize().to_vec()
+        }
+    }
+
+    impl Signer for MockSigner {
+        fn scheme(&self) -> SignatureScheme {
+            SignatureScheme::SchnorrEd25519
+        }
+
+        fn key_name(&self) -> String {
+            "mock_key".to_string()
+        }
+
+        async fn public_key(&self) -> Result<Vec<u8>, String> {
+            Ok(Sha256::digest(&self.secret).to_vec())
+        }
+
+        async fn sign(&self, message_hash: &[u8; 32]) -> Result<Vec<u8>, String> {
+            self.calls.set(self.calls.get() + 1);
+            if let Some(hook) = &self.during_sign {
+                hook();
+            }
+            Ok(self.expected_signature(message_hash))
+        }
+    }
+
+    fn block_on<F: std::future::Future>(future: F) -> F::Output {
+        tokio::runtime::Builder::new_current_thread().build().unwrap().block_on(future)
+    }
+
+    fn hex(bytes: &[u8]) -> String {
+        bytes.iter().map(|b| format!("{:02x}", b)).collect()
+    }
+
+    fn insert_nft(nft_id: u64, digest: &str) {
+        let principal = Principal::anonymous();
+        let nft = ProofOfHeritageNFT {
+            id: nft_id,
+            artifact_id: 1,
+            owner: principal,
+            created_at: 0,
+            metadata: Vec::new(),
+            is_transferable: false,
+            verification_hash: String::new(),
+            issuing_authority: principal,
+            heritage_certificate: HeritageCertificate {
+                certificate_number: format!("HER-{:06}-0", nft_id),
+                issuer_name: "Test Museum".to_string(),
+                verification_date: 0,
+                authenticity_guarantees: Vec::new(),
+                scientific_analysis: Vec::new(),
+                expert_endorsements: Vec::new(),
+                digital_signature: digest.to_string(),
+            },
+            access_rights: AccessRights {
+                can_view_detailed_metadata: true,
+                can_request_high_res_images: false,
+                can_access_research_data: false,
+                can_propose_studies: false,
+                special_permissions: Vec::new(),
+            },
+        };
+        NFTS.with(|nfts| nfts.borrow_mut().insert(nft_id, nft));
+    }
+
+    #[test]
+    fn signs_the_current_certificate_digest() {
+        let digest = Sha256::digest(b"certificate document");
+        insert_nft(7, &hex(&digest));
+        let signer = MockSigner::new();
+        let subject = SignedSubject::Certificate { nft_id: 7 };
+
+        let record = block_on(sign_subject(&signer, subject, 42)).unwrap();
+
+        assert_eq!(record.message_hash, digest.to_vec());
+        assert_eq!(record.signature, signer.expected_signature(&digest));
+        assert_eq!(record.scheme, SignatureScheme::SchnorrEd25519);
+        assert_eq!(record.signed_at, 42);
+        assert_eq!(get_signature(subject), Some(record));
+    }
+
+    #[test]
+    fn reuses_the_signature_of_an_unchanged_digest() {
+        insert_nft(8, &hex(&Sha256::digest(b"unchanged")));
+        let signer = MockSigner::new();
+        let subject = SignedSubject::Certificate { nft_id: 8 };
+
+        let first = block_on(sign_subject(&signer, subject, 1)).unwrap();
+        let second = block_on(sign_subject(&signer, subject, 2)).unwrap();
+
+        assert_eq!(first, second);
+        assert_eq!(signer.calls.get(), 1);
+    }
+
+    #[test]
+    fn resigns_after_the_digest_changes() {
+        insert_nft(9, &hex(&Sha256::digest(b"first seal")));
+        let signer = MockSigner::new();
+        let subject = SignedSubject::Certificate { nft_id: 9 };
+        block_on(sign_subject(&signer, subject, 1)).unwrap();
+
+        let resealed = Sha256::digest(b"second seal");
+        insert_nft(9, &hex(&resealed));
+        let record = block_on(sign_subject(&signer, subject, 2)).unwrap();
+
+        assert_eq!(record.message_hash, resealed.to_vec());
+        assert_eq!(signer.calls.get(), 2);
+    }
+
+    #[test]
+    fn discards_a_signature_of_a_record_changed_while_signing() {
+        insert_nft(10, &hex(&Sha256::digest(b"before")));
+        let mut signer = MockSigner::new();
+        signer.during_sign = Some(Box::new(|| insert_nft(10, &hex(&Sha256::digest(b"after")))));
+        let subject = SignedSubject::Certificate { nft_id: 10 };
+
+        let result = block_on(sign_subject(&signer, subject, 1));
+
+        assert!(result.is_err());
+        assert_eq!(get_signature(subject), None);
+    }
+
+    #[test]
+    fn only_moderators_and_controllers_request_signatures() {
+        insert_nft(12, &hex(&Sha256::digest(b"issued")));
+        let subject = SignedSubject::Certificate { nft_id: 12 };
+        for role in [UserRole::Institution, UserRole::Expert, UserRole::Community] {
+            let caller = crate::modules::testing::principal(3);
+            crate::modules::testing::register(caller, role, &[]);
+            crate::modules::testing::set_caller(caller);
+            assert_eq!(
+                block_on(sign_record(subject)).unwrap_err(),
+                "Only moderators and controllers can request signatures"
+            );
+        }
+        assert_eq!(get_signature(subject), None);
+    }
+
+    #[test]
+    fn refuses_records_without_a_digest() {
+        insert_nft(11, "not-a-sha256");
+        let signer = MockSigner::new();
+
+        assert!(block_on(sign_subject(&signer, SignedSubject::Certificate { nft_id: 11 }, 1)).is_err());
+        assert!(block_on(sign_subject(&signer, SignedSubject::Certificate { nft_id: 999 }, 1)).is_err());
+        assert_eq!(signer.calls.get(), 0);
+    }
+
+    #[test]
+    fn signature_keys_separate_subjects() {
+        let certificate = SignatureKey::new(SignedSubject::Certificate { nft_id: 5 });
+        let provenance = SignatureKey::new(SignedSubject::ProvenanceEntry { artifact_id: 5, entry_id: 0 });
+        assert_ne!(certificate, provenance);
+        assert_eq!(SignatureKey::from_bytes(certificate.to_bytes()), certificate);
+    }
+}
@@ -1,11 +1,25 @@
-use candid::{Encode, Decode, Principal};
+use candid::{CandidType, Encode, Decode, Principal};
 use ic_stable_structures::memory_manager::{MemoryId, MemoryManager, VirtualMemory};
-use ic_stable_structures::{DefaultMemoryImpl, StableBTreeMap, Storable};
+use ic_stable_structures::{DefaultMemoryImpl, StableBTreeMap, StableCell, Storable};
+use serde::de::DeserializeOwned;
 use std::borrow::Cow;
 use std::cell::RefCell;
 
 use crate::modules::types::*;
 use crate::modules::ai_analysis::{AIAnalysisResult};
+use crate::modules::utils::seal_legacy_history;
+use crate::modules::migrations::QuarantinedRecord;
+use crate::modules::lifecycle::LifecycleState;
+use crate::modules::indexes::IndexKey;
+use crate::modules::search::{PostingKey, Posting, SearchStats};
+use crate::modules::geo::{GeoKey, GeoPoint};
+use crate::modules::media::{MediaAsset, MediaUpload, MediaChunkKey, MediaChunk};
+use crate::modules::signing::{SignatureKey, SignatureRecord, SignerConfig};
+use crate::modules::scheduler::SchedulerConfig;
+use crate::modules::governance::{rule_for, GovernanceRules};
+use crate::modules::delegation::UserDelegations;
+use crate::modules::icrc3::Icrc3Value;
+use crate::modules::custody::CustodyTransfer;
 // Commented out disabled modules
 // use crate::modules::collaboration::{CollaborationRoom, Message, VirtualEvent};
 // use crate::modules::analytics::{AnalyticsReport, PatternAnalysis};
@@ -23,6 +37,29 @@ pub type ProposalStore = StableBTreeMap<u64, Proposal, Memory>;
 pub type UserStore = StableBTreeMap<Principal, User, Memory>;
 pub type VoteStore = StableBTreeMap<u64, Vote, Memory>;
 pub type AuditLogStore = StableBTreeMap<u64, AuditEntry, Memory>;
+pub type QuarantineStore = StableBTreeMap<u64, QuarantinedRecord, Memory>;
+pub type LifecycleStateStore = StableCell<LifecycleState, Memory>;
+pub type ArtifactIndexStore = StableBTreeMap<IndexKey, (), Memory>;
+pub type SearchPostingStore = StableBTreeMap<PostingKey, Posting, Memory>;
+pub type SearchDocumentStore = StableBTreeMap<u64, u32, Memory>;
+pub type SearchStatsStore = StableCell<SearchStats, Memory>;
+pub type GeoIndexStore = StableBTreeMap<GeoKey, GeoPoint, Memory>;
+pub type MediaAssetStore = StableBTreeMap<u64, MediaAsset, Memory>;
+pub type MediaChunkStore = StableBTreeMap<MediaChunkKey, MediaChunk, Memory>;
+pub type MediaUploadStore = StableBTreeMap<u64, MediaUpload, Memory>;
+pub type CertificateDocumentStore = StableBTreeMap<u64, String, Memory>;
+pub type SignerConfigStore = StableCell<SignerConfig, Memory>;
+pub type SchedulerConfigStore = StableCell<SchedulerConfig, Memory>;
+pub type GovernanceRulesStore = StableCell<GovernanceRules, Memory>;
+pub type SignatureStore = StableBTreeMap<SignatureKey, SignatureRecord, Memory>;
+pub type Icrc3BlockStore = StableBTreeMap<u64, Icrc3Value, Memory>;
+pub type CustodyTransferStore = StableBTreeMap<u64, CustodyTransfer, Memory>;
+pub type DelegationStore = StableBTreeMap<Principal, UserDelegations, Memory>;
+pub type CounterStore = StableCell<u64, Memory>;
+pub type NftOwnerIndexStore = StableBTreeMap<(Principal, u64), (), Memory>;
+pub type ProposalScheduleStore = StableBTreeMap<(u64, u64), (), Memory>;
+pub type VoteIndexStore = StableBTreeMap<(u64, u64), (), Memory>;
+pub type DelegateIndexStore = StableBTreeMap<(Principal, Principal), (), Memory>;
 
 // New amazing features storage
 pub type AIAnalysisStore = StableBTreeMap<u64, AIAnalysisResult, Memory>;
@@ -38,50 +75,170 @@ pub type CommunityStatsStore = RefCell<crate::modules::community::CommunityStats
 // pub type UserProgressStore = StableBTreeMap<Principal, UserProgress, Memory>;
 // pub type QuestStore = StableBTreeMap<u64, Quest, Memory>;
 
+// ============================================================================
+// VERSIONED RECORD ENVELOPE
+// ============================================================================
+//
+// Every stored record is written as `b"ASLV" | schema version (u16 BE) | Candid payload`.
+// Records written before the envelope existed are bare Candid (starting with "DIDL")
+// and are read as version 0.
+//
+// When a stored type changes shape, bump its SCHEMA_VERSION, keep a snapshot of the
+// previous shape and add an arm to `decode_version` that upgrades it. The post_upgrade
+// migration pass (`migrations::run_schema_migrations`) then rewrites older records.
+
+const RECORD_MAGIC: &[u8; 4] = b"ASLV";
+pub const LEGACY_SCHEMA_VERSION: u16 = 0;
+
+pub trait VersionedRecord: Sized + CandidType {
+    const RECORD_NAME: &'static str;
+    const SCHEMA_VERSION: u16;
+
+    fn decode_version(version: u16, payload: &[u8]) -> Result<Self, String>;
+}
+
+pub fn split_envelope(bytes: &[u8]) -> (u16, &[u8]) {
+    match bytes.strip_prefix(RECORD_MAGIC) {
+        Some(rest) if rest.len() >= 2 => (u16::from_be_bytes([rest[0], rest[1]]), &rest[2..]),
+        _ => (LEGACY_SCHEMA_VERSION, bytes),
+    }
+}
+
+pub fn encode_versioned<T: VersionedRecord>(record: &T) -> Vec<u8> {
+    let payload = Encode!(record).expect("Failed to encode stored record");
+    let mut bytes = Vec::with_capacity(RECORD_MAGIC.len() + 2 + payload.len());
+    bytes.extend_from_slice(RECORD_MAGIC);
+    bytes.extend_from_slice(&T::SCHEMA_VERSION.to_be_bytes());
+    bytes.extend_from_slice(&payload);
+    bytes
+}
+
+pub fn decode_versioned<T: VersionedRecord>(bytes: &[u8]) -> Result<T, String> {
+    let (version, payload) = split_envelope(bytes);
+    if version > T::SCHEMA_VERSION {
+        return Err(format!(
+            "{} schema version {} is newer than supported version {}",
+            T::RECORD_NAME, version, T::SCHEMA_VERSION
+        ));
+    }
+    T::decode_version(version, payload)
+}
+
+// Records that cannot be decoded are moved to quarantine by the post_upgrade
+// migration pass, so failing here means the store is corrupted.
+fn decode_stored<T: VersionedRecord>(bytes: &[u8]) -> T {
+    decode_versioned(bytes).unwrap_or_else(|error| {
+        ic_cdk::trap(&format!("Failed to decode stored {} record: {}", T::RECORD_NAME, error))
+    })
+}
+
+fn decode_candid<T: CandidType + DeserializeOwned>(payload: &[u8]) -> Result<T, String> {
+    Decode!(payload, T).map_err(|e| e.to_string())
+}
+
+fn unsupported_version(record_name: &str, version: u16) -> String {
+    format!("No upgrade path for {} schema version {}", record_name, version)
+}
+
 // ============================================================================
 // STORABLE IMPLEMENTATIONS
 // ============================================================================
 
+// Artifact up to schema v1, before uploaded files moved to the media store
+#[derive(CandidType, serde::Deserialize)]
+struct ArtifactV1 {
+    id: u64,
+    name: String,
+    description: String,
+    metadata: Vec<(String, String)>,
+    images: Vec<String>,
+    creator: Principal,
+    created_at: u64,
+    updated_at: u64,
+    status: ArtifactStatus,
+    heritage_proof: Option<String>,
+    authenticity_score: u32,
+    history: Vec<HistoryEntry>,
+    verification_level: VerificationLevel,
+    cultural_significance: CulturalSignificance,
+    geographic_origin: Option<GeographicOrigin>,
+    dating_information: Option<DatingInformation>,
+    physical_properties: Option<PhysicalProperties>,
+    conservation_status: ConservationStatus,
+    digital_fingerprint: Option<String>,
+}
+
+impl From<ArtifactV1> for Artifact {
+    // Inline images stay in `images` until media::migrate_inline_images moves them
+    fn from(v1: ArtifactV1) -> Self {
+        Artifact {
+            id: v1.id,
+            name: v1.name,
+            description: v1.description,
+            metadata: v1.metadata,
+            images: v1.images,
+            media: Vec::new(),
+            creator: v1.creator,
+            created_at: v1.created_at,
+            updated_at: v1.updated_at,
+            status: v1.status,
+            heritage_proof: v1.heritage_proof,
+            authenticity_score: v1.authenticity_score,
+            history: v1.history,
+            verification_level: v1.verification_level,
+            cultural_significance: v1.cultural_significance,
+            geographic_origin: v1.geographic_origin,
+            dating_information: v1.dating_information,
+            physical_properties: v1.physical_properties,
+            conservation_status: v1.conservation_status,
+            digital_fingerprint: v1.digital_fingerprint,
+        }
+    }
+}
+
+impl VersionedRecord for Artifact {
+    const RECORD_NAME: &'static str = "Artifact";
+    const SCHEMA_VERSION: u16 = 2;
+
+    fn decode_version(version: u16, payload: &[u8]) -> Result<Self, String> {
+        match version {
+            // v0: bare Candid; history entries had no `previous_hash`, which decodes
+            // as None, and are sealed into the chain here
+            0 => decode_candid::<ArtifactV1>(payload).map(|v1| {
+                let mut artifact = Artifact::from(v1);
+                seal_legacy_history(artifact.id, &mut artifact.history);
+                artifact
+            }),
+            // v1: no `media`
+            1 => decode_candid::<ArtifactV1>(payload).map(Artifact::from),
+            2 => decode_candid(payload),
+            _ => Err(unsupported_version(Self::RECORD_NAME, version)),
+        }
+    }
+}
+
 impl Storable for Artifact {
     const BOUND: ic_stable_structures::storable::Bound = ic_stable_structures::storable::Bound::Unbounded;
 
-    fn to_bytes(&self) -> Cow<[u8]> {
-        Cow::Owned(Encode!(self).unwrap())
+    fn to_bytes(&self) -> Cow<'_, [u8]> {
+        Cow::Owned(encode_versioned(self))
     }
 
     fn from_bytes(bytes: Cow<[u8]>) -> Self {
-        match Decode!(bytes.as_ref(), Self) {
-            Ok(artifact) => artifact,
-            Err(_) => {
-                // Default artifact for legacy compatibility
-                Self {
-                    id: 0,
-                    name: "Legacy Artifact".to_string(),
-                    description: "This artifact was created with an older format".to_string(),
-                    metadata: vec![],
-                    images: vec![],
-                    creator: Principal::anonymous(),
-                    created_at: 0,
-                    updated_at: 0,
-                    status: ArtifactStatus::PendingVerification,
-                    heritage_proof: None,
-                    authenticity_score: 0,
-                    history: vec![],
-                    verification_level: VerificationLevel::Unverified,
-                    cultural_significance: CulturalSignificance {
-                        historical_period: None,
-                        cultural_group: None,
-                        significance_level: SignificanceLevel::Local,
-                        unesco_status: None,
-                        cultural_tags: Vec::new(),
-                    },
-                    geographic_origin: None,
-                    dating_information: None,
-                    physical_properties: None,
-                    conservation_status: ConservationStatus::Good,
-                    digital_fingerprint: None,
-                }
-            }
+        decode_stored(bytes.as_ref())
+    }
+}
+
+impl VersionedRecord for ProofOfHeritageNFT {
+    const RECORD_NAME: &'static str = "ProofOfHeritageNFT";
+    const SCHEMA_VERSION: u16 = 1;
+
+    fn decode_version(version: u16, payload: &[u8]) -> Result<Self, String> {
+        match version {
+            // v0: bare Candid with the same shape
+            0 => decode_candid(payload),
+            1 => decode_candid(payload),
+            _ => Err(unsupported_version(Self::RECORD_NAME, version)),
         }
     }
 }
@@ -89,72 +246,532 @@ impl Storable for Artifact {
 impl Storable for ProofOfHeritageNFT {
     const BOUND: ic_stable_structures::storable::Bound = ic_stable_structures::storable::Bound::Unbounded;
 
-    fn to_bytes(&self) -> Cow<[u8]> {
-        Cow::Owned(Encode!(self).unwrap())
+    fn to_bytes(&self) -> Cow<'_, [u8]> {
+        Cow::Owned(encode_versioned(self))
     }
 
     fn from_bytes(bytes: Cow<[u8]>) -> Self {
-        Decode!(bytes.as_ref(), Self).unwrap()
+        decode_stored(bytes.as_ref())
+    }
+}
+
+// Proposal up to schema v1, before voting modes
+#[derive(CandidType, serde::Deserialize)]
+struct ProposalV1 {
+    id: u64,
+    proposal_type: ProposalType,
+    artifact_id: Option<u64>,
+    proposer: Principal,
+    title: String,
+    description: String,
+    evidence: Vec<String>,
+    created_at: u64,
+    voting_deadline: u64,
+    execution_deadline: Option<u64>,
+    quorum_required: u32,
+    status: ProposalStatus,
+    voting_results: VotingResults,
+    execution_payload: Option<ProposalAction>,
+    discussion_thread: Vec<Comment>,
+    required_expertise: Vec<String>,
+    urgency_level: UrgencyLevel,
+}
+
+impl From<ProposalV1> for ProposalV2 {
+    // Votes already cast on older proposals were weighed linearly
+    fn from(v1: ProposalV1) -> Self {
+        ProposalV2 {
+            id: v1.id,
+            proposal_type: v1.proposal_type,
+            artifact_id: v1.artifact_id,
+            proposer: v1.proposer,
+            title: v1.title,
+            description: v1.description,
+            evidence: v1.evidence,
+            created_at: v1.created_at,
+            voting_deadline: v1.voting_deadline,
+            execution_deadline: v1.execution_deadline,
+            quorum_required: v1.quorum_required,
+            status: v1.status,
+            voting_results: v1.voting_results,
+            execution_payload: v1.execution_payload,
+            discussion_thread: v1.discussion_thread,
+            required_expertise: v1.required_expertise,
+            urgency_level: v1.urgency_level,
+            voting_mode: VotingMode::Linear,
+        }
+    }
+}
+
+// Proposal at schema v2, before the pass threshold and abstention policy were
+// fixed at creation
+#[derive(CandidType, serde::Deserialize)]
+struct ProposalV2 {
+    id: u64,
+    proposal_type: ProposalType,
+    artifact_id: Option<u64>,
+    proposer: Principal,
+    title: String,
+    description: String,
+    evidence: Vec<String>,
+    created_at: u64,
+    voting_deadline: u64,
+    execution_deadline: Option<u64>,
+    quorum_required: u32,
+    status: ProposalStatus,
+    voting_results: VotingResults,
+    execution_payload: Option<ProposalAction>,
+    discussion_thread: Vec<Comment>,
+    required_expertise: Vec<String>,
+    urgency_level: UrgencyLevel,
+    voting_mode: VotingMode,
+}
+
+impl From<ProposalV2> for Proposal {
+    // Older proposals take the rule in force when they are migrated
+    fn from(v2: ProposalV2) -> Self {
+        let rule = rule_for(&v2.proposal_type);
+        Proposal {
+            id: v2.id,
+            proposal_type: v2.proposal_type,
+            artifact_id: v2.artifact_id,
+            proposer: v2.proposer,
+            title: v2.title,
+            description: v2.description,
+            evidence: v2.evidence,
+            created_at: v2.created_at,
+            voting_deadline: v2.voting_deadline,
+            execution_deadline: v2.execution_deadline,
+            quorum_required: v2.quorum_required,
+            pass_threshold_percent: rule.pass_threshold_percent,
+            abstention: rule.abstention,
+            status: v2.status,
+            voting_results: v2.voting_results,
+            execution_payload: v2.execution_payload,
+            discussion_thread: v2.discussion_thread,
+            required_expertise: v2.required_expertise,
+            urgency_level: v2.urgency_level,
+            voting_mode: v2.voting_mode,
+        }
+    }
+}
+
+impl VersionedRecord for Proposal {
+    const RECORD_NAME: &'static str = "Proposal";
+    const SCHEMA_VERSION: u16 = 3;
+
+    fn decode_version(version: u16, payload: &[u8]) -> Result<Self, String> {
+        match version {
+            // v0: bare Candid; the free-text `execution_payload` decodes as None
+            0 => decode_candid::<ProposalV1>(payload).map(|v1| Proposal::from(ProposalV2::from(v1))),
+            // v1: no `voting_mode`
+            1 => decode_candid::<ProposalV1>(payload).map(|v1| Proposal::from(ProposalV2::from(v1))),
+            // v2: no `pass_threshold_percent` or `abstention`
+            2 => decode_candid::<ProposalV2>(payload).map(Proposal::from),
+            3 => decode_candid(payload),
+            _ => Err(unsupported_version(Self::RECORD_NAME, version)),
+        }
     }
 }
 
 impl Storable for Proposal {
     const BOUND: ic_stable_structures::storable::Bound = ic_stable_structures::storable::Bound::Unbounded;
 
-    fn to_bytes(&self) -> Cow<[u8]> {
-        Cow::Owned(Encode!(self).unwrap())
+    fn to_bytes(&self) -> Cow<'_, [u8]> {
+        Cow::Owned(encode_versioned(self))
     }
 
     fn from_bytes(bytes: Cow<[u8]>) -> Self {
-        Decode!(bytes.as_ref(), Self).unwrap()
+        decode_stored(bytes.as_ref())
+    }
+}
+
+impl VersionedRecord for User {
+    const RECORD_NAME: &'static str = "User";
+    const SCHEMA_VERSION: u16 = 1;
+
+    fn decode_version(version: u16, payload: &[u8]) -> Result<Self, String> {
+        match version {
+            // v0: bare Candid with the same shape
+            0 => decode_candid(payload),
+            1 => decode_candid(payload),
+            _ => Err(unsupported_version(Self::RECORD_NAME, version)),
+        }
     }
 }
 
 impl Storable for User {
     const BOUND: ic_stable_structures::storable::Bound = ic_stable_structures::storable::Bound::Unbounded;
 
-    fn to_bytes(&self) -> Cow<[u8]> {
-        Cow::Owned(Encode!(self).unwrap())
+    fn to_bytes(&self) -> Cow<'_, [u8]> {
+        Cow::Owned(encode_versioned(self))
     }
 
     fn from_bytes(bytes: Cow<[u8]>) -> Self {
-        Decode!(bytes.as_ref(), Self).unwrap()
+        decode_stored(bytes.as_ref())
+    }
+}
+
+impl VersionedRecord for Vote {
+    const RECORD_NAME: &'static str = "Vote";
+    const SCHEMA_VERSION: u16 = 1;
+
+    fn decode_version(version: u16, payload: &[u8]) -> Result<Self, String> {
+        match version {
+            // v0: bare Candid with the same shape
+            0 => decode_candid(payload),
+            1 => decode_candid(payload),
+            _ => Err(unsupported_version(Self::RECORD_NAME, version)),
+        }
     }
 }
 
 impl Storable for Vote {
     const BOUND: ic_stable_structures::storable::Bound = ic_stable_structures::storable::Bound::Unbounded;
 
-    fn to_bytes(&self) -> Cow<[u8]> {
-        Cow::Owned(Encode!(self).unwrap())
+    fn to_bytes(&self) -> Cow<'_, [u8]> {
+        Cow::Owned(encode_versioned(self))
     }
 
     fn from_bytes(bytes: Cow<[u8]>) -> Self {
-        Decode!(bytes.as_ref(), Self).unwrap()
+        decode_stored(bytes.as_ref())
+    }
+}
+
+impl VersionedRecord for AuditEntry {
+    const RECORD_NAME: &'static str = "AuditEntry";
+    const SCHEMA_VERSION: u16 = 1;
+
+    fn decode_version(version: u16, payload: &[u8]) -> Result<Self, String> {
+        match version {
+            // v0: bare Candid with the same shape
+            0 => decode_candid(payload),
+            1 => decode_candid(payload),
+            _ => Err(unsupported_version(Self::RECORD_NAME, version)),
+        }
     }
 }
 
 impl Storable for AuditEntry {
     const BOUND: ic_stable_structures::storable::Bound = ic_stable_structures::storable::Bound::Unbounded;
 
-    fn to_bytes(&self) -> Cow<[u8]> {
-        Cow::Owned(Encode!(self).unwrap())
+    fn to_bytes(&self) -> Cow<'_, [u8]> {
+        Cow::Owned(encode_versioned(self))
     }
 
     fn from_bytes(bytes: Cow<[u8]>) -> Self {
-        Decode!(bytes.as_ref(), Self).unwrap()
+        decode_stored(bytes.as_ref())
+    }
+}
+
+impl VersionedRecord for crate::modules::community::CommunityPost {
+    const RECORD_NAME: &'static str = "CommunityPost";
+    const SCHEMA_VERSION: u16 = 1;
+
+    fn decode_version(version: u16, payload: &[u8]) -> Result<Self, String> {
+        match version {
+            // v0: bare Candid with the same shape
+            0 => decode_candid(payload),
+            1 => decode_candid(payload),
+            _ => Err(unsupported_version(Self::RECORD_NAME, version)),
+        }
     }
 }
 
 impl Storable for crate::modules::community::CommunityPost {
     const BOUND: ic_stable_structures::storable::Bound = ic_stable_structures::storable::Bound::Unbounded;
 
-    fn to_bytes(&self) -> Cow<[u8]> {
-        Cow::Owned(Encode!(self).unwrap())
+    fn to_bytes(&self) -> Cow<'_, [u8]> {
+        Cow::Owned(encode_versioned(self))
+    }
+
+    fn from_bytes(bytes: Cow<[u8]>) -> Self {
+        decode_stored(bytes.as_ref())
+    }
+}
+
+impl VersionedRecord for QuarantinedRecord {
+    const RECORD_NAME: &'static str = "QuarantinedRecord";
+    const SCHEMA_VERSION: u16 = 1;
+
+    fn decode_version(version: u16, payload: &[u8]) -> Result<Self, String> {
+        match version {
+            1 => decode_candid(payload),
+            _ => Err(unsupported_version(Self::RECORD_NAME, version)),
+        }
+    }
+}
+
+impl Storable for QuarantinedRecord {
+    const BOUND: ic_stable_structures::storable::Bound = ic_stable_structures::storable::Bound::Unbounded;
+
+    fn to_bytes(&self) -> Cow<'_, [u8]> {
+        Cow::Owned(encode_versioned(self))
+    }
+
+    fn from_bytes(bytes: Cow<[u8]>) -> Self {
+        decode_stored(bytes.as_ref())
+    }
+}
+
+// LifecycleState up to schema v1, before upgrade task versions
+#[derive(CandidType, serde::Deserialize)]
+struct LifecycleStateV1 {
+    memory_layout: Vec<crate::modules::lifecycle::MemoryAssignment>,
+    community_stats: Option<crate::modules::community::CommunityStats>,
+    saved_at: u64,
+}
+
+impl From<LifecycleStateV1> for LifecycleState {
+    // No task has run yet, so the first upgrade runs them all
+    fn from(v1: LifecycleStateV1) -> Self {
+        LifecycleState {
+            memory_layout: v1.memory_layout,
+            community_stats: v1.community_stats,
+            saved_at: v1.saved_at,
+            upgrade_tasks: Vec::new(),
+        }
+    }
+}
+
+impl VersionedRecord for LifecycleState {
+    const RECORD_NAME: &'static str = "LifecycleState";
+    const SCHEMA_VERSION: u16 = 2;
+
+    fn decode_version(version: u16, payload: &[u8]) -> Result<Self, String> {
+        match version {
+            1 => decode_candid::<LifecycleStateV1>(payload).map(LifecycleState::from),
+            2 => decode_candid(payload),
+            _ => Err(unsupported_version(Self::RECORD_NAME, version)),
+        }
+    }
+}
+
+impl Storable for LifecycleState {
+    const BOUND: ic_stable_structures::storable::Bound = ic_stable_structures::storable::Bound::Unbounded;
+
+    fn to_bytes(&self) -> Cow<'_, [u8]> {
+        Cow::Owned(encode_versioned(self))
+    }
+
+    fn from_bytes(bytes: Cow<[u8]>) -> Self {
+        decode_stored(bytes.as_ref())
+    }
+}
+
+impl VersionedRecord for MediaAsset {
+    const RECORD_NAME: &'static str = "MediaAsset";
+    const SCHEMA_VERSION: u16 = 1;
+
+    fn decode_version(version: u16, payload: &[u8]) -> Result<Self, String> {
+        match version {
+            1 => decode_candid(payload),
+            _ => Err(unsupported_version(Self::RECORD_NAME, version)),
+        }
+    }
+}
+
+impl Storable for MediaAsset {
+    const BOUND: ic_stable_structures::storable::Bound = ic_stable_structures::storable::Bound::Unbounded;
+
+    fn to_bytes(&self) -> Cow<'_, [u8]> {
+        Cow::Owned(encode_versioned(self))
+    }
+
+    fn from_bytes(bytes: Cow<[u8]>) -> Self {
+        decode_stored(bytes.as_ref())
+    }
+}
+
+impl VersionedRecord for MediaUpload {
+    const RECORD_NAME: &'static str = "MediaUpload";
+    const SCHEMA_VERSION: u16 = 1;
+
+    fn decode_version(version: u16, payload: &[u8]) -> Result<Self, String> {
+        match version {
+            1 => decode_candid(payload),
+            _ => Err(unsupported_version(Self::RECORD_NAME, version)),
+        }
+    }
+}
+
+impl Storable for MediaUpload {
+    const BOUND: ic_stable_structures::storable::Bound = ic_stable_structures::storable::Bound::Unbounded;
+
+    fn to_bytes(&self) -> Cow<'_, [u8]> {
+        Cow::Owned(encode_versioned(self))
+    }
+
+    fn from_bytes(bytes: Cow<[u8]>) -> Self {
+        decode_stored(bytes.as_ref())
+    }
+}
+
+impl VersionedRecord for Icrc3Value {
+    const RECORD_NAME: &'static str = "Icrc3Value";
+    const SCHEMA_VERSION: u16 = 1;
+
+    fn decode_version(version: u16, payload: &[u8]) -> Result<Self, String> {
+        match version {
+            1 => decode_candid(payload),
+            _ => Err(unsupported_version(Self::RECORD_NAME, version)),
+        }
+    }
+}
+
+impl Storable for Icrc3Value {
+    const BOUND: ic_stable_structures::storable::Bound = ic_stable_structures::storable::Bound::Unbounded;
+
+    fn to_bytes(&self) -> Cow<'_, [u8]> {
+        Cow::Owned(encode_versioned(self))
+    }
+
+    fn from_bytes(bytes: Cow<[u8]>) -> Self {
+        decode_stored(bytes.as_ref())
+    }
+}
+
+impl VersionedRecord for CustodyTransfer {
+    const RECORD_NAME: &'static str = "CustodyTransfer";
+    const SCHEMA_VERSION: u16 = 1;
+
+    fn decode_version(version: u16, payload: &[u8]) -> Result<Self, String> {
+        match version {
+            1 => decode_candid(payload),
+            _ => Err(unsupported_version(Self::RECORD_NAME, version)),
+        }
+    }
+}
+
+impl Storable for CustodyTransfer {
+    const BOUND: ic_stable_structures::storable::Bound = ic_stable_structures::storable::Bound::Unbounded;
+
+    fn to_bytes(&self) -> Cow<'_, [u8]> {
+        Cow::Owned(encode_versioned(self))
+    }
+
+    fn from_bytes(bytes: Cow<[u8]>) -> Self {
+        decode_stored(bytes.as_ref())
+    }
+}
+
+impl VersionedRecord for UserDelegations {
+    const RECORD_NAME: &'static str = "UserDelegations";
+    const SCHEMA_VERSION: u16 = 1;
+
+    fn decode_version(version: u16, payload: &[u8]) -> Result<Self, String> {
+        match version {
+            1 => decode_candid(payload),
+            _ => Err(unsupported_version(Self::RECORD_NAME, version)),
+        }
+    }
+}
+
+impl Storable for UserDelegations {
+    const BOUND: ic_stable_structures::storable::Bound = ic_stable_structures::storable::Bound::Unbounded;
+
+    fn to_bytes(&self) -> Cow<'_, [u8]> {
+        Cow::Owned(encode_versioned(self))
     }
 
     fn from_bytes(bytes: Cow<[u8]>) -> Self {
-        Decode!(bytes.as_ref(), Self).unwrap()
+        decode_stored(bytes.as_ref())
+    }
+}
+
+impl VersionedRecord for SignerConfig {
+    const RECORD_NAME: &'static str = "SignerConfig";
+    const SCHEMA_VERSION: u16 = 1;
+
+    fn decode_version(version: u16, payload: &[u8]) -> Result<Self, String> {
+        match version {
+            1 => decode_candid(payload),
+            _ => Err(unsupported_version(Self::RECORD_NAME, version)),
+        }
+    }
+}
+
+impl Storable for SignerConfig {
+    const BOUND: ic_stable_structures::storable::Bound = ic_stable_structures::storable::Bound::Unbounded;
+
+    fn to_bytes(&self) -> Cow<'_, [u8]> {
+        Cow::Owned(encode_versioned(self))
+    }
+
+    fn from_bytes(bytes: Cow<[u8]>) -> Self {
+        decode_stored(bytes.as_ref())
+    }
+}
+
+impl VersionedRecord for SchedulerConfig {
+    const RECORD_NAME: &'static str = "SchedulerConfig";
+    const SCHEMA_VERSION: u16 = 1;
+
+    fn decode_version(version: u16, payload: &[u8]) -> Result<Self, String> {
+        match version {
+            1 => decode_candid(payload),
+            _ => Err(unsupported_version(Self::RECORD_NAME, version)),
+        }
+    }
+}
+
+impl Storable for SchedulerConfig {
+    const BOUND: ic_stable_structures::storable::Bound = ic_stable_structures::storable::Bound::Unbounded;
+
+    fn to_bytes(&self) -> Cow<'_, [u8]> {
+        Cow::Owned(encode_versioned(self))
+    }
+
+    fn from_bytes(bytes: Cow<[u8]>) -> Self {
+        decode_stored(bytes.as_ref())
+    }
+}
+
+impl VersionedRecord for GovernanceRules {
+    const RECORD_NAME: &'static str = "GovernanceRules";
+    const SCHEMA_VERSION: u16 = 1;
+
+    fn decode_version(version: u16, payload: &[u8]) -> Result<Self, String> {
+        match version {
+            1 => decode_candid(payload),
+            _ => Err(unsupported_version(Self::RECORD_NAME, version)),
+        }
+    }
+}
+
+impl Storable for GovernanceRules {
+    const BOUND: ic_stable_structures::storable::Bound = ic_stable_structures::storable::Bound::Unbounded;
+
+    fn to_bytes(&self) -> Cow<'_, [u8]> {
+        Cow::Owned(encode_versioned(self))
+    }
+
+    fn from_bytes(bytes: Cow<[u8]>) -> Self {
+        decode_stored(bytes.as_ref())
+    }
+}
+
+impl VersionedRecord for SignatureRecord {
+    const RECORD_NAME: &'static str = "SignatureRecord";
+    const SCHEMA_VERSION: u16 = 1;
+
+    fn decode_version(version: u16, payload: &[u8]) -> Result<Self, String> {
+        match version {
+            1 => decode_candid(payload),
+            _ => Err(unsupported_version(Self::RECORD_NAME, version)),
+        }
+    }
+}
+
+impl Storable for SignatureRecord {
+    const BOUND: ic_stable_structures::storable::Bound = ic_stable_structures::storable::Bound::Unbounded;
+
+    fn to_bytes(&self) -> Cow<'_, [u8]> {
+        Cow::Owned(encode_versioned(self))
+    }
+
+    fn from_bytes(bytes: Cow<[u8]>) -> Self {
+        decode_stored(bytes.as_ref())
     }
 }
 
@@ -162,6 +779,103 @@ impl Storable for crate::modules::community::CommunityPost {
 // GLOBAL STATE MANAGEMENT
 // ============================================================================
 
+pub const ID_COUNTER_MEMORY_ID: u8 = 0;
+pub const ARTIFACTS_MEMORY_ID: u8 = 1;
+pub const NFTS_MEMORY_ID: u8 = 2;
+pub const PROPOSALS_MEMORY_ID: u8 = 3;
+pub const USERS_MEMORY_ID: u8 = 4;
+pub const VOTES_MEMORY_ID: u8 = 5;
+pub const AUDIT_LOG_MEMORY_ID: u8 = 6;
+pub const AI_ANALYSES_MEMORY_ID: u8 = 7;
+pub const COMMUNITY_POSTS_MEMORY_ID: u8 = 8;
+// 9-15 and 17 are reserved for the disabled collaboration, analytics and gamification stores
+pub const QUARANTINE_MEMORY_ID: u8 = 16;
+pub const LIFECYCLE_STATE_MEMORY_ID: u8 = 18;
+pub const ARTIFACT_INDEX_MEMORY_ID: u8 = 19;
+pub const SEARCH_POSTINGS_MEMORY_ID: u8 = 20;
+pub const SEARCH_DOCUMENTS_MEMORY_ID: u8 = 21;
+pub const SEARCH_STATS_MEMORY_ID: u8 = 22;
+pub const GEO_INDEX_MEMORY_ID: u8 = 23;
+pub const MEDIA_ASSETS_MEMORY_ID: u8 = 24;
+pub const MEDIA_CHUNKS_MEMORY_ID: u8 = 25;
+pub const MEDIA_UPLOADS_MEMORY_ID: u8 = 26;
+pub const CERTIFICATE_DOCUMENTS_MEMORY_ID: u8 = 27;
+pub const SIGNER_CONFIG_MEMORY_ID: u8 = 28;
+pub const SIGNATURES_MEMORY_ID: u8 = 29;
+pub const ICRC3_BLOCKS_MEMORY_ID: u8 = 30;
+pub const CUSTODY_TRANSFERS_MEMORY_ID: u8 = 31;
+pub const SCHEDULER_CONFIG_MEMORY_ID: u8 = 32;
+pub const GOVERNANCE_RULES_MEMORY_ID: u8 = 33;
+pub const DELEGATIONS_MEMORY_ID: u8 = 34;
+pub const COMMUNITY_ACTIVE_POSTS_MEMORY_ID: u8 = 35;
+pub const NFT_OWNER_INDEX_MEMORY_ID: u8 = 36;
+pub const PROPOSAL_SCHEDULE_MEMORY_ID: u8 = 37;
+pub const VOTE_INDEX_MEMORY_ID: u8 = 38;
+pub const DELEGATE_INDEX_MEMORY_ID: u8 = 39;
+
+// Every memory id ever handed out, including those of disabled stores. Ids are
+// never reused: entries may be appended but not renamed or removed, and
+// post_upgrade refuses a build whose layout contradicts the recorded one.
+pub const MEMORY_LAYOUT: &[(u8, &str)] = &[
+    (ID_COUNTER_MEMORY_ID, "id_counter"),
+    (ARTIFACTS_MEMORY_ID, "artifacts"),
+    (NFTS_MEMORY_ID, "nfts"),
+    (PROPOSALS_MEMORY_ID, "proposals"),
+    (USERS_MEMORY_ID, "users"),
+    (VOTES_MEMORY_ID, "votes"),
+    (AUDIT_LOG_MEMORY_ID, "audit_log"),
+    (AI_ANALYSES_MEMORY_ID, "ai_analyses"),
+    (COMMUNITY_POSTS_MEMORY_ID, "community_posts"),
+    (9, "collaboration_rooms"),
+    (10, "messages"),
+    (11, "virtual_events"),
+    (12, "analytics_reports"),
+    (13, "pattern_analyses"),
+    (14, "enhanced_nfts"),
+    (15, "user_progress"),
+    (QUARANTINE_MEMORY_ID, "quarantine"),
+    (17, "quests"),
+    (LIFECYCLE_STATE_MEMORY_ID, "lifecycle_state"),
+    (ARTIFACT_INDEX_MEMORY_ID, "artifact_index"),
+    (SEARCH_POSTINGS_MEMORY_ID, "search_postings"),
+    (SEARCH_DOCUMENTS_MEMORY_ID, "search_documents"),
+    (SEARCH_STATS_MEMORY_ID, "search_stats"),
+    (GEO_INDEX_MEMORY_ID, "geo_index"),
+    (MEDIA_ASSETS_MEMORY_ID, "media_assets"),
+    (MEDIA_CHUNKS_MEMORY_ID, "media_chunks"),
+    (MEDIA_UPLOADS_MEMORY_ID, "media_uploads"),
+    (CERTIFICATE_DOCUMENTS_MEMORY_ID, "certificate_documents"),
+    (SIGNER_CONFIG_MEMORY_ID, "signer_config"),
+    (SIGNATURES_MEMORY_ID, "signatures"),
+    (ICRC3_BLOCKS_MEMORY_ID, "icrc3_blocks"),
+    (CUSTODY_TRANSFERS_MEMORY_ID, "custody_transfers"),
+    (SCHEDULER_CONFIG_MEMORY_ID, "scheduler_config"),
+    (GOVERNANCE_RULES_MEMORY_ID, "governance_rules"),
+    (DELEGATIONS_MEMORY_ID, "delegations"),
+    (COMMUNITY_ACTIVE_POSTS_MEMORY_ID, "community_active_posts"),
+    (NFT_OWNER_INDEX_MEMORY_ID, "nft_owner_index"),
+    (PROPOSAL_SCHEDULE_MEMORY_ID, "proposal_schedule"),
+    (VOTE_INDEX_MEMORY_ID, "vote_index"),
+    (DELEGATE_INDEX_MEMORY_ID, "delegate_index"),
+];
+
+// Two stores sharing a memory id is a build error rather than silent corruption
+const _: () = {
+    let mut i = 0;
+    while i < MEMORY_LAYOUT.len() {
+        let mut j = i + 1;
+        while j < MEMORY_LAYOUT.len() {
+            assert!(MEMORY_LAYOUT[i].0 != MEMORY_LAYOUT[j].0, "duplicate MemoryId in MEMORY_LAYOUT");
+            j += 1;
+        }
+        i += 1;
+    }
+};
+
+pub fn get_memory(memory_id: u8) -> Memory {
+    MEMORY_MANAGER.with(|m| m.borrow().get(MemoryId::new(memory_id)))
+}
+
 thread_local! {
     static MEMORY_MANAGER: RefCell<MemoryManager<DefaultMemoryImpl>> = RefCell::new(
         MemoryManager::init(DefaultMemoryImpl::default())
@@ -169,43 +883,190 @@ thread_local! {
 
     static ID_COUNTER: RefCell<IdStore> = RefCell::new(
         IdStore::init(
-            MEMORY_MANAGER.with(|m| m.borrow().get(MemoryId::new(0))),
+            get_memory(ID_COUNTER_MEMORY_ID),
         )
     );
 
     pub static ARTIFACTS: RefCell<ArtifactStore> = RefCell::new(
         ArtifactStore::init(
-            MEMORY_MANAGER.with(|m| m.borrow().get(MemoryId::new(1))),
+            get_memory(ARTIFACTS_MEMORY_ID),
+        )
+    );
+
+    // Secondary indexes over ARTIFACTS, maintained by artifacts::store_artifact
+    pub static ARTIFACT_INDEX: RefCell<ArtifactIndexStore> = RefCell::new(
+        ArtifactIndexStore::init(
+            get_memory(ARTIFACT_INDEX_MEMORY_ID),
+        )
+    );
+
+    // Full-text index over ARTIFACTS, see modules::search
+    pub static SEARCH_POSTINGS: RefCell<SearchPostingStore> = RefCell::new(
+        SearchPostingStore::init(
+            get_memory(SEARCH_POSTINGS_MEMORY_ID),
+        )
+    );
+
+    pub static SEARCH_DOCUMENTS: RefCell<SearchDocumentStore> = RefCell::new(
+        SearchDocumentStore::init(
+            get_memory(SEARCH_DOCUMENTS_MEMORY_ID),
+        )
+    );
+
+    pub static SEARCH_STATS: RefCell<SearchStatsStore> = RefCell::new(
+        SearchStatsStore::init(
+            get_memory(SEARCH_STATS_MEMORY_ID),
+            SearchStats::default(),
+        ).expect("Failed to initialize search stats")
+    );
+
+    // Geohash index over artifact coordinates, see modules::geo
+    pub static GEO_INDEX: RefCell<GeoIndexStore> = RefCell::new(
+        GeoIndexStore::init(
+            get_memory(GEO_INDEX_MEMORY_ID),
+        )
+    );
+
+    // Uploaded files, see modules::media
+    pub static MEDIA_ASSETS: RefCell<MediaAssetStore> = RefCell::new(
+        MediaAssetStore::init(
+            get_memory(MEDIA_ASSETS_MEMORY_ID),
+        )
+    );
+
+    pub static MEDIA_CHUNKS: RefCell<MediaChunkStore> = RefCell::new(
+        MediaChunkStore::init(
+            get_memory(MEDIA_CHUNKS_MEMORY_ID),
+        )
+    );
+
+    pub static MEDIA_UPLOADS: RefCell<MediaUploadStore> = RefCell::new(
+        MediaUploadStore::init(
+            get_memory(MEDIA_UPLOADS_MEMORY_ID),
+        )
+    );
+
+    // Canonical certificate documents by NFT id, see modules::certificates
+    pub static CERTIFICATE_DOCUMENTS: RefCell<CertificateDocumentStore> = RefCell::new(
+        CertificateDocumentStore::init(
+            get_memory(CERTIFICATE_DOCUMENTS_MEMORY_ID),
+        )
+    );
+
+    // Threshold key settings and signatures, see modules::signing
+    pub static SIGNER_CONFIG: RefCell<SignerConfigStore> = RefCell::new(
+        SignerConfigStore::init(
+            get_memory(SIGNER_CONFIG_MEMORY_ID),
+            SignerConfig::default(),
+        ).expect("Failed to initialize signer config")
+    );
+
+    pub static SIGNATURES: RefCell<SignatureStore> = RefCell::new(
+        SignatureStore::init(
+            get_memory(SIGNATURES_MEMORY_ID),
+        )
+    );
+
+    // ICRC-3 transaction log of the NFT ledger, see modules::icrc3
+    pub static ICRC3_BLOCKS: RefCell<Icrc3BlockStore> = RefCell::new(
+        Icrc3BlockStore::init(
+            get_memory(ICRC3_BLOCKS_MEMORY_ID),
+        )
+    );
+
+    // Two-phase NFT custody transfers, see modules::custody
+    pub static CUSTODY_TRANSFERS: RefCell<CustodyTransferStore> = RefCell::new(
+        CustodyTransferStore::init(
+            get_memory(CUSTODY_TRANSFERS_MEMORY_ID),
+        )
+    );
+
+    // Proposal lifecycle timer settings, see modules::scheduler
+    pub static SCHEDULER_CONFIG: RefCell<SchedulerConfigStore> = RefCell::new(
+        SchedulerConfigStore::init(
+            get_memory(SCHEDULER_CONFIG_MEMORY_ID),
+            SchedulerConfig::default(),
+        ).expect("Failed to initialize scheduler config")
+    );
+
+    // Per proposal type voting rules, see modules::governance; empty means defaults
+    pub static GOVERNANCE_RULES: RefCell<GovernanceRulesStore> = RefCell::new(
+        GovernanceRulesStore::init(
+            get_memory(GOVERNANCE_RULES_MEMORY_ID),
+            GovernanceRules::default(),
+        ).expect("Failed to initialize governance rules")
+    );
+
+    // Vote delegations by delegator, see modules::delegation
+    pub static DELEGATIONS: RefCell<DelegationStore> = RefCell::new(
+        DelegationStore::init(
+            get_memory(DELEGATIONS_MEMORY_ID),
+        )
+    );
+
+    // (delegate, delegator) of every delegation, maintained by delegation::save_delegations
+    pub static DELEGATE_INDEX: RefCell<DelegateIndexStore> = RefCell::new(
+        DelegateIndexStore::init(
+            get_memory(DELEGATE_INDEX_MEMORY_ID),
+        )
+    );
+
+    // Number of Active community posts, the total of the paged post listing
+    pub static COMMUNITY_ACTIVE_POSTS: RefCell<CounterStore> = RefCell::new(
+        CounterStore::init(
+            get_memory(COMMUNITY_ACTIVE_POSTS_MEMORY_ID),
+            0,
+        ).expect("Failed to initialize community post count")
+    );
+
+    // NFT ids by owner, maintained by nft::store_nft
+    pub static NFT_OWNER_INDEX: RefCell<NftOwnerIndexStore> = RefCell::new(
+        NftOwnerIndexStore::init(
+            get_memory(NFT_OWNER_INDEX_MEMORY_ID),
         )
     );
 
     pub static NFTS: RefCell<NFTStore> = RefCell::new(
         NFTStore::init(
-            MEMORY_MANAGER.with(|m| m.borrow().get(MemoryId::new(2))),
+            get_memory(NFTS_MEMORY_ID),
         )
     );
 
     pub static PROPOSALS: RefCell<ProposalStore> = RefCell::new(
         ProposalStore::init(
-            MEMORY_MANAGER.with(|m| m.borrow().get(MemoryId::new(3))),
+            get_memory(PROPOSALS_MEMORY_ID),
+        )
+    );
+
+    // (due at, proposal id) of open proposals, maintained by dao::store_proposal
+    pub static PROPOSAL_SCHEDULE: RefCell<ProposalScheduleStore> = RefCell::new(
+        ProposalScheduleStore::init(
+            get_memory(PROPOSAL_SCHEDULE_MEMORY_ID),
         )
     );
 
     pub static USERS: RefCell<UserStore> = RefCell::new(
         UserStore::init(
-            MEMORY_MANAGER.with(|m| m.borrow().get(MemoryId::new(4))),
+            get_memory(USERS_MEMORY_ID),
         )
     );
 
     pub static VOTES: RefCell<VoteStore> = RefCell::new(
         VoteStore::init(
-            MEMORY_MANAGER.with(|m| m.borrow().get(MemoryId::new(5))),
+            get_memory(VOTES_MEMORY_ID),
+        )
+    );
+
+    // (proposal id, vote id) of every vote, maintained by voting::store_vote
+    pub static VOTE_INDEX: RefCell<VoteIndexStore> = RefCell::new(
+        VoteIndexStore::init(
+            get_memory(VOTE_INDEX_MEMORY_ID),
         )
     );
 
     pub static AUDIT_LOG: RefCell<AuditLogStore> = RefCell::new(
         AuditLogStore::init(
-            MEMORY_MANAGER.with(|m| m.borrow().get(MemoryId::new(6))),
+            get_memory(AUDIT_LOG_MEMORY_ID),
         )
     );
 
@@ -215,16 +1076,29 @@ thread_local! {
 
     pub static AI_ANALYSES: RefCell<AIAnalysisStore> = RefCell::new(
         AIAnalysisStore::init(
-            MEMORY_MANAGER.with(|m| m.borrow().get(MemoryId::new(7))),
+            get_memory(AI_ANALYSES_MEMORY_ID),
         )
     );
 
     pub static COMMUNITY_POSTS: RefCell<CommunityPostStore> = RefCell::new(
         CommunityPostStore::init(
-            MEMORY_MANAGER.with(|m| m.borrow().get(MemoryId::new(8))),
+            get_memory(COMMUNITY_POSTS_MEMORY_ID),
+        )
+    );
+
+    pub static QUARANTINED_RECORDS: RefCell<QuarantineStore> = RefCell::new(
+        QuarantineStore::init(
+            get_memory(QUARANTINE_MEMORY_ID),
         )
     );
 
+    pub static LIFECYCLE_STATE: RefCell<LifecycleStateStore> = RefCell::new(
+        LifecycleStateStore::init(
+            get_memory(LIFECYCLE_STATE_MEMORY_ID),
+            LifecycleState::default(),
+        ).expect("Failed to initialize lifecycle state")
+    );
+
     pub static COMMUNITY_STATS: CommunityStatsStore = RefCell::new(
         crate::modules::community::CommunityStats {
             total_members: 0,
@@ -283,7 +1157,7 @@ thread_local! {
 
     // pub static QUESTS: RefCell<QuestStore> = RefCell::new(
     //     QuestStore::init(
-    //         MEMORY_MANAGER.with(|m| m.borrow().get(MemoryId::new(15))),
+    //         MEMORY_MANAGER.with(|m| m.borrow().get(MemoryId::new(17))),
     //     )
     // );
 }
@@ -292,7 +1166,137 @@ thread_local! {
 // ID MANAGEMENT
 // ============================================================================
 
-pub fn get_next_id(counter_key: u64) -> u64 {
+// Each kind of record draws its ids from its own counter in ID_COUNTER
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum IdKind {
+    Artifact,
+    Proposal,
+    Nft,
+    Vote,
+    AuditEntry,
+    Comment,
+    // Every `HistoryEntry`, provenance entries included
+    HistoryEntry,
+    CommunityReply,
+    Media,
+    CustodyTransfer,
+    CommunityPost,
+    // Reserved for the disabled collaboration, analytics and gamification modules
+    CollaborationRoom,
+    CollaborationMessage,
+    VirtualEvent,
+    AnalyticsReport,
+    GamificationNft,
+    Achievement,
+    Quest,
+}
+
+impl IdKind {
+    pub const ALL: [IdKind; 18] = [
+        IdKind::Artifact,
+        IdKind::Proposal,
+        IdKind::Nft,
+        IdKind::Vote,
+        IdKind::AuditEntry,
+        IdKind::Comment,
+        IdKind::HistoryEntry,
+        IdKind::CommunityReply,
+        IdKind::Media,
+        IdKind::CustodyTransfer,
+        IdKind::CommunityPost,
+        IdKind::CollaborationRoom,
+        IdKind::CollaborationMessage,
+        IdKind::VirtualEvent,
+        IdKind::AnalyticsReport,
+        IdKind::GamificationNft,
+        IdKind::Achievement,
+        IdKind::Quest,
+    ];
+
+    pub const fn counter_key(self) -> u64 {
+        match self {
+            IdKind::Artifact => 1,
+            IdKind::Proposal => 2,
+            IdKind::Nft => 3,
+            IdKind::Vote => 4,
+            IdKind::AuditEntry => 6,
+            IdKind::Comment => 7,
+            IdKind::HistoryEntry => 8,
+            IdKind::CommunityReply => 9,
+            IdKind::CollaborationRoom => 11,
+            IdKind::CollaborationMessage => 12,
+            IdKind::VirtualEvent => 13,
+            IdKind::AnalyticsReport => 14,
+            IdKind::GamificationNft => 15,
+            IdKind::Achievement => 16,
+            IdKind::Quest => 17,
+            IdKind::CustodyTransfer => 18,
+            IdKind::CommunityPost => 19,
+            IdKind::Media => 20,
+        }
+    }
+}
+
+// Every counter key ever handed out. Like MEMORY_LAYOUT, keys are never
+// reused: retired keys stay listed with no kind, and the disabled modules keep
+// their kinds so nothing else can take their keys.
+pub const ID_COUNTER_LAYOUT: &[(u64, Option<IdKind>, &str)] = &[
+    (1, Some(IdKind::Artifact), "artifact"),
+    (2, Some(IdKind::Proposal), "proposal"),
+    (3, Some(IdKind::Nft), "nft"),
+    (4, Some(IdKind::Vote), "vote"),
+    (6, Some(IdKind::AuditEntry), "audit_entry"),
+    (7, Some(IdKind::Comment), "comment"),
+    (8, Some(IdKind::HistoryEntry), "history_entry"),
+    (9, Some(IdKind::CommunityReply), "community_reply"),
+    // Retired: provenance entries now draw from history_entry
+    (10, None, "provenance_entry"),
+    (11, Some(IdKind::CollaborationRoom), "collaboration_room"),
+    (12, Some(IdKind::CollaborationMessage), "collaboration_message"),
+    (13, Some(IdKind::VirtualEvent), "virtual_event"),
+    (14, Some(IdKind::AnalyticsReport), "analytics_report"),
+    (15, Some(IdKind::GamificationNft), "gamification_nft"),
+    (16, Some(IdKind::Achievement), "achievement"),
+    (17, Some(IdKind::Quest), "quest"),
+    (18, Some(IdKind::CustodyTransfer), "custody_transfer"),
+    (19, Some(IdKind::CommunityPost), "community_post"),
+    (20, Some(IdKind::Media), "media"),
+];
+
+// Two kinds sharing a counter, or a kind missing from the layout, is a build error
+const _: () = {
+    let layout = ID_COUNTER_LAYOUT;
+    let mut i = 0;
+    while i < layout.len() {
+        let mut j = i + 1;
+        while j < layout.len() {
+            assert!(layout[i].0 != layout[j].0, "duplicate counter key in ID_COUNTER_LAYOUT");
+            j += 1;
+        }
+        i += 1;
+    }
+
+    let mut k = 0;
+    while k < IdKind::ALL.len() {
+        let kind = IdKind::ALL[k];
+        let mut registered = 0;
+        let mut i = 0;
+        while i < layout.len() {
+            if let Some(listed) = layout[i].1 {
+                if listed as u8 == kind as u8 {
+                    assert!(layout[i].0 == kind.counter_key(), "IdKind counter key differs from ID_COUNTER_LAYOUT");
+                    registered += 1;
+                }
+            }
+            i += 1;
+        }
+        assert!(registered == 1, "IdKind must be listed exactly once in ID_COUNTER_LAYOUT");
+        k += 1;
+    }
+};
+
+pub fn get_next_id(kind: IdKind) -> u64 {
+    let counter_key = kind.counter_key();
     ID_COUNTER.with(|counter| {
         let mut counter = counter.borrow_mut();
         let current_id = counter.get(&counter_key).unwrap_or(0);
@@ -302,19 +1306,44 @@ pub fn get_next_id(counter_key: u64) -> u64 {
     })
 }
 
+// Raw counter access for migrations, by key so retired counters can be read
+pub fn get_id_counter(counter_key: u64) -> Option<u64> {
+    ID_COUNTER.with(|counter| counter.borrow().get(&counter_key))
+}
+
+pub fn set_id_counter(counter_key: u64, value: u64) {
+    ID_COUNTER.with(|counter| {
+        counter.borrow_mut().insert(counter_key, value);
+    });
+}
+
 // ============================================================================
 // STORABLE IMPLEMENTATIONS FOR NEW FEATURES
 // ============================================================================
 
+impl VersionedRecord for AIAnalysisResult {
+    const RECORD_NAME: &'static str = "AIAnalysisResult";
+    const SCHEMA_VERSION: u16 = 1;
+
+    fn decode_version(version: u16, payload: &[u8]) -> Result<Self, String> {
+        match version {
+            // v0: bare Candid with the same shape
+            0 => decode_candid(payload),
+            1 => decode_candid(payload),
+            _ => Err(unsupported_version(Self::RECORD_NAME, version)),
+        }
+    }
+}
+
 impl Storable for AIAnalysisResult {
     const BOUND: ic_stable_structures::storable::Bound = ic_stable_structures::storable::Bound::Unbounded;
 
-    fn to_bytes(&self) -> Cow<[u8]> {
-        Cow::Owned(Encode!(self).unwrap())
+    fn to_bytes(&self) -> Cow<'_, [u8]> {
+        Cow::Owned(encode_versioned(self))
     }
 
     fn from_bytes(bytes: Cow<[u8]>) -> Self {
-        Decode!(bytes.as_ref(), Self).unwrap()
+        decode_stored(bytes.as_ref())
     }
 }
 
@@ -0,0 +1,272 @@
+use crate::modules::types::*;
+use crate::modules::storage::USERS;
+use crate::modules::delegation::delegated_votes;
+use crate::modules::voting::proposal_votes;
+
+// ============================================================================
+// VOTE TALLYING
+// ============================================================================
+//
+// A proposal's `voting_mode` decides how much each vote weighs:
+//
+//   Linear      the voter's `UserPermissions.voting_weight`
+//   Quadratic   the square root of the reputation credits the voter spends on
+//               the vote; the first credit is free, the rest come out of
+//               `User.reputation`, so intensity costs quadratically
+//   Conviction  the voter's voting weight, growing linearly with the time the
+//               vote has been held to MAX_CONVICTION times its initial weight
+//               after CONVICTION_RAMP_NANOS; changing a vote restarts it
+//
+// Each mode is a `VoteWeighting`. The weight a vote is cast with is stored on
+// the vote and kept in `VotingResults` for display; outcomes are decided by
+// re-tallying the stored votes at decision time, which conviction needs and
+// which resolves delegations (modules::delegation) as they stand then.
+
+pub const CONVICTION_RAMP_NANOS: u64 = 7 * 24 * 3600 * 1_000_000_000;
+pub const MAX_CONVICTION: u64 = 4;
+pub const FREE_CREDITS: u32 = 1;
+
+pub trait VoteWeighting {
+    // Weight stored on the vote when it is cast
+    fn cast_weight(&self, voting_weight: u32, credits: u32) -> u32;
+
+    // Weight the vote carries when tallied at `now`
+    fn tally_weight(&self, vote: &Vote, _now: u64) -> u64 {
+        vote.weight as u64
+    }
+}
+
+pub struct LinearWeighting;
+pub struct QuadraticWeighting;
+pub struct ConvictionWeighting;
+
+impl VoteWeighting for LinearWeighting {
+    fn cast_weight(&self, voting_weight: u32, _credits: u32) -> u32 {
+        voting_weight
+    }
+}
+
+impl VoteWeighting for QuadraticWeighting {
+    fn cast_weight(&self, _voting_weight: u32, credits: u32) -> u32 {
+        credits.isqrt()
+    }
+}
+
+impl VoteWeighting for ConvictionWeighting {
+    fn cast_weight(&self, voting_weight: u32, _credits: u32) -> u32 {
+        voting_weight
+    }
+
+    fn tally_weight(&self, vote: &Vote, now: u64) -> u64 {
+        let held = now.saturating_sub(vote.timestamp).min(CONVICTION_RAMP_NANOS) as u128;
+        let weight = vote.weight as u128;
+        let ramp = CONVICTION_RAMP_NANOS as u128;
+        (weight * (ramp + held * (MAX_CONVICTION as u128 - 1)) / ramp) as u64
+    }
+}
+
+pub fn weighting(mode: &VotingMode) -> &'static dyn VoteWeighting {
+    match mode {
+        VotingMode::Linear => &LinearWeighting,
+        VotingMode::Quadratic => &QuadraticWeighting,
+        VotingMode::Conviction => &ConvictionWeighting,
+    }
+}
+
+// Contentious proposals are quadratic and policy changes run on conviction. The
+// type alone decides, so a proposer cannot pick the tally that suits them.
+pub fn voting_mode_for(proposal_type: &ProposalType) -> VotingMode {
+    match proposal_type {
+        ProposalType::DisputeArtifact | ProposalType::EmergencyIntervention => VotingMode::Quadratic,
+        ProposalType::UpdateVerificationCriteria => VotingMode::Conviction,
+        _ => VotingMode::Linear,
+    }
+}
+
+// Checks the credits a voter offers and returns the weight of their vote and
+// the reputation it costs
+pub fn price_vote(mode: &VotingMode, voting_weight: u32, reputation: u32, credits: Option<u32>) -> Result<(u32, u32), String> {
+    let credits = match (mode, credits) {
+        (VotingMode::Quadratic, credits) => credits.unwrap_or(FREE_CREDITS),
+        (_, None) => 0,
+        (_, Some(_)) => return Err("Credits can only be spent on quadratic votes".to_string()),
+    };
+    if *mode == VotingMode::Quadratic && credits < FREE_CREDITS {
+        return Err("A quadratic vote needs at least one credit".to_string());
+    }
+
+    let cost = credits.saturating_sub(FREE_CREDITS);
+    if cost > reputation {
+        return Err(format!(
+            "Spending {} credits costs {} reputation, but you have {}",
+            credits, cost, reputation
+        ));
+    }
+    Ok((weighting(mode).cast_weight(voting_weight, credits), cost))
+}
+
+pub fn spend_reputation(voter: candid::Principal, cost: u32) {
+    if cost == 0 {
+        return;
+    }
+    USERS.with(|users| {
+        let mut users = users.borrow_mut();
+        if let Some(mut user) = users.get(&voter) {
+            user.reputation = user.reputation.saturating_sub(cost);
+            users.insert(voter, user);
+        }
+    });
+}
+
+// Totals include delegated weight; delegators count as voters
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct VoteTally {
+    pub voters: u32,
+    pub abstaining_voters: u32,
+    pub weight_for: u64,
+    pub weight_against: u64,
+    pub weight_abstaining: u64,
+    pub breakdown: VoteBreakdown,
+}
+
+pub fn tally(mode: &VotingMode, direct: &[Vote], delegated: &[Vote], now: u64) -> VoteTally {
+    let weighting = weighting(mode);
+    let mut tally = VoteTally::default();
+    for (vote, is_delegated) in direct.iter().map(|vote| (vote, false)).chain(delegated.iter().map(|vote| (vote, true))) {
+        let weight = weighting.tally_weight(vote, now);
+        let split = &mut tally.breakdown;
+        let (direct_weight, delegated_weight) = match vote.vote_type {
+            VoteType::For => (&mut split.direct_for, &mut split.delegated_for),
+            VoteType::Against => (&mut split.direct_against, &mut split.delegated_against),
+            VoteType::Abstain | VoteType::RequiresMoreEvidence => {
+                tally.abstaining_voters += 1;
+                (&mut split.direct_abstaining, &mut split.delegated_abstaining)
+            },
+        };
+        if is_delegated {
+            *delegated_weight += weight;
+            split.delegators += 1;
+        } else {
+            *direct_weight += weight;
+        }
+        tally.voters += 1;
+    }
+
+    let split = &tally.breakdown;
+    tally.weight_for = split.direct_for + split.delegated_for;
+    tally.weight_against = split.direct_against + split.delegated_against;
+    tally.weight_abstaining = split.direct_abstaining + split.delegated_abstaining;
+    tally
+}
+
+pub fn tally_votes(proposal: &Proposal, now: u64) -> VoteTally {
+    let direct = proposal_votes(proposal.id);
+    let delegated = delegated_votes(proposal, &direct);
+    tally(&proposal.voting_mode, &direct, &delegated, now)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::modules::governance::{decide, AbstentionPolicy, VotingOutcome};
+    use candid::Principal;
+
+    const DAY: u64 = 24 * 3600 * 1_000_000_000;
+
+    // A voter with a linear voting weight, the credits they would spend on a
+    // quadratic vote, and when they vote
+    struct Voter {
+        vote_type: VoteType,
+        voting_weight: u32,
+        credits: u32,
+        voted_at: u64,
+    }
+
+    fn cast(mode: VotingMode, voters: &[Voter]) -> Vec<Vote> {
+        voters.iter().enumerate().map(|(i, voter)| {
+            let credits = (mode == VotingMode::Quadratic).then_some(voter.credits);
+            let (weight, _) = price_vote(&mode, voter.voting_weight, u32::MAX, credits).unwrap();
+            Vote {
+                id: i as u64,
+                proposal_id: 1,
+                voter: Principal::anonymous(),
+                vote_type: voter.vote_type.clone(),
+                weight,
+                timestamp: voter.voted_at,
+                rationale: None,
+                expertise_relevance: 0,
+            }
+        }).collect()
+    }
+
+    fn outcome(mode: VotingMode, voters: &[Voter], now: u64) -> VotingOutcome {
+        decide(50, AbstentionPolicy::CountTowardQuorum, &tally(&mode, &cast(mode.clone(), voters), &[], now), 1)
+    }
+
+    // One heavyweight against four ordinary voters
+    fn whale_against_crowd() -> Vec<Voter> {
+        let mut voters = vec![Voter { vote_type: VoteType::Against, voting_weight: 9, credits: 9, voted_at: 0 }];
+        voters.extend((0..4).map(|_| Voter { vote_type: VoteType::For, voting_weight: 1, credits: 1, voted_at: 0 }));
+        voters
+    }
+
+    #[test]
+    fn quadratic_voting_dampens_concentrated_weight() {
+        let voters = whale_against_crowd();
+        assert_eq!(outcome(VotingMode::Linear, &voters, DAY), VotingOutcome::Rejected);
+        assert_eq!(outcome(VotingMode::Quadratic, &voters, DAY), VotingOutcome::Passed);
+    }
+
+    #[test]
+    fn conviction_voting_rewards_votes_held_longer() {
+        // The crowd votes on day 0, a voter of equal total weight on day 6
+        let mut voters: Vec<Voter> = (0..4)
+            .map(|_| Voter { vote_type: VoteType::For, voting_weight: 1, credits: 1, voted_at: 0 })
+            .collect();
+        voters.push(Voter { vote_type: VoteType::Against, voting_weight: 5, credits: 25, voted_at: 6 * DAY });
+
+        assert_eq!(outcome(VotingMode::Linear, &voters, 7 * DAY), VotingOutcome::Rejected);
+        assert_eq!(outcome(VotingMode::Quadratic, &voters, 7 * DAY), VotingOutcome::Rejected);
+        assert_eq!(outcome(VotingMode::Conviction, &voters, 7 * DAY), VotingOutcome::Passed);
+    }
+
+    #[test]
+    fn conviction_grows_linearly_up_to_its_cap() {
+        let vote = &cast(VotingMode::Conviction, &[Voter { vote_type: VoteType::For, voting_weight: 10, credits: 0, voted_at: DAY }])[0];
+        let conviction = ConvictionWeighting;
+        assert_eq!(conviction.tally_weight(vote, 0), 10);
+        assert_eq!(conviction.tally_weight(vote, DAY), 10);
+        assert_eq!(conviction.tally_weight(vote, DAY + CONVICTION_RAMP_NANOS / 2), 25);
+        assert_eq!(conviction.tally_weight(vote, DAY + CONVICTION_RAMP_NANOS), 40);
+        assert_eq!(conviction.tally_weight(vote, DAY + 10 * CONVICTION_RAMP_NANOS), 40);
+    }
+
+    #[test]
+    fn delegated_weight_is_reported_apart_from_direct_weight() {
+        let direct = cast(VotingMode::Linear, &[
+            Voter { vote_type: VoteType::For, voting_weight: 3, credits: 0, voted_at: 0 },
+            Voter { vote_type: VoteType::Against, voting_weight: 2, credits: 0, voted_at: 0 },
+        ]);
+        let delegated = vec![Vote { weight: 4, ..direct[1].clone() }];
+
+        let result = tally(&VotingMode::Linear, &direct, &delegated, 0);
+        assert_eq!((result.voters, result.weight_for, result.weight_against), (3, 3, 6));
+        assert_eq!(result.breakdown, VoteBreakdown {
+            direct_for: 3,
+            direct_against: 2,
+            delegated_against: 4,
+            delegators: 1,
+            ..Default::default()
+        });
+    }
+
+    #[test]
+    fn quadratic_credits_cost_reputation() {
+        assert_eq!(price_vote(&VotingMode::Quadratic, 5, 0, None), Ok((1, 0)));
+        assert_eq!(price_vote(&VotingMode::Quadratic, 5, 8, Some(9)), Ok((3, 8)));
+        assert!(price_vote(&VotingMode::Quadratic, 5, 7, Some(9)).is_err());
+        assert!(price_vote(&VotingMode::Quadratic, 5, 7, Some(0)).is_err());
+        assert_eq!(price_vote(&VotingMode::Linear, 5, 0, None), Ok((5, 0)));
+        assert!(price_vote(&VotingMode::Linear, 5, 10, Some(4)).is_err());
+    }
+}
@@ -0,0 +1,104 @@
+use candid::Principal;
+use std::cell::{Cell, RefCell};
+
+use crate::modules::types::*;
+use crate::modules::storage::USERS;
+use crate::modules::auth::default_permissions_for_role;
+use crate::modules::signing::SignedSubject;
+
+// ============================================================================
+// UNIT TEST SUPPORT
+// ============================================================================
+//
+// Unit tests run outside a canister, where the system API is unavailable.
+// Under cfg(test) the clock, the caller, the canister id and signing requests
+// go through here instead. Every test runs on its own thread and so starts
+// with empty stores.
+
+thread_local! {
+    static NOW: Cell<u64> = const { Cell::new(1_700_000_000_000_000_000) };
+    static CALLER: Cell<Principal> = const { Cell::new(Principal::anonymous()) };
+    static SIGNATURE_REQUESTS: RefCell<Vec<SignedSubject>> = const { RefCell::new(Vec::new()) };
+}
+
+pub const HOUR: u64 = 60 * 60 * 1_000_000_000;
+
+pub fn now() -> u64 {
+    NOW.with(|now| now.get())
+}
+
+pub fn advance_time(nanos: u64) {
+    NOW.with(|now| now.set(now.get() + nanos));
+}
+
+pub fn caller() -> Principal {
+    CALLER.with(|caller| caller.get())
+}
+
+pub fn set_caller(principal: Principal) {
+    CALLER.with(|caller| caller.set(principal));
+}
+
+pub fn canister_id() -> Principal {
+    Principal::from_slice(&[0, 0, 0, 0, 0, 0, 0, 1, 1, 1])
+}
+
+pub fn record_signature_request(subject: SignedSubject) {
+    SIGNATURE_REQUESTS.with(|requests| requests.borrow_mut().push(subject));
+}
+
+// Subjects handed to signing::request_signature so far
+pub fn signature_requests() -> Vec<SignedSubject> {
+    SIGNATURE_REQUESTS.with(|requests| requests.borrow().clone())
+}
+
+pub fn principal(id: u8) -> Principal {
+    Principal::from_slice(&[id])
+}
+
+// Registers `principal` with the role's default permissions
+pub fn register(principal: Principal, role: UserRole, specialization: &[&str]) {
+    let user = User {
+        permissions: default_permissions_for_role(&role),
+        role,
+        reputation: 0,
+        verified_at: None,
+        institution: None,
+        specialization: specialization.iter().map(|area| area.to_string()).collect(),
+        activity_stats: UserStats {
+            artifacts_submitted: 0,
+            proposals_created: 0,
+            votes_cast: 0,
+            successful_verifications: 0,
+            peer_ratings: Vec::new(),
+            last_activity: now(),
+        },
+        verification_level: UserVerificationLevel::Unverified,
+    };
+    USERS.with(|users| users.borrow_mut().insert(principal, user));
+}
+
+pub fn artifact_request(name: &str) -> CreateArtifactRequest {
+    CreateArtifactRequest {
+        name: name.to_string(),
+        description: format!("{} recovered from a documented excavation", name),
+        metadata: Vec::new(),
+        images: Vec::new(),
+        heritage_proof: None,
+        cultural_significance: None,
+        geographic_origin: None,
+        dating_information: None,
+        physical_properties: None,
+        conservation_status: None,
+        media: None,
+    }
+}
+
+// Creates an artifact as `creator`, registering them as an institution first
+pub fn create_artifact_as(creator: Principal, request: CreateArtifactRequest) -> u64 {
+    if USERS.with(|users| !users.borrow().contains_key(&creator)) {
+        register(creator, UserRole::Institution, &[]);
+    }
+    set_caller(creator);
+    crate::modules::artifacts::create_artifact(request).expect("Failed to create test artifact")
+}
@@ -2,6 +2,8 @@ use candid::{CandidType, Principal};
 use serde::{Deserialize, Serialize};
 use std::collections::BTreeSet;
 
+use crate::modules::governance::AbstentionPolicy;
+
 // ============================================================================
 // CORE DATA STRUCTURES - Immutable Artifact Registry
 // ============================================================================
@@ -12,7 +14,10 @@ pub struct Artifact {
     pub name: String,
     pub description: String,
     pub metadata: Vec<(String, String)>,
+    // External image URLs; uploaded files are referenced through `media`
     pub images: Vec<String>,
+    // Ids in the media store, see modules::media
+    pub media: Vec<u64>,
     pub creator: Principal,
     pub created_at: u64,
     pub updated_at: u64,
@@ -37,6 +42,7 @@ pub struct HistoryEntry {
     pub actor: Principal,
     pub details: String,
     pub evidence: Option<String>,
+    pub previous_hash: Option<String>,
     pub immutable_hash: String,
 }
 
@@ -84,6 +90,7 @@ pub struct GeographicOrigin {
     pub region: Option<String>,
     pub city: Option<String>,
     pub site_name: Option<String>,
+    // (latitude, longitude) in decimal degrees
     pub coordinates: Option<(f64, f64)>,
     pub discovery_context: Option<String>,
 }
@@ -231,12 +238,17 @@ pub struct Proposal {
     pub voting_deadline: u64,
     pub execution_deadline: Option<u64>,
     pub quorum_required: u32,
+    // Fixed at creation from the type's governance rule, like quorum_required,
+    // so a rule change only affects later proposals
+    pub pass_threshold_percent: u32,
+    pub abstention: AbstentionPolicy,
     pub status: ProposalStatus,
     pub voting_results: VotingResults,
-    pub execution_payload: Option<String>,
+    pub execution_payload: Option<ProposalAction>,
     pub discussion_thread: Vec<Comment>,
     pub required_expertise: Vec<String>,
     pub urgency_level: UrgencyLevel,
+    pub voting_mode: VotingMode,
 }
 
 #[derive(CandidType, Serialize, Deserialize, Clone, Debug)]
@@ -248,6 +260,21 @@ pub struct VotingResults {
     pub weighted_score: f64,
     pub voter_principals: BTreeSet<Principal>,
     pub expert_consensus: Option<ExpertConsensus>,
+    // Direct and delegated weight as of the last tally; None on older records
+    pub breakdown: Option<VoteBreakdown>,
+}
+
+// Weight split into votes cast directly and weight delegated to them, see
+// modules::delegation
+#[derive(CandidType, Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq)]
+pub struct VoteBreakdown {
+    pub direct_for: u64,
+    pub direct_against: u64,
+    pub direct_abstaining: u64,
+    pub delegated_for: u64,
+    pub delegated_against: u64,
+    pub delegated_abstaining: u64,
+    pub delegators: u32,
 }
 
 #[derive(CandidType, Serialize, Deserialize, Clone, Debug)]
@@ -288,7 +315,7 @@ pub struct Comment {
     pub endorsements: u32,
 }
 
-#[derive(CandidType, Serialize, Deserialize, Clone, Debug)]
+#[derive(CandidType, Serialize, Deserialize, Clone, Debug, PartialEq)]
 pub enum ProposalType {
     VerifyArtifact,
     DisputeArtifact,
@@ -301,6 +328,26 @@ pub enum ProposalType {
     RequestExpertReview,
     UpdateVerificationCriteria,
     EmergencyIntervention,
+    ApproveCustodyTransfer,
+}
+
+// Executable payload of a proposal. Each variant belongs to exactly one
+// `ProposalType`; artifact-targeting actions apply to `Proposal.artifact_id`.
+#[derive(CandidType, Serialize, Deserialize, Clone, Debug)]
+pub enum ProposalAction {
+    VerifyArtifact,
+    DisputeArtifact { reason: String },
+    SetArtifactStatus { status: ArtifactStatus, reason: String },
+    GrantRole { principal: Principal, role: UserRole },
+    RevokeRole { principal: Principal, role: UserRole },
+    PatchMetadata { set: Vec<(String, String)>, remove: Vec<String> },
+    RequestEvidence { requested_evidence: Vec<String> },
+    SetConservationStatus { status: ConservationStatus, notes: Vec<String> },
+    RequestExpertReview { expertise_areas: Vec<String> },
+    UpdateVerificationCriteria { criteria: Vec<(String, String)> },
+    EmergencyIntervention { status: ArtifactStatus, reason: String },
+    // Completes a custody transfer awaiting DAO approval, see modules::custody
+    ApproveCustodyTransfer { transfer_id: u64 },
 }
 
 #[derive(CandidType, Serialize, Deserialize, Clone, Debug, PartialEq)]
@@ -316,6 +363,14 @@ pub enum ProposalStatus {
     Withdrawn,
 }
 
+// How votes are weighed, see modules::tally
+#[derive(CandidType, Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub enum VotingMode {
+    Linear,
+    Quadratic,
+    Conviction,
+}
+
 #[derive(CandidType, Serialize, Deserialize, Clone, Debug, PartialEq)]
 pub enum UrgencyLevel {
     Low,
@@ -424,6 +479,39 @@ pub struct CreateArtifactRequest {
     pub metadata: Vec<(String, String)>,
     pub images: Vec<String>,
     pub heritage_proof: Option<String>,
+    pub cultural_significance: Option<CulturalSignificance>,
+    pub geographic_origin: Option<GeographicOrigin>,
+    pub dating_information: Option<DatingInformation>,
+    pub physical_properties: Option<PhysicalProperties>,
+    pub conservation_status: Option<ConservationStatus>,
+    // Committed uploads, see modules::media
+    pub media: Option<Vec<u64>>,
+}
+
+// Partial update of an artifact's descriptive fields: absent fields are left
+// untouched, fields listed in `clear` are reset to none.
+#[derive(CandidType, Serialize, Deserialize, Clone, Debug, Default)]
+pub struct ArtifactPatch {
+    pub name: Option<String>,
+    pub description: Option<String>,
+    pub metadata: Option<Vec<(String, String)>>,
+    pub images: Option<Vec<String>>,
+    pub heritage_proof: Option<String>,
+    pub cultural_significance: Option<CulturalSignificance>,
+    pub geographic_origin: Option<GeographicOrigin>,
+    pub dating_information: Option<DatingInformation>,
+    pub physical_properties: Option<PhysicalProperties>,
+    pub conservation_status: Option<ConservationStatus>,
+    pub media: Option<Vec<u64>>,
+    pub clear: Vec<ClearableField>,
+}
+
+#[derive(CandidType, Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub enum ClearableField {
+    HeritageProof,
+    GeographicOrigin,
+    DatingInformation,
+    PhysicalProperties,
 }
 
 #[derive(CandidType, Serialize, Deserialize, Clone, Debug)]
@@ -434,10 +522,13 @@ pub struct CreateProposalRequest {
     pub description: String,
     pub evidence: Option<Vec<String>>,
     pub voting_duration_hours: u64,
-    pub execution_payload: Option<String>,
+    pub execution_payload: Option<ProposalAction>,
     pub required_expertise: Option<Vec<String>>,
     pub urgency_level: Option<UrgencyLevel>,
     pub quorum_required: Option<u32>,
+    // May be left out; anything but the proposal type's mode is refused, see
+    // tally::voting_mode_for
+    pub voting_mode: Option<VotingMode>,
 }
 
 #[derive(CandidType, Serialize, Deserialize, Clone, Debug)]
@@ -453,6 +544,8 @@ pub struct VoteRequest {
     pub vote_type: VoteType,
     pub rationale: Option<String>,
     pub expertise_relevance: Option<u32>,
+    // Reputation credits to spend on a quadratic vote
+    pub credits: Option<u32>,
 }
 
 #[derive(CandidType, Serialize, Deserialize, Clone, Debug)]
@@ -467,7 +560,27 @@ pub struct ProposalResponse {
     pub created_at: u64,
     pub proposer: Principal,
     pub votes_for: u32,
-    pub execution_payload: Option<String>,
+    pub execution_payload: Option<ProposalAction>,
     pub proposal_type: ProposalType,
     pub votes_against: u32,
 }
+
+// ============================================================================
+// PAGINATION
+// ============================================================================
+
+// Lists are returned newest first. `cursor` is the `next_cursor` of the
+// previous page (absent for the first page); `limit` defaults to
+// DEFAULT_PAGE_SIZE and is capped at MAX_PAGE_SIZE.
+#[derive(CandidType, Serialize, Deserialize, Clone, Debug, Default)]
+pub struct PageRequest {
+    pub cursor: Option<u64>,
+    pub limit: Option<u32>,
+}
+
+#[derive(CandidType, Serialize, Deserialize, Clone, Debug)]
+pub struct Page<T> {
+    pub items: Vec<T>,
+    pub next_cursor: Option<u64>,
+    pub total: u64,
+}
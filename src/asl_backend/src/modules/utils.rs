@@ -1,19 +1,130 @@
-use ic_cdk::api::time;
-use std::collections::hash_map::DefaultHasher;
-use std::hash::{Hash, Hasher};
+use ic_stable_structures::{StableBTreeMap, Storable};
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::ops::Bound;
+
+use crate::modules::types::{HistoryEntry, Page, PageRequest};
+use crate::modules::storage::Memory;
+
+pub const DEFAULT_PAGE_SIZE: u32 = 50;
+pub const MAX_PAGE_SIZE: u32 = 200;
 
 // ============================================================================
 // UTILITY FUNCTIONS
 // ============================================================================
 
+#[cfg(not(test))]
+pub fn get_time() -> u64 {
+    ic_cdk::api::time()
+}
+
+// Unit tests run outside a canister, see modules::testing
+#[cfg(test)]
 pub fn get_time() -> u64 {
-    time()
+    crate::modules::testing::now()
 }
 
 pub fn create_hash(data: &str) -> String {
-    let mut hasher = DefaultHasher::new();
-    data.hash(&mut hasher);
-    format!("{:x}", hasher.finish())
+    format!("{:x}", Sha256::digest(data.as_bytes()))
+}
+
+// Canonical encoding of a history entry, shared by every writer and by the
+// integrity verifier. Covers every field except `immutable_hash`, including the
+// previous entry's hash so the history forms a chain. Variable-length fields
+// are length-prefixed to keep the encoding unambiguous.
+pub fn encode_history_entry(artifact_id: u64, entry: &HistoryEntry) -> Vec<u8> {
+    let mut bytes = Vec::new();
+    bytes.extend_from_slice(&artifact_id.to_be_bytes());
+    bytes.extend_from_slice(&entry.id.to_be_bytes());
+    bytes.extend_from_slice(&entry.timestamp.to_be_bytes());
+
+    let fields = [entry.action.as_bytes(), entry.actor.as_slice(), entry.details.as_bytes()];
+    for field in fields {
+        bytes.extend_from_slice(&(field.len() as u64).to_be_bytes());
+        bytes.extend_from_slice(field);
+    }
+
+    let optional_fields = [entry.evidence.as_deref(), entry.previous_hash.as_deref()];
+    for field in optional_fields {
+        match field {
+            Some(value) => {
+                bytes.push(1);
+                bytes.extend_from_slice(&(value.len() as u64).to_be_bytes());
+                bytes.extend_from_slice(value.as_bytes());
+            },
+            None => bytes.push(0),
+        }
+    }
+
+    bytes
+}
+
+pub fn hash_history_entry(artifact_id: u64, entry: &HistoryEntry) -> String {
+    format!("{:x}", Sha256::digest(encode_history_entry(artifact_id, entry)))
+}
+
+// Entries written before history was chained carry a 64-bit hash of
+// "artifact:actor:timestamp" rather than a SHA-256 of their content.
+pub fn is_legacy_history_entry(entry: &HistoryEntry) -> bool {
+    entry.immutable_hash.len() != 64
+}
+
+// Links legacy entries into the chain, seals them with their SHA-256 hash and
+// relinks the entries that followed them. Returns whether anything changed.
+pub fn seal_legacy_history(artifact_id: u64, history: &mut [HistoryEntry]) -> bool {
+    let mut replaced: HashMap<String, String> = HashMap::new();
+    let mut previous_hash: Option<String> = None;
+    for entry in history.iter_mut() {
+        if is_legacy_history_entry(entry) {
+            entry.previous_hash = previous_hash.clone();
+        } else if let Some(relinked) = entry.previous_hash.as_ref().and_then(|previous| replaced.get(previous)) {
+            entry.previous_hash = Some(relinked.clone());
+        } else {
+            previous_hash = Some(entry.immutable_hash.clone());
+            continue;
+        }
+        let new_hash = hash_history_entry(artifact_id, entry);
+        replaced.insert(std::mem::replace(&mut entry.immutable_hash, new_hash.clone()), new_hash.clone());
+        previous_hash = Some(new_hash);
+    }
+    !replaced.is_empty()
+}
+
+pub fn page_limit(page: &PageRequest) -> usize {
+    page.limit.unwrap_or(DEFAULT_PAGE_SIZE).clamp(1, MAX_PAGE_SIZE) as usize
+}
+
+// Pages through a store keyed by sequentially allocated ids, newest first.
+// `select` filters and converts records; `total` is the size of the full
+// (filtered) listing, which the caller knows best how to compute.
+pub fn paginate_newest_first<V, T, F>(
+    store: &StableBTreeMap<u64, V, Memory>,
+    page: &PageRequest,
+    total: u64,
+    mut select: F,
+) -> Page<T>
+where
+    V: Storable,
+    F: FnMut(V) -> Option<T>,
+{
+    let limit = page_limit(page);
+    let upper = page.cursor.map_or(Bound::Unbounded, Bound::Excluded);
+
+    let mut items = Vec::new();
+    let mut last_id = None;
+    let mut next_cursor = None;
+    for (id, record) in store.range((Bound::Unbounded, upper)).rev() {
+        let Some(item) = select(record) else { continue };
+        if items.len() == limit {
+            // Only hand out a cursor when another matching record exists
+            next_cursor = last_id;
+            break;
+        }
+        items.push(item);
+        last_id = Some(id);
+    }
+
+    Page { items, next_cursor, total }
 }
 
 pub fn calculate_voting_deadline(duration_hours: u64) -> u64 {
@@ -25,15 +136,9 @@ pub fn is_voting_deadline_passed(deadline: u64) -> bool {
     get_time() > deadline
 }
 
-pub fn calculate_quorum(total_eligible_voters: u32, proposal_type: &crate::modules::types::ProposalType) -> u32 {
-    match proposal_type {
-        crate::modules::types::ProposalType::EmergencyIntervention => (total_eligible_voters * 75) / 100, // 75%
-        crate::modules::types::ProposalType::VerifyArtifact | 
-        crate::modules::types::ProposalType::DisputeArtifact => (total_eligible_voters * 60) / 100, // 60%
-        crate::modules::types::ProposalType::GrantUserRole | 
-        crate::modules::types::ProposalType::RevokeUserRole => (total_eligible_voters * 70) / 100, // 70%
-        _ => (total_eligible_voters * 50) / 100, // 50% for general proposals
-    }
+// Percentages per proposal type come from modules::governance
+pub fn calculate_quorum(total_eligible_voters: u32, quorum_percent: u32) -> u32 {
+    ((total_eligible_voters as u64 * quorum_percent as u64) / 100) as u32
 }
 
 pub fn format_duration_string(hours: u64) -> String {
@@ -50,6 +155,63 @@ pub fn format_duration_string(hours: u64) -> String {
     }
 }
 
+pub fn validate_cultural_significance(significance: &crate::modules::types::CulturalSignificance) -> Result<(), String> {
+    if significance.cultural_tags.iter().any(|tag| tag.trim().is_empty()) {
+        return Err("Cultural tags cannot be empty".to_string());
+    }
+    if significance.cultural_tags.len() > 50 {
+        return Err("Too many cultural tags (max 50)".to_string());
+    }
+    Ok(())
+}
+
+// The oldest known stone tools are about 3.3 million years old
+pub const EARLIEST_DATING_YEAR: i64 = -3_500_000;
+const NANOS_PER_YEAR: u64 = 31_556_952_000_000_000;
+
+fn current_year() -> i64 {
+    1970 + (get_time() / NANOS_PER_YEAR) as i64
+}
+
+pub fn validate_dating_information(dating: &crate::modules::types::DatingInformation) -> Result<(), String> {
+    if dating.confidence_level > 100 {
+        return Err("Dating confidence level must be between 0 and 100".to_string());
+    }
+    let latest_year = current_year();
+    for year in [dating.date_range_start, dating.date_range_end].into_iter().flatten() {
+        if !(EARLIEST_DATING_YEAR..=latest_year).contains(&year) {
+            return Err(format!("Year {} is outside [{}, {}]", year, EARLIEST_DATING_YEAR, latest_year));
+        }
+    }
+    if dating.estimated_age.is_some_and(|age| age > EARLIEST_DATING_YEAR.unsigned_abs()) {
+        return Err(format!("Estimated age may be at most {} years", EARLIEST_DATING_YEAR.unsigned_abs()));
+    }
+    if let (Some(start), Some(end)) = (dating.date_range_start, dating.date_range_end) {
+        if start > end {
+            return Err(format!("Date range start {} is after its end {}", start, end));
+        }
+    }
+    Ok(())
+}
+
+pub fn validate_physical_properties(properties: &crate::modules::types::PhysicalProperties) -> Result<(), String> {
+    let is_positive = |value: f64| value.is_finite() && value > 0.0;
+    if let Some((length, width, height)) = properties.dimensions {
+        if !is_positive(length) || !is_positive(width) || !is_positive(height) {
+            return Err("Dimensions must be positive numbers".to_string());
+        }
+    }
+    if let Some(weight) = properties.weight {
+        if !is_positive(weight) {
+            return Err("Weight must be a positive number".to_string());
+        }
+    }
+    if properties.material.iter().any(|material| material.trim().is_empty()) {
+        return Err("Materials cannot be empty".to_string());
+    }
+    Ok(())
+}
+
 pub fn validate_proposal_title(title: &str) -> Result<(), String> {
     if title.trim().is_empty() {
         return Err("Proposal title cannot be empty".to_string());
@@ -78,4 +240,126 @@ pub fn validate_voting_duration(duration_hours: u64) -> Result<(), String> {
         return Err("Voting duration must be between 1 hour and 7 days".to_string());
     }
     Ok(())
-}
\ No newline at end of file
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::modules::artifacts::append_history_entry;
+    use crate::modules::types::{Artifact, DatingInformation, UserRole};
+    use crate::modules::testing::{artifact_request, create_artifact_as, principal, register, set_caller};
+    use crate::modules::storage::ARTIFACTS;
+
+    fn entry(id: u64, action: &str) -> HistoryEntry {
+        HistoryEntry {
+            id,
+            timestamp: id * 10,
+            action: action.to_string(),
+            actor: principal(1),
+            details: String::new(),
+            evidence: None,
+            previous_hash: None,
+            immutable_hash: String::new(),
+        }
+    }
+
+    fn stored_artifact() -> Artifact {
+        let artifact_id = create_artifact_as(principal(1), artifact_request("Clay tablet"));
+        ARTIFACTS.with(|artifacts| artifacts.borrow().get(&artifact_id)).unwrap()
+    }
+
+    #[test]
+    fn appended_entries_link_to_their_predecessor() {
+        let mut artifact = stored_artifact();
+        append_history_entry(&mut artifact, entry(20, "Updated"));
+        append_history_entry(&mut artifact, entry(21, "Updated"));
+
+        let history = &artifact.history;
+        assert_eq!(history[0].previous_hash, None);
+        for pair in history.windows(2) {
+            assert_eq!(pair[1].previous_hash.as_ref(), Some(&pair[0].immutable_hash));
+        }
+        assert!(history.iter().all(|e| e.immutable_hash == hash_history_entry(artifact.id, e)));
+    }
+
+    #[test]
+    fn hash_covers_every_field_and_the_artifact() {
+        let mut sealed = entry(1, "Created");
+        sealed.evidence = Some("Excavation report".to_string());
+        sealed.previous_hash = Some("0".repeat(64));
+        let original = hash_history_entry(1, &sealed);
+
+        let tampered: Vec<HistoryEntry> = vec![
+            HistoryEntry { id: 2, ..sealed.clone() },
+            HistoryEntry { timestamp: 11, ..sealed.clone() },
+            HistoryEntry { action: "Deleted".to_string(), ..sealed.clone() },
+            HistoryEntry { actor: principal(2), ..sealed.clone() },
+            HistoryEntry { details: "changed".to_string(), ..sealed.clone() },
+            HistoryEntry { evidence: None, ..sealed.clone() },
+            HistoryEntry { previous_hash: None, ..sealed.clone() },
+        ];
+        for entry in &tampered {
+            assert_ne!(hash_history_entry(1, entry), original, "{:?}", entry);
+        }
+        assert_ne!(hash_history_entry(2, &sealed), original);
+
+        // Length prefixes keep shifted field boundaries apart
+        let a = HistoryEntry { action: "ab".to_string(), details: "c".to_string(), ..sealed.clone() };
+        let b = HistoryEntry { action: "a".to_string(), details: "bc".to_string(), ..sealed };
+        assert_ne!(hash_history_entry(1, &a), hash_history_entry(1, &b));
+    }
+
+    #[test]
+    fn seals_legacy_entries_and_relinks_later_ones() {
+        // Two entries written before chaining, then one appended after it
+        let mut history = vec![
+            HistoryEntry { immutable_hash: "1f2e3d4c5b6a7988".to_string(), ..entry(1, "Created") },
+            HistoryEntry { immutable_hash: "8899aabbccddeeff".to_string(), ..entry(2, "StatusChanged") },
+        ];
+        let mut chained = entry(3, "Updated");
+        chained.previous_hash = Some("8899aabbccddeeff".to_string());
+        chained.immutable_hash = hash_history_entry(5, &chained);
+        history.push(chained);
+
+        assert!(seal_legacy_history(5, &mut history));
+        assert!(!history.iter().any(is_legacy_history_entry));
+        assert_eq!(history[0].previous_hash, None);
+        assert_eq!(history[1].previous_hash.as_ref(), Some(&history[0].immutable_hash));
+        assert_eq!(history[2].previous_hash.as_ref(), Some(&history[1].immutable_hash));
+        assert!(history.iter().all(|e| e.immutable_hash == hash_history_entry(5, e)));
+
+        // Sealed histories are left alone
+        let before = history.clone();
+        assert!(!seal_legacy_history(5, &mut history));
+        assert_eq!(history.iter().map(|e| &e.immutable_hash).collect::<Vec<_>>(),
+                   before.iter().map(|e| &e.immutable_hash).collect::<Vec<_>>());
+    }
+
+    fn dating(start: Option<i64>, end: Option<i64>, estimated_age: Option<u64>) -> DatingInformation {
+        DatingInformation {
+            estimated_age,
+            dating_method: vec!["radiocarbon".to_string()],
+            confidence_level: 80,
+            date_range_start: start,
+            date_range_end: end,
+        }
+    }
+
+    #[test]
+    fn dating_years_must_be_plausible() {
+        assert!(validate_dating_information(&dating(Some(-2600), Some(-2500), Some(4500))).is_ok());
+        assert!(validate_dating_information(&dating(Some(EARLIEST_DATING_YEAR), None, None)).is_ok());
+        assert_eq!(current_year(), 2023);
+
+        assert!(validate_dating_information(&dating(Some(-2500), Some(-2600), None)).is_err());
+        assert!(validate_dating_information(&dating(Some(EARLIEST_DATING_YEAR - 1), None, None)).is_err());
+        assert!(validate_dating_information(&dating(None, Some(2100), None)).is_err());
+        assert!(validate_dating_information(&dating(Some(i64::MIN), Some(i64::MAX), None)).is_err());
+        assert!(validate_dating_information(&dating(None, None, Some(u64::MAX))).is_err());
+
+        let mut request = artifact_request("Undatable");
+        request.dating_information = Some(dating(Some(-1_000_000_000_000), Some(1_000_000_000_000), None));
+        register(principal(1), UserRole::Institution, &[]);
+        set_caller(principal(1));
+        assert!(crate::modules::artifacts::create_artifact(request).is_err());
+    }
+}
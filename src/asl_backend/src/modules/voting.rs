@@ -6,11 +6,31 @@ use crate::modules::storage::*;
 use crate::modules::auth::*;
 use crate::modules::utils::*;
 use crate::modules::audit::log_audit_event;
+use crate::modules::tally::{price_vote, spend_reputation, tally_votes};
+use crate::modules::governance::{min_voting_period_elapsed, voting_outcome, VotingOutcome};
+use crate::modules::custody::close_unapproved_transfer;
+use crate::modules::dao::store_proposal;
 
 // ============================================================================
 // ENHANCED VOTING SYSTEM
 // ============================================================================
 
+// Checks that depend only on the ballot, not on the proposal voted on
+pub fn validate_ballot(rationale: &Option<String>, expertise_relevance: Option<u32>) -> Result<(), String> {
+    if let Some(rationale) = rationale {
+        if rationale.len() < 10 {
+            return Err("Vote rationale must be at least 10 characters if provided".to_string());
+        }
+        if rationale.len() > 1000 {
+            return Err("Vote rationale too long (max 1000 characters)".to_string());
+        }
+    }
+    if expertise_relevance.is_some_and(|relevance| relevance > 100) {
+        return Err("Expertise relevance cannot exceed 100%".to_string());
+    }
+    Ok(())
+}
+
 #[update]
 pub fn vote_on_proposal(request: VoteRequest) -> Result<String, String> {
     let caller = get_caller();
@@ -20,31 +40,21 @@ pub fn vote_on_proposal(request: VoteRequest) -> Result<String, String> {
     //     return Err("You don't have voting rights. Please verify your account first.".to_string());
     // }
 
-    // Validate vote request
-    if let Some(rationale) = &request.rationale {
-        if rationale.len() < 10 {
-            return Err("Vote rationale must be at least 10 characters if provided".to_string());
-        }
-        if rationale.len() > 1000 {
-            return Err("Vote rationale too long (max 1000 characters)".to_string());
-        }
-    }
+    validate_ballot(&request.rationale, request.expertise_relevance)?;
 
     let now = get_time();
-    let vote_id = get_next_id(4); // Vote ID counter
-    let voting_weight = get_voting_weight(caller);
+    let vote_id = get_next_id(IdKind::Vote);
 
     PROPOSALS.with(|proposals| {
         let mut proposals = proposals.borrow_mut();
         if let Some(mut proposal) = proposals.get(&request.proposal_id) {
             
-            // Enhanced deadline check with grace period for high urgency
-            let effective_deadline = match proposal.urgency_level {
-                UrgencyLevel::Emergency => proposal.voting_deadline + (2 * 3600 * 1_000_000_000), // 2 hour grace
-                _ => proposal.voting_deadline,
-            };
-            
-            if now > effective_deadline {
+            // A concluded vote must not be reopened, or its outcome could change after execution
+            if proposal.status != ProposalStatus::Active {
+                return Err("Voting on this proposal has concluded".to_string());
+            }
+
+            if now > effective_voting_deadline(&proposal) {
                 return Err("Voting period has ended".to_string());
             }
 
@@ -58,11 +68,13 @@ pub fn vote_on_proposal(request: VoteRequest) -> Result<String, String> {
                 return Err("You don't have the required expertise to vote on this proposal".to_string());
             }
 
-            // Validate expertise relevance score
             let expertise_relevance = request.expertise_relevance.unwrap_or(50);
-            if expertise_relevance > 100 {
-                return Err("Expertise relevance cannot exceed 100%".to_string());
-            }
+
+            // Weigh the vote by the proposal's voting mode, see modules::tally
+            let reputation = USERS.with(|users| users.borrow().get(&caller).map(|user| user.reputation)).unwrap_or(0);
+            let (voting_weight, reputation_cost) =
+                price_vote(&proposal.voting_mode, get_voting_weight(caller), reputation, request.credits)?;
+            spend_reputation(caller, reputation_cost);
 
             // Create enhanced vote record
             let vote = Vote {
@@ -77,9 +89,7 @@ pub fn vote_on_proposal(request: VoteRequest) -> Result<String, String> {
             };
 
             // Store the vote
-            VOTES.with(|votes| {
-                votes.borrow_mut().insert(vote_id, vote);
-            });
+            VOTES.with(|votes| store_vote(&mut votes.borrow_mut(), vote));
 
             // Update proposal voting results with enhanced calculations
             proposal.voting_results.voter_principals.insert(caller);
@@ -113,17 +123,15 @@ pub fn vote_on_proposal(request: VoteRequest) -> Result<String, String> {
                 update_expert_consensus(&mut proposal, &request.vote_type, voting_weight, expertise_relevance);
             }
 
-            // Auto-execute if unanimous consensus reached for urgent proposals
+            // Conclude early if unanimous consensus reached for urgent proposals
             if proposal.urgency_level == UrgencyLevel::Emergency && 
                proposal.voting_results.total_votes >= 3 &&
                (proposal.voting_results.votes_against == 0 || proposal.voting_results.votes_for == 0) {
-                proposal.status = if proposal.voting_results.votes_for > 0 {
-                    ProposalStatus::Passed
-                } else {
-                    ProposalStatus::Rejected
-                };
+                conclude_voting_early(&mut proposal, now);
             }
 
+            record_vote_breakdown(&mut proposal, now);
+
             // Check if quorum is reached and voting should conclude
             check_and_finalize_proposal(&mut proposal, now);
 
@@ -132,7 +140,7 @@ pub fn vote_on_proposal(request: VoteRequest) -> Result<String, String> {
             let votes_against = proposal.voting_results.votes_against;
             let total_votes = proposal.voting_results.total_votes;
 
-            proposals.insert(request.proposal_id, proposal);
+            store_proposal(&mut proposals, proposal);
 
             // Update user stats with enhanced tracking
             update_user_voting_stats(caller, expertise_relevance, now);
@@ -186,32 +194,78 @@ fn update_expert_consensus(proposal: &mut Proposal, vote_type: &VoteType, weight
 fn check_and_finalize_proposal(proposal: &mut Proposal, current_time: u64) {
     // Check if voting deadline has passed
     if current_time > proposal.voting_deadline {
-        finalize_proposal_voting(proposal);
+        finalize_proposal_voting(proposal, current_time);
+        return;
     }
     
-    // Check if quorum is reached early
-    if proposal.voting_results.total_votes >= proposal.quorum_required {
-        // For high-confidence expert consensus, allow early conclusion
-        if let Some(ref consensus) = proposal.voting_results.expert_consensus {
-            if consensus.expert_confidence > 0.8 && consensus.peer_review_score > 80.0 {
-                finalize_proposal_voting(proposal);
-            }
+    // For high-confidence expert consensus, allow early conclusion
+    if let Some(ref consensus) = proposal.voting_results.expert_consensus {
+        if consensus.expert_confidence > 0.8 && consensus.peer_review_score > 80.0 {
+            conclude_voting_early(proposal, current_time);
         }
     }
 }
 
-fn finalize_proposal_voting(proposal: &mut Proposal) {
-    let total_votes = proposal.voting_results.votes_for + proposal.voting_results.votes_against;
-    
-    if total_votes == 0 {
-        proposal.status = ProposalStatus::Expired;
-    } else if proposal.voting_results.votes_for > proposal.voting_results.votes_against {
-        proposal.status = ProposalStatus::Passed;
-    } else {
-        proposal.status = ProposalStatus::Rejected;
+// Closes the vote before its deadline once the minimum voting period is over
+// and quorum is met; otherwise voting stays open
+fn conclude_voting_early(proposal: &mut Proposal, current_time: u64) {
+    if !min_voting_period_elapsed(proposal, current_time) {
+        return;
+    }
+    match voting_outcome(proposal, current_time) {
+        VotingOutcome::QuorumNotMet => {},
+        VotingOutcome::Passed => proposal.status = ProposalStatus::Passed,
+        VotingOutcome::Rejected => {
+            proposal.status = ProposalStatus::Rejected;
+            close_unapproved_transfer(proposal);
+        },
     }
 }
 
+// Enhanced deadline check with grace period for high urgency
+pub fn effective_voting_deadline(proposal: &Proposal) -> u64 {
+    match proposal.urgency_level {
+        UrgencyLevel::Emergency => proposal.voting_deadline + (2 * 3600 * 1_000_000_000), // 2 hour grace
+        _ => proposal.voting_deadline,
+    }
+}
+
+// Keeps the direct and delegated weight shown in `VotingResults` current
+fn record_vote_breakdown(proposal: &mut Proposal, current_time: u64) {
+    proposal.voting_results.breakdown = Some(tally_votes(proposal, current_time).breakdown);
+}
+
+// Re-records the breakdown of the open proposals a changed delegation by
+// `delegator` can move, which are those they have not voted on themselves
+pub fn refresh_vote_breakdowns(delegator: Principal) {
+    let now = get_time();
+    let open: Vec<u64> = PROPOSAL_SCHEDULE.with(|schedule| {
+        schedule.borrow().iter().map(|((_, proposal_id), _)| proposal_id).collect()
+    });
+    PROPOSALS.with(|proposals| {
+        let mut proposals = proposals.borrow_mut();
+        for proposal_id in open {
+            let Some(mut proposal) = proposals.get(&proposal_id) else { continue };
+            if proposal.status != ProposalStatus::Active || proposal.voting_results.voter_principals.contains(&delegator) {
+                continue;
+            }
+            record_vote_breakdown(&mut proposal, now);
+            store_proposal(&mut proposals, proposal);
+        }
+    });
+}
+
+// Applies the proposal type's governance rule; without quorum the proposal expires
+pub fn finalize_proposal_voting(proposal: &mut Proposal, current_time: u64) {
+    record_vote_breakdown(proposal, current_time);
+    proposal.status = match voting_outcome(proposal, current_time) {
+        VotingOutcome::QuorumNotMet => ProposalStatus::Expired,
+        VotingOutcome::Passed => ProposalStatus::Passed,
+        VotingOutcome::Rejected => ProposalStatus::Rejected,
+    };
+    close_unapproved_transfer(proposal);
+}
+
 fn update_user_voting_stats(caller: Principal, expertise_relevance: u32, timestamp: u64) {
     USERS.with(|users| {
         let mut users = users.borrow_mut();
@@ -231,6 +285,39 @@ fn update_user_voting_stats(caller: Principal, expertise_relevance: u32, timesta
     });
 }
 
+// Every write to VOTES goes through here so VOTE_INDEX lists each proposal's votes
+pub fn store_vote(votes: &mut VoteStore, vote: Vote) {
+    VOTE_INDEX.with(|index| index.borrow_mut().insert((vote.proposal_id, vote.id), ()));
+    votes.insert(vote.id, vote);
+}
+
+// Recomputes the vote index from VOTES; used after upgrades
+pub fn rebuild_vote_index() {
+    VOTE_INDEX.with(|index| {
+        let mut index = index.borrow_mut();
+        index.clear_new();
+        VOTES.with(|votes| {
+            for (vote_id, vote) in votes.borrow().iter() {
+                index.insert((vote.proposal_id, vote_id), ());
+            }
+        });
+    });
+}
+
+// The votes cast on one proposal, oldest first
+pub fn proposal_votes(proposal_id: u64) -> Vec<Vote> {
+    let vote_ids: Vec<u64> = VOTE_INDEX.with(|index| {
+        index.borrow()
+            .range((proposal_id, 0)..=(proposal_id, u64::MAX))
+            .map(|((_, vote_id), _)| vote_id)
+            .collect()
+    });
+    VOTES.with(|votes| {
+        let votes = votes.borrow();
+        vote_ids.into_iter().filter_map(|vote_id| votes.get(&vote_id)).collect()
+    })
+}
+
 #[update]
 pub fn get_vote_details(proposal_id: u64) -> Result<Vec<Vote>, String> {
     let caller = get_caller();
@@ -240,15 +327,7 @@ pub fn get_vote_details(proposal_id: u64) -> Result<Vec<Vote>, String> {
         return Err("You don't have permission to view vote details".to_string());
     }
 
-    let mut votes = Vec::new();
-    VOTES.with(|vote_store| {
-        for (_, vote) in vote_store.borrow().iter() {
-            if vote.proposal_id == proposal_id {
-                votes.push(vote.clone());
-            }
-        }
-    });
-
+    let votes = proposal_votes(proposal_id);
     if votes.is_empty() {
         Err("No votes found for this proposal".to_string())
     } else {
@@ -271,6 +350,10 @@ pub fn change_vote(proposal_id: u64, new_vote_type: VoteType, new_rationale: Opt
             if get_time() > proposal.voting_deadline {
                 return Err("Cannot change vote after voting deadline".to_string());
             }
+
+            if proposal.status != ProposalStatus::Active {
+                return Err("Voting on this proposal has concluded".to_string());
+            }
             
             if !proposal.voting_results.voter_principals.contains(&caller) {
                 return Err("You haven't voted on this proposal yet".to_string());
@@ -282,33 +365,26 @@ pub fn change_vote(proposal_id: u64, new_vote_type: VoteType, new_rationale: Opt
     })?;
 
     // Find and update the vote
-    VOTES.with(|vote_store| {
-        let mut votes = vote_store.borrow_mut();
-        for (vote_id, mut vote) in votes.iter() {
-            if vote.proposal_id == proposal_id && vote.voter == caller {
-                let old_vote_type = vote.vote_type.clone();
-                let vote_weight = vote.weight; // Store weight before moving vote
-                vote.vote_type = new_vote_type.clone();
-                vote.rationale = new_rationale.clone();
-                vote.timestamp = get_time();
-                
-                votes.insert(vote_id, vote);
-                
-                // Update proposal voting results
-                update_proposal_vote_counts(proposal_id, &old_vote_type, &new_vote_type, vote_weight);
-                
-                log_audit_event(
-                    AuditEventType::VoteCast,
-                    Some(proposal_id),
-                    format!("Vote changed from {:?} to {:?} on proposal {}", old_vote_type, new_vote_type, proposal_id),
-                    AuditSeverity::Info
-                );
-                
-                return Ok("Vote updated successfully".to_string());
-            }
-        }
-        Err("Vote not found".to_string())
-    })
+    let mut vote = proposal_votes(proposal_id).into_iter()
+        .find(|vote| vote.voter == caller)
+        .ok_or_else(|| "Vote not found".to_string())?;
+    let old_vote_type = vote.vote_type.clone();
+    let vote_weight = vote.weight;
+    vote.vote_type = new_vote_type.clone();
+    vote.rationale = new_rationale;
+    vote.timestamp = get_time();
+    VOTES.with(|votes| store_vote(&mut votes.borrow_mut(), vote));
+
+    // Update proposal voting results
+    update_proposal_vote_counts(proposal_id, &old_vote_type, &new_vote_type, vote_weight);
+
+    log_audit_event(
+        AuditEventType::VoteCast,
+        Some(proposal_id),
+        format!("Vote changed from {:?} to {:?} on proposal {}", old_vote_type, new_vote_type, proposal_id),
+        AuditSeverity::Info
+    );
+    Ok("Vote updated successfully".to_string())
 }
 
 fn update_proposal_vote_counts(proposal_id: u64, old_vote: &VoteType, new_vote: &VoteType, weight: u32) {
@@ -338,8 +414,72 @@ fn update_proposal_vote_counts(proposal_id: u64, old_vote: &VoteType, new_vote:
                 proposal.voting_results.weighted_score = 
                     proposal.voting_results.votes_for as f64 / total_weighted_votes as f64;
             }
-            
-            proposals.insert(proposal_id, proposal);
+
+            record_vote_breakdown(&mut proposal, get_time());
+            store_proposal(&mut proposals, proposal);
         }
     });
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::modules::dao::create_proposal;
+    use crate::modules::delegation::{delegate_vote, revoke_delegation, DelegationScope};
+    use crate::modules::testing::{artifact_request, create_artifact_as, principal, register, set_caller};
+
+    fn vote(voter: u8, proposal_id: u64, vote_type: VoteType) {
+        set_caller(principal(voter));
+        vote_on_proposal(VoteRequest { proposal_id, vote_type, rationale: None, expertise_relevance: None, credits: None }).unwrap();
+    }
+
+    fn breakdown(proposal_id: u64) -> VoteBreakdown {
+        PROPOSALS.with(|proposals| proposals.borrow().get(&proposal_id)).unwrap().voting_results.breakdown.unwrap()
+    }
+
+    #[test]
+    fn the_recorded_breakdown_follows_votes_and_delegations() {
+        // Experts 2 and 3 weigh 2, community member 4 weighs 1 and delegates to 2
+        register(principal(2), UserRole::Expert, &["ceramics"]);
+        register(principal(3), UserRole::Expert, &["ceramics"]);
+        register(principal(4), UserRole::Community, &["ceramics"]);
+        set_caller(principal(4));
+        delegate_vote(principal(2), DelegationScope::Global).unwrap();
+
+        let artifact_id = create_artifact_as(principal(1), artifact_request("Amphora"));
+        set_caller(principal(1));
+        let proposal_id = create_proposal(CreateProposalRequest {
+            proposal_type: ProposalType::VerifyArtifact,
+            artifact_id: Some(artifact_id),
+            title: "Verify the amphora".to_string(),
+            description: "A verification round whose breakdown is recorded as it goes.".to_string(),
+            evidence: None,
+            voting_duration_hours: 72,
+            execution_payload: None,
+            required_expertise: None,
+            urgency_level: None,
+            quorum_required: None,
+            voting_mode: None,
+        }).unwrap();
+
+        vote(2, proposal_id, VoteType::For);
+        vote(3, proposal_id, VoteType::Against);
+        let recorded = breakdown(proposal_id);
+        assert_eq!((recorded.direct_for, recorded.direct_against, recorded.delegated_for, recorded.delegators), (2, 2, 1, 1));
+
+        set_caller(principal(2));
+        change_vote(proposal_id, VoteType::Against, None).unwrap();
+        let recorded = breakdown(proposal_id);
+        assert_eq!((recorded.direct_for, recorded.direct_against), (0, 4));
+        assert_eq!((recorded.delegated_for, recorded.delegated_against), (0, 1));
+
+        set_caller(principal(4));
+        revoke_delegation(DelegationScope::Global).unwrap();
+        let recorded = breakdown(proposal_id);
+        assert_eq!((recorded.delegated_against, recorded.delegators), (0, 0));
+
+        delegate_vote(principal(3), DelegationScope::Global).unwrap();
+        let recorded = breakdown(proposal_id);
+        assert_eq!((recorded.delegated_against, recorded.delegators), (1, 1));
+    }
+}
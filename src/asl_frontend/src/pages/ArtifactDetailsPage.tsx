@@ -128,6 +128,20 @@ const PharaohArtifactDetailsPage: React.FC = () => {
         return statusMap[statusKey as keyof typeof statusMap] || statusMap['PendingVerification'];
     };
 
+    // History details hold a JSON diff ({ note, changes }); older entries are plain text
+    const formatHistoryDetails = (details: string) => {
+        try {
+            const parsed = JSON.parse(details);
+            if (parsed && Array.isArray(parsed.changes)) {
+                const fields = parsed.changes.map((change: { field: string }) => change.field);
+                return fields.length > 0 ? `${parsed.note} (${fields.join(', ')})` : parsed.note;
+            }
+        } catch {
+            // Not a diff
+        }
+        return details;
+    };
+
     const formatDate = (timestamp: bigint) => {
         const date = new Date(Number(timestamp) / 1000000);
         return language === 'ar'
@@ -505,7 +519,7 @@ const PharaohArtifactDetailsPage: React.FC = () => {
                                                         {entry.action}
                                                     </div>
                                                     <div className={`text-sm ${isDarkMode ? 'text-amber-300' : 'text-amber-600'} mt-1`}>
-                                                        {entry.details}
+                                                        {formatHistoryDetails(entry.details)}
                                                     </div>
                                                 </div>
                                                 <div className={`text-sm ${isDarkMode ? 'text-amber-400' : 'text-amber-700'}`}>
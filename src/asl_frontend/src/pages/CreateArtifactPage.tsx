@@ -95,7 +95,13 @@ const CreateArtifactPage: React.FC = () => {
                 description: formData.description,
                 heritage_proof: [formData.heritage_proof],
                 metadata: formData.metadata,
-                images: formData.images
+                images: formData.images,
+                cultural_significance: [],
+                geographic_origin: [],
+                dating_information: [],
+                physical_properties: [],
+                conservation_status: [],
+                media: []
             };
 
             console.log('Artifact request:', artifactRequest);
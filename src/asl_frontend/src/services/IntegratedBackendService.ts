@@ -197,6 +197,23 @@ function validateProposal(proposal: any): Proposal | null {
   return validatedProposal as Proposal;
 }
 
+// Helper function to walk every page of a paginated list endpoint
+async function collectPages<T>(
+  fetchPage: (page: {
+    cursor: [] | [bigint];
+    limit: [] | [number];
+  }) => Promise<{ items: T[]; next_cursor: [] | [bigint] }>
+): Promise<T[]> {
+  const items: T[] = [];
+  let cursor: [] | [bigint] = [];
+  do {
+    const page = await fetchPage({ cursor, limit: [] });
+    items.push(...page.items);
+    cursor = page.next_cursor;
+  } while (cursor.length > 0);
+  return items;
+}
+
 // Helper function to handle optional Result types
 function handleOptionalResult<T>(result: any): T | null {
   try {
@@ -345,7 +362,7 @@ export class BackendService {
       const backend = getAslBackend();
       if (!backend) throw new Error("Backend not available");
 
-      return await backend.get_all_artifacts_public();
+      return await collectPages((page) => backend.get_all_artifacts_public(page));
     } catch (error) {
       console.error("Failed to get all artifacts:", error);
       return [];
@@ -390,16 +407,17 @@ export class BackendService {
     }
   }
 
+  // Resolves to the id of the artifact's verification round proposal
   static async voteOnArtifact(
     artifactId: bigint,
     support: boolean
-  ): Promise<string> {
+  ): Promise<bigint | null> {
     try {
       const backend = getAslBackend();
       if (!backend) throw new Error("Backend not available");
 
       const result = await backend.vote_on_artifact_public(artifactId, support);
-      return handleResult<string>(result) || "success";
+      return handleResult<bigint>(result);
     } catch (error) {
       console.error("Failed to vote on artifact:", error);
       throw error;
@@ -570,7 +588,9 @@ export class BackendService {
 
   static async getAllProposals(): Promise<ProposalResponse[]> {
     try {
-      return await originalBackend.get_all_proposals_public();
+      return await collectPages((page) =>
+        originalBackend.get_all_proposals_public(page)
+      );
     } catch (error) {
       console.error("Failed to get all proposals:", error);
       return [];
@@ -600,14 +620,16 @@ export class BackendService {
   static async voteOnProposal(
     proposalId: number,
     voteType: "For" | "Against" | "Abstain",
-    rationale?: string
+    rationale?: string,
+    credits?: number // Reputation credits, quadratic proposals only
   ): Promise<string> {
     try {
       const vote = { [voteType]: null };
       const result = await originalBackend.vote_on_proposal_public(
         BigInt(proposalId),
         vote as VoteType,
-        rationale ? [rationale] : []
+        rationale ? [rationale] : [],
+        credits !== undefined ? [credits] : []
       );
       return handleResult<string>(result) || "success";
     } catch (error) {
@@ -723,7 +745,9 @@ export class BackendService {
 
   static async getAllNFTs(): Promise<HeritageNFT[]> {
     try {
-      return await originalBackend.get_all_nfts_public();
+      return await collectPages((page) =>
+        originalBackend.get_all_nfts_public(page)
+      );
     } catch (error) {
       console.error("Failed to get all NFTs:", error);
       return [];
@@ -1326,7 +1350,11 @@ export class BackendService {
       const backend = getAslBackend();
       if (!backend) throw new Error("Backend not available");
 
-      return await backend.get_audit_logs(limit ? [limit] : []);
+      const page = await backend.get_audit_logs({
+        cursor: [],
+        limit: limit ? [Number(limit)] : [],
+      });
+      return page.items;
     } catch (error) {
       console.error("Failed to get audit logs:", error);
       return [];